@@ -12,6 +12,53 @@
 //! - **Soft Deletion**: Items are marked as "dead" but remain in memory until explicitly cleaned up
 //! - **Memory Pressure Tracking**: Built-in monitoring of how much memory is consumed by dead items
 //!
+//! Thing and connection data don't need to implement `PartialEq`. It is only
+//! required by the methods that compare data, such as `==` between handles or
+//! `Connection::contains`, while navigation and traversal compare things by identity.
+//!
+//! ## Identity and Data Equality
+//!
+//! Two handles have the same *identity* when they refer to the same thing or
+//! connection, for example because one was cloned from the other. Two things or
+//! connections have equal *data* when their payloads compare equal, even if they
+//! are separate items in the graph.
+//!
+//! - Identity-based: navigation and traversal (`get_other_thing`,
+//!   `is_connected_through`, `shortest_path`, ...) and every method that takes a
+//!   handle to find its place in the graph.
+//! - Data-based: `==` between handles or against a value, `Connection::contains`,
+//!   and lookups by value such as `Things::contains_data`,
+//!   `Things::find_thing_equal` and `Things::find_connection_equal`.
+//!
+//! Reach for data equality when looking something up by value, and for identity
+//! when the question is about a particular item, as data can be duplicated.
+//!
+//! ## Self-loops
+//!
+//! A self-loop is attached to its thing at both ends. Methods that list
+//! connections or neighbors, such as `Thing::neighbors_with_edges`,
+//! `Thing::outgoing` or `Thing::other_endpoints_with`, list it once. Methods
+//! that count attachments, such as `Thing::connection_count`,
+//! `UndirectedView::degree`, `Things::k_core` and `Things::trim_leaves`, count it
+//! twice, as is usual for degrees. Only the raw list lent out by
+//! `Thing::with_connections` holds it twice.
+//!
+//! ## Deep Graphs
+//!
+//! None of the traversals recurse. Depth-first walks, topological sorting,
+//! cycle checks, path searches and component splitting all keep their work
+//! lists on the heap, so their stack usage doesn't grow with the depth of the
+//! graph. A path of thousands of things is fine even on the small stacks of
+//! embedded `no_std` targets, and no traversal needs a depth cap to stay safe.
+//!
+//! ## Features
+//!
+//! - `float` (default): Graph measures that produce floating point scores, such as betweenness.
+//! - `test-util`: The `test_util` module, with checks that a graph is internally consistent.
+//! - `debug-invariants`: `Things::clean` checks the dead count first and panics if it drifted.
+//! - `fixtures`: The `fixtures` module, with small example graphs for tests and benchmarks.
+//! - `dead-access-audit`: `Things::enable_dead_access_audit`, which reports dead items handed out by queries.
+//!
 //! ## Example: Building a Complete Knowledge Graph
 //!
 //! ```rust
@@ -46,7 +93,7 @@
 //!     // Query the knowledge: What category of food does Alice like?
 //!     let alice_preferences = alice.do_for_all_connections(|conn| {
 //!         if conn.points_away_from(&alice) && conn == &Relationship::Likes {
-//!             Do::Take(conn)
+//!             Do::Take(conn.clone())
 //!         } else {
 //!             Do::Nothing
 //!         }
@@ -65,7 +112,7 @@
 //!             for category_rel in food_categories {
 //!                 if let Some(category) = category_rel.get_directed_towards() {
 //!                     println!("Alice likes food in category: {:?}",
-//!                         category.access(|data| data));
+//!                         category.access(|data| data.clone()));
 //!                 }
 //!             }
 //!         }
@@ -73,18 +120,47 @@
 //! }
 //! ```
 extern crate alloc;
-use alloc::rc::Rc;
+use alloc::boxed::Box;
+use alloc::collections::btree_map::Entry;
+use alloc::collections::{BTreeMap, BTreeSet, VecDeque};
+use alloc::rc::{Rc, Weak};
+use alloc::string::String;
 use alloc::vec::Vec;
+use core::any::Any;
 
 use core::cell::RefCell;
 
 /// A signal to return a value or continue iterating.
 /// Mainly to keep semantics clean.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub enum Do<R> {
     Take(R),
     Nothing,
 }
 
+impl<R> Do<R> {
+    /// Returns true if this is `Do::Take`.
+    pub fn is_take(&self) -> bool {
+        matches!(self, Do::Take(_))
+    }
+
+    /// Converts into an `Option`, with `Do::Take` becoming `Some`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::Do;
+    /// assert_eq!(Do::Take(3).into_option(), Some(3));
+    /// assert_eq!(Do::<u8>::Nothing.into_option(), None);
+    /// ```
+    pub fn into_option(self) -> Option<R> {
+        match self {
+            Do::Take(value) => Some(value),
+            Do::Nothing => None,
+        }
+    }
+}
+
 /// A node in the graph that holds data and maintains connections to other things.
 ///
 /// Things use reference counting (`Rc`) and interior mutability (`RefCell`) to allow
@@ -103,7 +179,7 @@ pub enum Do<R> {
 /// use connect_things::Thing;
 ///
 /// // Create a simple thing holding a string
-/// let person = Thing::new("Alice");
+/// let person = Thing::<_, ()>::new("Alice");
 ///
 /// // Access the data safely
 /// let name = person.access(|data| data.clone());
@@ -123,7 +199,7 @@ pub enum Do<R> {
 ///
 /// // Navigate from person to their hobby
 /// let alice_hobbies = person.do_for_all_connections(|conn| {
-///     conn.access(|data| return if *data == "enjoys" { Do::Take(conn) } else { Do::Nothing })
+///     conn.access(|data| return if *data == "enjoys" { Do::Take(conn.clone()) } else { Do::Nothing })
 /// });
 ///
 /// for hobby_connection in alice_hobbies {
@@ -133,22 +209,56 @@ pub enum Do<R> {
 ///     }
 /// }
 /// ```
-pub struct Thing<T: PartialEq, C: PartialEq> {
+pub struct Thing<T, C> {
     inner: Rc<RefCell<ThingInner<T, C>>>,
 }
 
-struct ThingInner<T: PartialEq, C: PartialEq> {
+/// Callbacks registered with `on_kill`, taking the data of the killed item.
+type KillCallbacks<D> = Vec<Box<dyn FnOnce(&D)>>;
+
+/// The sink shared by a container and its things while a dead access audit is enabled.
+#[cfg(feature = "dead-access-audit")]
+type AuditSink = Rc<RefCell<Box<dyn FnMut(DeadAccessEvent)>>>;
+
+/// Picks the shard of a new thing in a `ShardedThings` from its data.
+type ShardKey<T> = Box<dyn Fn(&T) -> usize>;
+
+/// How a search with a budget of expanded things ended: with the path, with
+/// `None` once the goal is proved unreachable, or with the distance of the
+/// closest unexpanded thing and the number expanded when the budget ran out.
+type Budgeted<P> = Result<Option<P>, (usize, usize)>;
+
+/// Passes an event to an audit sink, dropping events raised by the sink itself.
+#[cfg(feature = "dead-access-audit")]
+fn report_dead_access(sink: &AuditSink, event: DeadAccessEvent) {
+    if let Ok(mut sink) = sink.try_borrow_mut() {
+        sink(event);
+    }
+}
+
+struct ThingInner<T, C> {
     connections: Vec<Connection<T, C>>,
     data: T,
     is_alive: bool,
+    /// Position in the creation order of the owning container.
+    seq: u64,
+    /// Callbacks registered with `Thing::on_kill`, run once when the thing dies.
+    on_kill: KillCallbacks<T>,
+    /// Where dead accesses are reported, see `Things::enable_dead_access_audit`.
+    #[cfg(feature = "dead-access-audit")]
+    audit: Option<AuditSink>,
 }
 
-impl<T: PartialEq, C: PartialEq> ThingInner<T, C> {
+impl<T, C> ThingInner<T, C> {
     pub fn new(data: T) -> Self {
         ThingInner {
             connections: Vec::new(),
             data,
             is_alive: true,
+            seq: 0,
+            on_kill: Vec::new(),
+            #[cfg(feature = "dead-access-audit")]
+            audit: None,
         }
     }
 
@@ -161,7 +271,7 @@ impl<T: PartialEq, C: PartialEq> ThingInner<T, C> {
     }
 }
 
-impl<T: PartialEq, C: PartialEq> Thing<T, C> {
+impl<T, C> Thing<T, C> {
     /// Creates a new thing with the provided data.
     ///
     /// The thing starts alive and with no connections. Connections must be
@@ -172,7 +282,7 @@ impl<T: PartialEq, C: PartialEq> Thing<T, C> {
     /// ```rust
     /// use connect_things::Thing;
     ///
-    /// let widget = Thing::new("Button");
+    /// let widget = Thing::<_, ()>::new("Button");
     /// ```
     pub fn new(data: T) -> Self {
         Thing {
@@ -185,16 +295,37 @@ impl<T: PartialEq, C: PartialEq> Thing<T, C> {
     /// This is typically called internally by the `Things` container when
     /// creating connections. Manual use should be done carefully to maintain
     /// graph consistency.
+    ///
+    /// # Safety
+    /// The connection must also be registered with the other endpoint and with
+    /// the owning `Things` container, otherwise the graph becomes inconsistent.
     pub unsafe fn connect(&self, connection: Connection<T, C>) {
         let mut inner = self.inner.borrow_mut();
         inner.connections.push(connection);
     }
 
     /// Checks if a connection is present for a thing.
+    ///
+    /// Connections are compared by identity, so another connection holding
+    /// equal data doesn't count.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut graph = Things::<&str, &str>::new();
+    /// let a = graph.new_thing("a");
+    /// let b = graph.new_thing("b");
+    /// let link = graph.new_undirected_connection([a.clone(), b.clone()], "link");
+    /// let lookalike = Connection::new_undirected([a.clone(), b], "link");
+    ///
+    /// assert!(a.is_connected_through(&link));
+    /// assert!(!a.is_connected_through(&lookalike));
+    /// ```
     pub fn is_connected_through(&self, other: &Connection<T, C>) -> bool {
         let inner = self.inner.borrow();
         for conn in &inner.connections {
-            if conn == other {
+            if conn.ptr_eq(other) {
                 return true;
             }
         }
@@ -233,6 +364,8 @@ impl<T: PartialEq, C: PartialEq> Thing<T, C> {
     ) -> Option<R> {
         let inner = self.inner.borrow();
         for conn in inner.connections.iter() {
+            #[cfg(feature = "dead-access-audit")]
+            self.audit_dead_connection(conn, "Thing::do_for_a_connection");
             if let Do::Take(value) = do_for(conn) {
                 return Some(value.clone());
             }
@@ -251,6 +384,8 @@ impl<T: PartialEq, C: PartialEq> Thing<T, C> {
         let mut connections = Vec::new();
         let inner = self.inner.borrow();
         for conn in inner.connections.iter() {
+            #[cfg(feature = "dead-access-audit")]
+            self.audit_dead_connection(conn, "Thing::do_for_all_connections");
             if let Do::Take(value) = do_for(conn) {
                 connections.push(value)
             }
@@ -258,16 +393,565 @@ impl<T: PartialEq, C: PartialEq> Thing<T, C> {
         connections
     }
 
+    /// Returns the number of live connections attached to this thing.
+    ///
+    /// A self-loop is attached at both of its ends, so it counts twice.
+    pub fn connection_count(&self) -> usize {
+        let inner = self.inner.borrow();
+        inner
+            .connections
+            .iter()
+            .filter(|connection| connection.is_alive())
+            .count()
+    }
+
+    /// Gives `read` the raw list of this thing's connections, without cloning any handles.
+    ///
+    /// The list includes dead connections that haven't been cleaned yet, and
+    /// self-loops appear twice. This thing stays borrowed while `read` runs, so
+    /// `read` can inspect the connections and their endpoints but must not
+    /// modify this thing or connect anything to it, or it panics.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut graph = Things::<&str, u32>::new();
+    /// let hub = graph.new_thing("hub");
+    /// for weight in 1..=3 {
+    ///     let spoke = graph.new_thing("spoke");
+    ///     graph.new_directed_connection(hub.clone(), weight, spoke);
+    /// }
+    ///
+    /// let total: u32 = hub.with_connections(|connections| {
+    ///     connections.iter().map(|connection| connection.access(|weight| *weight)).sum()
+    /// });
+    /// assert_eq!(total, 6);
+    /// ```
+    pub fn with_connections<R>(&self, read: impl FnOnce(&[Connection<T, C>]) -> R) -> R {
+        let inner = self.inner.borrow();
+        read(&inner.connections)
+    }
+
     /// Removes connections that match the given predicate from this thing's connection list.
     ///
     /// Note: This only removes the connection from this thing's local list.
     /// To properly remove connections from the entire graph, use the methods
     /// on the `Things` container instead.
+    ///
+    /// # Safety
+    /// The removed connections stay registered with their other endpoint and
+    /// with the owning `Things` container, so the caller is responsible for
+    /// keeping the graph consistent.
     pub unsafe fn remove_connections(&mut self, remove: impl Fn(&Connection<T, C>) -> bool) {
         let mut inner = self.inner.borrow_mut();
         inner.connections.retain(|c| !remove(c))
     }
 
+    /// Lists every live connection of this thing together with the thing at its other end.
+    ///
+    /// This covers connections in both directions. Parallel connections to the
+    /// same neighbor each produce their own pair, and a self-loop pairs the
+    /// connection with this thing itself, once. Neighbors that are dead are
+    /// skipped.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut graph = Things::<&str, &str>::new();
+    /// let alice = graph.new_thing("Alice");
+    /// let bob = graph.new_thing("Bob");
+    /// graph.new_directed_connection(alice.clone(), "likes", bob);
+    ///
+    /// for (connection, neighbor) in alice.neighbors_with_edges() {
+    ///     let label = connection.access(|data| *data);
+    ///     let name = neighbor.access(|data| *data);
+    ///     println!("Alice {} {}", label, name);
+    /// }
+    /// ```
+    pub fn neighbors_with_edges(&self) -> Vec<(Connection<T, C>, Thing<T, C>)> {
+        let inner = self.inner.borrow();
+        let mut neighbors: Vec<(Connection<T, C>, Thing<T, C>)> = Vec::new();
+        for connection in inner
+            .connections
+            .iter()
+            .filter(|connection| connection.is_alive())
+        {
+            let Some(other) = connection.inner.borrow().other_end(self) else {
+                continue;
+            };
+            // A self-loop is in the connection list twice, but is listed once
+            if !other.is_alive()
+                || other.ptr_eq(self)
+                    && neighbors
+                        .iter()
+                        .any(|(listed, _)| listed.ptr_eq(connection))
+            {
+                continue;
+            }
+            neighbors.push((connection.clone(), other));
+        }
+        neighbors
+    }
+
+    /// Lists the live things at the other end of this thing's live connections.
+    ///
+    /// Each neighbor is listed once, in the order of the first connection
+    /// leading to it, no matter how many connections lead there.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut graph = Things::<&str, ()>::new();
+    /// let hub = graph.new_thing("hub");
+    /// let gone = graph.new_thing("gone");
+    /// graph.new_undirected_connection([hub.clone(), gone], ());
+    /// assert_eq!(hub.neighbors_alive().len(), 1);
+    ///
+    /// graph.kill_things(|thing| *thing == "gone");
+    /// assert!(hub.neighbors_alive().is_empty());
+    /// ```
+    pub fn neighbors_alive(&self) -> Vec<Thing<T, C>> {
+        let mut seen = BTreeSet::new();
+        self.neighbors_with_edges()
+            .into_iter()
+            .filter_map(|(_, neighbor)| seen.insert(neighbor.key()).then_some(neighbor))
+            .collect()
+    }
+
+    /// Lists the things at the far end of the live connections accepted by `pred`.
+    ///
+    /// The connection list is walked once, ignoring direction. Each thing is
+    /// listed once, in the order of the first accepted connection leading to
+    /// it, and dead far ends are left out. A self-loop leads back to this thing.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut graph = Things::<&str, &str>::new();
+    /// let alice = graph.new_thing("Alice");
+    /// let bob = graph.new_thing("Bob");
+    /// let carol = graph.new_thing("Carol");
+    /// graph.new_directed_connection(alice.clone(), "knows", bob);
+    /// graph.new_directed_connection(carol, "knows", alice.clone());
+    /// let dan = graph.new_thing("Dan");
+    /// graph.new_undirected_connection([alice.clone(), dan], "sibling");
+    ///
+    /// let known = alice.other_endpoints(|connection| *connection == "knows");
+    /// assert!(known[0] == "Bob" && known[1] == "Carol");
+    /// ```
+    pub fn other_endpoints(&self, pred: impl Fn(&Connection<T, C>) -> bool) -> Vec<Thing<T, C>> {
+        let mut seen = BTreeSet::new();
+        self.other_endpoints_with(pred)
+            .into_iter()
+            .filter_map(|(_, other)| seen.insert(other.key()).then_some(other))
+            .collect()
+    }
+
+    /// Like `other_endpoints`, but pairs each far end with the connection leading there.
+    ///
+    /// Nothing is deduplicated by thing, so parallel connections give one pair
+    /// each. A self-loop is listed once.
+    pub fn other_endpoints_with(
+        &self,
+        pred: impl Fn(&Connection<T, C>) -> bool,
+    ) -> Vec<(Connection<T, C>, Thing<T, C>)> {
+        let mut seen = BTreeSet::new();
+        let inner = self.inner.borrow();
+        inner
+            .connections
+            .iter()
+            .filter(|connection| connection.is_alive() && pred(connection))
+            .filter(|connection| seen.insert(connection.key()))
+            .filter_map(|connection| {
+                let other = connection.inner.borrow().other_end(self)?;
+                other.is_alive().then(|| (connection.clone(), other))
+            })
+            .collect()
+    }
+
+    /// Compares everything reachable from this thing with everything reachable from `other`.
+    ///
+    /// Two things match when their data is equal and their live outgoing
+    /// steps match one by one, in the order the things store them: the
+    /// connections must both be directed or both undirected, hold equal data,
+    /// and lead to matching things. Directed connections are followed from
+    /// source to target and undirected ones both ways; dead items are
+    /// skipped. Pairs already being compared are assumed to match, so cycles
+    /// are handled, and the walk keeps its own stack, so deep structures
+    /// don't overflow the call stack.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut tree = Things::<&str, &str>::new();
+    /// let root = tree.new_thing("root");
+    /// for _ in 0..2 {
+    ///     let branch = tree.new_thing("branch");
+    ///     let leaf = tree.new_thing("leaf");
+    ///     tree.new_directed_connection(root.clone(), "has", branch.clone());
+    ///     tree.new_directed_connection(branch, "has", leaf);
+    /// }
+    ///
+    /// let branches = root.follow(&"has");
+    /// assert!(branches[0].subtree_eq(&branches[1]));
+    /// assert!(!branches[0].subtree_eq(&root));
+    /// ```
+    pub fn subtree_eq(&self, other: &Thing<T, C>) -> bool
+    where
+        T: PartialEq,
+        C: PartialEq,
+    {
+        let mut visited = BTreeSet::new();
+        let mut stack = alloc::vec![(self.clone(), other.clone())];
+        visited.insert((self.key(), other.key()));
+        while let Some((left, right)) = stack.pop() {
+            if left.ptr_eq(&right) {
+                continue;
+            }
+            if *left.inner.borrow().get_data() != *right.inner.borrow().get_data() {
+                return false;
+            }
+            let (left_steps, right_steps) = (left.forward_steps(), right.forward_steps());
+            if left_steps.len() != right_steps.len() {
+                return false;
+            }
+            for ((left_connection, left_next), (right_connection, right_next)) in
+                left_steps.into_iter().zip(right_steps)
+            {
+                if left_connection.is_directed() != right_connection.is_directed() {
+                    return false;
+                }
+                let same_data = {
+                    let (left_inner, right_inner) = (
+                        left_connection.inner.borrow(),
+                        right_connection.inner.borrow(),
+                    );
+                    left_inner.get_data() == right_inner.get_data()
+                };
+                if !same_data {
+                    return false;
+                }
+                if visited.insert((left_next.key(), right_next.key())) {
+                    stack.push((left_next, right_next));
+                }
+            }
+        }
+        true
+    }
+
+    /// Follows the live connections labelled `label` away from this thing.
+    ///
+    /// Directed connections are only followed from source to target, and
+    /// undirected connections either way. Each matching connection gives one
+    /// thing, in connection order, so a self-loop gives this thing once. Dead
+    /// far ends are left out.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut graph = Things::<&str, &str>::new();
+    /// let alice = graph.new_thing("Alice");
+    /// let apples = graph.new_thing("Apples");
+    /// let bob = graph.new_thing("Bob");
+    /// graph.new_directed_connection(alice.clone(), "likes", apples);
+    /// graph.new_directed_connection(bob.clone(), "likes", alice.clone());
+    ///
+    /// let liked = alice.follow(&"likes");
+    /// assert_eq!(liked.len(), 1);
+    /// assert!(liked[0] == "Apples");
+    /// ```
+    pub fn follow(&self, label: &C) -> Vec<Thing<T, C>>
+    where
+        C: PartialEq,
+    {
+        let inner = self.inner.borrow();
+        let mut seen = BTreeSet::new();
+        inner
+            .connections
+            .iter()
+            .filter(|connection| connection.is_alive())
+            .filter(|connection| seen.insert(connection.key()))
+            .filter_map(|connection| {
+                let inner = connection.inner.borrow();
+                if inner.get_data() != label {
+                    return None;
+                }
+                inner.step_from(self).filter(|next| next.is_alive())
+            })
+            .collect()
+    }
+
+    /// Returns the live directed connections leaving this thing, paired with their targets.
+    ///
+    /// Undirected connections are left out. Direction is determined by identity,
+    /// so a self-loop is listed here as well as in `incoming`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut tasks = Things::<&str, &str>::new();
+    /// let build = tasks.new_thing("build");
+    /// let fetch = tasks.new_thing("fetch");
+    /// tasks.new_directed_connection(build.clone(), "depends_on", fetch);
+    ///
+    /// for (connection, target) in build.outgoing() {
+    ///     if connection == "depends_on" {
+    ///         assert!(target == "fetch");
+    ///     }
+    /// }
+    /// ```
+    pub fn outgoing(&self) -> Vec<(Connection<T, C>, Thing<T, C>)> {
+        self.directed_neighbors(Direction::AwayFrom, false)
+    }
+
+    /// Returns the live directed connections entering this thing, paired with their sources.
+    ///
+    /// Undirected connections are left out.
+    pub fn incoming(&self) -> Vec<(Connection<T, C>, Thing<T, C>)> {
+        self.directed_neighbors(Direction::Towards, false)
+    }
+
+    /// Wraps this thing so only its outgoing side can be explored.
+    ///
+    /// The view follows live directed connections from this thing to their
+    /// targets, and live undirected connections too if `include_undirected`
+    /// is set. It only narrows what the view itself lists: the handles it
+    /// hands out still reach the rest of the graph.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut tasks = Things::<&str, &str>::new();
+    /// let build = tasks.new_thing("build");
+    /// let fetch = tasks.new_thing("fetch");
+    /// let release = tasks.new_thing("release");
+    /// tasks.new_directed_connection(build.clone(), "depends_on", fetch);
+    /// tasks.new_directed_connection(release, "depends_on", build.clone());
+    ///
+    /// let view = build.outgoing_view(false);
+    /// assert_eq!(view.count(), 1);
+    /// assert!(view.neighbors()[0] == "fetch");
+    /// ```
+    pub fn outgoing_view(&self, include_undirected: bool) -> DirectedView<T, C> {
+        DirectedView {
+            thing: self.clone(),
+            direction: Direction::AwayFrom,
+            include_undirected,
+        }
+    }
+
+    /// Wraps this thing so only its incoming side can be explored.
+    ///
+    /// The mirror of `outgoing_view`, following live directed connections
+    /// into this thing back to their sources.
+    pub fn incoming_view(&self, include_undirected: bool) -> DirectedView<T, C> {
+        DirectedView {
+            thing: self.clone(),
+            direction: Direction::Towards,
+            include_undirected,
+        }
+    }
+
+    /// Picks a neighbor at random, with a chance proportional to the weight of
+    /// the connection leading to it.
+    ///
+    /// The crate has no random number generator of its own: `roll` receives the
+    /// total weight of this thing's live connections and should return a value
+    /// in `0..total` (larger values wrap around). The connection whose share of
+    /// the running total contains the roll is picked, in connection order.
+    /// Only connections a walk can take count, as in `weighted_random_neighbor`:
+    /// directed connections from source to target, undirected ones both ways.
+    /// A self-loop counts once.
+    ///
+    /// # Returns
+    /// The picked connection and the neighbor it leads to, or `None` if there are
+    /// no live connections or their total weight is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut graph = Things::<&str, u64>::new();
+    /// let me = graph.new_thing("me");
+    /// let rarely = graph.new_thing("rarely");
+    /// let often = graph.new_thing("often");
+    /// graph.new_undirected_connection([me.clone(), rarely], 1);
+    /// graph.new_undirected_connection([me.clone(), often], 9);
+    ///
+    /// // A roll of 0 lands in the first connection's share, 1 to 9 in the second's
+    /// let weight = |connection: &Connection<&str, u64>| connection.access(|weight| *weight);
+    /// let (_, picked) = me.pick_neighbor_weighted(weight, |total| total - 1).unwrap();
+    /// assert!(picked == "often");
+    /// ```
+    pub fn pick_neighbor_weighted(
+        &self,
+        weight: impl Fn(&Connection<T, C>) -> u64,
+        roll: impl FnOnce(u64) -> u64,
+    ) -> Option<(Connection<T, C>, Thing<T, C>)> {
+        let mut seen = BTreeSet::new();
+        let steps = self
+            .forward_steps()
+            .into_iter()
+            .filter(|(connection, _)| seen.insert(connection.key()))
+            .collect();
+        pick_weighted(steps, weight, roll)
+    }
+
+    /// Picks a neighbor at random like `pick_neighbor_weighted`, but only
+    /// among the targets of this thing's live outgoing directed connections.
+    pub fn pick_outgoing_weighted(
+        &self,
+        weight: impl Fn(&Connection<T, C>) -> u64,
+        roll: impl FnOnce(u64) -> u64,
+    ) -> Option<(Connection<T, C>, Thing<T, C>)> {
+        pick_weighted(self.outgoing(), weight, roll)
+    }
+
+    /// Picks a live neighbor with a chance proportional to the data based
+    /// weight of the connection leading to it, drawing from `rng`.
+    ///
+    /// Meant for biased random walks: `rng` is any generator of uniformly
+    /// distributed `u64` values. It is usually called once per pick, but a
+    /// value that would favor some neighbors over their weight is thrown away
+    /// and drawn again. Neighbors are reached the way a walk goes: directed
+    /// connections from source to target, undirected ones both ways. Each
+    /// weight is computed once per pick, and if every weight is zero, each
+    /// connection is equally likely instead. See `pick_neighbor_weighted` for
+    /// weights that depend on more than the connection data.
+    ///
+    /// # Returns
+    /// The picked neighbor, or `None` if no live connection leads away from
+    /// this thing.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut graph = Things::<&str, u64>::new();
+    /// let here = graph.new_thing("here");
+    /// let far = graph.new_thing("far");
+    /// graph.new_undirected_connection([here.clone(), far], 3);
+    ///
+    /// let mut seed = 12345u64;
+    /// let mut rng = || {
+    ///     seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+    ///     seed
+    /// };
+    /// let next = here.weighted_random_neighbor(|weight| *weight, &mut rng);
+    /// assert!(next.unwrap() == "far");
+    /// ```
+    pub fn weighted_random_neighbor(
+        &self,
+        weight: impl Fn(&C) -> u64,
+        rng: &mut impl FnMut() -> u64,
+    ) -> Option<Thing<T, C>> {
+        let steps = self.forward_steps();
+        if steps.is_empty() {
+            return None;
+        }
+        let weights: Vec<u64> = steps
+            .iter()
+            .map(|(connection, _)| connection.access(|data| weight(data)))
+            .collect();
+        let total = weights
+            .iter()
+            .fold(0u64, |total, weight| total.saturating_add(*weight));
+        let picked = if total == 0 {
+            roll_below(rng, steps.len() as u64) as usize
+        } else {
+            share_holding(&weights, roll_below(rng, total))?
+        };
+        steps.into_iter().nth(picked).map(|(_, neighbor)| neighbor)
+    }
+
+    /// Copies this thing's data and its local view of the graph.
+    ///
+    /// The snapshot holds the data of this thing and, for every live connection
+    /// to a live neighbor, the connection data, the neighbor's data and the
+    /// direction relative to this thing. It owns all of it, so it can be
+    /// serialized or sent elsewhere without touching the rest of the graph.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut graph = Things::<&str, &str>::new();
+    /// let alice = graph.new_thing("alice");
+    /// let bob = graph.new_thing("bob");
+    /// graph.new_directed_connection(alice.clone(), "follows", bob);
+    ///
+    /// let snapshot = alice.snapshot();
+    /// assert_eq!(snapshot.data, "alice");
+    /// assert_eq!(snapshot.edges, [("follows", "bob", Some(Direction::AwayFrom))]);
+    /// ```
+    pub fn snapshot(&self) -> ThingSnapshot<T, C>
+    where
+        T: Clone,
+        C: Clone,
+    {
+        let edges = self
+            .neighbors_with_edges()
+            .into_iter()
+            .map(|(connection, neighbor)| {
+                let direction = connection.get_direction_relative_to(self).ok();
+                (
+                    connection.access(|data| data.clone()),
+                    neighbor.access(|data| data.clone()),
+                    direction,
+                )
+            })
+            .collect();
+        ThingSnapshot {
+            data: self.access(|data| data.clone()),
+            edges,
+        }
+    }
+
+    /// Returns true if no live connection accepted by `follow` leads into this thing.
+    ///
+    /// Undirected connections lead both ways, so a thing with a followed
+    /// undirected connection is neither a root nor a leaf. A thing without
+    /// followed connections is both.
+    pub fn is_root(&self, follow: impl Fn(&Connection<T, C>) -> bool) -> bool {
+        !self.has_followed_connection(&follow, Direction::Towards)
+    }
+
+    /// Returns true if no live connection accepted by `follow` leads out of this thing.
+    ///
+    /// See `is_root` for how undirected connections and isolated things count.
+    pub fn is_leaf(&self, follow: impl Fn(&Connection<T, C>) -> bool) -> bool {
+        !self.has_followed_connection(&follow, Direction::AwayFrom)
+    }
+
+    /// Returns this thing's live connections ordered by a key computed from their data.
+    ///
+    /// Each key is computed exactly once, and connections with equal keys keep
+    /// the order in which they were added to this thing. A self-loop is
+    /// listed once.
+    pub fn sorted_connections_by_key<K: Ord>(
+        &self,
+        key: impl Fn(&C) -> K,
+    ) -> Vec<Connection<T, C>> {
+        let inner = self.inner.borrow();
+        let mut seen = BTreeSet::new();
+        sort_by_extracted_key(
+            inner
+                .connections
+                .iter()
+                .filter(|connection| connection.is_alive())
+                .filter(|connection| seen.insert(connection.key())),
+            |connection| connection.access(&key),
+        )
+    }
+
     /// Provides read-only access to this thing's data.
     ///
     /// The closure receives a reference to the data and can return any value.
@@ -277,7 +961,7 @@ impl<T: PartialEq, C: PartialEq> Thing<T, C> {
     ///
     /// ```rust
     /// # use connect_things::Thing;
-    /// # let person = Thing::new("Alice");
+    /// # let person = Thing::<_, ()>::new("Alice");
     ///
     /// let name_length = person.access(|data| data.len());
     /// let is_alice = person.access(|data| *data == "Alice");
@@ -295,7 +979,7 @@ impl<T: PartialEq, C: PartialEq> Thing<T, C> {
     ///
     /// ```rust
     /// # use connect_things::Thing;
-    /// # let person = Thing::new("Alice");
+    /// # let person = Thing::<_, ()>::new("Alice");
     ///
     /// // Update a person's name
     /// person.access_mut(|name| {
@@ -307,6 +991,62 @@ impl<T: PartialEq, C: PartialEq> Thing<T, C> {
         access(inner.get_data_mut())
     }
 
+    /// Returns a copy of this thing's data.
+    ///
+    /// Equivalent to `access(|data| *data)` without the closure, for hot loops
+    /// over small `Copy` payloads. Panics if the data is currently borrowed
+    /// mutably, like `access`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let particle = Thing::<(f32, f32), ()>::new((1.0, 2.0));
+    /// assert_eq!(particle.get_copy(), (1.0, 2.0));
+    /// ```
+    pub fn get_copy(&self) -> T
+    where
+        T: Copy,
+    {
+        self.inner.borrow().data
+    }
+
+    /// Reads this thing's data without registering a borrow.
+    ///
+    /// `access` marks the data as borrowed for as long as its closure runs, so
+    /// that a nested `access_mut` panics instead of aliasing. This method only
+    /// checks that no mutable borrow is active when it starts and then reads
+    /// the data without any bookkeeping. Prefer `access` or `get_copy`; this is
+    /// meant for measured hot paths where that bookkeeping matters.
+    ///
+    /// # Safety
+    /// While `read` runs, nothing may borrow this thing mutably. In particular
+    /// `read` must not call `access_mut` on this thing, connect anything to it,
+    /// or kill it (directly or through a container), and no other code may do
+    /// so in the meantime. Violating this creates a mutable reference to data
+    /// that `read` is still reading, which is undefined behavior.
+    ///
+    /// # Panics
+    /// If the data is already borrowed mutably when this is called.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let sensor = Thing::<[u8; 4], ()>::new([1, 2, 3, 4]);
+    /// // SAFETY: the closure only reads, and nothing else touches the sensor
+    /// let sum: u32 = unsafe { sensor.map_data_unchecked(|bytes| bytes.iter().map(|&b| u32::from(b)).sum()) };
+    /// assert_eq!(sum, 10);
+    /// ```
+    pub unsafe fn map_data_unchecked<R>(&self, read: impl FnOnce(&T) -> R) -> R {
+        // SAFETY: the caller guarantees no mutable borrow is created while
+        // the returned reference is in use, which is the contract of
+        // `try_borrow_unguarded`.
+        let inner = unsafe { self.inner.try_borrow_unguarded() }
+            .expect("thing data is already mutably borrowed");
+        read(&inner.data)
+    }
+
     /// Returns whether this thing is still alive (not marked for deletion).
     fn is_alive(&self) -> bool {
         let inner = self.inner.borrow();
@@ -319,30 +1059,258 @@ impl<T: PartialEq, C: PartialEq> Thing<T, C> {
     /// This represents the semantic that when an entity ceases to exist, all its
     /// relationships also cease to exist.
     ///
-    /// # Returns
-    /// The number of items killed (this thing plus any live connections that were killed).
-    fn kill(&self) -> usize {
-        let mut amount = 0;
-        let mut inner = self.inner.borrow_mut();
+    /// Every item killed (this thing if alive, plus any live connections) is added to
+    /// `dead` right before it dies, so the count stays accurate even if a kill
+    /// callback panics halfway through.
+    fn kill(&self, dead: &mut usize) {
+        // Release the borrow first, so kill callbacks can read the things they touch
+        let connections = self.inner.borrow().connections.clone();
         // Only kill connections that are still alive to avoid double-counting
-        for connection in inner.connections.iter() {
+        for connection in connections.iter() {
             if connection.is_alive() {
+                *dead = dead.saturating_add(1);
                 connection.kill();
-                amount += 1;
             }
         }
-        inner.is_alive = false;
-        amount + 1 // +1 for this thing itself
+        // A thing that is already dead was counted when it died
+        if self.is_alive() {
+            *dead = dead.saturating_add(1);
+            self.mark_dead();
+        }
     }
 
-    /// Removes dead connections.
-    fn clean(&mut self) {
-        let mut inner = self.inner.borrow_mut();
-        inner.connections.retain(|c| c.is_alive());
-    }
+    /// Marks this thing as dead without touching its connections, running its
+    /// kill callbacks if it was alive.
+    fn mark_dead(&self) {
+        let callbacks = {
+            let mut inner = self.inner.borrow_mut();
+            let was_alive = core::mem::replace(&mut inner.is_alive, false);
+            if !was_alive {
+                return;
+            }
+            core::mem::take(&mut inner.on_kill)
+        };
+        let inner = self.inner.borrow();
+        for callback in callbacks {
+            callback(&inner.data);
+        }
+    }
+
+    /// Registers a callback to run when this thing is killed.
+    ///
+    /// The callback runs exactly once, at the moment the thing dies: when it is
+    /// killed directly, through `Things::kill_things`, or when it is absorbed by
+    /// `Things::contract_connection`. It gets read access to the data, which is
+    /// useful for releasing resources tied to the thing without waiting for the
+    /// data to be dropped. Callbacks run in the order they were registered. If
+    /// the thing is already dead, the callback runs right away.
+    ///
+    /// Callbacks run while the container is being modified, so they must not
+    /// modify the graph: mutating this thing's data, or borrowing a container
+    /// shared through a `RefCell`, panics with a borrow error instead of
+    /// corrupting the graph. Reading other things and connections is fine.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// # use std::cell::Cell;
+    /// # use std::rc::Rc;
+    /// let mut windows = Things::<&str, ()>::new();
+    /// let main = windows.new_thing("main");
+    ///
+    /// let closed = Rc::new(Cell::new(false));
+    /// let flag = closed.clone();
+    /// main.on_kill(move |_| flag.set(true));
+    ///
+    /// windows.kill_things(|window| *window == "main");
+    /// assert!(closed.get());
+    /// ```
+    pub fn on_kill(&self, callback: impl FnOnce(&T) + 'static) {
+        if self.is_alive() {
+            self.inner.borrow_mut().on_kill.push(Box::new(callback));
+        } else {
+            callback(&self.inner.borrow().data);
+        }
+    }
+
+    /// Removes dead connections.
+    fn clean(&mut self) {
+        let mut inner = self.inner.borrow_mut();
+        inner.connections.retain(|c| c.is_alive());
+    }
+
+    /// Creates a non-owning handle to this thing.
+    ///
+    /// Weak handles don't keep the thing in memory, so caches and secondary
+    /// indexes can hold them without interfering with `Things::clean`. Once the
+    /// thing has been cleaned and every `Thing` handle to it dropped, the weak
+    /// handle can no longer be upgraded.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut graph = Things::<&str, ()>::new();
+    /// let weak = graph.new_thing("temporary").downgrade();
+    /// assert!(weak.upgrade().is_some());
+    ///
+    /// graph.kill_things(|thing| *thing == "temporary");
+    /// graph.clean();
+    /// assert!(weak.upgrade().is_none());
+    /// ```
+    pub fn downgrade(&self) -> WeakThing<T, C> {
+        WeakThing {
+            inner: Rc::downgrade(&self.inner),
+        }
+    }
+
+    /// Collects the live directed connections pointing towards or away from
+    /// this thing, paired with the thing on their other end. Undirected
+    /// connections are added too if `include_undirected` is set.
+    fn directed_neighbors(
+        &self,
+        direction: Direction,
+        include_undirected: bool,
+    ) -> Vec<(Connection<T, C>, Thing<T, C>)> {
+        let inner = self.inner.borrow();
+        let mut neighbors: Vec<(Connection<T, C>, Thing<T, C>)> = Vec::new();
+        for connection in inner
+            .connections
+            .iter()
+            .filter(|connection| connection.is_alive())
+        {
+            let next = match (connection.directed_ends(), direction) {
+                (Some((from, to)), Direction::AwayFrom) if from.ptr_eq(self) => to,
+                (Some((from, to)), Direction::Towards) if to.ptr_eq(self) => from,
+                (None, _) if include_undirected => {
+                    let Some(other) = connection.inner.borrow().other_end(self) else {
+                        continue;
+                    };
+                    other
+                }
+                _ => continue,
+            };
+            // A self-loop is in the connection list twice, but is listed once
+            if next.ptr_eq(self)
+                && neighbors
+                    .iter()
+                    .any(|(listed, _)| listed.ptr_eq(connection))
+            {
+                continue;
+            }
+            neighbors.push((connection.clone(), next));
+        }
+        neighbors
+    }
+
+    /// Returns true if a live connection accepted by `follow` leads towards or
+    /// away from this thing. Undirected connections lead both ways.
+    fn has_followed_connection(
+        &self,
+        follow: &impl Fn(&Connection<T, C>) -> bool,
+        direction: Direction,
+    ) -> bool {
+        let inner = self.inner.borrow();
+        inner
+            .connections
+            .iter()
+            .filter(|connection| connection.is_alive() && follow(connection))
+            .any(|connection| match connection.directed_ends() {
+                Some((from, to)) => match direction {
+                    Direction::Towards => to.ptr_eq(self),
+                    Direction::AwayFrom => from.ptr_eq(self),
+                },
+                None => true,
+            })
+    }
+
+    /// Returns true if both handles refer to the same underlying thing.
+    ///
+    /// Unlike `==`, this compares identity rather than data.
+    fn ptr_eq(&self, other: &Thing<T, C>) -> bool {
+        Rc::ptr_eq(&self.inner, &other.inner)
+    }
+
+    /// An identity key for this thing, used by traversals to track visited nodes.
+    fn key(&self) -> usize {
+        Rc::as_ptr(&self.inner) as *const () as usize
+    }
+
+    /// Reports this thing to the dead access audit if it is dead.
+    #[cfg(feature = "dead-access-audit")]
+    fn audit_if_dead(&self, operation: &'static str) {
+        let (sink, id) = {
+            let inner = self.inner.borrow();
+            match &inner.audit {
+                Some(sink) if !inner.is_alive => (sink.clone(), inner.seq),
+                _ => return,
+            }
+        };
+        let event = DeadAccessEvent {
+            item: AuditedItem::Thing,
+            id,
+            operation,
+        };
+        report_dead_access(&sink, event);
+    }
+
+    /// Reports a connection handed out by this thing to the dead access audit
+    /// if it is dead.
+    #[cfg(feature = "dead-access-audit")]
+    fn audit_dead_connection(&self, connection: &Connection<T, C>, operation: &'static str) {
+        let Some(sink) = self.inner.borrow().audit.clone() else {
+            return;
+        };
+        if !connection.is_alive() {
+            let event = DeadAccessEvent {
+                item: AuditedItem::Connection,
+                id: connection.seq(),
+                operation,
+            };
+            report_dead_access(&sink, event);
+        }
+    }
+
+    /// The position of this thing in its container's creation order.
+    fn seq(&self) -> u64 {
+        self.inner.borrow().seq
+    }
+
+    /// Collects the live connections that can be traversed away from this thing,
+    /// paired with the thing on the other side.
+    ///
+    /// Directed connections are only followed from source to target, while
+    /// undirected connections are followed in both directions.
+    fn forward_steps(&self) -> Vec<(Connection<T, C>, Thing<T, C>)> {
+        let inner = self.inner.borrow();
+        let mut steps = Vec::new();
+        for connection in inner.connections.iter() {
+            if !connection.is_alive() {
+                continue;
+            }
+            if let Some(next) = connection.inner.borrow().step_from(self)
+                && next.is_alive()
+            {
+                steps.push((connection.clone(), next));
+            }
+        }
+        steps
+    }
+
+    /// Collects the live connections that can be traversed towards this thing,
+    /// paired with the live thing they come from.
+    ///
+    /// The reverse of `forward_steps`: directed connections are only followed
+    /// from target back to source.
+    fn backward_steps(&self) -> Vec<(Connection<T, C>, Thing<T, C>)> {
+        let mut steps = self.directed_neighbors(Direction::Towards, true);
+        steps.retain(|(_, previous)| previous.is_alive());
+        steps
+    }
 }
 
-impl<T: PartialEq, C: PartialEq> Clone for Thing<T, C> {
+impl<T, C> Clone for Thing<T, C> {
     /// Creates a new reference to the same thing.
     ///
     /// This is a shallow clone - both instances refer to the same underlying
@@ -355,19 +1323,42 @@ impl<T: PartialEq, C: PartialEq> Clone for Thing<T, C> {
     }
 }
 
-impl<T: PartialEq, C: PartialEq> PartialEq for Thing<T, C> {
+impl<T: PartialEq, C> PartialEq for Thing<T, C> {
     fn eq(&self, other: &Self) -> bool {
         self.access(|data| other.access(|other_data| data == other_data))
     }
 }
 
-impl<T: PartialEq, C: PartialEq> PartialEq<T> for Thing<T, C> {
+impl<T: PartialEq, C> PartialEq<T> for Thing<T, C> {
     fn eq(&self, other: &T) -> bool {
         self.access(|data| data == other)
     }
+}
+
+/// A non-owning handle to a thing, created with `Thing::downgrade`.
+///
+/// Holding a weak handle doesn't keep the thing alive in memory, which makes it
+/// suitable for caches and indexes kept next to the graph.
+pub struct WeakThing<T, C> {
+    inner: Weak<RefCell<ThingInner<T, C>>>,
+}
+
+impl<T, C> WeakThing<T, C> {
+    /// Returns a strong handle to the thing if it is still in memory.
+    ///
+    /// A thing stays in memory while it is part of a `Things` container or any
+    /// `Thing` handle to it exists. The returned thing may already be dead, so
+    /// check `is_alive` if that matters.
+    pub fn upgrade(&self) -> Option<Thing<T, C>> {
+        self.inner.upgrade().map(|inner| Thing { inner })
+    }
+}
 
-    fn ne(&self, other: &T) -> bool {
-        self.access(|data| data != other)
+impl<T, C> Clone for WeakThing<T, C> {
+    fn clone(&self) -> Self {
+        WeakThing {
+            inner: self.inner.clone(),
+        }
     }
 }
 
@@ -431,92 +1422,102 @@ impl<T: PartialEq, C: PartialEq> PartialEq<T> for Thing<T, C> {
 ///     // Bob is being followed
 /// }
 /// ```
-pub struct Connection<T: PartialEq, C: PartialEq> {
+pub struct Connection<T, C> {
     inner: Rc<RefCell<ConnectionInner<T, C>>>,
 }
 
-enum ConnectionInner<T: PartialEq, C: PartialEq> {
-    Directed {
-        from: Thing<T, C>,
-        to: Thing<T, C>,
-        data: C,
-        is_alive: bool,
-    },
-    Undirected {
-        things: [Thing<T, C>; 2],
-        data: C,
-        is_alive: bool,
-    },
+struct ConnectionInner<T, C> {
+    ends: Ends<T, C>,
+    data: C,
+    is_alive: bool,
+    seq: u64,
+    on_kill: KillCallbacks<C>,
+    weight: Option<u64>,
+    history: Option<Box<ConnectionHistory<C>>>,
 }
 
-impl<T: PartialEq, C: PartialEq> ConnectionInner<T, C> {
-    fn new_directed(from: Thing<T, C>, data: C, to: Thing<T, C>) -> Self {
-        Self::Directed {
-            from,
-            to,
-            data,
-            is_alive: true,
+/// The endpoints of a connection, and whether it has a direction.
+enum Ends<T, C> {
+    Directed { from: Thing<T, C>, to: Thing<T, C> },
+    Undirected { things: [Thing<T, C>; 2] },
+}
+
+/// The previous data values of a connection, most recent first, kept while
+/// the container records connection history.
+struct ConnectionHistory<C> {
+    depth: usize,
+    values: VecDeque<C>,
+}
+
+impl<C> ConnectionHistory<C> {
+    fn new(depth: usize) -> Self {
+        ConnectionHistory {
+            depth,
+            values: VecDeque::new(),
+        }
+    }
+
+    fn record(&mut self, old: C) {
+        if self.depth == 0 {
+            return;
         }
+        self.values.truncate(self.depth - 1);
+        self.values.push_front(old);
+    }
+}
+
+impl<T, C> ConnectionInner<T, C> {
+    fn new_directed(from: Thing<T, C>, data: C, to: Thing<T, C>) -> Self {
+        Self::new(Ends::Directed { from, to }, data)
     }
 
     fn new_undirected(things: [Thing<T, C>; 2], data: C) -> Self {
-        Self::Undirected {
-            things,
+        Self::new(Ends::Undirected { things }, data)
+    }
+
+    fn new(ends: Ends<T, C>, data: C) -> Self {
+        ConnectionInner {
+            ends,
             data,
             is_alive: true,
+            seq: 0,
+            on_kill: Vec::new(),
+            weight: None,
+            history: None,
         }
     }
 
+    fn is_directed(&self) -> bool {
+        matches!(self.ends, Ends::Directed { .. })
+    }
+
     fn get_things(&self) -> [Thing<T, C>; 2] {
-        match self {
-            &ConnectionInner::Directed {
-                ref from, ref to, ..
-            } => [from.clone(), to.clone()],
-            &ConnectionInner::Undirected { ref things, .. } => {
-                [things[0].clone(), things[1].clone()]
-            }
-        }
+        let [first, second] = self.end_refs();
+        [first.clone(), second.clone()]
     }
 
-    fn get_data(&self) -> &C {
-        match self {
-            &ConnectionInner::Directed { ref data, .. } => data,
-            &ConnectionInner::Undirected { ref data, .. } => data,
+    /// Both endpoints, source first for a directed connection.
+    fn end_refs(&self) -> [&Thing<T, C>; 2] {
+        match &self.ends {
+            Ends::Directed { from, to } => [from, to],
+            Ends::Undirected { things } => [&things[0], &things[1]],
         }
     }
 
-    fn get_data_mut(&mut self) -> &mut C {
-        match self {
-            &mut ConnectionInner::Directed { ref mut data, .. } => data,
-            &mut ConnectionInner::Undirected { ref mut data, .. } => data,
-        }
+    fn get_data(&self) -> &C {
+        &self.data
     }
 
-    fn contains(&self, thing: &Thing<T, C>) -> bool {
-        match &self {
-            &ConnectionInner::Directed { from, to, .. } => {
-                if (from == thing) || (to == thing) {
-                    true
-                } else {
-                    false
-                }
-            }
-            &ConnectionInner::Undirected { things, .. } => {
-                if (&things[0] == thing) || (&things[1] == thing) {
-                    true
-                } else {
-                    false
-                }
-            }
-        }
+    fn get_data_mut(&mut self) -> &mut C {
+        &mut self.data
     }
 
     fn get_direction_relative_to(&self, thing: &Thing<T, C>) -> Result<Direction, ()> {
-        match &self {
-            &ConnectionInner::Directed { from, to, .. } => {
-                if thing == from {
+        match &self.ends {
+            Ends::Directed { from, to } => {
+                if thing.ptr_eq(from) {
                     Ok(Direction::AwayFrom)
-                } else if thing == to {
+                } else if thing.ptr_eq(to) {
                     Ok(Direction::Towards)
                 } else {
                     Err(())
@@ -527,75 +1528,157 @@ impl<T: PartialEq, C: PartialEq> ConnectionInner<T, C> {
     }
 
     fn points_away_from(&self, thing: &Thing<T, C>) -> bool {
-        if let Ok(Direction::AwayFrom) = self.get_direction_relative_to(thing) {
-            true
-        } else {
-            false
-        }
+        matches!(
+            self.get_direction_relative_to(thing),
+            Ok(Direction::AwayFrom)
+        )
     }
 
     fn points_towards(&self, thing: &Thing<T, C>) -> bool {
-        if let Ok(Direction::Towards) = self.get_direction_relative_to(thing) {
-            true
+        matches!(
+            self.get_direction_relative_to(thing),
+            Ok(Direction::Towards)
+        )
+    }
+
+    fn get_other_thing(&self, thing: &Thing<T, C>) -> Result<Thing<T, C>, ()> {
+        self.other_end(thing).ok_or(())
+    }
+
+    /// Resolves the endpoint opposite to `thing`, comparing by identity and
+    /// ignoring direction.
+    fn other_end(&self, thing: &Thing<T, C>) -> Option<Thing<T, C>> {
+        let [first, second] = self.end_refs();
+        if first.ptr_eq(thing) {
+            Some(second.clone())
+        } else if second.ptr_eq(thing) {
+            Some(first.clone())
         } else {
-            false
+            None
         }
     }
 
-    fn get_other_thing(&self, thing: &Thing<T, C>) -> Result<Thing<T, C>, ()> {
-        match &self {
-            &ConnectionInner::Directed { from, to, .. } => {
-                if thing == from {
-                    Ok(to.clone())
-                } else if thing == to {
-                    Ok(from.clone())
-                } else {
-                    Err(())
-                }
+    /// Points every end that is `old` (by identity) at `new` instead.
+    fn replace_end(&mut self, old: &Thing<T, C>, new: &Thing<T, C>) {
+        let ends = match &mut self.ends {
+            Ends::Directed { from, to } => [from, to],
+            Ends::Undirected { things } => {
+                let [first, second] = things;
+                [first, second]
+            }
+        };
+        for end in ends {
+            if end.ptr_eq(old) {
+                *end = new.clone();
             }
-            &ConnectionInner::Undirected { things, .. } => {
-                if thing == &things[0] {
-                    Ok(things[1].clone())
-                } else if thing == &things[1] {
-                    Ok(things[0].clone())
+        }
+    }
+
+    /// Resolves the thing reached by traversing this connection from `thing`,
+    /// comparing endpoints by identity and respecting direction.
+    fn step_from(&self, thing: &Thing<T, C>) -> Option<Thing<T, C>> {
+        match &self.ends {
+            Ends::Directed { from, to } => {
+                if from.ptr_eq(thing) {
+                    Some(to.clone())
                 } else {
-                    Err(())
+                    None
                 }
             }
+            Ends::Undirected { .. } => self.other_end(thing),
         }
     }
 
     fn is_alive(&self) -> bool {
-        match self {
-            &ConnectionInner::Directed { is_alive, .. } => is_alive,
-            &ConnectionInner::Undirected { is_alive, .. } => is_alive,
+        self.is_alive
+    }
+
+    /// Marks the connection as dead.
+    ///
+    /// # Returns
+    /// The kill callbacks to run, or none if it was already dead.
+    fn kill(&mut self) -> KillCallbacks<C> {
+        if core::mem::replace(&mut self.is_alive, false) {
+            core::mem::take(&mut self.on_kill)
+        } else {
+            Vec::new()
         }
     }
 
-    fn kill(&mut self) {
-        match self {
-            &mut ConnectionInner::Directed {
-                ref mut is_alive, ..
-            } => {
-                *is_alive = false;
-            }
-            &mut ConnectionInner::Undirected {
-                ref mut is_alive, ..
-            } => {
-                *is_alive = false;
-            }
+    fn weight_mut(&mut self) -> &mut Option<u64> {
+        &mut self.weight
+    }
+
+    fn history(&self) -> Option<&ConnectionHistory<C>> {
+        self.history.as_deref()
+    }
+
+    fn history_mut(&mut self) -> &mut Option<Box<ConnectionHistory<C>>> {
+        &mut self.history
+    }
+
+    fn on_kill_mut(&mut self) -> &mut KillCallbacks<C> {
+        &mut self.on_kill
+    }
+
+    fn seq(&self) -> u64 {
+        self.seq
+    }
+
+    fn seq_mut(&mut self) -> &mut u64 {
+        &mut self.seq
+    }
+}
+
+impl<T: PartialEq, C> ConnectionInner<T, C> {
+    fn contains(&self, thing: &Thing<T, C>) -> bool {
+        let [first, second] = self.end_refs();
+        (first == thing) || (second == thing)
+    }
+}
+
+/// A connection as seen from one of its endpoints, created by `Connection::perspective`.
+pub struct EdgeView<T, C> {
+    /// The endpoint the connection is viewed from.
+    pub near: Thing<T, C>,
+    /// The other endpoint. For a self-loop this is `near` again.
+    pub far: Thing<T, C>,
+    /// Whether the connection is directed.
+    pub directed: bool,
+    /// Whether the connection can be followed from `near` to `far`: true for
+    /// undirected connections, and for directed connections leaving `near`.
+    pub outgoing: bool,
+}
+
+impl<T, C> Clone for EdgeView<T, C> {
+    fn clone(&self) -> Self {
+        EdgeView {
+            near: self.near.clone(),
+            far: self.far.clone(),
+            directed: self.directed,
+            outgoing: self.outgoing,
         }
     }
 }
 
+/// An owned copy of one thing and its neighborhood, created by `Thing::snapshot`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct ThingSnapshot<T, C> {
+    /// The data of the thing.
+    pub data: T,
+    /// One entry per live connection: its data, the data of the neighbor it
+    /// leads to, and its direction relative to the thing (`None` if undirected).
+    pub edges: Vec<(C, T, Option<Direction>)>,
+}
+
 /// Used to check whether a connection is directed towards or away from a thing.
-#[derive(PartialEq, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub enum Direction {
     Towards,
     AwayFrom,
 }
 
-impl<T: PartialEq, C: PartialEq> Connection<T, C> {
+impl<T, C> Connection<T, C> {
     /// Creates a new directed connection from one thing to another.
     ///
     /// Directed connections represent asymmetric relationships. The order matters:
@@ -656,16 +1739,27 @@ impl<T: PartialEq, C: PartialEq> Connection<T, C> {
     /// Use this to determine the type of relationship before accessing
     /// directional properties.
     pub fn is_directed(&self) -> bool {
-        let inner = self.inner.borrow();
-        matches!(*inner, ConnectionInner::Directed { .. })
+        self.inner.borrow().is_directed()
     }
 
     /// Returns true if this is an undirected connection.
     ///
     /// Undirected connections represent symmetric relationships.
     pub fn is_undirected(&self) -> bool {
-        let inner = self.inner.borrow();
-        matches!(*inner, ConnectionInner::Undirected { .. })
+        !self.inner.borrow().is_directed()
+    }
+
+    /// Returns the id this connection was given when it was added to a container.
+    ///
+    /// Ids never change, survive `Things::clean`, and are not reused, so they
+    /// can be resolved again with `Things::get_connection_by_id`. They are
+    /// only unique within one container. `Things::to_record` keeps them and
+    /// `Things::from_record` restores them, so an id written out next to a
+    /// record with `ConnectionId::as_u64` and read back with
+    /// `ConnectionId::from_raw` still finds its connection in the restored
+    /// graph.
+    pub fn id(&self) -> ConnectionId {
+        ConnectionId(self.seq())
     }
 
     /// Provides read-only access to this connection's data.
@@ -688,11 +1782,23 @@ impl<T: PartialEq, C: PartialEq> Connection<T, C> {
     /// Provides mutable access to this connection's data.
     ///
     /// Allows modification of the relationship data while maintaining safety.
+    /// Changes made here aren't recorded in the connection history, see
+    /// `Things::record_connection_history`; use `set` or `replace` for that.
     pub fn access_mut<R>(&self, access: impl Fn(&mut C) -> R) -> R {
         let mut inner = self.inner.borrow_mut();
         access(inner.get_data_mut())
     }
 
+    /// Returns a copy of this connection's data.
+    ///
+    /// See `Thing::get_copy`.
+    pub fn get_copy(&self) -> C
+    where
+        C: Copy,
+    {
+        *self.inner.borrow().get_data()
+    }
+
     /// Returns the two things connected by this connection.
     ///
     /// For directed connections, returns [from, to]. For undirected connections,
@@ -701,8 +1807,12 @@ impl<T: PartialEq, C: PartialEq> Connection<T, C> {
     /// # Returns
     /// An array containing exactly two things.
     pub fn get_things(&self) -> [Thing<T, C>; 2] {
-        let inner = self.inner.borrow();
-        inner.get_things().clone()
+        let things = self.ends();
+        #[cfg(feature = "dead-access-audit")]
+        for thing in things.iter() {
+            thing.audit_if_dead("Connection::get_things");
+        }
+        things
     }
 
     /// Returns the source thing in a directed connection.
@@ -725,13 +1835,25 @@ impl<T: PartialEq, C: PartialEq> Connection<T, C> {
     ///     println!("Found the parent");
     /// }
     /// ```
+    ///
+    /// This is the raw accessor, which returns the source even if it is dead.
+    /// Use `get_directed_from_alive` to skip dead things.
     pub fn get_directed_from(&self) -> Option<Thing<T, C>> {
-        let inner = self.inner.borrow();
-        if self.is_directed() {
-            Some(inner.get_things()[0].clone())
-        } else {
-            None
+        let from = self.directed_ends().map(|(from, _)| from);
+        #[cfg(feature = "dead-access-audit")]
+        if let Some(from) = &from {
+            from.audit_if_dead("Connection::get_directed_from");
         }
+        from
+    }
+
+    /// Returns the source thing in a directed connection, if it is alive.
+    ///
+    /// Like `get_directed_from`, but returns `None` when the source is dead.
+    pub fn get_directed_from_alive(&self) -> Option<Thing<T, C>> {
+        self.directed_ends()
+            .map(|(from, _)| from)
+            .filter(|thing| thing.is_alive())
     }
 
     /// Returns the target thing in a directed connection.
@@ -754,17 +1876,32 @@ impl<T: PartialEq, C: PartialEq> Connection<T, C> {
     ///     println!("Found the child");
     /// }
     /// ```
+    ///
+    /// This is the raw accessor, which returns the target even if it is dead.
+    /// Use `get_directed_towards_alive` to skip dead things.
     pub fn get_directed_towards(&self) -> Option<Thing<T, C>> {
-        let inner = self.inner.borrow();
-        if self.is_directed() {
-            Some(inner.get_things()[1].clone())
-        } else {
-            None
+        let to = self.directed_ends().map(|(_, to)| to);
+        #[cfg(feature = "dead-access-audit")]
+        if let Some(to) = &to {
+            to.audit_if_dead("Connection::get_directed_towards");
         }
+        to
+    }
+
+    /// Returns the target thing in a directed connection, if it is alive.
+    ///
+    /// Like `get_directed_towards`, but returns `None` when the target is dead.
+    pub fn get_directed_towards_alive(&self) -> Option<Thing<T, C>> {
+        self.directed_ends()
+            .map(|(_, to)| to)
+            .filter(|thing| thing.is_alive())
     }
 
     /// Tells you whether a thing is part of a connection.
     ///
+    /// Things are compared by their data, so a different thing holding equal
+    /// data also counts as part of the connection.
+    ///
     /// # Example
     ///
     /// ```rust
@@ -778,13 +1915,19 @@ impl<T: PartialEq, C: PartialEq> Connection<T, C> {
     /// assert!(ab.contains(&b));
     ///
     /// ```
-    pub fn contains(&self, thing: &Thing<T, C>) -> bool {
+    pub fn contains(&self, thing: &Thing<T, C>) -> bool
+    where
+        T: PartialEq,
+    {
         let inner = self.inner.borrow();
         inner.contains(thing)
     }
 
     /// Reveals whether a thing is the target or source of the directed connection.
     ///
+    /// The thing is matched by identity, so it must be a handle to one of the
+    /// connection's own endpoints.
+    ///
     /// # Returns
     /// - `Ok(Direction)`: The direction if the connection is directed and the thing is part of the connection.
     /// - `Err(())`: If the above conditions were not satisfied.
@@ -800,6 +1943,7 @@ impl<T: PartialEq, C: PartialEq> Connection<T, C> {
     ///
     /// assert_eq!(Direction::AwayFrom, comparison.get_direction_relative_to(&apples).unwrap());
     /// ```
+    #[allow(clippy::result_unit_err)]
     pub fn get_direction_relative_to(&self, thing: &Thing<T, C>) -> Result<Direction, ()> {
         let inner = self.inner.borrow();
         inner.get_direction_relative_to(thing)
@@ -810,7 +1954,7 @@ impl<T: PartialEq, C: PartialEq> Connection<T, C> {
     /// # Returns
     /// - `true`: If `connection.get_direction_relative_to(&thing)` returns `Ok(Direction::AwayFrom)`.
     /// - `false`: Otherwise
-    pub fn points_away_from(&self, thing: &Thing<T,C>) -> bool {
+    pub fn points_away_from(&self, thing: &Thing<T, C>) -> bool {
         let inner = self.inner.borrow();
         inner.points_away_from(thing)
     }
@@ -820,13 +1964,16 @@ impl<T: PartialEq, C: PartialEq> Connection<T, C> {
     /// # Returns
     /// - `true`: If `connection.get_direction_relative_to(&thing)` returns `Ok(Direction::Towards)`.
     /// - `false`: Otherwise
-    pub fn points_towards(&self, thing: &Thing<T,C>) -> bool {
+    pub fn points_towards(&self, thing: &Thing<T, C>) -> bool {
         let inner = self.inner.borrow();
         inner.points_towards(thing)
     }
 
     /// Finds the thing at the other end of a connection.
     ///
+    /// The thing is matched by identity, so it must be a handle to one of the
+    /// connection's own endpoints.
+    ///
     /// # Returns
     /// - `Ok(Thing<T,C>)`: The other thing if the argument is part of the connection.
     /// - `Err(())`: Otherwise.
@@ -842,54 +1989,303 @@ impl<T: PartialEq, C: PartialEq> Connection<T, C> {
     ///
     /// assert!(thing2 == connection.get_other_thing(&thing1).unwrap());
     /// ```
+    ///
+    /// This is the raw accessor, which returns the other thing even if it is
+    /// dead. Use `get_other_thing_alive` to skip dead things.
+    #[allow(clippy::result_unit_err)]
     pub fn get_other_thing(&self, thing: &Thing<T, C>) -> Result<Thing<T, C>, ()> {
-        let inner = self.inner.borrow();
-        inner.get_other_thing(thing)
+        let other = self.inner.borrow().get_other_thing(thing);
+        #[cfg(feature = "dead-access-audit")]
+        if let Ok(other) = &other {
+            other.audit_if_dead("Connection::get_other_thing");
+        }
+        other
     }
 
-    /// Returns whether this connection is still alive (not marked for deletion).
-    fn is_alive(&self) -> bool {
-        let inner = self.inner.borrow();
-        inner.is_alive()
+    /// Finds the thing at the other end of a connection, if it is alive.
+    ///
+    /// # Returns
+    /// - `Some(thing)`: The other thing, if the argument is part of the connection and the other thing is alive
+    /// - `None`: Otherwise
+    pub fn get_other_thing_alive(&self, thing: &Thing<T, C>) -> Option<Thing<T, C>> {
+        self.inner
+            .borrow()
+            .get_other_thing(thing)
+            .ok()
+            .filter(|other| other.is_alive())
     }
 
-    /// Marks this connection as dead.
+    /// Describes this connection as seen from one of its endpoints.
     ///
-    /// Unlike thing.kill(), connection.kill() only affects the connection itself,
-    /// not the things it connects. This represents the semantic that a relationship
-    /// can end without the entities ceasing to exist.
-    fn kill(&self) {
-        let mut inner = self.inner.borrow_mut();
-        inner.kill();
+    /// The view names the endpoint you are standing on (`near`), the other one
+    /// (`far`), and whether the connection can be followed from near to far, so
+    /// navigation code can treat directed and undirected connections alike.
+    /// The endpoint is matched by identity.
+    ///
+    /// # Returns
+    /// - `Some(view)`: If `from` is one of the connection's endpoints
+    /// - `None`: Otherwise
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let parent = Thing::new("parent");
+    /// let child = Thing::new("child");
+    /// let edge = Connection::new_directed(parent.clone(), "parent_of", child.clone());
+    ///
+    /// let view = edge.perspective(&child).unwrap();
+    /// assert!(view.far == "parent");
+    /// assert!(!view.outgoing);
+    /// ```
+    pub fn perspective(&self, from: &Thing<T, C>) -> Option<EdgeView<T, C>> {
+        let inner = self.inner.borrow();
+        let far = inner.other_end(from)?;
+        let directed = inner.is_directed();
+        Some(EdgeView {
+            near: from.clone(),
+            outgoing: !directed || inner.points_away_from(from),
+            far,
+            directed,
+        })
     }
-}
 
-impl<T: PartialEq, C: PartialEq> Clone for Connection<T, C> {
-    /// Creates a new reference to the same connection.
+    /// Registers a callback to run when this connection is killed.
     ///
-    /// This is a shallow clone - both instances refer to the same underlying
-    /// connection data and connected things.
-    fn clone(&self) -> Self {
-        Connection {
+    /// Works like `Thing::on_kill`: the callback runs exactly once, when the
+    /// connection dies directly or because one of its things was killed, and
+    /// must not modify the graph.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// # use std::cell::Cell;
+    /// # use std::rc::Rc;
+    /// let mut network = Things::<&str, &str>::new();
+    /// let client = network.new_thing("client");
+    /// let server = network.new_thing("server");
+    /// let socket = network.new_directed_connection(client, "socket", server);
+    ///
+    /// let open = Rc::new(Cell::new(true));
+    /// let flag = open.clone();
+    /// socket.on_kill(move |_| flag.set(false));
+    ///
+    /// network.kill_things(|host| *host == "server");
+    /// assert!(!open.get());
+    /// ```
+    pub fn on_kill(&self, callback: impl FnOnce(&C) + 'static) {
+        if self.is_alive() {
+            self.inner
+                .borrow_mut()
+                .on_kill_mut()
+                .push(Box::new(callback));
+        } else {
+            callback(self.inner.borrow().get_data());
+        }
+    }
+
+    /// Annotates this connection with a traversal weight.
+    ///
+    /// The weight is stored next to the data rather than in it, so algorithms
+    /// can be given costs even when `C` can't hold them. Cost based searches
+    /// read it when passed `CostSource::Annotation`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let road = Connection::new_undirected([Thing::new("a"), Thing::new("b")], ());
+    /// assert_eq!(road.weight(), None);
+    ///
+    /// road.set_weight(7);
+    /// assert_eq!(road.weight(), Some(7));
+    /// ```
+    pub fn set_weight(&self, weight: u64) {
+        *self.inner.borrow_mut().weight_mut() = Some(weight);
+    }
+
+    /// Replaces this connection's data.
+    ///
+    /// If its container records connection history, the old data is added to
+    /// the history. See `Things::record_connection_history`.
+    pub fn set(&self, data: C) {
+        self.swap_data(data, |old, history| {
+            if let Some(history) = history {
+                history.record(old);
+            }
+        });
+    }
+
+    /// Replaces this connection's data, returning the old data.
+    ///
+    /// Like `set`, the old data is added to the history if its container
+    /// records connection history.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut graph = Things::<&str, &str>::new();
+    /// graph.record_connection_history(true);
+    /// let a = graph.new_thing("a");
+    /// let b = graph.new_thing("b");
+    /// let link = graph.new_undirected_connection([a, b], "met");
+    ///
+    /// assert_eq!(link.replace("dating"), "met");
+    /// link.set("married");
+    /// assert_eq!(link.history(), ["dating", "met"]);
+    /// ```
+    pub fn replace(&self, data: C) -> C
+    where
+        C: Clone,
+    {
+        self.swap_data(data, |old, history| {
+            if let Some(history) = history {
+                history.record(old.clone());
+            }
+            old
+        })
+    }
+
+    /// Puts `data` in place of the current data, handing the old data and
+    /// the history, if one is kept, to `finish`.
+    fn swap_data<R>(
+        &self,
+        data: C,
+        finish: impl FnOnce(C, Option<&mut ConnectionHistory<C>>) -> R,
+    ) -> R {
+        let mut inner = self.inner.borrow_mut();
+        let old = core::mem::replace(inner.get_data_mut(), data);
+        finish(old, inner.history_mut().as_deref_mut())
+    }
+
+    /// Returns the previous data values of this connection, most recent first.
+    ///
+    /// Empty unless its container records connection history.
+    pub fn history(&self) -> Vec<C>
+    where
+        C: Clone,
+    {
+        match self.inner.borrow().history() {
+            Some(history) => history.values.iter().cloned().collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Forgets the previous data values of this connection.
+    ///
+    /// Recording continues if its container still records connection history.
+    pub fn clear_history(&self) {
+        if let Some(history) = self.inner.borrow_mut().history_mut() {
+            history.values.clear();
+        }
+    }
+
+    /// Returns the weight set with `set_weight`, if any.
+    pub fn weight(&self) -> Option<u64> {
+        self.inner.borrow().weight
+    }
+
+    /// Returns whether this connection is still alive (not marked for deletion).
+    fn is_alive(&self) -> bool {
+        let inner = self.inner.borrow();
+        inner.is_alive()
+    }
+
+    /// Marks this connection as dead.
+    ///
+    /// Unlike thing.kill(), connection.kill() only affects the connection itself,
+    /// not the things it connects. This represents the semantic that a relationship
+    /// can end without the entities ceasing to exist.
+    fn kill(&self) {
+        let callbacks = self.inner.borrow_mut().kill();
+        if !callbacks.is_empty() {
+            let inner = self.inner.borrow();
+            for callback in callbacks {
+                callback(inner.get_data());
+            }
+        }
+    }
+
+    /// Returns true if both handles refer to the same underlying connection.
+    ///
+    /// Unlike `==`, this compares identity rather than data.
+    fn ptr_eq(&self, other: &Connection<T, C>) -> bool {
+        Rc::ptr_eq(&self.inner, &other.inner)
+    }
+
+    /// Returns the source and target of a directed connection.
+    fn directed_ends(&self) -> Option<(Thing<T, C>, Thing<T, C>)> {
+        match &self.inner.borrow().ends {
+            Ends::Directed { from, to } => Some((from.clone(), to.clone())),
+            Ends::Undirected { .. } => None,
+        }
+    }
+
+    /// Returns true if both connections join the same things the same way:
+    /// in the same direction if both are directed, or in either order if both
+    /// are undirected. Things are compared by identity, liveness is ignored.
+    fn runs_parallel_to(&self, other: &Connection<T, C>) -> bool {
+        let directed = self.is_directed();
+        if directed != other.is_directed() {
+            return false;
+        }
+        let ([a, b], [c, d]) = (self.ends(), other.ends());
+        (a.ptr_eq(&c) && b.ptr_eq(&d)) || (!directed && a.ptr_eq(&d) && b.ptr_eq(&c))
+    }
+
+    /// Returns true if this is a live directed connection from `from` to `to`,
+    /// comparing endpoints by identity.
+    fn goes_between(&self, from: &Thing<T, C>, to: &Thing<T, C>) -> bool {
+        let inner = self.inner.borrow();
+        match &inner.ends {
+            Ends::Directed {
+                from: source,
+                to: target,
+            } => inner.is_alive && source.ptr_eq(from) && target.ptr_eq(to),
+            Ends::Undirected { .. } => false,
+        }
+    }
+
+    /// The position of this connection in its container's creation order.
+    fn seq(&self) -> u64 {
+        self.inner.borrow().seq()
+    }
+
+    /// Returns both endpoints, like `get_things` but without auditing them.
+    fn ends(&self) -> [Thing<T, C>; 2] {
+        self.inner.borrow().get_things().clone()
+    }
+
+    /// An identity key for this connection, used to index per-connection results.
+    fn key(&self) -> usize {
+        Rc::as_ptr(&self.inner) as *const () as usize
+    }
+}
+
+impl<T, C> Clone for Connection<T, C> {
+    /// Creates a new reference to the same connection.
+    ///
+    /// This is a shallow clone - both instances refer to the same underlying
+    /// connection data and connected things.
+    fn clone(&self) -> Self {
+        Connection {
             inner: self.inner.clone(),
         }
     }
 }
 
-impl<T: PartialEq, C: PartialEq> PartialEq for Connection<T, C> {
+impl<T, C: PartialEq> PartialEq for Connection<T, C> {
     fn eq(&self, other: &Self) -> bool {
         self.access(|data| other.access(|other_data| data == other_data))
     }
 }
 
-impl<T: PartialEq, C: PartialEq> PartialEq<C> for Connection<T, C> {
+impl<T, C: PartialEq> PartialEq<C> for Connection<T, C> {
     fn eq(&self, other: &C) -> bool {
         self.access(|data| data == other)
     }
-
-    fn ne(&self, other: &C) -> bool {
-        self.access(|data| data != other)
-    }
 }
 
 /// A container that manages a collection of things and their connections.
@@ -952,22 +2348,462 @@ impl<T: PartialEq, C: PartialEq> PartialEq<C> for Connection<T, C> {
 /// // Keep final results, clean up intermediate data
 /// graph.clean();
 /// ```
-pub struct Things<T: PartialEq, C: PartialEq> {
+pub struct Things<T, C> {
     things: Vec<Thing<T, C>>,
     connections: Vec<Connection<T, C>>,
     dead_amount: usize,
+    /// Sequence number handed to the next thing or connection created.
+    next_seq: u64,
+    /// Connections bucketed by class, once a classifier has been registered.
+    classes: Option<ConnectionClasses<T, C>>,
+    /// Bumped on every structural change, so derived data can tell it is stale.
+    generation: u64,
+    lookup_cache: LookupCache<T, C>,
+    config: ThingsConfig<T>,
+    /// The sink of an enabled dead access audit, shared with every thing.
+    #[cfg(feature = "dead-access-audit")]
+    audit: Option<AuditSink>,
+    /// Whether connections keep their previous data values.
+    record_history: bool,
+}
+
+/// The score one whole shortest path contributes in `Things::edge_betweenness_approx`.
+///
+/// Edge betweenness is a sum of fractions, so the scores are kept in fixed
+/// point: divide by this value to get the usual floating point score.
+pub const EDGE_BETWEENNESS_SCALE: u64 = 1_000_000;
+
+/// A thing and its live connections, as listed by `Things::adjacency_listing`.
+pub type AdjacencyRow<T, C> = (Thing<T, C>, Vec<Connection<T, C>>);
+
+/// A chain of two things and the connection between them, found by `Things::find_binding2`.
+pub type Binding2<T, C> = (Thing<T, C>, Connection<T, C>, Thing<T, C>);
+
+/// A chain of three things and the two connections between them, found by
+/// `Things::find_binding3`.
+pub type Binding3<T, C> = (
+    Thing<T, C>,
+    Connection<T, C>,
+    Thing<T, C>,
+    Connection<T, C>,
+    Thing<T, C>,
+);
+
+/// The new middle thing and the two connections through it, in path order,
+/// made by `Things::split_connection`.
+pub type SplitConnection<T, C> = (Thing<T, C>, Connection<T, C>, Connection<T, C>);
+
+/// The default number of lookups remembered by `Things::find_cached`.
+const LOOKUP_CACHE_CAPACITY: usize = 16;
+
+/// The default number of previous values kept per connection, see
+/// `ThingsConfig::connection_history_depth`.
+const DEFAULT_HISTORY_DEPTH: usize = 8;
+
+/// Remembers recent `Things::find_cached` results, most recently used first.
+struct LookupCache<T, C> {
+    entries: VecDeque<(Box<dyn Any>, Thing<T, C>)>,
+    capacity: usize,
+    /// The container generation the entries were recorded at.
+    generation: u64,
+}
+
+struct ConnectionClasses<T, C> {
+    classify: Box<dyn Fn(&C) -> u16>,
+    buckets: BTreeMap<u16, Vec<Connection<T, C>>>,
+}
+
+impl<T, C> ConnectionClasses<T, C> {
+    fn insert(&mut self, connection: Connection<T, C>) {
+        let class = connection.access(|data| (self.classify)(data));
+        self.buckets.entry(class).or_default().push(connection);
+    }
 }
 
-impl<T: PartialEq, C: PartialEq> Things<T, C> {
+impl<T, C> Things<T, C> {
     /// Creates a new, empty graph container.
     ///
     /// The container starts with no things, no connections, and zero dead items.
     pub fn new() -> Things<T, C> {
+        Self::with_config(ThingsConfig::new())
+    }
+
+    /// Creates an empty container that never holds more than `max_things`
+    /// things and `max_connections` connections.
+    ///
+    /// Nothing is reserved up front, as a limit may be far above what is ever
+    /// used; combine `ThingsConfig::max_things` and `max_connections` with
+    /// `thing_capacity` and `connection_capacity` in `with_config` to reserve
+    /// space as well. Items count towards the limits until
+    /// they are cleaned, not just until they are killed: call `clean` (or
+    /// enable `ThingsConfig::auto_clean_at`) to make room again. Once a limit
+    /// is reached, `try_new_thing` and the `try_new_*_connection` methods
+    /// return an error and the other constructors panic.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut graph = Things::<&str, ()>::with_limits(8, 16);
+    /// graph.new_thing("node");
+    /// assert_eq!(graph.remaining_capacity(), (7, 16));
+    /// ```
+    pub fn with_limits(max_things: usize, max_connections: usize) -> Things<T, C> {
+        Self::with_config(
+            ThingsConfig::new()
+                .max_things(max_things)
+                .max_connections(max_connections),
+        )
+    }
+
+    /// Returns how many more things and connections fit under the container's limits.
+    ///
+    /// Dead items that haven't been cleaned take up room. Without limits, the
+    /// result is close to `usize::MAX`.
+    pub fn remaining_capacity(&self) -> (usize, usize) {
+        (
+            self.config.max_things.saturating_sub(self.things.len()),
+            self.config
+                .max_connections
+                .saturating_sub(self.connections.len()),
+        )
+    }
+
+    /// Creates a new, empty graph container that follows the given configuration.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let config = ThingsConfig::new()
+    ///     .auto_clean_at(50)
+    ///     .reject_self_loops(true)
+    ///     .unique_things(true)
+    ///     .thing_capacity(1024);
+    /// let mut graph = Things::<&str, ()>::with_config(config);
+    ///
+    /// let first = graph.new_thing("alice");
+    /// let again = graph.new_thing("alice");
+    /// assert!(first == again);
+    /// assert!(graph.do_for_all_things(|thing| Do::Take(thing.clone())).len() == 1);
+    /// ```
+    pub fn with_config(config: ThingsConfig<T>) -> Things<T, C> {
         Things {
-            things: Vec::new(),
-            connections: Vec::new(),
+            things: Vec::with_capacity(config.thing_capacity),
+            connections: Vec::with_capacity(config.connection_capacity),
             dead_amount: 0,
+            // Sequence numbers start at 1 so that a cursor of 0 means "from the start"
+            next_seq: 1,
+            classes: None,
+            generation: 0,
+            lookup_cache: LookupCache {
+                entries: VecDeque::new(),
+                capacity: LOOKUP_CACHE_CAPACITY,
+                generation: 0,
+            },
+            config,
+            #[cfg(feature = "dead-access-audit")]
+            audit: None,
+            record_history: false,
+        }
+    }
+
+    /// Returns the configuration this container follows.
+    pub fn config(&self) -> &ThingsConfig<T> {
+        &self.config
+    }
+
+    /// Starts reporting every dead item handed out by a query or navigation method.
+    ///
+    /// This is a migration aid for code moving to alive-only semantics: it
+    /// finds the places that still see dead things and connections. `sink`
+    /// receives an event whenever
+    /// - `Things::do_for_a_thing`, `Things::do_for_all_things` or
+    ///   `Things::things_rev` passes on a dead thing
+    /// - `Things::do_for_a_connection`, `Things::do_for_all_connections`,
+    ///   `Thing::do_for_a_connection` or `Thing::do_for_all_connections`
+    ///   passes on a dead connection
+    /// - `Connection::get_things`, `get_directed_from`, `get_directed_towards`
+    ///   or `get_other_thing` returns a dead thing of this container
+    ///
+    /// Events carry the creation sequence number of the item, which is unique
+    /// within the container, and the name of the method. Methods that skip
+    /// dead items, such as the `_alive` variants, never report anything.
+    ///
+    /// Only available with the `dead-access-audit` feature; without it none
+    /// of the audit is compiled in. With it, a disabled audit costs one
+    /// pointer per thing and a branch per item handed out. The sink is called
+    /// in the middle of queries, so it must not modify the graph; audits
+    /// raised from inside the sink are dropped. Enabling again replaces the
+    /// previous sink.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// # use std::cell::RefCell;
+    /// # use std::rc::Rc;
+    /// let mut graph = Things::<&str, ()>::new();
+    /// graph.new_thing("gone");
+    /// graph.kill_things(|_| true);
+    ///
+    /// let events = Rc::new(RefCell::new(Vec::new()));
+    /// let log = events.clone();
+    /// graph.enable_dead_access_audit(move |event| log.borrow_mut().push(event));
+    ///
+    /// graph.do_for_all_things(|_| Do::Take(()));
+    /// assert_eq!(events.borrow()[0].item, AuditedItem::Thing);
+    /// assert_eq!(events.borrow()[0].operation, "Things::do_for_all_things");
+    /// ```
+    #[cfg(feature = "dead-access-audit")]
+    pub fn enable_dead_access_audit(&mut self, sink: impl FnMut(DeadAccessEvent) + 'static) {
+        let sink: AuditSink = Rc::new(RefCell::new(Box::new(sink)));
+        self.set_audit(Some(sink));
+    }
+
+    /// Stops the audit started by `enable_dead_access_audit`.
+    #[cfg(feature = "dead-access-audit")]
+    pub fn disable_dead_access_audit(&mut self) {
+        self.set_audit(None);
+    }
+
+    /// Turns recording of previous connection data on or off.
+    ///
+    /// While on, every connection of the container keeps the values its data
+    /// had before each `Connection::set` or `Connection::replace`, up to
+    /// `ThingsConfig::connection_history_depth` values, dropping the oldest
+    /// first. Read them with `Connection::history`. Changes made through
+    /// `Connection::access_mut` aren't recorded, which keeps that path as
+    /// cheap as without history. Turning recording off drops every recorded
+    /// value; while it is off connections carry no history at all.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let config = ThingsConfig::new().connection_history_depth(2);
+    /// let mut graph = Things::<&str, u32>::with_config(config);
+    /// let a = graph.new_thing("a");
+    /// let b = graph.new_thing("b");
+    /// let limit = graph.new_directed_connection(a, 10, b);
+    ///
+    /// graph.record_connection_history(true);
+    /// for value in [20, 30, 40] {
+    ///     limit.set(value);
+    /// }
+    /// assert_eq!(limit.history(), [30, 20]);
+    /// ```
+    pub fn record_connection_history(&mut self, record: bool) {
+        self.record_history = record;
+        let depth = self.config.connection_history_depth;
+        for connection in &self.connections {
+            let mut inner = connection.inner.borrow_mut();
+            let history = inner.history_mut();
+            if !record {
+                *history = None;
+            } else if history.is_none() {
+                *history = Some(Box::new(ConnectionHistory::new(depth)));
+            }
+        }
+    }
+
+    /// Hands the audit sink to the container and all of its things.
+    #[cfg(feature = "dead-access-audit")]
+    fn set_audit(&mut self, sink: Option<AuditSink>) {
+        for thing in &self.things {
+            thing.inner.borrow_mut().audit = sink.clone();
+        }
+        self.audit = sink;
+    }
+
+    /// Reports a connection handed out by the container to the dead access
+    /// audit if it is dead.
+    #[cfg(feature = "dead-access-audit")]
+    fn audit_dead_connection(&self, connection: &Connection<T, C>, operation: &'static str) {
+        if let Some(sink) = &self.audit
+            && !connection.is_alive()
+        {
+            let event = DeadAccessEvent {
+                item: AuditedItem::Connection,
+                id: connection.seq(),
+                operation,
+            };
+            report_dead_access(sink, event);
+        }
+    }
+
+    /// Replaces the configuration of this container.
+    ///
+    /// The new rules apply to everything created or killed from now on.
+    /// Existing things and connections are not checked against them, and a
+    /// new auto-clean threshold is first checked by the next call that adds
+    /// something. Capacities only ever grow the reserved space.
+    pub fn set_config(&mut self, config: ThingsConfig<T>) {
+        self.things
+            .reserve(config.thing_capacity.saturating_sub(self.things.len()));
+        self.connections.reserve(
+            config
+                .connection_capacity
+                .saturating_sub(self.connections.len()),
+        );
+        self.config = config;
+    }
+
+    /// Cleans the container if the configured auto-clean threshold is reached.
+    ///
+    /// This is the one place `ThingsConfig::auto_clean_at` takes effect, at
+    /// the start of every call that adds things or connections.
+    fn auto_clean(&mut self) {
+        if let Some(percent) = self.config.auto_clean_at
+            && self.dead_percentage().is_ok_and(|dead| dead >= percent)
+        {
+            self.clean();
+        }
+    }
+
+    /// Enforces the configured rules for a new connection between two things.
+    ///
+    /// Breaking the simple graph mode is only caught in debug builds.
+    fn check_new_connection(&self, first: &Thing<T, C>, second: &Thing<T, C>, directed: bool) {
+        if let Some(error) = self.connection_error(first, second, directed) {
+            self.refuse_connection(error);
+        }
+    }
+
+    /// Panics with the message for a broken connection rule, unless the rule
+    /// is only enforced in debug builds and this is a release build.
+    fn refuse_connection(&self, error: ConnectError) {
+        match error {
+            ConnectError::LimitReached => panic!(
+                "cannot create connection: the limit of {} connections is reached, clean dead connections first",
+                self.config.max_connections
+            ),
+            ConnectError::DeadEndpoint => {
+                panic!("cannot connect a dead thing when strict liveness is enabled")
+            }
+            ConnectError::SelfLoop if self.config.reject_self_loops => {
+                panic!("cannot connect a thing to itself when self-loops are rejected")
+            }
+            error if cfg!(debug_assertions) => {
+                panic!("connection not allowed in simple graph mode: {error:?}")
+            }
+            _ => {}
+        }
+    }
+
+    /// Checks the connections that contracting `connection` would keep
+    /// against the self-loop and simple graph rules, before anything changes.
+    fn check_contraction(
+        &self,
+        connection: &Connection<T, C>,
+        survivor: &Thing<T, C>,
+        absorbed: &Thing<T, C>,
+        loops: LoopPolicy,
+        parallels: ParallelPolicy,
+    ) {
+        let simple = self.config.mode == GraphMode::Simple;
+        let check_loops = loops == LoopPolicy::Keep && (simple || self.config.reject_self_loops);
+        let check_parallels = parallels == ParallelPolicy::Keep && simple;
+        if !check_loops && !check_parallels {
+            return;
+        }
+        // The ends a connection will have once the absorbed thing is merged
+        let moved_key = |other: &Connection<T, C>| {
+            let [first, second] = other.ends().map(|end| {
+                if end.ptr_eq(absorbed) {
+                    survivor.key()
+                } else {
+                    end.key()
+                }
+            });
+            let directed = other.is_directed();
+            if directed || first <= second {
+                (first, second, directed)
+            } else {
+                (second, first, directed)
+            }
+        };
+        let mut kept = BTreeSet::new();
+        for thing in [survivor, absorbed] {
+            for other in thing.inner.borrow().connections.iter() {
+                if !other.is_alive() || other.ptr_eq(connection) {
+                    continue;
+                }
+                let key = moved_key(other);
+                if key.0 == key.1 {
+                    // Connections to the other endpoint are checked once, as moved
+                    if thing.ptr_eq(absorbed) && check_loops {
+                        self.refuse_connection(ConnectError::SelfLoop);
+                    }
+                } else if !kept.insert(key) && check_parallels && thing.ptr_eq(absorbed) {
+                    self.refuse_connection(ConnectError::ParallelEdge);
+                }
+            }
+        }
+    }
+
+    /// Finds the first configured rule that a new connection between two
+    /// things would break.
+    fn connection_error(
+        &self,
+        first: &Thing<T, C>,
+        second: &Thing<T, C>,
+        directed: bool,
+    ) -> Option<ConnectError> {
+        if self.connections.len() >= self.config.max_connections {
+            return Some(ConnectError::LimitReached);
+        }
+        if self.config.strict_liveness && !(first.is_alive() && second.is_alive()) {
+            return Some(ConnectError::DeadEndpoint);
+        }
+        let simple = self.config.mode == GraphMode::Simple;
+        if (simple || self.config.reject_self_loops) && first.ptr_eq(second) {
+            return Some(ConnectError::SelfLoop);
+        }
+        if simple && has_parallel_connection(first, second, directed) {
+            return Some(ConnectError::ParallelEdge);
+        }
+        None
+    }
+
+    /// Records that the structure of the graph changed.
+    fn touch(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+    }
+
+    /// Hands out the next creation sequence number.
+    fn take_seq(&mut self) -> u64 {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        seq
+    }
+
+    /// Adds a thing to the container, stamping it with a sequence number.
+    fn register_thing(&mut self, thing: Thing<T, C>) {
+        let seq = self.take_seq();
+        let mut inner = thing.inner.borrow_mut();
+        inner.seq = seq;
+        #[cfg(feature = "dead-access-audit")]
+        {
+            inner.audit = self.audit.clone();
+        }
+        drop(inner);
+        self.things.push(thing);
+        self.touch();
+    }
+
+    /// Adds a connection to the container, stamping it with a sequence number.
+    fn register_connection(&mut self, connection: Connection<T, C>) {
+        *connection.inner.borrow_mut().seq_mut() = self.take_seq();
+        if self.record_history {
+            let history = ConnectionHistory::new(self.config.connection_history_depth);
+            *connection.inner.borrow_mut().history_mut() = Some(Box::new(history));
         }
+        if let Some(classes) = self.classes.as_mut() {
+            classes.insert(connection.clone());
+        }
+        self.connections.push(connection);
+        self.touch();
     }
 
     /// Creates a new thing with the provided data and adds it to the graph.
@@ -981,20 +2817,69 @@ impl<T: PartialEq, C: PartialEq> Things<T, C> {
     /// # Examples
     ///
     /// ```rust
+    /// # #[derive(PartialEq)]
     /// # struct DocumentData {
     /// #     title: &'static str,
     /// #     pages: usize
     /// # }
     /// # use connect_things::*;
-    /// # let mut graph1 = Things::new();
-    /// # let mut graph2 = Things::new();
+    /// # let mut graph1 = Things::<_, ()>::new();
+    /// # let mut graph2 = Things::<_, ()>::new();
     ///
     /// let person = graph1.new_thing("Alice");
     /// let document = graph2.new_thing(DocumentData { title: "Report", pages: 10 });
+    /// ```
+    ///
+    /// With `ThingsConfig::unique_things` enabled, a live thing with equal data
+    /// is returned instead of creating a duplicate, which takes a scan over all
+    /// the things in the container.
+    ///
+    /// # Panics
+    /// If the container already holds `ThingsConfig::max_things` things. Use
+    /// `try_new_thing` to handle this case instead.
     pub fn new_thing(&mut self, data: T) -> Thing<T, C> {
+        match self.try_new_thing(data) {
+            Ok(thing) => thing,
+            Err(LimitReached) => panic!(
+                "cannot create thing: the limit of {} things is reached, clean dead things first",
+                self.config.max_things
+            ),
+        }
+    }
+
+    /// Creates a new thing, unless the container is full.
+    ///
+    /// Dead things count towards `ThingsConfig::max_things` until they are
+    /// cleaned, so a full container can make room with `clean`.
+    ///
+    /// # Returns
+    /// - `Ok(thing)`: The new thing, or an equal existing one with `ThingsConfig::unique_things`
+    /// - `Err(LimitReached)`: If the container already holds as many things as allowed
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut sensors = Things::<u8, ()>::with_limits(1, 0);
+    /// assert!(sensors.try_new_thing(1).is_ok());
+    /// assert_eq!(sensors.try_new_thing(2).err(), Some(LimitReached));
+    /// ```
+    pub fn try_new_thing(&mut self, data: T) -> Result<Thing<T, C>, LimitReached> {
+        self.auto_clean();
+        if let Some(same) = self.config.same_data
+            && let Some(existing) = self
+                .things
+                .iter()
+                .find(|thing| thing.is_alive() && thing.access(|existing| same(existing, &data)))
+        {
+            return Ok(existing.clone());
+        }
+        if self.things.len() >= self.config.max_things {
+            return Err(LimitReached);
+        }
         let thing = Thing::<T, C>::new(data);
-        self.things.push(thing.clone());
-        thing
+        self.register_thing(thing.clone());
+        Ok(thing)
     }
 
     /// Creates a directed connection between two things.
@@ -1023,16 +2908,66 @@ impl<T: PartialEq, C: PartialEq> Things<T, C> {
     /// let follows = graph.new_directed_connection(alice, "follows", bob);
     /// let manages = graph.new_directed_connection(manager, "manages", employee);
     /// ```
+    ///
+    /// # Panics
+    /// If an endpoint is dead while `ThingsConfig::strict_liveness` is enabled,
+    /// or if `from` and `to` are the same thing while
+    /// `ThingsConfig::reject_self_loops` is enabled. In debug builds, also if
+    /// the connection isn't allowed in `GraphMode::Simple`. Use
+    /// `try_new_directed_connection` to handle these cases instead.
     pub fn new_directed_connection(
         &mut self,
         from: Thing<T, C>,
         data: C,
         to: Thing<T, C>,
     ) -> Connection<T, C> {
+        self.auto_clean();
+        self.check_new_connection(&from, &to, true);
+        self.attach_directed(from, data, to)
+    }
+
+    /// Creates a directed connection between two things, unless the
+    /// container's configuration forbids it.
+    ///
+    /// # Returns
+    /// - `Ok(connection)`: The new connection
+    /// - `Err(error)`: Which configured rule the connection would break
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut graph = Things::<&str, &str>::with_config(ThingsConfig::new().mode(GraphMode::Simple));
+    /// let a = graph.new_thing("a");
+    /// let b = graph.new_thing("b");
+    ///
+    /// assert!(graph.try_new_directed_connection(a.clone(), "first", b.clone()).is_ok());
+    /// assert_eq!(
+    ///     graph.try_new_directed_connection(a.clone(), "second", b.clone()).err(),
+    ///     Some(ConnectError::ParallelEdge)
+    /// );
+    /// // The opposite direction isn't parallel
+    /// assert!(graph.try_new_directed_connection(b, "back", a).is_ok());
+    /// ```
+    pub fn try_new_directed_connection(
+        &mut self,
+        from: Thing<T, C>,
+        data: C,
+        to: Thing<T, C>,
+    ) -> Result<Connection<T, C>, ConnectError> {
+        self.auto_clean();
+        match self.connection_error(&from, &to, true) {
+            Some(error) => Err(error),
+            None => Ok(self.attach_directed(from, data, to)),
+        }
+    }
+
+    /// Registers a new directed connection with both endpoints and the container.
+    fn attach_directed(&mut self, from: Thing<T, C>, data: C, to: Thing<T, C>) -> Connection<T, C> {
         let connection = Connection::<T, C>::new_directed(from.clone(), data, to.clone());
         unsafe { from.connect(connection.clone()) };
         unsafe { to.connect(connection.clone()) };
-        self.connections.push(connection.clone());
+        self.register_connection(connection.clone());
         connection
     }
 
@@ -1057,15 +2992,43 @@ impl<T: PartialEq, C: PartialEq> Things<T, C> {
     /// let friendship = graph.new_undirected_connection([alice, bob], "friendship");
     /// let similarity = graph.new_undirected_connection([doc1, doc2], "similar");
     /// ```
+    ///
+    /// # Panics
+    /// Under the same configuration rules as `new_directed_connection`.
     pub fn new_undirected_connection(
         &mut self,
         things: [Thing<T, C>; 2],
         data: C,
     ) -> Connection<T, C> {
+        self.auto_clean();
+        self.check_new_connection(&things[0], &things[1], false);
+        self.attach_undirected(things, data)
+    }
+
+    /// Creates an undirected connection between two things, unless the
+    /// container's configuration forbids it.
+    ///
+    /// # Returns
+    /// - `Ok(connection)`: The new connection
+    /// - `Err(error)`: Which configured rule the connection would break
+    pub fn try_new_undirected_connection(
+        &mut self,
+        things: [Thing<T, C>; 2],
+        data: C,
+    ) -> Result<Connection<T, C>, ConnectError> {
+        self.auto_clean();
+        match self.connection_error(&things[0], &things[1], false) {
+            Some(error) => Err(error),
+            None => Ok(self.attach_undirected(things, data)),
+        }
+    }
+
+    /// Registers a new undirected connection with both endpoints and the container.
+    fn attach_undirected(&mut self, things: [Thing<T, C>; 2], data: C) -> Connection<T, C> {
         let connection = Connection::<T, C>::new_undirected(things.clone(), data);
         unsafe { things[0].connect(connection.clone()) };
         unsafe { things[1].connect(connection.clone()) };
-        self.connections.push(connection.clone());
+        self.register_connection(connection.clone());
         connection
     }
 
@@ -1081,14 +3044,18 @@ impl<T: PartialEq, C: PartialEq> Things<T, C> {
     ///
     /// ```rust
     /// # use connect_things::*;
-    /// # let mut graph = Things::new();
+    /// # #[derive(PartialEq)]
+    /// # struct Person { name: &'static str }
+    /// # let mut graph = Things::<Person, ()>::new();
     ///
     /// let alice = graph.do_for_a_thing(|thing| {
-    ///     thing.access(|data| return if data.name == "Alice" { Do::Take(thing) } else { Do::Nothing })
+    ///     thing.access(|data| return if data.name == "Alice" { Do::Take(thing.clone()) } else { Do::Nothing })
     /// });
     /// ```
     pub fn do_for_a_thing<R>(&self, do_for: impl Fn(&Thing<T, C>) -> Do<R>) -> Option<R> {
         for thing in &self.things {
+            #[cfg(feature = "dead-access-audit")]
+            thing.audit_if_dead("Things::do_for_a_thing");
             if let Do::Take(value) = do_for(thing) {
                 return Some(value);
             }
@@ -1096,6 +3063,514 @@ impl<T: PartialEq, C: PartialEq> Things<T, C> {
         None
     }
 
+    /// Checks whether a live thing holds data equal to `data`.
+    ///
+    /// This is data-based: any live thing with equal data counts. Stops at the
+    /// first match.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut graph = Things::<&str, &str>::new();
+    /// graph.new_thing("Apple");
+    ///
+    /// assert!(graph.contains_data(&"Apple"));
+    /// assert!(!graph.contains_data(&"Pear"));
+    /// ```
+    pub fn contains_data(&self, data: &T) -> bool
+    where
+        T: PartialEq,
+    {
+        self.things
+            .iter()
+            .any(|thing| thing.is_alive() && thing == data)
+    }
+
+    /// Checks whether a live connection holds data equal to `data`.
+    ///
+    /// This is data-based: any live connection with equal data counts. Stops at
+    /// the first match.
+    pub fn contains_connection_data(&self, data: &C) -> bool
+    where
+        C: PartialEq,
+    {
+        self.connections
+            .iter()
+            .any(|connection| connection.is_alive() && connection == data)
+    }
+
+    /// Finds the first live thing, in container order, holding data equal to `value`.
+    ///
+    /// This is data-based: when several things hold equal data, the returned
+    /// handle is the first of them, not necessarily the one you have in mind.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut graph = Things::<&str, ()>::new();
+    /// graph.new_thing("twin");
+    /// let second = graph.new_thing("twin");
+    ///
+    /// // `found` is the first twin, which has the same data as `second`
+    /// let found = graph.find_thing_equal(&"twin").unwrap();
+    /// assert!(found == second);
+    /// assert!(graph.find_thing_equal(&"nobody").is_none());
+    /// ```
+    pub fn find_thing_equal(&self, value: &T) -> Option<Thing<T, C>>
+    where
+        T: PartialEq,
+    {
+        self.things
+            .iter()
+            .find(|thing| thing.is_alive() && *thing == value)
+            .cloned()
+    }
+
+    /// Finds the first live connection, in container order, holding data equal
+    /// to `value`.
+    ///
+    /// This is data-based, like `find_thing_equal`.
+    pub fn find_connection_equal(&self, value: &C) -> Option<Connection<T, C>>
+    where
+        C: PartialEq,
+    {
+        self.connections
+            .iter()
+            .find(|connection| connection.is_alive() && *connection == value)
+            .cloned()
+    }
+
+    /// Finds the first live thing, in container order, whose data matches
+    /// `matches`.
+    ///
+    /// The predicate gets the data directly, so there is no need to go through
+    /// `do_for_a_thing` and `Thing::access` for the most common lookup. Dead
+    /// things are skipped without being looked at.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut people = Things::<(&str, u32), ()>::new();
+    /// people.new_thing(("alice", 34));
+    /// people.new_thing(("bob", 19));
+    ///
+    /// let young = people.find_thing(|(_, age)| *age < 30).unwrap();
+    /// assert!(young == ("bob", 19));
+    /// assert!(people.find_thing(|(name, _)| *name == "carol").is_none());
+    /// ```
+    pub fn find_thing(&self, matches: impl Fn(&T) -> bool) -> Option<Thing<T, C>> {
+        self.things
+            .iter()
+            .find(|thing| thing.is_alive() && thing.access(&matches))
+            .cloned()
+    }
+
+    /// Finds every live thing whose data matches `matches`, in container order.
+    ///
+    /// See `find_thing`.
+    pub fn find_things(&self, matches: impl Fn(&T) -> bool) -> Vec<Thing<T, C>> {
+        self.things
+            .iter()
+            .filter(|thing| thing.is_alive() && thing.access(&matches))
+            .cloned()
+            .collect()
+    }
+
+    /// Finds the first live connection, in container order, whose data matches
+    /// `matches`.
+    ///
+    /// Like `find_thing`, for connections.
+    pub fn find_connection(&self, matches: impl Fn(&C) -> bool) -> Option<Connection<T, C>> {
+        self.connections
+            .iter()
+            .find(|connection| connection.is_alive() && connection.access(&matches))
+            .cloned()
+    }
+
+    /// Finds every live connection whose data matches `matches`, in container
+    /// order.
+    ///
+    /// Like `find_things`, for connections.
+    pub fn find_connections(&self, matches: impl Fn(&C) -> bool) -> Vec<Connection<T, C>> {
+        self.connections
+            .iter()
+            .filter(|connection| connection.is_alive() && connection.access(&matches))
+            .cloned()
+            .collect()
+    }
+
+    /// Resolves an id from `Connection::id` back to its connection.
+    ///
+    /// # Returns
+    /// The connection with that id, or `None` if it is dead or was never in
+    /// this container.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut graph = Things::<&str, &str>::new();
+    /// let a = graph.new_thing("a");
+    /// let b = graph.new_thing("b");
+    /// let id = graph.new_directed_connection(a, "next", b).id();
+    ///
+    /// assert!(graph.get_connection_by_id(id).unwrap() == "next");
+    /// graph.kill_connections(|_| true);
+    /// assert!(graph.get_connection_by_id(id).is_none());
+    /// ```
+    pub fn get_connection_by_id(&self, id: ConnectionId) -> Option<Connection<T, C>> {
+        self.connections
+            .iter()
+            .find(|connection| connection.is_alive() && connection.seq() == id.0)
+            .cloned()
+    }
+
+    /// Returns the things with a live directed connection to `target` whose
+    /// data matches `data_matches`.
+    ///
+    /// This answers questions like "who follows Alice" in one call. `target` is
+    /// matched by identity, and each source is listed once, in the order its
+    /// first matching connection was attached to `target`. Undirected
+    /// connections never match.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut social = Things::<&str, &str>::new();
+    /// let alice = social.new_thing("alice");
+    /// let bob = social.new_thing("bob");
+    /// let carol = social.new_thing("carol");
+    /// social.new_directed_connection(bob, "follows", alice.clone());
+    /// social.new_directed_connection(alice.clone(), "follows", carol);
+    ///
+    /// let followers = social.sources_of(&alice, |label| *label == "follows");
+    /// assert_eq!(followers.len(), 1);
+    /// assert!(followers[0] == "bob");
+    /// ```
+    pub fn sources_of(
+        &self,
+        target: &Thing<T, C>,
+        data_matches: impl Fn(&C) -> bool,
+    ) -> Vec<Thing<T, C>> {
+        target.other_endpoints(|connection| {
+            connection
+                .directed_ends()
+                .is_some_and(|(_, to)| to.ptr_eq(target))
+                && connection.access(&data_matches)
+        })
+    }
+
+    /// Returns the things that `source` has a live directed connection to
+    /// whose data matches `data_matches`.
+    ///
+    /// The mirror of `sources_of`, following outgoing connections instead.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut social = Things::<&str, &str>::new();
+    /// let alice = social.new_thing("alice");
+    /// let bob = social.new_thing("bob");
+    /// social.new_directed_connection(alice.clone(), "follows", bob);
+    ///
+    /// let followed = social.targets_of(&alice, |label| *label == "follows");
+    /// assert!(followed[0] == "bob");
+    /// ```
+    pub fn targets_of(
+        &self,
+        source: &Thing<T, C>,
+        data_matches: impl Fn(&C) -> bool,
+    ) -> Vec<Thing<T, C>> {
+        source.other_endpoints(|connection| {
+            connection
+                .directed_ends()
+                .is_some_and(|(from, _)| from.ptr_eq(source))
+                && connection.access(&data_matches)
+        })
+    }
+
+    /// Finds the first chain `first -edge-> second` matching the given predicates.
+    ///
+    /// Live things are tried as `first` in container order, and their live
+    /// connections in the order they were attached. Directed connections only
+    /// match from source to target; undirected ones match both ways. The
+    /// result is deterministic, so with several matches the first one wins.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut family = Things::<&str, &str>::new();
+    /// let anna = family.new_thing("anna");
+    /// let ben = family.new_thing("ben");
+    /// family.new_directed_connection(anna, "parent_of", ben);
+    ///
+    /// let (parent, _, child) = family
+    ///     .find_binding2(|_| true, |edge| *edge == "parent_of", |_| true)
+    ///     .unwrap();
+    /// assert!(parent == "anna" && child == "ben");
+    /// ```
+    pub fn find_binding2(
+        &self,
+        first: impl Fn(&Thing<T, C>) -> bool,
+        edge: impl Fn(&Connection<T, C>) -> bool,
+        second: impl Fn(&Thing<T, C>) -> bool,
+    ) -> Option<Binding2<T, C>> {
+        self.things
+            .iter()
+            .filter(|thing| thing.is_alive() && first(thing))
+            .find_map(|start| {
+                start
+                    .forward_steps()
+                    .into_iter()
+                    .find(|(connection, next)| edge(connection) && second(next))
+                    .map(|(connection, next)| (start.clone(), connection, next))
+            })
+    }
+
+    /// Finds the first chain `first -first_edge-> second -second_edge-> third`
+    /// matching the given predicates.
+    ///
+    /// Works like `find_binding2`, extended by one step. Things may be bound
+    /// more than once, which allows matching cycles through a predicate on
+    /// `third`, but the two connections are always different.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut family = Things::<&str, &str>::new();
+    /// let anna = family.new_thing("anna");
+    /// let ben = family.new_thing("ben");
+    /// let cleo = family.new_thing("cleo");
+    /// family.new_directed_connection(anna, "parent_of", ben.clone());
+    /// family.new_directed_connection(ben, "parent_of", cleo);
+    ///
+    /// let parent_of = |edge: &Connection<&str, &str>| *edge == "parent_of";
+    /// let (grandparent, _, _, _, grandchild) = family
+    ///     .find_binding3(|_| true, parent_of, |_| true, parent_of, |_| true)
+    ///     .unwrap();
+    /// assert!(grandparent == "anna" && grandchild == "cleo");
+    /// ```
+    pub fn find_binding3(
+        &self,
+        first: impl Fn(&Thing<T, C>) -> bool,
+        first_edge: impl Fn(&Connection<T, C>) -> bool,
+        second: impl Fn(&Thing<T, C>) -> bool,
+        second_edge: impl Fn(&Connection<T, C>) -> bool,
+        third: impl Fn(&Thing<T, C>) -> bool,
+    ) -> Option<Binding3<T, C>> {
+        self.things
+            .iter()
+            .filter(|thing| thing.is_alive() && first(thing))
+            .find_map(|start| {
+                start
+                    .forward_steps()
+                    .into_iter()
+                    .filter(|(connection, middle)| first_edge(connection) && second(middle))
+                    .find_map(|(first_connection, middle)| {
+                        middle
+                            .forward_steps()
+                            .into_iter()
+                            .find(|(connection, end)| {
+                                !connection.ptr_eq(&first_connection)
+                                    && second_edge(connection)
+                                    && third(end)
+                            })
+                            .map(|(second_connection, end)| {
+                                (
+                                    start.clone(),
+                                    first_connection,
+                                    middle,
+                                    second_connection,
+                                    end,
+                                )
+                            })
+                    })
+            })
+    }
+
+    /// Reorders the things in this container by comparing their data.
+    ///
+    /// The sort is stable, so things that compare equal keep their relative
+    /// order. Everything that iterates in container order, such as
+    /// `do_for_all_things`, follows the new order afterwards, which makes
+    /// traversals deterministic in a meaningful way (alphabetical, by priority).
+    ///
+    /// Positions change with the order, so positions taken from an earlier
+    /// `compile` no longer line up with the container. Creation order, as used by
+    /// `recent_things` and `things_rev`, is not affected.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut concepts = Things::<&str, ()>::new();
+    /// concepts.new_thing("pear");
+    /// concepts.new_thing("apple");
+    ///
+    /// concepts.sort_things_by(|a, b| a.cmp(b));
+    /// let first = concepts.do_for_a_thing(|thing| Do::Take(thing.clone())).unwrap();
+    /// assert!(first == "apple");
+    /// ```
+    pub fn sort_things_by(&mut self, cmp: impl Fn(&T, &T) -> core::cmp::Ordering) {
+        self.things
+            .sort_by(|a, b| a.access(|a| b.access(|b| cmp(a, b))));
+        self.touch();
+    }
+
+    /// Returns the live things ordered by a key computed from their data.
+    ///
+    /// Each key is computed exactly once, with a single borrow per thing, and
+    /// things with equal keys keep their creation order. Sort by a tuple key to
+    /// order by several criteria at once.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut catalog = Things::<(&str, &str), ()>::new();
+    /// catalog.new_thing(("fruit", "pear"));
+    /// catalog.new_thing(("dairy", "milk"));
+    /// catalog.new_thing(("fruit", "apple"));
+    ///
+    /// // By category, then by name
+    /// let sorted = catalog.sorted_things_by_key(|(category, name)| (*category, *name));
+    /// let names: Vec<_> = sorted.iter().map(|thing| thing.access(|data| data.1)).collect();
+    /// assert_eq!(names, ["milk", "apple", "pear"]);
+    /// ```
+    pub fn sorted_things_by_key<K: Ord>(&self, key: impl Fn(&T) -> K) -> Vec<Thing<T, C>> {
+        sort_by_extracted_key(
+            self.things.iter().filter(|thing| thing.is_alive()),
+            |thing| thing.access(&key),
+        )
+    }
+
+    /// Returns the live connections ordered by a key computed from their data.
+    ///
+    /// Like `sorted_things_by_key`, each key is computed exactly once and
+    /// connections with equal keys keep their creation order.
+    pub fn sorted_connections_by_key<K: Ord>(
+        &self,
+        key: impl Fn(&C) -> K,
+    ) -> Vec<Connection<T, C>> {
+        sort_by_extracted_key(
+            self.connections
+                .iter()
+                .filter(|connection| connection.is_alive()),
+            |connection| connection.access(&key),
+        )
+    }
+
+    /// Returns the live connections ordered by a comparator over the
+    /// connections themselves.
+    ///
+    /// Unlike `sorted_connections_by_key`, the comparator sees whole
+    /// connections, so it can order by `Connection::weight`, by endpoints, or
+    /// by anything else, which gives deterministic input for exports or for
+    /// algorithms such as Kruskal's. The sort is stable: connections that
+    /// compare equal keep their order in the container, which is creation
+    /// order until `clean_unordered` scrambles it. Break ties with
+    /// `Connection::id` when the order must not depend on the container.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut roads = Things::<&str, &str>::new();
+    /// let a = roads.new_thing("a");
+    /// let b = roads.new_thing("b");
+    /// roads.new_undirected_connection([a.clone(), b.clone()], "slow").set_weight(9);
+    /// roads.new_undirected_connection([a, b], "fast").set_weight(2);
+    ///
+    /// let sorted = roads.edges_sorted_by(|x, y| x.weight().cmp(&y.weight()));
+    /// assert!(sorted[0] == "fast" && sorted[1] == "slow");
+    /// ```
+    pub fn edges_sorted_by(
+        &self,
+        cmp: impl Fn(&Connection<T, C>, &Connection<T, C>) -> core::cmp::Ordering,
+    ) -> Vec<Connection<T, C>> {
+        let mut sorted: Vec<_> = self
+            .connections
+            .iter()
+            .filter(|connection| connection.is_alive())
+            .cloned()
+            .collect();
+        sorted.sort_by(|a, b| cmp(a, b));
+        sorted
+    }
+
+    /// Finds the first live thing matching `finder`, remembering the answer under `key`.
+    ///
+    /// Repeated lookups with an equal key are answered from a small cache of
+    /// the most recently used results without scanning the graph again, which
+    /// helps interactive code that keeps asking for the same few things. Only
+    /// successful lookups are remembered, and the least recently used entry is
+    /// dropped once the cache is full.
+    ///
+    /// The cache is cleared whenever the structure of the graph changes, such as
+    /// things or connections being created, killed or cleaned. Changes made to a
+    /// thing's data through `access_mut` are not noticed, so use a key that
+    /// describes what `finder` looks for and avoid caching on mutable data.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut graph = Things::<&str, ()>::new();
+    /// graph.new_thing("Alice");
+    ///
+    /// let alice = graph.find_cached("alice", |thing| thing == &"Alice");
+    /// // Answered from the cache, the finder is not called again
+    /// let again = graph.find_cached("alice", |_| unreachable!());
+    /// assert!(alice.is_some() && again.is_some());
+    /// ```
+    pub fn find_cached<K: PartialEq + 'static>(
+        &mut self,
+        key: K,
+        finder: impl Fn(&Thing<T, C>) -> bool,
+    ) -> Option<Thing<T, C>> {
+        let cache = &mut self.lookup_cache;
+        if cache.generation != self.generation {
+            cache.entries.clear();
+            cache.generation = self.generation;
+        }
+
+        let hit = cache
+            .entries
+            .iter()
+            .position(|(cached, _)| cached.downcast_ref::<K>() == Some(&key));
+        if let Some(position) = hit {
+            let entry = cache.entries.remove(position)?;
+            let thing = entry.1.clone();
+            cache.entries.push_front(entry);
+            return Some(thing);
+        }
+
+        let thing = self
+            .things
+            .iter()
+            .find(|thing| thing.is_alive() && finder(thing))?
+            .clone();
+        if cache.capacity > 0 {
+            cache.entries.truncate(cache.capacity - 1);
+            cache.entries.push_front((Box::new(key), thing.clone()));
+        }
+        Some(thing)
+    }
+
+    /// Sets how many lookups `find_cached` remembers, dropping the least
+    /// recently used ones if there are already more. Zero disables caching.
+    pub fn set_lookup_cache_capacity(&mut self, capacity: usize) {
+        self.lookup_cache.capacity = capacity;
+        self.lookup_cache.entries.truncate(capacity);
+    }
+
     /// Finds all things that match the given predicate.
     ///
     /// Useful for finding groups of related entities or filtering the graph
@@ -1106,6 +3581,8 @@ impl<T: PartialEq, C: PartialEq> Things<T, C> {
     pub fn do_for_all_things<R>(&self, get: impl Fn(&Thing<T, C>) -> Do<R>) -> Vec<R> {
         let mut things = Vec::new();
         for thing in &self.things {
+            #[cfg(feature = "dead-access-audit")]
+            thing.audit_if_dead("Things::do_for_all_things");
             if let Do::Take(value) = get(thing) {
                 things.push(value);
             }
@@ -1119,13 +3596,17 @@ impl<T: PartialEq, C: PartialEq> Things<T, C> {
     /// Dead items remain in memory until `clean()` is called, allowing for
     /// better performance during active graph manipulation.
     ///
-    /// The dead count is automatically updated to track memory pressure.
+    /// The dead count is automatically updated to track memory pressure. It is
+    /// kept up to date item by item, so if the predicate or a kill callback
+    /// panics, the things killed so far are still accounted for.
     ///
     /// # Examples
     ///
     /// ```rust
     /// # use connect_things::*;
-    /// # let mut graph = Things::new();
+    /// # #[derive(PartialEq)]
+    /// # struct Item { is_temporary: bool }
+    /// # let mut graph = Things::<Item, ()>::new();
     ///
     /// // Remove all temporary items
     /// graph.kill_things(|thing| {
@@ -1133,12 +3614,12 @@ impl<T: PartialEq, C: PartialEq> Things<T, C> {
     /// });
     /// ```
     pub fn kill_things(&mut self, kill: impl Fn(&Thing<T, C>) -> bool) {
-        self.things.iter().for_each(|thing| {
+        self.touch();
+        for thing in self.things.iter() {
             if kill(thing) {
-                let amount = thing.kill();
-                self.dead_amount = self.dead_amount.saturating_add(amount);
+                thing.kill(&mut self.dead_amount);
             }
-        });
+        }
     }
 
     /// Finds the first connection that matches the given predicate.
@@ -1149,17 +3630,16 @@ impl<T: PartialEq, C: PartialEq> Things<T, C> {
     ///
     /// ```rust
     /// # use connect_things::*;
-    /// # let mut graph = Things::new();
+    /// # let mut graph = Things::<&str, &str>::new();
     ///
     /// let friendship = graph.do_for_a_connection(|conn| {
-    ///     conn.access(|data| return if *data == "friendship" { Do::Take(conn) } else { Do::Nothing })
+    ///     conn.access(|data| return if *data == "friendship" { Do::Take(conn.clone()) } else { Do::Nothing })
     /// });
     /// ```
-    pub fn do_for_a_connection<'l, R>(
-        &self,
-        get: impl Fn(&Connection<T, C>) -> Do<R>,
-    ) -> Option<R> {
+    pub fn do_for_a_connection<R>(&self, get: impl Fn(&Connection<T, C>) -> Do<R>) -> Option<R> {
         for connection in &self.connections {
+            #[cfg(feature = "dead-access-audit")]
+            self.audit_dead_connection(connection, "Things::do_for_a_connection");
             if let Do::Take(value) = get(connection) {
                 return Some(value);
             }
@@ -1174,6 +3654,8 @@ impl<T: PartialEq, C: PartialEq> Things<T, C> {
     pub fn do_for_all_connections<R>(&self, found: impl Fn(&Connection<T, C>) -> Do<R>) -> Vec<R> {
         let mut connections = Vec::new();
         for connection in &self.connections {
+            #[cfg(feature = "dead-access-audit")]
+            self.audit_dead_connection(connection, "Things::do_for_all_connections");
             if let Do::Take(value) = found(connection) {
                 connections.push(value);
             }
@@ -1181,740 +3663,12689 @@ impl<T: PartialEq, C: PartialEq> Things<T, C> {
         connections
     }
 
-    /// Marks connections matching the predicate as dead.
+    /// Applies a mutation to the data of every live thing.
     ///
-    /// Unlike `kill_things`, this only affects the connections themselves,
-    /// not the things they connect. The connected things remain alive.
+    /// Dead things are left untouched. Each thing is borrowed mutably only
+    /// while `mutate` runs on its data.
     ///
     /// # Examples
     ///
     /// ```rust
     /// # use connect_things::*;
-    /// # let mut graph = Things::new();
+    /// let mut tasks = Things::<(&str, bool), ()>::new();
+    /// tasks.new_thing(("write docs", true));
+    /// tasks.new_thing(("fix bug", true));
     ///
-    /// // Remove all temporary relationships
-    /// graph.kill_connections(|conn| {
-    ///     conn.access(|data| data.is_temporary)
-    /// });
+    /// // Mark all tasks incomplete
+    /// tasks.access_all_things_mut(|(_, done)| *done = false);
+    /// assert!(tasks.do_for_all_things(|thing| {
+    ///     if thing.access(|(_, done)| *done) { Do::Take(()) } else { Do::Nothing }
+    /// }).is_empty());
     /// ```
-    pub fn kill_connections(&mut self, kill: impl Fn(&Connection<T, C>) -> bool) {
-        self.connections.iter().for_each(|connection| {
-            if kill(connection) {
-                connection.kill();
-                self.dead_amount = self.dead_amount.saturating_add(1);
-            }
-        });
+    pub fn access_all_things_mut(&mut self, mut mutate: impl FnMut(&mut T)) {
+        for thing in self.things.iter().filter(|thing| thing.is_alive()) {
+            mutate(&mut thing.inner.borrow_mut().data);
+        }
     }
 
-    /// Calculates the percentage of dead items relative to total items.
-    ///
-    /// This provides a "memory pressure" metric to help decide when cleanup
-    /// might be beneficial. The percentage represents how much of your graph's
-    /// memory is consumed by logically deleted items.
+    /// Applies a mutation to the data of every live connection.
     ///
-    /// # Returns
-    /// - `Ok(percentage)`: The percentage (0-100) of dead items
-    /// - `Err(())`: If the graph is empty (division by zero)
+    /// Dead connections are left untouched.
+    pub fn access_all_connections_mut(&mut self, mut mutate: impl FnMut(&mut C)) {
+        for connection in self
+            .connections
+            .iter()
+            .filter(|connection| connection.is_alive())
+        {
+            mutate(connection.inner.borrow_mut().get_data_mut());
+        }
+    }
+
+    /// Calls `visit` on every live thing, giving it mutable access to the container.
     ///
-    /// # Memory Pressure Guidelines
-    /// - 0-10%: Minimal waste, cleanup probably unnecessary
-    /// - 10-25%: Moderate waste, consider cleanup during idle periods
-    /// - 25-50%: Significant waste, cleanup recommended
-    /// - 50%+: High waste, cleanup should be prioritized
+    /// Unlike `do_for_all_things`, the closure can create, connect and kill
+    /// things while the pass runs. The list of things to visit is taken up
+    /// front, so things created during the pass are not visited, and things
+    /// killed during the pass are skipped if they haven't been visited yet.
     ///
     /// # Examples
     ///
     /// ```rust
     /// # use connect_things::*;
-    /// # let mut graph = Things::new();
+    /// let mut graph = Things::<&str, &str>::new();
+    /// graph.new_thing("a");
+    /// graph.new_thing("b");
     ///
-    /// match graph.dead_percentage() {
-    ///     Ok(percent) if percent > 25 => {
-    ///         println!("High memory pressure: {}%", percent);
-    ///         graph.clean();
-    ///     }
-    ///     Ok(percent) => println!("Memory pressure: {}%", percent),
-    ///     Err(_) => println!("Empty graph"),
-    /// }
+    /// // Give every thing a backup, without visiting the backups
+    /// graph.for_each_thing_snapshot(|graph, thing| {
+    ///     let backup = graph.new_thing("backup");
+    ///     graph.new_directed_connection(thing, "backed_up_by", backup);
+    /// });
+    /// assert_eq!(graph.do_for_all_things(|_| Do::Take(())).len(), 4);
     /// ```
-    pub fn dead_percentage(&mut self) -> Result<usize, ()> {
-        let total = self
+    pub fn for_each_thing_snapshot(
+        &mut self,
+        mut visit: impl FnMut(&mut Things<T, C>, Thing<T, C>),
+    ) {
+        let snapshot: Vec<Thing<T, C>> = self
             .things
-            .len()
-            .saturating_add(self.connections.len());
-
-        if total == 0 {
-            self.dead_amount = 0;
-            return Err(());
+            .iter()
+            .filter(|thing| thing.is_alive())
+            .cloned()
+            .collect();
+        for thing in snapshot {
+            if thing.is_alive() {
+                visit(self, thing);
+            }
         }
+    }
 
-        let multiplied = self.dead_amount.saturating_mul(100);
-
-        let divided = multiplied / total;
-
-        Ok(divided)
+    /// Calls `visit` on every live connection, giving it mutable access to the container.
+    ///
+    /// Works like `for_each_thing_snapshot`: connections created during the
+    /// pass are not visited, and connections killed before their turn are skipped.
+    pub fn for_each_connection_snapshot(
+        &mut self,
+        mut visit: impl FnMut(&mut Things<T, C>, Connection<T, C>),
+    ) {
+        let snapshot: Vec<Connection<T, C>> = self
+            .connections
+            .iter()
+            .filter(|connection| connection.is_alive())
+            .cloned()
+            .collect();
+        for connection in snapshot {
+            if connection.is_alive() {
+                visit(self, connection);
+            }
+        }
     }
 
-    /// Removes all dead things and connections from memory.
+    /// Lets `edit` read the graph while queueing changes, then applies them.
     ///
-    /// This performs the actual cleanup of items that were previously marked
-    /// as dead. After cleaning, only live items remain in the graph and the
-    /// dead count is reset to zero.
+    /// Inside `edit` the graph can be walked freely through
+    /// `GraphEditor::graph`, since nothing changes until `edit` returns. Things
+    /// and connections created through the editor get their handles right
+    /// away, so later queued changes can refer to them. When `edit` returns,
+    /// the queued changes are applied in this order: new things, new
+    /// connections, killed connections, killed things.
     ///
-    /// This operation can be expensive for large graphs, so it's typically
-    /// called strategically based on memory pressure or at natural breakpoints
-    /// in your application.
+    /// New things skip the `ThingsConfig::unique_things` check, as their
+    /// handles are already in use. Every other configured rule is checked for
+    /// the whole queue before any change is applied, with the same panics as
+    /// the direct methods, so a refused edit leaves the graph untouched. In
+    /// simple graph mode, queued connections must not run parallel to each
+    /// other either.
     ///
     /// # Examples
     ///
     /// ```rust
     /// # use connect_things::*;
-    /// # let mut graph = Things::new();
+    /// let mut graph = Things::<&str, &str>::new();
+    /// graph.new_thing("a");
+    /// graph.new_thing("b");
     ///
-    /// // Clean up when memory pressure gets high
-    /// if graph.dead_percentage().unwrap_or(0) > 30 {
-    ///     graph.clean();
-    ///     println!("Graph cleaned");
-    /// }
+    /// // Link every existing thing to a new hub
+    /// graph.modify(|editor| {
+    ///     let hub = editor.new_thing("hub");
+    ///     for thing in editor.graph().do_for_all_things(|thing| Do::Take(thing.clone())) {
+    ///         editor.new_directed_connection(hub.clone(), "links", thing);
+    ///     }
+    /// });
+    /// let hub = graph.find_thing_equal(&"hub").unwrap();
+    /// assert_eq!(hub.outgoing().len(), 2);
     /// ```
-    pub fn clean(&mut self) {
-        self.things.retain_mut(|thing| {
-            return if thing.is_alive() {
-                thing.clean();
-                true
+    pub fn modify(&mut self, edit: impl FnOnce(&mut GraphEditor<T, C>)) {
+        self.auto_clean();
+        let mut editor = GraphEditor {
+            graph: self,
+            new_things: Vec::new(),
+            new_connections: Vec::new(),
+            killed_things: Vec::new(),
+            killed_connections: Vec::new(),
+        };
+        edit(&mut editor);
+        let GraphEditor {
+            new_things,
+            new_connections,
+            killed_things,
+            killed_connections,
+            ..
+        } = editor;
+
+        self.check_queued_changes(&new_things, &new_connections);
+        self.touch();
+        for thing in new_things {
+            self.register_thing(thing);
+        }
+        for connection in new_connections {
+            let [first, second] = connection.ends();
+            unsafe { first.connect(connection.clone()) };
+            unsafe { second.connect(connection.clone()) };
+            self.register_connection(connection);
+        }
+        for connection in killed_connections {
+            if connection.is_alive() {
+                self.dead_amount = self.dead_amount.saturating_add(1);
+                connection.kill();
+            }
+        }
+        for thing in killed_things {
+            if thing.is_alive() {
+                thing.kill(&mut self.dead_amount);
+            }
+        }
+    }
+
+    /// Enforces the configured rules for everything queued by a `GraphEditor`
+    /// at once, before any of it is applied.
+    fn check_queued_changes(
+        &self,
+        new_things: &[Thing<T, C>],
+        new_connections: &[Connection<T, C>],
+    ) {
+        let (things_left, connections_left) = self.remaining_capacity();
+        if new_things.len() > things_left {
+            panic!(
+                "cannot create thing: the limit of {} things is reached, clean dead things first",
+                self.config.max_things
+            );
+        }
+        if new_connections.len() > connections_left {
+            panic!(
+                "cannot create connection: the limit of {} connections is reached, clean dead connections first",
+                self.config.max_connections
+            );
+        }
+
+        let mut queued = BTreeSet::new();
+        for connection in new_connections {
+            let [first, second] = connection.ends();
+            let directed = connection.is_directed();
+            self.check_new_connection(&first, &second, directed);
+            let (first, second) = (first.key(), second.key());
+            let pair = if directed {
+                (first, second, directed)
             } else {
-                false
+                (first.min(second), first.max(second), directed)
             };
-        });
+            if cfg!(debug_assertions)
+                && self.config.mode == GraphMode::Simple
+                && !queued.insert(pair)
+            {
+                panic!(
+                    "connection not allowed in simple graph mode: {:?}",
+                    ConnectError::ParallelEdge
+                );
+            }
+        }
+    }
 
-        self.connections.retain(|connection| connection.is_alive());
+    /// Marks connections matching the predicate as dead.
+    ///
+    /// Unlike `kill_things`, this only affects the connections themselves,
+    /// not the things they connect. The connected things remain alive.
+    ///
+    /// Connections that are already dead are skipped, so they are neither
+    /// passed to the predicate nor counted again. The dead count is updated
+    /// per connection, so it stays accurate if the predicate panics.
+    ///
+    /// # Returns
+    /// The number of connections that were alive and have now been killed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// # #[derive(PartialEq)]
+    /// # struct Relation { is_temporary: bool }
+    /// # let mut graph = Things::<(), Relation>::new();
+    ///
+    /// // Remove all temporary relationships
+    /// let removed = graph.kill_connections(|conn| {
+    ///     conn.access(|data| data.is_temporary)
+    /// });
+    /// println!("Removed {} temporary relationships", removed);
+    /// ```
+    pub fn kill_connections(&mut self, kill: impl Fn(&Connection<T, C>) -> bool) -> usize {
+        let mut amount = 0;
+        for connection in self.connections.iter() {
+            if connection.is_alive() && kill(connection) {
+                if amount == 0 {
+                    // Same as `touch`, which would borrow all of `self`
+                    self.generation = self.generation.wrapping_add(1);
+                }
+                self.dead_amount = self.dead_amount.saturating_add(1);
+                amount += 1;
+                connection.kill();
+            }
+        }
+        amount
+    }
 
-        self.dead_amount = 0;
+    /// Repeatedly kills the things with at most one live connection, peeling
+    /// dangling chains and isolated things away from the core of the graph.
+    ///
+    /// Each round kills every live thing that has at most one live connection
+    /// at the start of the round, whichever way it points, along with its
+    /// connections. A self-loop counts twice. This runs for `rounds` rounds,
+    /// or until a round finds nothing to kill if `rounds` is 0, which leaves
+    /// the 2-core: the things with at least two connections among themselves.
+    ///
+    /// # Returns
+    /// The number of things killed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut graph = Things::<&str, ()>::new();
+    /// let a = graph.new_thing("a");
+    /// let b = graph.new_thing("b");
+    /// let c = graph.new_thing("c");
+    /// let tail = graph.new_thing("tail");
+    /// graph.new_undirected_connection([a.clone(), b.clone()], ());
+    /// graph.new_undirected_connection([b, c.clone()], ());
+    /// graph.new_undirected_connection([c.clone(), a.clone()], ());
+    /// graph.new_undirected_connection([c, tail], ());
+    ///
+    /// assert_eq!(graph.trim_leaves(0), 1);
+    /// assert!(graph.find_thing_equal(&"tail").is_none());
+    /// assert_eq!(a.neighbors_alive().len(), 2);
+    /// ```
+    pub fn trim_leaves(&mut self, rounds: usize) -> usize {
+        let (things, index) = self.index_things();
+        let peeled = self.peel_below(&things, &index, 2, rounds);
+        let leaves: Vec<_> = things
+            .iter()
+            .zip(peeled)
+            .filter_map(|(thing, peeled)| peeled.then_some(thing))
+            .collect();
+        if leaves.is_empty() {
+            return 0;
+        }
+
+        self.touch();
+        for leaf in leaves.iter() {
+            leaf.kill(&mut self.dead_amount);
+        }
+        leaves.len()
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use alloc::format;
-    use alloc::string::{String, ToString};
+    /// Inserts a new thing in the middle of an existing connection.
+    ///
+    /// The original connection is killed and replaced by two connections
+    /// through a new thing holding `middle_data`. A directed connection from
+    /// `from` to `to` becomes `from -> middle` (with `data_a`) and
+    /// `middle -> to` (with `data_b`). An undirected connection becomes two
+    /// undirected connections, `data_a` on the side of its first thing. The
+    /// middle thing is always new, even with `ThingsConfig::unique_things`.
+    ///
+    /// # Returns
+    /// - `Ok((middle, first, second))`: The middle thing and the two new
+    ///   connections, in path order
+    /// - `Err(())`: If the connection is already dead, in which case nothing changes
+    ///
+    /// # Panics
+    /// If the container has no room for one more thing and two more
+    /// connections under its limits. This is checked before anything changes.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut flow = Things::<&str, &str>::new();
+    /// let start = flow.new_thing("start");
+    /// let end = flow.new_thing("end");
+    /// let edge = flow.new_directed_connection(start.clone(), "next", end.clone());
+    ///
+    /// let (middle, first, second) = flow.split_connection(&edge, "check", "next", "next").unwrap();
+    /// assert!(first.get_directed_from().unwrap() == "start");
+    /// assert!(second.get_directed_towards().unwrap() == "end");
+    /// assert!(middle.neighbors_alive().len() == 2);
+    /// ```
+    #[allow(clippy::result_unit_err)]
+    pub fn split_connection(
+        &mut self,
+        connection: &Connection<T, C>,
+        middle_data: T,
+        data_a: C,
+        data_b: C,
+    ) -> Result<SplitConnection<T, C>, ()> {
+        if !connection.is_alive() {
+            return Err(());
+        }
+        self.auto_clean();
+        // The middle thing is new, so neither new connection can be a self-loop
+        // or run parallel to another one: only the limits can refuse them
+        let (things_left, connections_left) = self.remaining_capacity();
+        if things_left < 1 {
+            panic!(
+                "cannot create thing: the limit of {} things is reached, clean dead things first",
+                self.config.max_things
+            );
+        }
+        if connections_left < 2 {
+            panic!(
+                "cannot create connection: the limit of {} connections is reached, clean dead connections first",
+                self.config.max_connections
+            );
+        }
 
-    /// Creates a sample knowledge graph for testing.
-    /// This represents a simple taxonomy with foods, categories, and preferences.
-    fn test_knowledge_graph<'a>() -> Things<&'a str, &'a str> {
-        let mut graph = Things::<&str, &str>::new();
+        let [first, second] = connection.ends();
+        let directed = connection.is_directed();
+        connection.kill();
+        self.dead_amount = self.dead_amount.saturating_add(1);
+
+        let middle = Thing::new(middle_data);
+        self.register_thing(middle.clone());
+        let (a, b) = if directed {
+            (
+                self.new_directed_connection(first, data_a, middle.clone()),
+                self.new_directed_connection(middle.clone(), data_b, second),
+            )
+        } else {
+            (
+                self.new_undirected_connection([first, middle.clone()], data_a),
+                self.new_undirected_connection([middle.clone(), second], data_b),
+            )
+        };
+        Ok((middle, a, b))
+    }
 
-        let apple = graph.new_thing("Apple");
-        let apples = graph.new_thing("Apples");
-        graph.new_directed_connection(apples.clone(), "plural of", apple.clone());
+    /// Contracts a connection, merging its two endpoints into one thing.
+    ///
+    /// This is the counterpart of `split_connection`. The connection is killed
+    /// and its second thing (the target, for a directed connection) is merged
+    /// into its first: the absorbed thing is killed, its data is moved out and
+    /// handed to `merge_data` along with the surviving data, and every other
+    /// live connection of the absorbed thing is moved over to the survivor.
+    /// Its kill callbacks still see its data; afterwards it holds
+    /// `T::default()`. Moved connections keep their data, direction and
+    /// identity.
+    ///
+    /// Connections that joined the two things become self-loops on the
+    /// survivor, and are dropped (killed). A moved connection that runs
+    /// parallel to one the survivor already has, such as the two sides of a
+    /// triangle meeting at the same thing, is merged into it: the existing
+    /// connection stays as it is and the moved one is killed. Use
+    /// `contract_connection_with` with `LoopPolicy::Keep` or
+    /// `ParallelPolicy::Keep` to keep either, and `merge_parallel_connections`
+    /// to combine the data of kept parallel connections. A self-loop being
+    /// contracted only kills the connection.
+    ///
+    /// # Returns
+    /// - `Ok(survivor)`: The thing the endpoints were merged into
+    /// - `Err(())`: If the connection is already dead
+    ///
+    /// # Panics
+    /// Like `new_directed_connection`, for a connection the contraction keeps:
+    /// a kept self-loop when self-loops are rejected, and in simple graph mode
+    /// with debug assertions enabled, a kept self-loop or parallel connection.
+    /// This is checked before anything changes. Moving connections creates
+    /// none, so the connection limit doesn't apply, and moved connections keep
+    /// their data, so their class doesn't change either.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut clusters = Things::<u32, ()>::new();
+    /// let a = clusters.new_thing(2);
+    /// let b = clusters.new_thing(3);
+    /// let c = clusters.new_thing(1);
+    /// let ab = clusters.new_undirected_connection([a.clone(), b.clone()], ());
+    /// clusters.new_undirected_connection([b, c.clone()], ());
+    ///
+    /// // Collapse a and b into one cluster, adding up their sizes
+    /// let merged = clusters.contract_connection(&ab, |size, other| *size += other).unwrap();
+    /// assert!(merged == 5);
+    /// assert!(merged.neighbors_alive()[0] == c);
+    /// ```
+    #[allow(clippy::result_unit_err)]
+    pub fn contract_connection(
+        &mut self,
+        connection: &Connection<T, C>,
+        merge_data: impl FnOnce(&mut T, T),
+    ) -> Result<Thing<T, C>, ()>
+    where
+        T: Default,
+    {
+        self.contract_connection_with(
+            connection,
+            LoopPolicy::Drop,
+            ParallelPolicy::Merge,
+            merge_data,
+        )
+    }
+
+    /// Contracts a connection like `contract_connection`, choosing what happens
+    /// to connections that become self-loops or run parallel to another one.
+    #[allow(clippy::result_unit_err)]
+    pub fn contract_connection_with(
+        &mut self,
+        connection: &Connection<T, C>,
+        loops: LoopPolicy,
+        parallels: ParallelPolicy,
+        merge_data: impl FnOnce(&mut T, T),
+    ) -> Result<Thing<T, C>, ()>
+    where
+        T: Default,
+    {
+        if !connection.is_alive() {
+            return Err(());
+        }
+        let [survivor, absorbed] = connection.ends();
+        if !survivor.ptr_eq(&absorbed) {
+            self.check_contraction(connection, &survivor, &absorbed, loops, parallels);
+        }
+        self.touch();
+        self.dead_amount = self.dead_amount.saturating_add(1);
+        connection.kill();
+
+        if !survivor.ptr_eq(&absorbed) {
+            self.dead_amount = self.dead_amount.saturating_add(1);
+            absorbed.mark_dead();
+            let absorbed_data = core::mem::take(&mut absorbed.inner.borrow_mut().data);
+            merge_data(&mut survivor.inner.borrow_mut().data, absorbed_data);
+            let moved = core::mem::take(&mut absorbed.inner.borrow_mut().connections);
+            for other in moved.into_iter().filter(|other| other.is_alive()) {
+                other.inner.borrow_mut().replace_end(&absorbed, &survivor);
+                let [first, second] = other.ends();
+                let drop = if first.ptr_eq(&second) {
+                    loops == LoopPolicy::Drop
+                } else {
+                    parallels == ParallelPolicy::Merge
+                        && survivor.inner.borrow().connections.iter().any(|existing| {
+                            existing.is_alive() && existing.runs_parallel_to(&other)
+                        })
+                };
+                if drop {
+                    // Self-loops are listed twice, but only killed once
+                    if other.is_alive() {
+                        self.dead_amount = self.dead_amount.saturating_add(1);
+                        other.kill();
+                    }
+                } else {
+                    unsafe { survivor.connect(other) };
+                }
+            }
+        }
+
+        Ok(survivor)
+    }
+
+    /// Moves a connection to new endpoints, keeping its data and whether it is directed.
+    ///
+    /// A directed connection then goes from `new_a` to `new_b`; an undirected
+    /// one joins them in that order. The connection is taken out of its old
+    /// endpoints' connection lists and added to the new ones, so handles to it
+    /// stay valid. Nothing happens if the connection is dead or already joins
+    /// the new endpoints.
+    ///
+    /// # Panics
+    /// Under the same conditions as `new_directed_connection`, checked as if
+    /// the connection was created between the new endpoints.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut diagram = Things::<&str, &str>::new();
+    /// let start = diagram.new_thing("start");
+    /// let old = diagram.new_thing("old");
+    /// let new = diagram.new_thing("new");
+    /// let arrow = diagram.new_directed_connection(start.clone(), "flows", old.clone());
+    ///
+    /// diagram.reattach_connection(&arrow, &start, &new);
+    /// assert!(arrow.get_directed_towards().unwrap() == "new");
+    /// assert!(old.neighbors_alive().is_empty());
+    /// ```
+    pub fn reattach_connection(
+        &mut self,
+        connection: &Connection<T, C>,
+        new_a: &Thing<T, C>,
+        new_b: &Thing<T, C>,
+    ) {
+        if !connection.is_alive() {
+            return;
+        }
+        let directed = connection.is_directed();
+        let [old_a, old_b] = connection.ends();
+        let unchanged = (old_a.ptr_eq(new_a) && old_b.ptr_eq(new_b))
+            || (!directed && old_a.ptr_eq(new_b) && old_b.ptr_eq(new_a));
+        if unchanged {
+            return;
+        }
+        self.check_new_connection(new_a, new_b, directed);
+
+        for mut old in [old_a, old_b] {
+            unsafe { old.remove_connections(|other| other.ptr_eq(connection)) };
+        }
+        match &mut connection.inner.borrow_mut().ends {
+            Ends::Directed { from, to } => {
+                *from = new_a.clone();
+                *to = new_b.clone();
+            }
+            Ends::Undirected { things } => {
+                *things = [new_a.clone(), new_b.clone()];
+            }
+        }
+        unsafe { new_a.connect(connection.clone()) };
+        unsafe { new_b.connect(connection.clone()) };
+        self.touch();
+    }
+
+    /// Makes a connection directed, with `from` as its source and the other
+    /// endpoint as its target.
+    ///
+    /// An undirected connection is turned into a directed one, and a directed
+    /// connection pointing the other way is flipped. The connection keeps its
+    /// identity, data, weight and kill callbacks, and stays listed by both
+    /// endpoints.
+    ///
+    /// # Returns
+    /// - `Ok(())`: If the connection now goes from `from` to its other endpoint
+    /// - `Err(())`: If the connection is dead or `from` isn't one of its
+    ///   endpoints, in which case nothing changes
+    ///
+    /// # Panics
+    /// In simple graph mode with debug assertions enabled, if another live
+    /// connection already goes the same way.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut graph = Things::<&str, &str>::new();
+    /// let manager = graph.new_thing("manager");
+    /// let report = graph.new_thing("report");
+    /// let link = graph.new_undirected_connection([report.clone(), manager.clone()], "works with");
+    ///
+    /// assert!(graph.set_direction(&link, &manager).is_ok());
+    /// assert!(link.get_directed_from().unwrap() == "manager");
+    /// assert!(link.get_directed_towards().unwrap() == "report");
+    /// ```
+    #[allow(clippy::result_unit_err)]
+    pub fn set_direction(
+        &mut self,
+        connection: &Connection<T, C>,
+        from: &Thing<T, C>,
+    ) -> Result<(), ()> {
+        if !connection.is_alive() {
+            return Err(());
+        }
+        let to = connection.inner.borrow().other_end(from).ok_or(())?;
+        if connection.goes_between(from, &to) {
+            return Ok(());
+        }
+        if cfg!(debug_assertions)
+            && self.config.mode == GraphMode::Simple
+            && !from.ptr_eq(&to)
+            && has_parallel_connection(from, &to, true)
+        {
+            panic!(
+                "connection not allowed in simple graph mode: {:?}",
+                ConnectError::ParallelEdge
+            );
+        }
+
+        connection.inner.borrow_mut().ends = Ends::Directed {
+            from: from.clone(),
+            to,
+        };
+        self.touch();
+        Ok(())
+    }
+
+    /// Flips a directed connection and replaces its data with the reversed label.
+    ///
+    /// For relationships that read differently from either side, such as
+    /// "parent of" and "child of", so the label keeps describing the
+    /// connection after it changes direction. The connection keeps its
+    /// identity. If a connection classifier is registered, the connection is
+    /// reclassified with its new data.
+    ///
+    /// # Returns
+    /// - `true`: If the connection was flipped
+    /// - `false`: If it is undirected or dead, in which case nothing changes
+    ///
+    /// # Panics
+    /// In simple graph mode with debug assertions enabled, if a live
+    /// connection already goes the other way.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// #[derive(PartialEq)]
+    /// enum Family {
+    ///     ParentOf,
+    ///     ChildOf,
+    /// }
+    ///
+    /// impl Reversible for Family {
+    ///     fn reverse(&self) -> Self {
+    ///         match self {
+    ///             Family::ParentOf => Family::ChildOf,
+    ///             Family::ChildOf => Family::ParentOf,
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// let mut family = Things::new();
+    /// let ada = family.new_thing("Ada");
+    /// let ben = family.new_thing("Ben");
+    /// let link = family.new_directed_connection(ada, Family::ParentOf, ben);
+    ///
+    /// assert!(family.reverse_connection_relabeled(&link));
+    /// assert!(link == Family::ChildOf);
+    /// assert!(link.get_directed_from().unwrap() == "Ben");
+    /// ```
+    pub fn reverse_connection_relabeled(&mut self, connection: &Connection<T, C>) -> bool
+    where
+        C: Reversible,
+    {
+        let Some((from, to)) = connection.directed_ends() else {
+            return false;
+        };
+        if !connection.is_alive() {
+            return false;
+        }
+        if cfg!(debug_assertions)
+            && self.config.mode == GraphMode::Simple
+            && !from.ptr_eq(&to)
+            && has_parallel_connection(&to, &from, true)
+        {
+            panic!(
+                "connection not allowed in simple graph mode: {:?}",
+                ConnectError::ParallelEdge
+            );
+        }
+
+        let mut inner = connection.inner.borrow_mut();
+        if let Ends::Directed { from, to } = &mut inner.ends {
+            core::mem::swap(from, to);
+            inner.data = inner.data.reverse();
+        }
+        drop(inner);
+
+        if let Some(classes) = self.classes.as_mut() {
+            for bucket in classes.buckets.values_mut() {
+                bucket.retain(|other| !other.ptr_eq(connection));
+            }
+            classes.insert(connection.clone());
+        }
+        self.touch();
+        true
+    }
+
+    /// Calculates the percentage of dead items relative to total items.
+    ///
+    /// This provides a "memory pressure" metric to help decide when cleanup
+    /// might be beneficial. The percentage represents how much of your graph's
+    /// memory is consumed by logically deleted items.
+    ///
+    /// # Returns
+    /// - `Ok(percentage)`: The percentage (0-100) of dead items
+    /// - `Err(())`: If the graph is empty (division by zero)
+    ///
+    /// # Memory Pressure Guidelines
+    /// - 0-10%: Minimal waste, cleanup probably unnecessary
+    /// - 10-25%: Moderate waste, consider cleanup during idle periods
+    /// - 25-50%: Significant waste, cleanup recommended
+    /// - 50%+: High waste, cleanup should be prioritized
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// # let mut graph = Things::<&str, &str>::new();
+    ///
+    /// match graph.dead_percentage() {
+    ///     Ok(percent) if percent > 25 => {
+    ///         println!("High memory pressure: {}%", percent);
+    ///         graph.clean();
+    ///     }
+    ///     Ok(percent) => println!("Memory pressure: {}%", percent),
+    ///     Err(_) => println!("Empty graph"),
+    /// }
+    /// ```
+    #[allow(clippy::result_unit_err)]
+    pub fn dead_percentage(&mut self) -> Result<usize, ()> {
+        let pressure = self.pressure();
+        if pressure.is_none() {
+            self.dead_amount = 0;
+        }
+        pressure.ok_or(())
+    }
+
+    /// Computes the dead percentage, or `None` for an empty container.
+    fn pressure(&self) -> Option<usize> {
+        let total = self.things.len().saturating_add(self.connections.len());
+
+        if total == 0 {
+            return None;
+        }
+
+        let multiplied = self.dead_amount.saturating_mul(100);
+
+        let divided = multiplied / total;
+
+        Some(divided)
+    }
+
+    /// Sums up how healthy the graph is, with the default `HealthThresholds`.
+    ///
+    /// See `health_with`.
+    pub fn health(&self) -> HealthReport {
+        self.health_with(HealthThresholds::default())
+    }
+
+    /// Sums up how healthy the graph is and recommends what to do about it.
+    ///
+    /// The report combines the dead percentage, the number of weakly connected
+    /// components, the connections reported by `orphaned_connections`, and
+    /// the parallel connections reported by `violations_of_simple`, leaving
+    /// out self-loops. Each of them is worked out once, in a linear pass.
+    ///
+    /// The recommendation is for the most pressing problem above its
+    /// threshold: orphaned connections first, as they point outside the
+    /// container, then the dead percentage, then parallel connections.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut graph = Things::<&str, ()>::new();
+    /// let a = graph.new_thing("a");
+    /// let b = graph.new_thing("b");
+    /// graph.new_undirected_connection([a.clone(), b.clone()], ());
+    /// graph.new_undirected_connection([a, b], ());
+    ///
+    /// let report = graph.health_with(HealthThresholds::default());
+    /// assert_eq!(report.components, 1);
+    /// assert_eq!(report.parallel_connections, 1);
+    /// assert_eq!(report.recommendation, Recommendation::Dedup);
+    /// ```
+    pub fn health_with(&self, thresholds: HealthThresholds) -> HealthReport {
+        let dead_percentage = self.pressure();
+        let components = self
+            .weak_components()
+            .1
+            .values()
+            .max()
+            .map_or(0, |last| last + 1);
+        let orphaned_connections = self.orphaned_connections().len();
+        let parallel_connections = self
+            .violations_of_simple()
+            .iter()
+            .filter(|connection| {
+                let [first, second] = connection.ends();
+                !first.ptr_eq(&second)
+            })
+            .count();
+
+        let recommendation = if orphaned_connections > thresholds.max_orphaned_connections {
+            Recommendation::RepairDangling
+        } else if dead_percentage.is_some_and(|dead| dead > thresholds.max_dead_percentage) {
+            Recommendation::Clean
+        } else if parallel_connections > thresholds.max_parallel_connections {
+            Recommendation::Dedup
+        } else {
+            Recommendation::None
+        };
+
+        HealthReport {
+            dead_percentage,
+            components,
+            orphaned_connections,
+            parallel_connections,
+            recommendation,
+        }
+    }
+
+    /// Recomputes the dead count from scratch, replacing the cached one.
+    ///
+    /// The dead count behind `dead_percentage` and auto-cleaning is kept up to
+    /// date as items are killed. This scans every thing and connection to
+    /// check it, which is useful after long sessions or when hunting a
+    /// bookkeeping bug. With the `debug-invariants` feature, `clean` does this
+    /// first and panics if the count had drifted.
+    ///
+    /// # Returns
+    /// The cached count before the call, the dead things and connections
+    /// actually found, and whether the cached count was wrong.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut graph = Things::<&str, ()>::new();
+    /// let a = graph.new_thing("a");
+    /// let b = graph.new_thing("b");
+    /// graph.new_undirected_connection([a, b], ());
+    /// graph.kill_things(|name| *name == "a");
+    ///
+    /// let report = graph.recount();
+    /// assert_eq!(report.actual_dead_things, 1);
+    /// assert_eq!(report.actual_dead_connections, 1);
+    /// assert!(!report.corrected);
+    /// ```
+    pub fn recount(&mut self) -> RecountReport {
+        let actual_dead_things = self.things.iter().filter(|thing| !thing.is_alive()).count();
+        let actual_dead_connections = self
+            .connections
+            .iter()
+            .filter(|connection| !connection.is_alive())
+            .count();
+        let previous = self.dead_amount;
+        self.dead_amount = actual_dead_things + actual_dead_connections;
+        RecountReport {
+            previous,
+            actual_dead_things,
+            actual_dead_connections,
+            corrected: previous != self.dead_amount,
+        }
+    }
+
+    /// Copies the dead things and connections that haven't been cleaned yet
+    /// into a new graph.
+    ///
+    /// Useful for showing "what you just deleted" before calling `clean`. The
+    /// copies are alive in the new graph and keep container order. A dead
+    /// connection is copied when both of its ends are dead; connections between
+    /// a dead thing and a live one, or killed on their own between live things,
+    /// have nothing to attach to and are left out.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut files = Things::<&str, &str>::new();
+    /// let root = files.new_thing("/");
+    /// let docs = files.new_thing("docs");
+    /// let notes = files.new_thing("notes.txt");
+    /// files.new_directed_connection(root, "contains", docs.clone());
+    /// files.new_directed_connection(docs, "contains", notes);
+    ///
+    /// files.kill_things(|file| *file == "docs" || *file == "notes.txt");
+    /// let deleted = files.dead_subgraph();
+    /// assert!(deleted.find_thing_equal(&"docs").is_some());
+    /// assert!(deleted.find_connection_equal(&"contains").is_some());
+    /// assert!(deleted.find_thing_equal(&"/").is_none());
+    /// ```
+    pub fn dead_subgraph(&self) -> Things<T, C>
+    where
+        T: Clone,
+        C: Clone,
+    {
+        let mut graph = Things::new();
+        let mut copies = BTreeMap::new();
+        for thing in self.things.iter().filter(|thing| !thing.is_alive()) {
+            let copy = graph.new_thing(thing.access(|data| data.clone()));
+            copies.insert(thing.key(), copy);
+        }
+        for connection in self
+            .connections
+            .iter()
+            .filter(|connection| !connection.is_alive())
+        {
+            let [first, second] = connection.ends();
+            let (Some(first), Some(second)) = (copies.get(&first.key()), copies.get(&second.key()))
+            else {
+                continue;
+            };
+            let data = connection.access(|data| data.clone());
+            if connection.is_directed() {
+                graph.new_directed_connection(first.clone(), data, second.clone());
+            } else {
+                graph.new_undirected_connection([first.clone(), second.clone()], data);
+            }
+        }
+        graph
+    }
+
+    /// Copies two graphs into a new one and connects matching things across them.
+    ///
+    /// The live things and connections of `a` and then `b` are copied, keeping
+    /// container order. Then `matches` is called for every pair of a thing from
+    /// `a` and a thing from `b`, in that order, and each `Some(data)` becomes a
+    /// directed connection from the copy on the `a` side to the copy on the `b`
+    /// side. Neither input is changed.
+    ///
+    /// Trying every pair takes O(|A|·|B|) calls to `matches`. When things
+    /// match on equal keys, `join_by_key` does the same work in
+    /// O((|A| + |B|) log |B|).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut people = Things::<&str, &str>::new();
+    /// people.new_thing("alice@acme");
+    /// people.new_thing("bob@initech");
+    /// let mut companies = Things::<&str, &str>::new();
+    /// companies.new_thing("acme");
+    ///
+    /// let combined = Things::join(&people, &companies, |person, company| {
+    ///     person.ends_with(company).then_some("works_at")
+    /// });
+    /// assert_eq!(combined.things_rev().count(), 3);
+    /// let works_at = combined.find_connection_equal(&"works_at").unwrap();
+    /// assert!(works_at.get_directed_from().unwrap() == "alice@acme");
+    /// ```
+    pub fn join(
+        a: &Things<T, C>,
+        b: &Things<T, C>,
+        matches: impl Fn(&T, &T) -> Option<C>,
+    ) -> Things<T, C>
+    where
+        T: Clone,
+        C: Clone,
+    {
+        let mut graph = Things::new();
+        let left = a.copy_live_into(&mut graph, |_| true);
+        let right = b.copy_live_into(&mut graph, |_| true);
+        for from in left.iter() {
+            for to in right.iter() {
+                if let Some(data) = from.access(|first| to.access(|second| matches(first, second)))
+                {
+                    graph.new_directed_connection(from.clone(), data, to.clone());
+                }
+            }
+        }
+        graph
+    }
+
+    /// Joins two graphs like `join`, connecting the things whose keys are equal.
+    ///
+    /// `key_a` and `key_b` extract the keys on either side, and `data` makes
+    /// the connection data for each matching pair. The result is the same as
+    /// `join` with a closure comparing the keys, including the order of the
+    /// connections, but only pairs with equal keys are ever looked at.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut people = Things::<(&str, u32), ()>::new();
+    /// people.new_thing(("alice", 1));
+    /// people.new_thing(("bob", 2));
+    /// let mut companies = Things::<(&str, u32), ()>::new();
+    /// companies.new_thing(("acme", 1));
+    ///
+    /// let combined = Things::join_by_key(&people, &companies, |p| p.1, |c| c.1, |_, _| ());
+    /// assert!(combined.find_connection_equal(&()).is_some());
+    /// ```
+    pub fn join_by_key<K: Ord>(
+        a: &Things<T, C>,
+        b: &Things<T, C>,
+        key_a: impl Fn(&T) -> K,
+        key_b: impl Fn(&T) -> K,
+        data: impl Fn(&T, &T) -> C,
+    ) -> Things<T, C>
+    where
+        T: Clone,
+        C: Clone,
+    {
+        let mut graph = Things::new();
+        let left = a.copy_live_into(&mut graph, |_| true);
+        let right = b.copy_live_into(&mut graph, |_| true);
+        let mut by_key: BTreeMap<K, Vec<usize>> = BTreeMap::new();
+        for (position, thing) in right.iter().enumerate() {
+            by_key
+                .entry(thing.access(&key_b))
+                .or_default()
+                .push(position);
+        }
+        for from in left.iter() {
+            let Some(matching) = by_key.get(&from.access(&key_a)) else {
+                continue;
+            };
+            for &position in matching {
+                let to = &right[position];
+                let edge = from.access(|first| to.access(|second| data(first, second)));
+                graph.new_directed_connection(from.clone(), edge, to.clone());
+            }
+        }
+        graph
+    }
+
+    /// Copies the live things accepted by `keep` into `graph`, keeping
+    /// container order, along with the live connections between them.
+    ///
+    /// # Returns
+    /// The copies of the kept things, in container order.
+    fn copy_live_into(
+        &self,
+        graph: &mut Things<T, C>,
+        keep: impl Fn(&Thing<T, C>) -> bool,
+    ) -> Vec<Thing<T, C>>
+    where
+        T: Clone,
+        C: Clone,
+    {
+        let mut copies = Vec::new();
+        let mut copy_of = BTreeMap::new();
+        for thing in self
+            .things
+            .iter()
+            .filter(|thing| thing.is_alive() && keep(thing))
+        {
+            let copy = graph.new_thing(thing.access(|data| data.clone()));
+            copy_of.insert(thing.key(), copies.len());
+            copies.push(copy);
+        }
+        for connection in self
+            .connections
+            .iter()
+            .filter(|connection| connection.is_alive())
+        {
+            let [first, second] = connection.ends();
+            let (Some(&first), Some(&second)) =
+                (copy_of.get(&first.key()), copy_of.get(&second.key()))
+            else {
+                continue;
+            };
+            let (first, second) = (&copies[first], &copies[second]);
+            let data = connection.access(|data| data.clone());
+            if connection.is_directed() {
+                graph.new_directed_connection(first.clone(), data, second.clone());
+            } else {
+                graph.new_undirected_connection([first.clone(), second.clone()], data);
+            }
+        }
+        copies
+    }
+
+    /// Copies the k-core of the graph: the largest part of it in which every
+    /// thing has at least `k` connections to other things of that part.
+    ///
+    /// Things with fewer than `k` live connections are peeled away one by one,
+    /// each removal lowering the count of its neighbors, until every remaining
+    /// thing has `k`. Connections count whichever way they point, parallel
+    /// connections count separately and a self-loop counts twice, as in
+    /// `trim_leaves`, which does the same for `k = 2` in place. The remaining
+    /// things and the live connections between them are copied into a new
+    /// graph in container order; it is empty if there is no k-core.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut friends = Things::<&str, ()>::new();
+    /// let people: Vec<_> = ["a", "b", "c", "d", "e"]
+    ///     .iter()
+    ///     .map(|name| friends.new_thing(*name))
+    ///     .collect();
+    /// // a, b, c and d all know each other, e only knows a
+    /// for first in 0..4 {
+    ///     for second in first + 1..4 {
+    ///         friends.new_undirected_connection([people[first].clone(), people[second].clone()], ());
+    ///     }
+    /// }
+    /// friends.new_undirected_connection([people[0].clone(), people[4].clone()], ());
+    ///
+    /// let core = friends.k_core(3);
+    /// assert!(core.find_thing_equal(&"d").is_some());
+    /// assert!(core.find_thing_equal(&"e").is_none());
+    /// assert!(friends.k_core(4).find_thing_equal(&"a").is_none());
+    /// ```
+    pub fn k_core(&self, k: usize) -> Things<T, C>
+    where
+        T: Clone,
+        C: Clone,
+    {
+        let (things, index) = self.index_things();
+        let removed = self.peel_below(&things, &index, k, 0);
+
+        let mut core = Things::new();
+        self.copy_live_into(&mut core, |thing| {
+            index
+                .get(&thing.key())
+                .is_some_and(|&position| !removed[position])
+        });
+        core
+    }
+
+    /// Peels away the live things with fewer than `k` live connections to the
+    /// things not yet peeled, as `k_core` and `trim_leaves` do.
+    ///
+    /// Each round peels every thing that is below `k` at its start, and only
+    /// the neighbors of those can fall below `k` for the next round, so the
+    /// whole peeling takes time linear in the size of the graph. This runs for
+    /// `rounds` rounds, or until a round finds nothing to peel if `rounds` is 0.
+    ///
+    /// # Returns
+    /// Whether each of `things`, as listed by `index_things`, was peeled.
+    fn peel_below(
+        &self,
+        things: &[Thing<T, C>],
+        index: &BTreeMap<usize, usize>,
+        k: usize,
+        rounds: usize,
+    ) -> Vec<bool> {
+        let mut adjacent: Vec<Vec<usize>> = things.iter().map(|_| Vec::new()).collect();
+        for connection in self
+            .connections
+            .iter()
+            .filter(|connection| connection.is_alive())
+        {
+            let [first, second] = connection.ends();
+            if let (Some(&first), Some(&second)) =
+                (index.get(&first.key()), index.get(&second.key()))
+            {
+                adjacent[first].push(second);
+                adjacent[second].push(first);
+            }
+        }
+
+        let mut degree: Vec<usize> = adjacent.iter().map(|neighbors| neighbors.len()).collect();
+        let mut peeled = alloc::vec![false; things.len()];
+        let mut below: Vec<usize> = (0..things.len())
+            .filter(|&position| degree[position] < k)
+            .collect();
+        let mut round = 0;
+        while !below.is_empty() && (rounds == 0 || round < rounds) {
+            // Peel the whole round first, so it only sees the degrees at its start
+            for &position in below.iter() {
+                peeled[position] = true;
+            }
+            let mut next = Vec::new();
+            for &position in below.iter() {
+                for &neighbor in adjacent[position].iter() {
+                    if !peeled[neighbor] {
+                        degree[neighbor] -= 1;
+                        // Each thing falls below `k` only once
+                        if degree[neighbor] + 1 == k {
+                            next.push(neighbor);
+                        }
+                    }
+                }
+            }
+            below = next;
+            round += 1;
+        }
+        peeled
+    }
+
+    /// Removes all dead things and connections from memory.
+    ///
+    /// This performs the actual cleanup of items that were previously marked
+    /// as dead. After cleaning, only live items remain in the graph and the
+    /// dead count is reset to zero.
+    ///
+    /// This operation can be expensive for large graphs, so it's typically
+    /// called strategically based on memory pressure or at natural breakpoints
+    /// in your application.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// # let mut graph = Things::<&str, &str>::new();
+    ///
+    /// // Clean up when memory pressure gets high
+    /// if graph.dead_percentage().unwrap_or(0) > 30 {
+    ///     graph.clean();
+    ///     println!("Graph cleaned");
+    /// }
+    /// ```
+    pub fn clean(&mut self) {
+        self.check_dead_count();
+
+        self.things.retain_mut(|thing| {
+            if thing.is_alive() {
+                thing.clean();
+                true
+            } else {
+                // Break the reference cycle through its connections so the
+                // thing is freed once no handles to it remain
+                thing.inner.borrow_mut().connections.clear();
+                false
+            }
+        });
+
+        self.connections.retain(|connection| connection.is_alive());
+
+        self.finish_clean();
+    }
+
+    /// Removes all dead things and connections, like `clean`, then reports
+    /// each removed one to `on_removed`.
+    ///
+    /// Things are reported first, then connections, each in container order.
+    /// A reported thing has already lost its connection list, but its data
+    /// can still be read, which helps with releasing resources tied to it.
+    /// Reporting starts once the container is fully cleaned, so a panic in
+    /// `on_removed` leaves the graph consistent.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut graph = Things::<&str, ()>::new();
+    /// let a = graph.new_thing("a");
+    /// let b = graph.new_thing("b");
+    /// graph.new_undirected_connection([a, b], ());
+    /// graph.kill_things(|name| *name == "a");
+    ///
+    /// let mut removed = 0;
+    /// graph.clean_with_progress(|_| removed += 1);
+    /// assert_eq!(removed, 2);
+    /// ```
+    pub fn clean_with_progress(&mut self, on_removed: impl FnMut(CleanedItem<T, C>)) {
+        self.check_dead_count();
+
+        let mut removed = Vec::new();
+        self.things.retain_mut(|thing| {
+            if thing.is_alive() {
+                thing.clean();
+                true
+            } else {
+                thing.inner.borrow_mut().connections.clear();
+                removed.push(CleanedItem::Thing(thing.clone()));
+                false
+            }
+        });
+
+        self.connections.retain(|connection| {
+            let alive = connection.is_alive();
+            if !alive {
+                removed.push(CleanedItem::Connection(connection.clone()));
+            }
+            alive
+        });
+
+        self.finish_clean();
+        removed.into_iter().for_each(on_removed);
+    }
+
+    /// Removes all dead things and connections, like `clean`, without keeping
+    /// the order of the survivors.
+    ///
+    /// Each dead item is swapped with the last one before it is removed, so
+    /// fewer items are moved than with `clean` when many are dead. The order
+    /// of things and connections in the container is scrambled, which changes
+    /// the results of everything that goes by container order, such as
+    /// `find_thing_equal` with duplicate data or the indices handed out by
+    /// `compile`. Connection ids and cursors don't depend on
+    /// container order, and each thing's own connection list keeps its order.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut graph = Things::<u32, ()>::new();
+    /// for number in 0..6 {
+    ///     graph.new_thing(number);
+    /// }
+    /// graph.kill_things(|thing| thing.access(|number| number % 2 == 0));
+    ///
+    /// graph.clean_unordered();
+    /// assert_eq!(graph.dead_percentage(), Ok(0));
+    /// assert_eq!(graph.things_rev().count(), 3);
+    /// ```
+    pub fn clean_unordered(&mut self) {
+        self.check_dead_count();
+
+        let mut position = 0;
+        while position < self.things.len() {
+            let thing = &mut self.things[position];
+            if thing.is_alive() {
+                thing.clean();
+                position += 1;
+            } else {
+                thing.inner.borrow_mut().connections.clear();
+                self.things.swap_remove(position);
+            }
+        }
+
+        let mut position = 0;
+        while position < self.connections.len() {
+            if self.connections[position].is_alive() {
+                position += 1;
+            } else {
+                self.connections.swap_remove(position);
+            }
+        }
+
+        self.finish_clean();
+    }
+
+    /// With the `debug-invariants` feature, panics if the dead count drifted.
+    fn check_dead_count(&mut self) {
+        #[cfg(feature = "debug-invariants")]
+        {
+            let report = self.recount();
+            assert!(!report.corrected, "the dead count drifted: {report:?}");
+        }
+    }
+
+    /// Updates the bookkeeping once every dead item has left the container.
+    fn finish_clean(&mut self) {
+        if let Some(classes) = self.classes.as_mut() {
+            for bucket in classes.buckets.values_mut() {
+                bucket.retain(|connection| connection.is_alive());
+            }
+        }
+
+        // Cached lookups could otherwise keep removed things in memory
+        self.lookup_cache.entries.clear();
+        self.dead_amount = 0;
+        self.touch();
+    }
+
+    /// Does a bounded amount of cleanup work, for callers with a fixed
+    /// per-frame maintenance budget.
+    ///
+    /// Unlike `clean`, which removes every dead item at once, this removes at
+    /// most `budget.max_items` dead things and connections per call, connections
+    /// first. Calling it once per frame spreads the cost of cleaning over time
+    /// while the graph converges to being fully clean.
+    ///
+    /// # Returns
+    /// A report of the work done, the dead percentage before and after, and
+    /// whether dead items remain for later calls.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut world = Things::<u32, ()>::new();
+    /// for id in 0..10 {
+    ///     world.new_thing(id);
+    /// }
+    /// world.kill_things(|thing| thing.access(|id| id % 2 == 0));
+    ///
+    /// // Each frame does at most two items of cleanup
+    /// let mut frames = 0;
+    /// while world.maintain(MaintenanceBudget { max_items: 2 }).more_work {
+    ///     frames += 1;
+    /// }
+    /// assert_eq!(frames, 2);
+    /// assert_eq!(world.dead_percentage(), Ok(0));
+    /// ```
+    pub fn maintain(&mut self, budget: MaintenanceBudget) -> MaintenanceReport {
+        let pressure_before = self.dead_percentage().ok();
+
+        let dead_connections: Vec<_> = self
+            .connections
+            .iter()
+            .filter(|connection| !connection.is_alive())
+            .take(budget.max_items)
+            .cloned()
+            .collect();
+        if !dead_connections.is_empty() {
+            self.detach_connections(&dead_connections);
+        }
+
+        let mut thing_budget = budget.max_items - dead_connections.len();
+        let mut removed_things = 0;
+        self.things.retain(|thing| {
+            if thing_budget == 0 || thing.is_alive() {
+                return true;
+            }
+            // Break the reference cycle through its connections, as in `clean`
+            thing.inner.borrow_mut().connections.clear();
+            thing_budget -= 1;
+            removed_things += 1;
+            false
+        });
+        if removed_things > 0 {
+            self.lookup_cache.entries.clear();
+            self.touch();
+        }
+
+        let removed = dead_connections.len() + removed_things;
+        let more_work = self.things.iter().any(|thing| !thing.is_alive())
+            || self
+                .connections
+                .iter()
+                .any(|connection| !connection.is_alive());
+        self.dead_amount = if more_work {
+            self.dead_amount.saturating_sub(removed)
+        } else {
+            0
+        };
+
+        MaintenanceReport {
+            removed,
+            pressure_before,
+            pressure_after: self.dead_percentage().ok(),
+            more_work,
+        }
+    }
+
+    /// Returns up to `n` of the most recently created live things, newest first.
+    ///
+    /// Recency follows the order in which the things were created by this
+    /// container, regardless of how they are currently stored.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut graph = Things::<&str, ()>::new();
+    /// graph.new_thing("first");
+    /// graph.new_thing("second");
+    /// graph.new_thing("third");
+    ///
+    /// let recent = graph.recent_things(2);
+    /// assert!(recent[0] == "third");
+    /// assert!(recent[1] == "second");
+    /// ```
+    pub fn recent_things(&self, n: usize) -> Vec<Thing<T, C>> {
+        let mut recent: Vec<_> = self
+            .things
+            .iter()
+            .filter(|thing| thing.is_alive())
+            .cloned()
+            .collect();
+        recent.sort_by_key(|thing| core::cmp::Reverse(thing.seq()));
+        recent.truncate(n);
+        recent
+    }
+
+    /// Returns up to `n` of the most recently created live connections, newest first.
+    ///
+    /// Like `recent_things`, recency follows creation order rather than storage order.
+    pub fn recent_connections(&self, n: usize) -> Vec<Connection<T, C>> {
+        let mut recent: Vec<_> = self
+            .connections
+            .iter()
+            .filter(|connection| connection.is_alive())
+            .cloned()
+            .collect();
+        recent.sort_by_key(|connection| core::cmp::Reverse(connection.seq()));
+        recent.truncate(n);
+        recent
+    }
+
+    /// Returns a page of up to `limit` live things created after the cursor `seq`.
+    ///
+    /// Things come in creation order. Pass `0` to get the first page, then
+    /// pass back the returned cursor to get the next one. The cursor is the
+    /// sequence number of the last thing handed out, so paging is unaffected
+    /// by kills, cleans and insertions between calls: things killed before
+    /// their page is reached are skipped, and new things show up on later
+    /// pages. Only `limit` handles are held at a time, however big the graph.
+    ///
+    /// # Returns
+    /// The page, and the cursor for the next page, or `None` if no live thing
+    /// comes after this page. A `limit` of 0 gives an empty page and no
+    /// cursor, so a paging loop can't get stuck on it.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut graph = Things::<u32, ()>::new();
+    /// for number in 0..5 {
+    ///     graph.new_thing(number);
+    /// }
+    ///
+    /// let (page, cursor) = graph.cursor_after(0, 3);
+    /// assert_eq!(page.len(), 3);
+    /// let (page, cursor) = graph.cursor_after(cursor.unwrap(), 3);
+    /// assert!(page[0] == 3 && page[1] == 4);
+    /// assert!(cursor.is_none());
+    /// ```
+    pub fn cursor_after(&self, seq: u64, limit: usize) -> (Vec<Thing<T, C>>, Option<u64>) {
+        page_after(&self.things, seq, limit, |thing| {
+            thing.is_alive().then(|| thing.seq())
+        })
+    }
+
+    /// Returns a page of up to `limit` live connections created after the cursor `seq`.
+    ///
+    /// Works like `cursor_after`, in connection creation order.
+    pub fn connection_cursor_after(
+        &self,
+        seq: u64,
+        limit: usize,
+    ) -> (Vec<Connection<T, C>>, Option<u64>) {
+        page_after(&self.connections, seq, limit, |connection| {
+            connection.is_alive().then(|| connection.seq())
+        })
+    }
+
+    /// Sums up a number extracted from every live thing's data.
+    ///
+    /// Each live thing is borrowed once and nothing is allocated, which suits
+    /// exporting gauges on every scrape.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut servers = Things::<u64, ()>::new();
+    /// for load in [30, 70, 50] {
+    ///     servers.new_thing(load);
+    /// }
+    ///
+    /// let load = servers.aggregate_things(|load| *load);
+    /// assert_eq!((load.count, load.sum), (3, 150));
+    /// assert_eq!((load.min, load.max), (Some(30), Some(70)));
+    /// ```
+    pub fn aggregate_things(&self, extract: impl Fn(&T) -> u64) -> AggregateResult {
+        self.aggregate_things_filtered(|_| true, extract)
+    }
+
+    /// Like `aggregate_things`, but only over live things whose data `filter` accepts.
+    pub fn aggregate_things_filtered(
+        &self,
+        filter: impl Fn(&T) -> bool,
+        extract: impl Fn(&T) -> u64,
+    ) -> AggregateResult {
+        let mut result = AggregateResult::default();
+        for thing in &self.things {
+            let inner = thing.inner.borrow();
+            if inner.is_alive && filter(&inner.data) {
+                result.add(extract(&inner.data));
+            }
+        }
+        result
+    }
+
+    /// Sums up a number extracted from every live connection's data, like
+    /// `aggregate_things` does for things.
+    pub fn aggregate_connections(&self, extract: impl Fn(&C) -> u64) -> AggregateResult {
+        self.aggregate_connections_filtered(|_| true, extract)
+    }
+
+    /// Like `aggregate_connections`, but only over live connections whose data
+    /// `filter` accepts.
+    pub fn aggregate_connections_filtered(
+        &self,
+        filter: impl Fn(&C) -> bool,
+        extract: impl Fn(&C) -> u64,
+    ) -> AggregateResult {
+        let mut result = AggregateResult::default();
+        for connection in &self.connections {
+            let inner = connection.inner.borrow();
+            if inner.is_alive() && filter(inner.get_data()) {
+                result.add(extract(inner.get_data()));
+            }
+        }
+        result
+    }
+
+    /// Lists every live thing with its live connections, for rendering.
+    ///
+    /// Things come in creation order, and each thing's connections in the
+    /// order the thing stores them, which is the order they were attached.
+    /// A self-loop is listed once. Both orders only depend on creation, so
+    /// calling this again gives the same order as long as nothing changes,
+    /// and `clean` never reorders the items that survive it.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut graph = Things::<&str, &str>::new();
+    /// let a = graph.new_thing("a");
+    /// let b = graph.new_thing("b");
+    /// graph.new_directed_connection(a.clone(), "first", b.clone());
+    /// graph.new_directed_connection(b, "second", a);
+    ///
+    /// let listing = graph.adjacency_listing();
+    /// assert!(listing[0].0 == "a");
+    /// assert!(listing[0].1[0] == "first" && listing[0].1[1] == "second");
+    /// ```
+    pub fn adjacency_listing(&self) -> Vec<AdjacencyRow<T, C>> {
+        let mut things: Vec<_> = self
+            .things
+            .iter()
+            .filter(|thing| thing.is_alive())
+            .cloned()
+            .collect();
+        things.sort_by_key(|thing| thing.seq());
+        things
+            .into_iter()
+            .map(|thing| {
+                let mut seen = BTreeSet::new();
+                let connections = thing.with_connections(|connections| {
+                    connections
+                        .iter()
+                        .filter(|connection| connection.is_alive())
+                        .filter(|connection| seen.insert(connection.key()))
+                        .cloned()
+                        .collect()
+                });
+                (thing, connections)
+            })
+            .collect()
+    }
+
+    /// Iterates over all things in the container, newest first.
+    ///
+    /// Like `do_for_all_things`, this includes things that are dead but not yet cleaned.
+    pub fn things_rev(&self) -> impl Iterator<Item = &Thing<T, C>> {
+        let mut things: Vec<_> = self.things.iter().collect();
+        things.sort_by_key(|thing| core::cmp::Reverse(thing.seq()));
+        let things = things.into_iter();
+        #[cfg(feature = "dead-access-audit")]
+        let things = things.inspect(|thing| thing.audit_if_dead("Things::things_rev"));
+        things
+    }
+
+    /// Registers a classifier that sorts connections into numbered classes.
+    ///
+    /// This is meant for connection data with a handful of kinds, such as a
+    /// large enum, where "all connections of kind X" is a frequent query. Every
+    /// connection is bucketed by its class when it is created, so
+    /// `connections_of_class` and `count_of_class` only look at the connections
+    /// of the requested class instead of scanning the whole graph.
+    ///
+    /// Connections that already exist are classified once when the classifier
+    /// is registered, and registering again replaces the previous classifier.
+    /// A connection's class is computed only once, so changing its data through
+    /// `access_mut` does not move it to another class.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// #[derive(PartialEq)]
+    /// enum Relation {
+    ///     Follows,
+    ///     Blocks,
+    /// }
+    ///
+    /// let mut graph = Things::<&str, Relation>::new();
+    /// let alice = graph.new_thing("alice");
+    /// let bob = graph.new_thing("bob");
+    ///
+    /// graph.register_connection_class(|relation| match relation {
+    ///     Relation::Follows => 0,
+    ///     Relation::Blocks => 1,
+    /// });
+    /// graph.new_directed_connection(alice, Relation::Follows, bob);
+    ///
+    /// assert_eq!(graph.count_of_class(0), 1);
+    /// assert_eq!(graph.count_of_class(1), 0);
+    /// ```
+    pub fn register_connection_class(&mut self, classify: impl Fn(&C) -> u16 + 'static) {
+        let mut classes = ConnectionClasses {
+            classify: Box::new(classify),
+            buckets: BTreeMap::new(),
+        };
+        for connection in self.connections.iter() {
+            classes.insert(connection.clone());
+        }
+        self.classes = Some(classes);
+    }
+
+    /// Recomputes the data of every live connection from its endpoints.
+    ///
+    /// `relabel` receives the source, the current data and the target of each
+    /// directed connection, and returns the new data. Undirected connections
+    /// pass their endpoints in the order they were given at creation. This is
+    /// handy after importing a graph, for example to turn a generic "relates"
+    /// label into a specific one based on what it connects.
+    ///
+    /// If a connection classifier is registered, connections are reclassified
+    /// with their new data.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut graph = Things::<&str, &str>::new();
+    /// let alice = graph.new_thing("alice");
+    /// let report = graph.new_thing("report.pdf");
+    /// let link = graph.new_directed_connection(alice, "relates", report);
+    ///
+    /// graph.relabel_connections(|from, data, to| {
+    ///     if to.access(|name| name.ends_with(".pdf")) { "authored" } else { *data }
+    /// });
+    /// assert!(link == "authored");
+    /// ```
+    pub fn relabel_connections(&mut self, relabel: impl Fn(&Thing<T, C>, &C, &Thing<T, C>) -> C) {
+        for connection in self
+            .connections
+            .iter()
+            .filter(|connection| connection.is_alive())
+        {
+            let [from, to] = connection.ends();
+            let data = connection.access(|data| relabel(&from, data, &to));
+            *connection.inner.borrow_mut().get_data_mut() = data;
+        }
+
+        self.reclassify_connections();
+    }
+
+    /// Sorts every connection in the container into its class bucket again,
+    /// after their data or the container changed under the classifier.
+    fn reclassify_connections(&mut self) {
+        if let Some(classes) = self.classes.as_mut() {
+            classes.buckets.clear();
+            for connection in self.connections.iter() {
+                classes.insert(connection.clone());
+            }
+        }
+    }
+
+    /// Collapses live parallel connections into one, folding their data with `merge`.
+    ///
+    /// Connections are parallel when they join the same things and are either
+    /// both directed or both undirected. If `between_same_endpoints` is true,
+    /// directed connections must also go the same way; otherwise `a -> b` and
+    /// `b -> a` are merged too, keeping the direction of the earlier one.
+    ///
+    /// In every group the earliest connection survives. Its data is folded
+    /// with `merge(merged_data, other_data)` over each later one, in container
+    /// order, and written back once the whole group is merged; nothing is
+    /// borrowed while `merge` runs. The others are killed and removed from
+    /// both endpoints and from the container right away, so they don't add to
+    /// the dead amount. Handles to them still work and keep their data.
+    ///
+    /// # Returns
+    /// The number of connections removed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut chat = Things::<&str, u32>::new();
+    /// let alice = chat.new_thing("alice");
+    /// let bob = chat.new_thing("bob");
+    /// chat.new_directed_connection(alice.clone(), 1, bob.clone());
+    /// chat.new_directed_connection(bob.clone(), 1, alice.clone());
+    ///
+    /// assert_eq!(chat.merge_parallel_connections(false, |a, b| a + b), 1);
+    /// let remaining = alice.do_for_all_connections(|connection| Do::Take(connection.clone()));
+    /// assert_eq!(remaining.len(), 1);
+    /// assert!(remaining[0] == 2);
+    /// ```
+    pub fn merge_parallel_connections(
+        &mut self,
+        between_same_endpoints: bool,
+        merge: impl Fn(C, C) -> C,
+    ) -> usize
+    where
+        C: Clone,
+    {
+        let mut groups: Vec<Vec<Connection<T, C>>> = Vec::new();
+        let mut group_of: BTreeMap<(usize, usize, bool), usize> = BTreeMap::new();
+        for connection in self
+            .connections
+            .iter()
+            .filter(|connection| connection.is_alive())
+        {
+            let [first, second] = connection.ends();
+            let (first, second) = (first.key(), second.key());
+            let directed = connection.is_directed();
+            let pair = if directed && between_same_endpoints {
+                (first, second, directed)
+            } else {
+                (first.min(second), first.max(second), directed)
+            };
+            let group = *group_of.entry(pair).or_insert_with(|| {
+                groups.push(Vec::new());
+                groups.len() - 1
+            });
+            groups[group].push(connection.clone());
+        }
+
+        // Settle the structure before running `merge`, so a panicking merge
+        // leaves a consistent graph behind
+        let mut removed = Vec::new();
+        for group in groups.iter().filter(|group| group.len() > 1) {
+            for other in &group[1..] {
+                self.dead_amount = self.dead_amount.saturating_add(1);
+                other.kill();
+                removed.push(other.clone());
+            }
+        }
+        if removed.is_empty() {
+            return 0;
+        }
+
+        self.detach_connections(&removed);
+        self.dead_amount = self.dead_amount.saturating_sub(removed.len());
+        self.reclassify_connections();
+
+        for group in groups.iter().filter(|group| group.len() > 1) {
+            let survivor = &group[0];
+            let merged = group[1..]
+                .iter()
+                .fold(survivor.access(C::clone), |merged, other| {
+                    merge(merged, other.access(C::clone))
+                });
+            *survivor.inner.borrow_mut().get_data_mut() = merged;
+        }
+        // Merged data may fall into another class
+        self.reclassify_connections();
+        removed.len()
+    }
+
+    /// Returns the live connections of the given class.
+    ///
+    /// Returns an empty list if no classifier has been registered.
+    pub fn connections_of_class(&self, class: u16) -> Vec<Connection<T, C>> {
+        self.class_bucket(class)
+            .iter()
+            .filter(|connection| connection.is_alive())
+            .cloned()
+            .collect()
+    }
+
+    /// Counts the live connections of the given class.
+    ///
+    /// Returns zero if no classifier has been registered.
+    pub fn count_of_class(&self, class: u16) -> usize {
+        self.class_bucket(class)
+            .iter()
+            .filter(|connection| connection.is_alive())
+            .count()
+    }
+
+    fn class_bucket(&self, class: u16) -> &[Connection<T, C>] {
+        self.classes
+            .as_ref()
+            .and_then(|classes| classes.buckets.get(&class))
+            .map_or(&[], |bucket| bucket.as_slice())
+    }
+
+    /// Finds the live connections that `GraphMode::Simple` would not allow.
+    ///
+    /// These are self-loops, and connections parallel to an earlier live
+    /// connection: going the same way between the same things for directed
+    /// connections, or joining the same two things for undirected ones. The
+    /// earliest connection of a parallel group is not reported. Use this to
+    /// audit a multigraph before switching it to simple mode.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut graph = Things::<&str, &str>::new();
+    /// let a = graph.new_thing("a");
+    /// let b = graph.new_thing("b");
+    /// graph.new_undirected_connection([a.clone(), b.clone()], "first");
+    /// graph.new_undirected_connection([b.clone(), a.clone()], "second");
+    ///
+    /// let violations = graph.violations_of_simple();
+    /// assert!(violations.len() == 1 && violations[0] == "second");
+    /// ```
+    pub fn violations_of_simple(&self) -> Vec<Connection<T, C>> {
+        let mut seen = BTreeSet::new();
+        self.connections
+            .iter()
+            .filter(|connection| connection.is_alive())
+            .filter(|connection| {
+                let [first, second] = connection.ends();
+                if first.ptr_eq(&second) {
+                    return true;
+                }
+                let (first, second) = (first.key(), second.key());
+                let pair = if connection.is_directed() {
+                    (first, second, true)
+                } else {
+                    (first.min(second), first.max(second), false)
+                };
+                !seen.insert(pair)
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Reports anomalies that aren't consistency errors but are often
+    /// unintended, especially in imported data.
+    ///
+    /// Only live items are considered. The warnings are, in this order:
+    /// - `StructuralWarning::SelfLoop` for every connection from a thing to itself
+    /// - `StructuralWarning::ParallelEdge` for every connection that repeats an
+    ///   earlier connection with equal data between the same things, going the
+    ///   same way if directed
+    /// - `StructuralWarning::IsolatedThing` for every thing without connections
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut imported = Things::<&str, &str>::new();
+    /// let a = imported.new_thing("a");
+    /// let b = imported.new_thing("b");
+    /// imported.new_directed_connection(a.clone(), "cites", b.clone());
+    /// imported.new_directed_connection(a, "cites", b);
+    ///
+    /// let warnings = imported.structural_warnings();
+    /// assert_eq!(warnings.len(), 1);
+    /// assert!(matches!(warnings[0], StructuralWarning::ParallelEdge { .. }));
+    /// ```
+    pub fn structural_warnings(&self) -> Vec<StructuralWarning<T, C>>
+    where
+        C: PartialEq,
+    {
+        let mut self_loops = Vec::new();
+        let mut parallel = Vec::new();
+        let mut between: BTreeMap<(usize, usize, bool), Vec<Connection<T, C>>> = BTreeMap::new();
+        for connection in self
+            .connections
+            .iter()
+            .filter(|connection| connection.is_alive())
+        {
+            let [first, second] = connection.ends();
+            if first.ptr_eq(&second) {
+                self_loops.push(StructuralWarning::SelfLoop(connection.clone()));
+            }
+            let (first, second) = (first.key(), second.key());
+            let pair = if connection.is_directed() {
+                (first, second, true)
+            } else {
+                (first.min(second), first.max(second), false)
+            };
+            let earlier = between.entry(pair).or_default();
+            if let Some(original) = earlier.iter().find(|earlier| *earlier == connection) {
+                parallel.push(StructuralWarning::ParallelEdge {
+                    original: original.clone(),
+                    duplicate: connection.clone(),
+                });
+            } else {
+                earlier.push(connection.clone());
+            }
+        }
+
+        let isolated = self
+            .things
+            .iter()
+            .filter(|thing| thing.is_alive() && thing.neighbors_with_edges().is_empty())
+            .map(|thing| StructuralWarning::IsolatedThing(thing.clone()));
+
+        self_loops
+            .into_iter()
+            .chain(parallel)
+            .chain(isolated)
+            .collect()
+    }
+
+    /// Lists the changes that turn this graph into `other`.
+    ///
+    /// Graphs usually don't share handles, so things are matched by data and
+    /// connections by their data, the data of their ends and whether they are
+    /// directed. An undirected connection matches with its ends in either
+    /// order. Repeated payloads are matched one to one, so a graph with two
+    /// equal things differs from one with three. Only live items are compared.
+    ///
+    /// # Returns
+    /// Payloads present only in `other` as added and payloads present only in
+    /// `self` as removed, each in container order.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut before = Things::<&str, &str>::new();
+    /// let draft = before.new_thing("draft");
+    /// let review = before.new_thing("review");
+    /// before.new_directed_connection(draft, "then", review);
+    ///
+    /// let mut after = Things::<&str, &str>::new();
+    /// let draft = after.new_thing("draft");
+    /// let publish = after.new_thing("publish");
+    /// after.new_directed_connection(draft, "then", publish);
+    ///
+    /// let diff = before.diff(&after);
+    /// assert_eq!(diff.added_things, ["publish"]);
+    /// assert_eq!(diff.removed_things, ["review"]);
+    /// assert_eq!(diff.added_connections[0].ends, ["draft", "publish"]);
+    /// assert_eq!(diff.removed_connections[0].ends, ["draft", "review"]);
+    /// ```
+    pub fn diff(&self, other: &Self) -> GraphDiff<T, C>
+    where
+        T: PartialEq + Clone,
+        C: PartialEq + Clone,
+    {
+        let things = |graph: &Self| -> Vec<T> {
+            graph
+                .things
+                .iter()
+                .filter(|thing| thing.is_alive())
+                .map(|thing| thing.access(|data| data.clone()))
+                .collect()
+        };
+        let connections = |graph: &Self| -> Vec<DiffConnection<T, C>> {
+            graph
+                .connections
+                .iter()
+                .filter(|connection| connection.is_alive())
+                .map(|connection| {
+                    let [first, second] = connection.ends();
+                    DiffConnection {
+                        ends: [
+                            first.access(|data| data.clone()),
+                            second.access(|data| data.clone()),
+                        ],
+                        data: connection.access(|data| data.clone()),
+                        directed: connection.is_directed(),
+                    }
+                })
+                .collect()
+        };
+        let same_connection = |first: &DiffConnection<T, C>, second: &DiffConnection<T, C>| {
+            let [a, b] = &first.ends;
+            let [c, d] = &second.ends;
+            first.directed == second.directed
+                && first.data == second.data
+                && ((a == c && b == d) || (!first.directed && a == d && b == c))
+        };
+
+        let (old_things, new_things) = (things(self), things(other));
+        let (old_connections, new_connections) = (connections(self), connections(other));
+        GraphDiff {
+            added_things: unmatched(&new_things, &old_things, |a, b| a == b),
+            removed_things: unmatched(&old_things, &new_things, |a, b| a == b),
+            added_connections: unmatched(&new_connections, &old_connections, same_connection),
+            removed_connections: unmatched(&old_connections, &new_connections, same_connection),
+        }
+    }
+
+    /// Finds live connections with an endpoint that is not part of this container.
+    ///
+    /// This can happen when connecting things that belong to another container
+    /// (or to none), or after using the `unsafe` connection APIs directly.
+    /// Endpoints are looked up by identity, so things with equal data don't
+    /// hide each other.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut graph = Things::<&str, &str>::new();
+    /// let local = graph.new_thing("local");
+    /// let stranger = Thing::new("stranger");
+    ///
+    /// graph.new_directed_connection(local, "knows", stranger);
+    ///
+    /// assert_eq!(graph.orphaned_connections().len(), 1);
+    /// ```
+    pub fn orphaned_connections(&self) -> Vec<Connection<T, C>> {
+        let present: BTreeSet<usize> = self.things.iter().map(|thing| thing.key()).collect();
+        self.connections
+            .iter()
+            .filter(|connection| {
+                connection.is_alive()
+                    && connection
+                        .ends()
+                        .iter()
+                        .any(|thing| !present.contains(&thing.key()))
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Kills and removes every connection reported by `orphaned_connections`.
+    ///
+    /// The connections are taken out of both endpoints' connection lists and
+    /// out of the container right away, so they don't add to the dead amount.
+    ///
+    /// # Returns
+    /// The number of connections removed.
+    pub fn prune_orphaned(&mut self) -> usize {
+        let orphaned = self.orphaned_connections();
+        for connection in orphaned.iter() {
+            connection.kill();
+        }
+        self.detach_connections(&orphaned);
+        orphaned.len()
+    }
+
+    /// Adds the reverse of every live directed connection that doesn't have one yet.
+    ///
+    /// For each live directed connection from `a` to `b`, a connection from `b`
+    /// back to `a` is created with data derived by `data`, unless a live directed
+    /// connection from `b` to `a` already exists. Endpoints are compared by
+    /// identity. Parallel connections from `a` to `b` share a single reverse
+    /// connection, and self-loops are their own reverse so they are left alone.
+    ///
+    /// # Returns
+    /// The number of connections created.
+    ///
+    /// # Panics
+    /// If the reverses don't all fit under the container's connection limit,
+    /// in which case none of them is added.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut family = Things::<&str, &str>::new();
+    /// let parent = family.new_thing("parent");
+    /// let child = family.new_thing("child");
+    /// family.new_directed_connection(parent, "parent_of", child.clone());
+    ///
+    /// let added = family.symmetrize(|_| "child_of");
+    ///
+    /// assert_eq!(added, 1);
+    /// assert!(child.do_for_a_connection(|conn| {
+    ///     if conn == &"child_of" { Do::Take(()) } else { Do::Nothing }
+    /// }).is_some());
+    /// ```
+    pub fn symmetrize(&mut self, data: impl Fn(&C) -> C) -> usize {
+        // Work out every reverse first, so the limit is checked before any is added
+        let mut planned = BTreeSet::new();
+        let mut reverses = Vec::new();
+        for connection in self
+            .connections
+            .iter()
+            .filter(|connection| connection.is_alive())
+        {
+            let Some((from, to)) = connection.directed_ends() else {
+                continue;
+            };
+            if from.ptr_eq(&to) || !planned.insert((to.key(), from.key())) {
+                continue;
+            }
+            let has_reverse = to.with_connections(|others| {
+                others.iter().any(|other| other.goes_between(&to, &from))
+            });
+            if !has_reverse {
+                let reverse_data = connection.access(|existing| data(existing));
+                reverses.push((to, reverse_data, from));
+            }
+        }
+
+        if reverses.len() > self.remaining_capacity().1 {
+            panic!(
+                "cannot create connection: the limit of {} connections is reached, clean dead connections first",
+                self.config.max_connections
+            );
+        }
+        let added = reverses.len();
+        for (to, reverse_data, from) in reverses {
+            self.new_directed_connection(to, reverse_data, from);
+        }
+        added
+    }
+
+    /// Kills directed connections that merely repeat an older connection in reverse.
+    ///
+    /// Connections are paired up in creation order: a live directed connection
+    /// from `b` to `a` is killed when an older live directed connection from
+    /// `a` to `b` exists whose data matches according to `eq`, called as
+    /// `eq(older, newer)`. Each older connection absorbs at most one reverse,
+    /// endpoints are compared by identity, and self-loops are never removed.
+    /// This undoes `symmetrize` when `eq` recognises the derived data.
+    ///
+    /// # Returns
+    /// The number of connections killed.
+    pub fn drop_reverse_duplicates(&mut self, eq: impl Fn(&C, &C) -> bool) -> usize {
+        let mut directed: Vec<_> = self
+            .connections
+            .iter()
+            .filter(|connection| connection.is_alive())
+            .filter_map(|connection| {
+                let (from, to) = connection.directed_ends()?;
+                Some((connection.clone(), from, to))
+            })
+            .collect();
+        directed.sort_by_key(|(connection, _, _)| connection.seq());
+
+        let mut partnered = alloc::vec![false; directed.len()];
+        let mut dropped = 0;
+        for newer in 0..directed.len() {
+            let (connection, from, to) = &directed[newer];
+            if from.ptr_eq(to) || partnered[newer] {
+                continue;
+            }
+            let partner = (0..newer).find(|&older| {
+                let (older_connection, older_from, older_to) = &directed[older];
+                !partnered[older]
+                    && older_connection.is_alive()
+                    && older_from.ptr_eq(to)
+                    && older_to.ptr_eq(from)
+                    && older_connection
+                        .access(|older_data| connection.access(|data| eq(older_data, data)))
+            });
+            if let Some(older) = partner {
+                partnered[older] = true;
+                if dropped == 0 {
+                    self.touch();
+                }
+                self.dead_amount = self.dead_amount.saturating_add(1);
+                dropped += 1;
+                connection.kill();
+            }
+        }
+
+        dropped
+    }
+
+    /// Splits the graph into its weakly connected components.
+    ///
+    /// Each component becomes an independent container holding its things and
+    /// the connections between them, ignoring direction when deciding what is
+    /// connected. The things keep their existing connections, so the handles
+    /// stay interlinked within each component. This is useful for handing
+    /// independent subgraphs to separate workers or rendering passes.
+    ///
+    /// Dead things and connections are cleaned away first, and components are
+    /// returned in the order of their oldest thing. A registered connection
+    /// class registry is not carried over.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut graph = Things::<&str, ()>::new();
+    /// let a = graph.new_thing("a");
+    /// let b = graph.new_thing("b");
+    /// graph.new_thing("c");
+    /// graph.new_directed_connection(a, (), b);
+    ///
+    /// let components = graph.split_components();
+    /// assert_eq!(components.len(), 2);
+    /// ```
+    pub fn split_components(mut self) -> Vec<Things<T, C>> {
+        self.clean();
+        let (things, component_of) = self.weak_components();
+
+        let mut components: Vec<Things<T, C>> = Vec::new();
+        for thing in things {
+            let component = component_of[&thing.key()];
+            if component == components.len() {
+                let mut new = Things::with_config(self.config);
+                new.next_seq = self.next_seq;
+                #[cfg(feature = "dead-access-audit")]
+                {
+                    new.audit = self.audit.clone();
+                }
+                components.push(new);
+            }
+            components[component].things.push(thing);
+        }
+
+        for connection in self.connections.drain(..) {
+            let [first, second] = connection.ends();
+            let component = component_of
+                .get(&first.key())
+                .or_else(|| component_of.get(&second.key()));
+            if let Some(&component) = component {
+                components[component].connections.push(connection);
+            }
+        }
+
+        components
+    }
+
+    /// Groups the live things into weakly connected components, ignoring the
+    /// direction of live connections.
+    ///
+    /// # Returns
+    /// The live things in container order, and a map from each thing's
+    /// identity key to its component. Components are numbered from 0 in the
+    /// order of their oldest thing.
+    fn weak_components(&self) -> (Vec<Thing<T, C>>, BTreeMap<usize, usize>) {
+        let (things, index) = self.index_things();
+
+        // Union-find over thing positions, joining the ends of every connection
+        let mut parent: Vec<usize> = (0..things.len()).collect();
+        fn root(parent: &mut [usize], mut position: usize) -> usize {
+            while parent[position] != position {
+                parent[position] = parent[parent[position]];
+                position = parent[position];
+            }
+            position
+        }
+        for connection in self
+            .connections
+            .iter()
+            .filter(|connection| connection.is_alive())
+        {
+            let [first, second] = connection.ends();
+            if let (Some(&first), Some(&second)) =
+                (index.get(&first.key()), index.get(&second.key()))
+            {
+                let (first, second) = (root(&mut parent, first), root(&mut parent, second));
+                parent[first] = second;
+            }
+        }
+
+        let mut component_of_root = BTreeMap::new();
+        let mut component_of = BTreeMap::new();
+        for (position, thing) in things.iter().enumerate() {
+            let group = root(&mut parent, position);
+            let next = component_of_root.len();
+            let component = *component_of_root.entry(group).or_insert(next);
+            component_of.insert(thing.key(), component);
+        }
+        (things, component_of)
+    }
+
+    /// Numbers the live things so graph algorithms can work with plain indices.
+    ///
+    /// # Returns
+    /// The live things in container order, and a map from each thing's
+    /// identity key to its position in that list.
+    fn index_things(&self) -> (Vec<Thing<T, C>>, BTreeMap<usize, usize>) {
+        let mut things = Vec::new();
+        let mut index = BTreeMap::new();
+        for thing in self.things.iter().filter(|thing| thing.is_alive()) {
+            index.insert(thing.key(), things.len());
+            things.push(thing.clone());
+        }
+        (things, index)
+    }
+
+    /// Creates directed connections in bulk from `(from_key, label, to_key)` rows.
+    ///
+    /// Each key is turned into a thing with `resolve`, and each label into
+    /// connection data with `make_data`. A row is a duplicate when a live
+    /// directed connection with equal data already goes from the same source to
+    /// the same target (compared by identity), including connections created by
+    /// earlier rows of the same import; `on_duplicate` decides what happens then.
+    ///
+    /// Every row is checked against the container's configuration like
+    /// `try_new_directed_connection`, so a row that would break a rule or the
+    /// connection limit fails instead of panicking. In `GraphMode::Simple` a
+    /// duplicate row under `DuplicatePolicy::CreateParallel` fails this way.
+    ///
+    /// The import is all-or-nothing: if any row fails, every connection created
+    /// during this call is removed again and the graph is left as it was. The
+    /// container is not auto-cleaned during an import, so a failed import
+    /// doesn't compact it either.
+    ///
+    /// # Returns
+    /// - `Ok(ImportReport)`: How many connections were created and which rows were skipped
+    /// - `Err(ImportAbort)`: The first row that failed, after rolling back
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut graph = Things::<&str, &str>::new();
+    /// let alice = graph.new_thing("alice");
+    /// let bob = graph.new_thing("bob");
+    ///
+    /// let rows = [("alice", "follows", "bob"), ("alice", "follows", "bob")];
+    /// let report = graph
+    ///     .import_edges(
+    ///         rows,
+    ///         |key| match key {
+    ///             "alice" => Some(alice.clone()),
+    ///             "bob" => Some(bob.clone()),
+    ///             _ => None,
+    ///         },
+    ///         DuplicatePolicy::Skip,
+    ///         |label| if label == "follows" { "follows" } else { "other" },
+    ///     )
+    ///     .unwrap();
+    ///
+    /// assert_eq!(report.created, 1);
+    /// assert_eq!(report.skipped_duplicates, [1]);
+    /// ```
+    pub fn import_edges<I, S>(
+        &mut self,
+        rows: I,
+        mut resolve: impl FnMut(&str) -> Option<Thing<T, C>>,
+        on_duplicate: DuplicatePolicy,
+        make_data: impl Fn(&str) -> C,
+    ) -> Result<ImportReport, ImportAbort>
+    where
+        I: IntoIterator<Item = (S, S, S)>,
+        S: AsRef<str>,
+        C: PartialEq,
+    {
+        let mut report = ImportReport {
+            created: 0,
+            skipped_duplicates: Vec::new(),
+        };
+        let mut created = Vec::new();
+
+        for (row, (from_key, label, to_key)) in rows.into_iter().enumerate() {
+            let outcome = (|| {
+                let from = resolve(from_key.as_ref()).ok_or_else(|| ImportAbort::UnknownKey {
+                    row,
+                    key: String::from(from_key.as_ref()),
+                })?;
+                let to = resolve(to_key.as_ref()).ok_or_else(|| ImportAbort::UnknownKey {
+                    row,
+                    key: String::from(to_key.as_ref()),
+                })?;
+                let data = make_data(label.as_ref());
+
+                let is_duplicate = from.with_connections(|connections| {
+                    connections.iter().any(|connection| {
+                        let existing = connection.inner.borrow();
+                        matches!(
+                            &existing.ends,
+                            Ends::Directed {
+                                from: existing_from,
+                                to: existing_to,
+                            } if existing.is_alive
+                                && existing_from.ptr_eq(&from)
+                                && existing_to.ptr_eq(&to)
+                                && existing.data == data
+                        )
+                    })
+                });
+
+                if is_duplicate {
+                    match on_duplicate {
+                        DuplicatePolicy::Skip => return Ok(None),
+                        DuplicatePolicy::Error => return Err(ImportAbort::Duplicate { row }),
+                        DuplicatePolicy::CreateParallel => {}
+                    }
+                }
+
+                Ok(Some((from, data, to)))
+            })();
+
+            let outcome = outcome.and_then(|planned| match planned {
+                Some((from, data, to)) => match self.connection_error(&from, &to, true) {
+                    Some(ConnectError::LimitReached) => Err(ImportAbort::Limit {
+                        row,
+                        error: LimitReached,
+                    }),
+                    Some(error) => Err(ImportAbort::Connect { row, error }),
+                    None => Ok(Some((from, data, to))),
+                },
+                None => Ok(None),
+            });
+
+            match outcome {
+                Ok(Some((from, data, to))) => {
+                    created.push(self.attach_directed(from, data, to));
+                    report.created += 1;
+                }
+                Ok(None) => report.skipped_duplicates.push(row),
+                Err(abort) => {
+                    self.detach_connections(&created);
+                    return Err(abort);
+                }
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Copies the graph into a `GraphRecord`, plain data that can be stored
+    /// and later turned back into a graph with `from_record`.
+    ///
+    /// With `include_dead`, things and connections that are killed but not yet
+    /// cleaned are kept along with their alive flags, and the dead amount is
+    /// copied as is, so a restored graph is in exactly the same soft-deleted
+    /// state. Without it, dead items are left out and the dead amount is zero.
+    /// Things and connections keep their container order either way.
+    ///
+    /// Connections to things outside the container are left out.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut graph = Things::<&str, &str>::new();
+    /// let alice = graph.new_thing("alice");
+    /// let bob = graph.new_thing("bob");
+    /// graph.new_directed_connection(alice, "knows", bob);
+    /// graph.kill_things(|name| *name == "bob");
+    ///
+    /// let record = graph.to_record(SerializeOptions { include_dead: true });
+    /// assert_eq!(record.things.len(), 2);
+    /// assert!(!record.things[1].alive);
+    ///
+    /// let mut restored = Things::from_record(record);
+    /// assert_eq!(restored.dead_percentage(), graph.dead_percentage());
+    /// ```
+    pub fn to_record(&self, options: SerializeOptions) -> GraphRecord<T, C>
+    where
+        T: Clone,
+        C: Clone,
+    {
+        self.record(options, None)
+    }
+
+    /// Copies the graph into a `GraphRecord` like `to_record`, taking thing
+    /// data from a table made by `with_thing_data_table`.
+    ///
+    /// The data of every thing in the table is cloned from the table instead
+    /// of being read from the thing again. Things the table doesn't hold,
+    /// such as dead ones or ones added after it was made, are read from the
+    /// graph as usual.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut graph = Things::<&str, ()>::new();
+    /// graph.new_thing("alice");
+    ///
+    /// let table = graph.with_thing_data_table(|name| *name);
+    /// let record = graph.to_record_with_table(SerializeOptions { include_dead: false }, &table);
+    /// assert_eq!(record.things[0].data, "alice");
+    /// ```
+    pub fn to_record_with_table(
+        &self,
+        options: SerializeOptions,
+        table: &DataTable<T>,
+    ) -> GraphRecord<T, C>
+    where
+        T: Clone,
+        C: Clone,
+    {
+        self.record(options, Some(table))
+    }
+
+    /// Builds the record behind `to_record` and `to_record_with_table`.
+    fn record(&self, options: SerializeOptions, table: Option<&DataTable<T>>) -> GraphRecord<T, C>
+    where
+        T: Clone,
+        C: Clone,
+    {
+        let mut index = BTreeMap::new();
+        let mut things = Vec::new();
+        for thing in &self.things {
+            let inner = thing.inner.borrow();
+            if !inner.is_alive && !options.include_dead {
+                continue;
+            }
+            index.insert(thing.key(), things.len());
+            let data = match table.and_then(|table| table.get(thing)) {
+                Some(data) => data.clone(),
+                None => inner.data.clone(),
+            };
+            things.push(ThingRecord {
+                data,
+                alive: inner.is_alive,
+            });
+        }
+
+        let mut connections = Vec::new();
+        for connection in &self.connections {
+            let alive = connection.is_alive();
+            if !alive && !options.include_dead {
+                continue;
+            }
+            let [first, second] = connection.ends();
+            let (Some(&first), Some(&second)) = (index.get(&first.key()), index.get(&second.key()))
+            else {
+                continue;
+            };
+            connections.push(ConnectionRecord {
+                ends: [first, second],
+                directed: connection.is_directed(),
+                data: connection.inner.borrow().get_data().clone(),
+                weight: connection.weight(),
+                alive,
+                id: connection.id(),
+            });
+        }
+
+        GraphRecord {
+            things,
+            connections,
+            dead_amount: if options.include_dead {
+                self.dead_amount
+            } else {
+                0
+            },
+        }
+    }
+
+    /// Extracts a value from every live thing's data into a `DataTable`.
+    ///
+    /// Each live thing is borrowed exactly once, so exporters and renderers
+    /// that need the same data over and over can look it up by handle instead
+    /// of borrowing the thing again each time. The crate's own exporters take
+    /// a table in `to_record_with_table` and `export_with_table`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut graph = Things::<&str, ()>::new();
+    /// let alice = graph.new_thing("alice");
+    ///
+    /// let lengths = graph.with_thing_data_table(|name| name.len());
+    /// assert_eq!(lengths.get(&alice), Some(&5));
+    /// ```
+    pub fn with_thing_data_table<R>(&self, extract: impl Fn(&T) -> R) -> DataTable<R> {
+        let entries = self
+            .things
+            .iter()
+            .filter_map(|thing| {
+                let inner = thing.inner.borrow();
+                inner.is_alive.then(|| (inner.seq, extract(&inner.data)))
+            })
+            .collect();
+        DataTable {
+            entries,
+            generation: self.generation,
+        }
+    }
+
+    /// Builds a graph from keyed things and a keyed adjacency list, the way
+    /// graphs are often written in configuration and data files.
+    ///
+    /// Every entry of `nodes` becomes a thing, in key order. Every
+    /// `(data, target, directed)` entry listed under a key becomes one
+    /// connection from that key's thing to the target's thing, directed or
+    /// undirected as requested, in key order and then list order. An
+    /// undirected connection should only be listed under one of its ends,
+    /// as each entry creates its own connection.
+    ///
+    /// # Returns
+    /// - `Ok(graph)`: If every key in `adjacency` is a key of `nodes`
+    /// - `Err(AdjacencyError)`: The first key that isn't, with nothing built
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// use std::collections::BTreeMap;
+    ///
+    /// let nodes = BTreeMap::from([("db", "Database"), ("api", "API")]);
+    /// let adjacency = BTreeMap::from([("api", vec![("reads", "db", true)])]);
+    ///
+    /// let graph = Things::<&str, &str>::from_adjacency(nodes, adjacency).unwrap();
+    /// let api = graph.find_thing_equal(&"API").unwrap();
+    /// assert!(api.follow(&"reads")[0] == "Database");
+    /// ```
+    pub fn from_adjacency<K: Ord + Clone>(
+        nodes: BTreeMap<K, T>,
+        adjacency: BTreeMap<K, Vec<(C, K, bool)>>,
+    ) -> Result<Things<T, C>, AdjacencyError<K>> {
+        for (from, entries) in &adjacency {
+            if !nodes.contains_key(from) {
+                return Err(AdjacencyError::UnknownSource(from.clone()));
+            }
+            if let Some((_, to, _)) = entries.iter().find(|(_, to, _)| !nodes.contains_key(to)) {
+                return Err(AdjacencyError::UnknownTarget {
+                    from: from.clone(),
+                    to: to.clone(),
+                });
+            }
+        }
+
+        let mut graph = Things::new();
+        let things: BTreeMap<K, Thing<T, C>> = nodes
+            .into_iter()
+            .map(|(key, data)| (key, graph.new_thing(data)))
+            .collect();
+        for (from, entries) in adjacency {
+            let source = &things[&from];
+            for (data, to, directed) in entries {
+                let target = things[&to].clone();
+                if directed {
+                    graph.new_directed_connection(source.clone(), data, target);
+                } else {
+                    graph.new_undirected_connection([source.clone(), target], data);
+                }
+            }
+        }
+        Ok(graph)
+    }
+
+    /// Builds a graph from a `GraphRecord`, restoring alive flags and the dead amount.
+    ///
+    /// Things and connections are recreated in record order with the default
+    /// configuration. Items recorded as dead are dead in the new graph, ready
+    /// for `clean`, without running any kill callbacks. Connections keep
+    /// their recorded ids, and later connections get ids above all of them.
+    ///
+    /// # Panics
+    /// If a connection refers to a thing index outside the record.
+    pub fn from_record(record: GraphRecord<T, C>) -> Things<T, C> {
+        let mut graph = Things::new();
+        let last_id = record
+            .connections
+            .iter()
+            .map(|connection| connection.id.0)
+            .max();
+        graph.next_seq = graph.next_seq.max(last_id.map_or(0, |id| id + 1));
+        let mut things = Vec::with_capacity(record.things.len());
+        for ThingRecord { data, alive } in record.things {
+            let thing = Thing::new(data);
+            thing.inner.borrow_mut().is_alive = alive;
+            graph.register_thing(thing.clone());
+            things.push(thing);
+        }
+        for ConnectionRecord {
+            ends: [first, second],
+            directed,
+            data,
+            weight,
+            alive,
+            id,
+        } in record.connections
+        {
+            let (first, second) = (things[first].clone(), things[second].clone());
+            let connection = if directed {
+                Connection::new_directed(first.clone(), data, second.clone())
+            } else {
+                Connection::new_undirected([first.clone(), second.clone()], data)
+            };
+            {
+                let mut inner = connection.inner.borrow_mut();
+                *inner.weight_mut() = weight;
+                if !alive {
+                    inner.kill();
+                }
+            }
+            unsafe { first.connect(connection.clone()) };
+            unsafe { second.connect(connection.clone()) };
+            graph.register_connection(connection.clone());
+            *connection.inner.borrow_mut().seq_mut() = id.0;
+        }
+        graph.dead_amount = record.dead_amount;
+        graph
+    }
+
+    /// Walks the live graph for an exporter, lending out labels instead of
+    /// copying them.
+    ///
+    /// `sink` gets every live thing in container order, then every live
+    /// connection between them. Labels come from `thing_label` and
+    /// `conn_label` and borrow the data directly: the data stays borrowed only
+    /// while `sink` runs, so no label is cloned. The only allocation is a map
+    /// from each live thing to its index, which the connections are reported
+    /// against. Labels are handed over as they are, so escaping them for the
+    /// output format is up to the exporter.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut graph = Things::<String, String>::new();
+    /// let a = graph.new_thing("a".to_string());
+    /// let b = graph.new_thing("b".to_string());
+    /// graph.new_directed_connection(a, "next".to_string(), b);
+    ///
+    /// let mut dot = String::from("digraph {\n");
+    /// graph.export_with_labels(String::as_str, String::as_str, |item| match item {
+    ///     ExportItem::Thing { index, label } => {
+    ///         dot += &format!("  {index} [label={label:?}];\n");
+    ///     }
+    ///     ExportItem::Connection { ends: [from, to], label, .. } => {
+    ///         dot += &format!("  {from} -> {to} [label={label:?}];\n");
+    ///     }
+    /// });
+    /// dot += "}";
+    /// assert!(dot.contains("0 -> 1 [label=\"next\"]"));
+    /// ```
+    pub fn export_with_labels(
+        &self,
+        thing_label: impl Fn(&T) -> &str,
+        conn_label: impl Fn(&C) -> &str,
+        mut sink: impl FnMut(ExportItem<'_>),
+    ) {
+        let mut index = BTreeMap::new();
+        for thing in self.things.iter().filter(|thing| thing.is_alive()) {
+            let position = index.len();
+            index.insert(thing.key(), position);
+            let inner = thing.inner.borrow();
+            sink(ExportItem::Thing {
+                index: position,
+                label: thing_label(&inner.data),
+            });
+        }
+        self.export_connections(&index, conn_label, sink);
+    }
+
+    /// Walks the live graph for an exporter like `export_with_labels`, taking
+    /// thing labels from a table made by `with_thing_data_table`.
+    ///
+    /// Things are not borrowed for their labels: `thing_label` reads them
+    /// from the values in `table`. Connection labels still come from
+    /// `conn_label`.
+    ///
+    /// # Panics
+    /// If a live thing is missing from the table, which means things were
+    /// added after it was made. Check `DataTable::is_stale` first when that
+    /// can happen.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut graph = Things::<u32, &str>::new();
+    /// let a = graph.new_thing(1);
+    /// let b = graph.new_thing(2);
+    /// graph.new_directed_connection(a, "next", b);
+    ///
+    /// let names = graph.with_thing_data_table(|number| format!("node {number}"));
+    /// let mut lines = Vec::new();
+    /// graph.export_with_table(&names, String::as_str, |label| label, |item| match item {
+    ///     ExportItem::Thing { label, .. } => lines.push(label.to_string()),
+    ///     ExportItem::Connection { label, .. } => lines.push(label.to_string()),
+    /// });
+    /// assert_eq!(lines, ["node 1", "node 2", "next"]);
+    /// ```
+    pub fn export_with_table<R>(
+        &self,
+        table: &DataTable<R>,
+        thing_label: impl Fn(&R) -> &str,
+        conn_label: impl Fn(&C) -> &str,
+        mut sink: impl FnMut(ExportItem<'_>),
+    ) {
+        let mut index = BTreeMap::new();
+        for thing in self.things.iter().filter(|thing| thing.is_alive()) {
+            let position = index.len();
+            index.insert(thing.key(), position);
+            let value = table
+                .get(thing)
+                .expect("the data table is missing a live thing, make a new one");
+            sink(ExportItem::Thing {
+                index: position,
+                label: thing_label(value),
+            });
+        }
+        self.export_connections(&index, conn_label, sink);
+    }
+
+    /// Reports the live connections between exported things, for
+    /// `export_with_labels` and `export_with_table`.
+    fn export_connections(
+        &self,
+        index: &BTreeMap<usize, usize>,
+        conn_label: impl Fn(&C) -> &str,
+        mut sink: impl FnMut(ExportItem<'_>),
+    ) {
+        for connection in self
+            .connections
+            .iter()
+            .filter(|connection| connection.is_alive())
+        {
+            let [first, second] = connection.ends();
+            let (Some(&first), Some(&second)) = (index.get(&first.key()), index.get(&second.key()))
+            else {
+                continue;
+            };
+            let id = connection.id();
+            let directed = connection.is_directed();
+            let inner = connection.inner.borrow();
+            sink(ExportItem::Connection {
+                id,
+                ends: [first, second],
+                directed,
+                label: conn_label(inner.get_data()),
+            });
+        }
+    }
+
+    /// Fully removes connections from the graph, undoing their registration
+    /// with both endpoints and with the container, identified by identity.
+    fn detach_connections(&mut self, removed: &[Connection<T, C>]) {
+        for connection in removed.iter() {
+            for mut thing in connection.ends() {
+                unsafe { thing.remove_connections(|c| c.ptr_eq(connection)) };
+            }
+        }
+        let is_removed =
+            |connection: &Connection<T, C>| removed.iter().any(|other| other.ptr_eq(connection));
+        self.connections
+            .retain(|connection| !is_removed(connection));
+        if let Some(classes) = self.classes.as_mut() {
+            for bucket in classes.buckets.values_mut() {
+                bucket.retain(|connection| !is_removed(connection));
+            }
+        }
+        self.touch();
+    }
+
+    /// Checks whether a live directed connection goes from `from` to `to`.
+    ///
+    /// The check is orientation-sensitive: a connection from `to` to `from`, or
+    /// an undirected connection between them, doesn't count. Things are
+    /// matched by identity. This answers questions like "does A directly
+    /// depend on B".
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut tasks = Things::<&str, &str>::new();
+    /// let build = tasks.new_thing("build");
+    /// let fetch = tasks.new_thing("fetch");
+    /// tasks.new_directed_connection(build.clone(), "depends_on", fetch.clone());
+    ///
+    /// assert!(tasks.has_directed_edge(&build, &fetch));
+    /// assert!(!tasks.has_directed_edge(&fetch, &build));
+    /// ```
+    pub fn has_directed_edge(&self, from: &Thing<T, C>, to: &Thing<T, C>) -> bool {
+        from.inner
+            .borrow()
+            .connections
+            .iter()
+            .any(|connection| connection.goes_between(from, to))
+    }
+
+    /// Finds a path with the fewest hops from `from` to `to`.
+    ///
+    /// Only connections accepted by `follow` are traversed. Directed connections
+    /// are followed from source to target and undirected connections both ways,
+    /// and dead things and connections are skipped.
+    ///
+    /// # Returns
+    /// - `Some(path)`: The things along the path, starting with `from` and ending with `to`
+    /// - `None`: If `to` can't be reached
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut map = Things::<&str, &str>::new();
+    /// let home = map.new_thing("home");
+    /// let park = map.new_thing("park");
+    /// let shop = map.new_thing("shop");
+    /// map.new_undirected_connection([home.clone(), park.clone()], "road");
+    /// map.new_undirected_connection([park, shop.clone()], "road");
+    ///
+    /// let path = map.shortest_path(&home, &shop, |_| true).unwrap();
+    /// assert_eq!(path.len(), 3);
+    /// ```
+    pub fn shortest_path(
+        &self,
+        from: &Thing<T, C>,
+        to: &Thing<T, C>,
+        follow: impl Fn(&Connection<T, C>) -> bool,
+    ) -> Option<Vec<Thing<T, C>>> {
+        let connections = self.search_path(from, to, |connection, _| follow(connection))?;
+        Self::things_along(from, connections)
+    }
+
+    /// Like `shortest_path`, but gives up once `max_expanded` things have had
+    /// their connections looked at, so a query on a huge graph can't block
+    /// for long.
+    ///
+    /// # Returns
+    /// - `Found(path)`: The same path `shortest_path` finds, if the search
+    ///   gets there within the budget
+    /// - `Truncated { .. }`: If the budget ran out first, with the number of
+    ///   steps from `from` to the closest thing left unexpanded, so `to` is at
+    ///   least one step further than that
+    /// - `Unreachable`: If the search ran out of things to expand, which proves
+    ///   `to` can't be reached, or if either thing is dead
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut line = Things::<u32, ()>::new();
+    /// let stops: Vec<_> = (0..10).map(|stop| line.new_thing(stop)).collect();
+    /// for pair in stops.windows(2) {
+    ///     line.new_directed_connection(pair[0].clone(), (), pair[1].clone());
+    /// }
+    ///
+    /// let first = &stops[0];
+    /// let last = &stops[9];
+    /// match line.shortest_path_bounded(first, last, |_| true, 4) {
+    ///     BoundedSearchResult::Truncated { best_frontier_distance, expanded } => {
+    ///         assert_eq!((best_frontier_distance, expanded), (4, 4));
+    ///     }
+    ///     _ => unreachable!(),
+    /// }
+    /// assert!(matches!(
+    ///     line.shortest_path_bounded(first, last, |_| true, 100),
+    ///     BoundedSearchResult::Found(path) if path.len() == 10
+    /// ));
+    /// assert!(matches!(
+    ///     line.shortest_path_bounded(last, first, |_| true, 100),
+    ///     BoundedSearchResult::Unreachable
+    /// ));
+    /// ```
+    pub fn shortest_path_bounded(
+        &self,
+        from: &Thing<T, C>,
+        to: &Thing<T, C>,
+        follow: impl Fn(&Connection<T, C>) -> bool,
+        max_expanded: usize,
+    ) -> BoundedSearchResult<T, C> {
+        let search = self.search_path_within(
+            from,
+            to,
+            |connection, _| follow(connection),
+            max_expanded,
+            &mut 0,
+        );
+        BoundedSearchResult::from_search(from, search)
+    }
+
+    /// Lists the things along a path of connections walked from `from`.
+    fn things_along(
+        from: &Thing<T, C>,
+        connections: Vec<Connection<T, C>>,
+    ) -> Option<Vec<Thing<T, C>>> {
+        let mut path = alloc::vec![from.clone()];
+        for connection in connections {
+            let next = connection.inner.borrow().other_end(&path[path.len() - 1])?;
+            path.push(next);
+        }
+        Some(path)
+    }
+
+    /// Finds a path with the fewest steps from `from` to `to`, searching from
+    /// both ends at once.
+    ///
+    /// One search walks forward from `from` while the other walks backward from
+    /// `to`, each growing by a whole level at a time, always on the side with
+    /// the smaller frontier, until they meet in the middle. On large graphs this
+    /// looks at far fewer things than `shortest_path`, which has to cover
+    /// everything closer to `from` than `to` is. Directions are respected, and
+    /// dead things and connections are skipped. Every connection may be
+    /// followed; use `shortest_path` to filter them.
+    ///
+    /// When several paths are equally short, the one returned may differ from
+    /// the one `shortest_path` finds, but it always has the same length.
+    ///
+    /// # Returns
+    /// - `Some(path)`: The things along the path, starting with `from` and ending with `to`
+    /// - `None`: If `to` can't be reached
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut map = Things::<&str, &str>::new();
+    /// let home = map.new_thing("home");
+    /// let park = map.new_thing("park");
+    /// let shop = map.new_thing("shop");
+    /// map.new_undirected_connection([home.clone(), park.clone()], "road");
+    /// map.new_directed_connection(park, "one way", shop.clone());
+    ///
+    /// let path = map.shortest_path_bidirectional(&home, &shop).unwrap();
+    /// assert!(path[1] == "park");
+    /// assert!(map.shortest_path_bidirectional(&shop, &home).is_none());
+    /// ```
+    pub fn shortest_path_bidirectional(
+        &self,
+        from: &Thing<T, C>,
+        to: &Thing<T, C>,
+    ) -> Option<Vec<Thing<T, C>>> {
+        self.search_bidirectional(from, to, &mut 0)
+    }
+
+    /// Finds the connections joining consecutive things along a path, such as
+    /// the output of `shortest_path`, so the relationships along a route can be
+    /// inspected.
+    ///
+    /// Each step must be traversable the way `shortest_path` walks: a live
+    /// directed connection from the earlier thing to the later one, or a live
+    /// undirected connection between them. When several connections qualify,
+    /// the first in the earlier thing's connection list is used.
+    ///
+    /// # Returns
+    /// - `Some(connections)`: One connection per step, in path order
+    /// - `None`: If some consecutive pair isn't joined by such a connection
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut taxonomy = Things::<&str, &str>::new();
+    /// let fido = taxonomy.new_thing("Fido");
+    /// let dog = taxonomy.new_thing("Dog");
+    /// let mammal = taxonomy.new_thing("Mammal");
+    /// taxonomy.new_directed_connection(fido.clone(), "instance_of", dog.clone());
+    /// taxonomy.new_directed_connection(dog, "is_a", mammal.clone());
+    ///
+    /// let path = taxonomy.shortest_path(&fido, &mammal, |_| true).unwrap();
+    /// let edges = taxonomy.path_edges(&path).unwrap();
+    /// assert!(edges[0] == "instance_of" && edges[1] == "is_a");
+    /// ```
+    pub fn path_edges(&self, path: &[Thing<T, C>]) -> Option<Vec<Connection<T, C>>> {
+        path.windows(2)
+            .map(|step| {
+                step[0]
+                    .forward_steps()
+                    .into_iter()
+                    .find(|(_, next)| next.ptr_eq(&step[1]))
+                    .map(|(connection, _)| connection)
+            })
+            .collect()
+    }
+
+    /// Finds a path with the fewest hops from `from` to `to` that avoids the
+    /// given things and connections.
+    ///
+    /// Avoided items are matched by identity and treated as if they weren't
+    /// part of the graph, which suits "avoid toll roads" style routing.
+    /// Otherwise this follows the same rules as `shortest_path`.
+    ///
+    /// # Returns
+    /// - `Some(connections)`: The connections along the path, in travel order
+    /// - `None`: If `to` can't be reached, or if `from` or `to` is avoided
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut map = Things::<&str, &str>::new();
+    /// let home = map.new_thing("home");
+    /// let work = map.new_thing("work");
+    /// let toll_road = map.new_undirected_connection([home.clone(), work.clone()], "toll");
+    ///
+    /// let route = map.shortest_path_avoiding(&home, &work, &[], &[toll_road], |_| true);
+    /// assert!(route.is_none());
+    /// ```
+    pub fn shortest_path_avoiding(
+        &self,
+        from: &Thing<T, C>,
+        to: &Thing<T, C>,
+        avoid_things: &[Thing<T, C>],
+        avoid_connections: &[Connection<T, C>],
+        follow: impl Fn(&Connection<T, C>) -> bool,
+    ) -> Option<Vec<Connection<T, C>>> {
+        self.shortest_path_avoiding_if(
+            from,
+            to,
+            |thing| avoid_things.iter().any(|avoided| avoided.ptr_eq(thing)),
+            |connection| {
+                follow(connection)
+                    && !avoid_connections
+                        .iter()
+                        .any(|avoided| avoided.ptr_eq(connection))
+            },
+        )
+    }
+
+    /// Finds a path with the fewest hops from `from` to `to` that avoids every
+    /// thing for which `avoid` returns true.
+    ///
+    /// Connections can be avoided by rejecting them in `follow`. Otherwise this
+    /// works like `shortest_path_avoiding`.
+    pub fn shortest_path_avoiding_if(
+        &self,
+        from: &Thing<T, C>,
+        to: &Thing<T, C>,
+        avoid: impl Fn(&Thing<T, C>) -> bool,
+        follow: impl Fn(&Connection<T, C>) -> bool,
+    ) -> Option<Vec<Connection<T, C>>> {
+        if avoid(from) || avoid(to) {
+            return None;
+        }
+        self.search_path(from, to, |connection, next| {
+            follow(connection) && !avoid(next)
+        })
+    }
+
+    /// Finds the cheapest path from `from` to `to` using Dijkstra's algorithm.
+    ///
+    /// The cost of a path is the sum of its connections' costs, taken from
+    /// `cost`. With `CostSource::Annotation` a connection without a weight has
+    /// infinite cost: it is never traversed, as if it wasn't there. Directed
+    /// connections are followed from source to target and undirected ones
+    /// both ways.
+    ///
+    /// # Returns
+    /// - `Some((cost, connections))`: The total cost and the connections along the path
+    /// - `None`: If either thing is dead or `to` can't be reached
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut roads = Things::<&str, ()>::new();
+    /// let home = roads.new_thing("home");
+    /// let town = roads.new_thing("town");
+    /// let work = roads.new_thing("work");
+    /// roads.new_undirected_connection([home.clone(), work.clone()], ()).set_weight(10);
+    /// roads.new_undirected_connection([home.clone(), town.clone()], ()).set_weight(3);
+    /// roads.new_undirected_connection([town, work.clone()], ()).set_weight(4);
+    ///
+    /// let (cost, path) = roads.cheapest_path(&home, &work, CostSource::Annotation).unwrap();
+    /// assert_eq!(cost, 7);
+    /// assert_eq!(path.len(), 2);
+    ///
+    /// let hops = roads.cheapest_path(&home, &work, CostSource::Closure(&|_| 1)).unwrap();
+    /// assert_eq!(hops.0, 1);
+    /// ```
+    pub fn cheapest_path(
+        &self,
+        from: &Thing<T, C>,
+        to: &Thing<T, C>,
+        cost: CostSource<T, C>,
+    ) -> Option<(u64, Vec<Connection<T, C>>)> {
+        // An unlimited budget never runs out
+        self.search_cheapest(from, to, cost, usize::MAX)
+            .ok()
+            .flatten()
+    }
+
+    /// Like `cheapest_path`, but gives up once `max_expanded` things have had
+    /// their connections looked at.
+    ///
+    /// Works like `shortest_path_bounded`, except that distances are costs:
+    /// when the budget runs out, `best_frontier_distance` is the cost of
+    /// reaching the cheapest thing left unexpanded, capped at `usize::MAX`.
+    /// Use `path_edges` or `cheapest_path` to get at the connections of a
+    /// found path.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut roads = Things::<&str, ()>::new();
+    /// let home = roads.new_thing("home");
+    /// let town = roads.new_thing("town");
+    /// let work = roads.new_thing("work");
+    /// roads.new_undirected_connection([home.clone(), town.clone()], ()).set_weight(3);
+    /// roads.new_undirected_connection([town, work.clone()], ()).set_weight(4);
+    ///
+    /// let search = roads.cheapest_path_bounded(&home, &work, CostSource::Annotation, 1);
+    /// assert!(matches!(
+    ///     search,
+    ///     BoundedSearchResult::Truncated { best_frontier_distance: 3, expanded: 1 }
+    /// ));
+    /// ```
+    pub fn cheapest_path_bounded(
+        &self,
+        from: &Thing<T, C>,
+        to: &Thing<T, C>,
+        cost: CostSource<T, C>,
+        max_expanded: usize,
+    ) -> BoundedSearchResult<T, C> {
+        let search = self.search_cheapest(from, to, cost, max_expanded);
+        BoundedSearchResult::from_search(from, search.map(|found| found.map(|(_, path)| path)))
+    }
+
+    /// Dijkstra's algorithm behind `cheapest_path`, expanding at most
+    /// `max_expanded` things.
+    fn search_cheapest(
+        &self,
+        from: &Thing<T, C>,
+        to: &Thing<T, C>,
+        cost: CostSource<T, C>,
+        max_expanded: usize,
+    ) -> Budgeted<(u64, Vec<Connection<T, C>>)> {
+        use alloc::collections::BinaryHeap;
+        use core::cmp::Reverse;
+
+        if !from.is_alive() || !to.is_alive() {
+            return Ok(None);
+        }
+        let (things, index) = self.index_things();
+        let (Some(&start), Some(&goal)) = (index.get(&from.key()), index.get(&to.key())) else {
+            return Ok(None);
+        };
+
+        let mut distance: Vec<Option<u64>> = alloc::vec![None; things.len()];
+        let mut arrived_by: Vec<Option<(Connection<T, C>, usize)>> =
+            alloc::vec![None; things.len()];
+        let mut heap = BinaryHeap::new();
+        let mut expanded = 0;
+        distance[start] = Some(0);
+        heap.push(Reverse((0u64, start)));
+
+        while let Some(Reverse((spent, current))) = heap.pop() {
+            if distance[current].is_some_and(|known| spent > known) {
+                continue;
+            }
+            if current == goal {
+                let mut path = Vec::new();
+                let mut position = goal;
+                while let Some((connection, previous)) = arrived_by[position].take() {
+                    path.push(connection);
+                    position = previous;
+                }
+                path.reverse();
+                return Ok(Some((spent, path)));
+            }
+            if expanded == max_expanded {
+                return Err((usize::try_from(spent).unwrap_or(usize::MAX), expanded));
+            }
+            expanded += 1;
+            for (connection, next) in things[current].forward_steps() {
+                let Some(&next) = index.get(&next.key()) else {
+                    continue;
+                };
+                let Some(step) = cost.cost_of(&connection) else {
+                    continue;
+                };
+                let candidate = spent.saturating_add(step);
+                if distance[next].is_none_or(|known| candidate < known) {
+                    distance[next] = Some(candidate);
+                    arrived_by[next] = Some((connection, current));
+                    heap.push(Reverse((candidate, next)));
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Removes the weight annotation from every connection.
+    ///
+    /// See `Connection::set_weight`.
+    pub fn clear_weights(&mut self) {
+        for connection in self.connections.iter() {
+            *connection.inner.borrow_mut().weight_mut() = None;
+        }
+    }
+
+    /// Breadth-first search for the connections along a path with the fewest hops.
+    ///
+    /// `can_cross` is asked about every connection before it is traversed,
+    /// together with the thing it leads to.
+    fn search_path(
+        &self,
+        from: &Thing<T, C>,
+        to: &Thing<T, C>,
+        can_cross: impl Fn(&Connection<T, C>, &Thing<T, C>) -> bool,
+    ) -> Option<Vec<Connection<T, C>>> {
+        // An unlimited budget never runs out
+        self.search_path_within(from, to, can_cross, usize::MAX, &mut 0)
+            .ok()
+            .flatten()
+    }
+
+    /// Like `search_path`, expanding at most `max_expanded` things and adding
+    /// the number of things it discovers to `discovered`.
+    fn search_path_within(
+        &self,
+        from: &Thing<T, C>,
+        to: &Thing<T, C>,
+        can_cross: impl Fn(&Connection<T, C>, &Thing<T, C>) -> bool,
+        max_expanded: usize,
+        discovered: &mut usize,
+    ) -> Budgeted<Vec<Connection<T, C>>> {
+        if !from.is_alive() || !to.is_alive() {
+            return Ok(None);
+        }
+        if from.ptr_eq(to) {
+            return Ok(Some(Vec::new()));
+        }
+
+        let mut arrived_by = BTreeMap::new();
+        let mut visited = BTreeSet::new();
+        let mut queue = VecDeque::new();
+        let mut expanded = 0;
+        visited.insert(from.key());
+        queue.push_back((from.clone(), 0));
+
+        while let Some((current, depth)) = queue.pop_front() {
+            // The queue is in order of distance, so this is the closest one left
+            if expanded == max_expanded {
+                return Err((depth, expanded));
+            }
+            expanded += 1;
+            for (connection, next) in current.forward_steps() {
+                if visited.contains(&next.key()) || !can_cross(&connection, &next) {
+                    continue;
+                }
+                visited.insert(next.key());
+                *discovered += 1;
+                arrived_by.insert(next.key(), (connection, current.clone()));
+
+                if next.ptr_eq(to) {
+                    let mut path = Vec::new();
+                    let mut key = next.key();
+                    while let Some((connection, previous)) = arrived_by.remove(&key) {
+                        path.push(connection);
+                        key = previous.key();
+                    }
+                    path.reverse();
+                    return Ok(Some(path));
+                }
+                queue.push_back((next, depth + 1));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Meet-in-the-middle search behind `shortest_path_bidirectional`, adding
+    /// the number of things it discovers to `discovered`.
+    fn search_bidirectional(
+        &self,
+        from: &Thing<T, C>,
+        to: &Thing<T, C>,
+        discovered: &mut usize,
+    ) -> Option<Vec<Thing<T, C>>> {
+        if !from.is_alive() || !to.is_alive() {
+            return None;
+        }
+        if from.ptr_eq(to) {
+            return Some(alloc::vec![from.clone()]);
+        }
+
+        // The distance to everything each side reached, with the thing it was
+        // reached from: side 0 walks forward from `from`, side 1 backward from `to`
+        let mut reached = [
+            BTreeMap::from([(from.key(), (0, None))]),
+            BTreeMap::from([(to.key(), (0, None))]),
+        ];
+        let mut frontiers = [alloc::vec![from.clone()], alloc::vec![to.clone()]];
+        while !frontiers[0].is_empty() && !frontiers[1].is_empty() {
+            let side = usize::from(frontiers[1].len() < frontiers[0].len());
+            let mut level = Vec::new();
+            // The shortest crossing found on this level, from a thing on this
+            // side to one the other side reached
+            let mut shortest = usize::MAX;
+            let mut crossing = None;
+            for current in core::mem::take(&mut frontiers[side]) {
+                let distance = reached[side][&current.key()].0 + 1;
+                let steps = if side == 0 {
+                    current.forward_steps()
+                } else {
+                    current.backward_steps()
+                };
+                for (_, next) in steps {
+                    if let Some(&(remaining, _)) = reached[1 - side].get(&next.key())
+                        && distance + remaining < shortest
+                    {
+                        shortest = distance + remaining;
+                        crossing = Some((current.clone(), next.clone()));
+                    }
+                    if let Entry::Vacant(entry) = reached[side].entry(next.key()) {
+                        entry.insert((distance, Some(current.clone())));
+                        *discovered += 1;
+                        level.push(next);
+                    }
+                }
+            }
+
+            // Finishing the level first makes sure no shorter crossing is missed
+            if let Some((near, far)) = crossing {
+                let trace = |side: usize, end: Thing<T, C>| {
+                    let mut trace = alloc::vec![end];
+                    while let Some((_, Some(previous))) =
+                        reached[side].get(&trace[trace.len() - 1].key())
+                    {
+                        trace.push(previous.clone());
+                    }
+                    trace
+                };
+                let (forward_end, backward_end) = if side == 0 { (near, far) } else { (far, near) };
+                let mut path = trace(0, forward_end);
+                path.reverse();
+                path.extend(trace(1, backward_end));
+                return Some(path);
+            }
+            frontiers[side] = level;
+        }
+
+        None
+    }
+
+    /// Compiles the live graph into an immutable, query-optimized form.
+    ///
+    /// The result copies the data of every live thing and connection into
+    /// contiguous storage and lays out the adjacency in both directions as
+    /// compressed sparse rows, so queries on it don't go through any `RefCell`.
+    /// This suits graphs that are built once and then only read for a long time.
+    /// Use `compile_with_key` to also build a lookup index, and
+    /// `CompiledGraph::decompile` to get an editable container back.
+    ///
+    /// Things and connections keep their container order, and positions in the
+    /// compiled graph identify them. Live connections with an endpoint outside
+    /// the live graph are left out.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut graph = Things::<&str, &str>::new();
+    /// let alice = graph.new_thing("alice");
+    /// let bob = graph.new_thing("bob");
+    /// graph.new_directed_connection(alice, "follows", bob);
+    ///
+    /// let compiled = graph.compile();
+    /// assert_eq!(compiled.thing_count(), 2);
+    /// assert_eq!(compiled.neighbors(0), &[(0, 1)]);
+    /// assert_eq!(*compiled.data(1), "bob");
+    /// ```
+    pub fn compile(&self) -> CompiledGraph<T, C>
+    where
+        T: Clone,
+        C: Clone,
+    {
+        self.compile_unindexed()
+    }
+
+    /// Compiles the live graph like `compile`, adding an index over `key` so
+    /// `CompiledGraph::find_by_key` can find things by binary search.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut users = Things::<(u32, &str), ()>::new();
+    /// users.new_thing((42, "alice"));
+    /// users.new_thing((7, "bob"));
+    ///
+    /// let compiled = users.compile_with_key(|(id, _)| *id);
+    /// let bob = compiled.find_by_key(&7).unwrap();
+    /// assert_eq!(compiled.data(bob).1, "bob");
+    /// ```
+    pub fn compile_with_key<K: Ord>(&self, key: impl Fn(&T) -> K) -> CompiledGraph<T, C, K>
+    where
+        T: Clone,
+        C: Clone,
+    {
+        let mut compiled = self.compile_unindexed();
+        compiled.keys = compiled
+            .things
+            .iter()
+            .enumerate()
+            .map(|(at, data)| (key(data), at))
+            .collect();
+        // Stable, so things with equal keys stay in container order
+        compiled.keys.sort_by(|a, b| a.0.cmp(&b.0));
+        compiled
+    }
+
+    fn compile_unindexed<K>(&self) -> CompiledGraph<T, C, K>
+    where
+        T: Clone,
+        C: Clone,
+    {
+        let (things, index) = self.index_things();
+        let mut connection_data = Vec::new();
+        let mut ends = Vec::new();
+        let mut directed = Vec::new();
+        for connection in self
+            .connections
+            .iter()
+            .filter(|connection| connection.is_alive())
+        {
+            let [first, second] = connection.ends();
+            if let (Some(&first), Some(&second)) =
+                (index.get(&first.key()), index.get(&second.key()))
+            {
+                connection_data.push(connection.access(|data| data.clone()));
+                ends.push([first, second]);
+                directed.push(connection.is_directed());
+            }
+        }
+
+        let mut outgoing = alloc::vec![Vec::new(); things.len()];
+        let mut incoming = alloc::vec![Vec::new(); things.len()];
+        for (at, &[first, second]) in ends.iter().enumerate() {
+            outgoing[first].push((at, second));
+            incoming[second].push((at, first));
+            if !directed[at] {
+                outgoing[second].push((at, first));
+                incoming[first].push((at, second));
+            }
+        }
+        let (out_offsets, out_edges) = compressed_rows(outgoing);
+        let (in_offsets, in_edges) = compressed_rows(incoming);
+
+        CompiledGraph {
+            things: things
+                .iter()
+                .map(|thing| thing.access(|data| data.clone()))
+                .collect(),
+            connection_data,
+            ends,
+            directed,
+            out_offsets,
+            out_edges,
+            in_offsets,
+            in_edges,
+            keys: Vec::new(),
+        }
+    }
+
+    /// Extracts a breadth-first spanning tree rooted at `root`.
+    ///
+    /// For every thing reachable from `root`, the connection it was first
+    /// discovered through becomes a tree edge, which gives a canonical
+    /// hierarchy for rendering a tangled graph as a tree. Directed connections
+    /// are followed from source to target and undirected connections both
+    /// ways. Dead items and already visited things are skipped.
+    ///
+    /// # Returns
+    /// The tree edges in discovery order, one for each reached thing other than
+    /// the root. Empty if `root` is dead.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut graph = Things::<&str, ()>::new();
+    /// let a = graph.new_thing("a");
+    /// let b = graph.new_thing("b");
+    /// let c = graph.new_thing("c");
+    /// graph.new_undirected_connection([a.clone(), b.clone()], ());
+    /// graph.new_undirected_connection([b.clone(), c.clone()], ());
+    /// graph.new_undirected_connection([c, a.clone()], ());
+    ///
+    /// // The triangle loses one of its edges
+    /// assert_eq!(graph.bfs_tree(&a).len(), 2);
+    /// ```
+    pub fn bfs_tree(&self, root: &Thing<T, C>) -> Vec<Connection<T, C>> {
+        let mut tree = Vec::new();
+        if !root.is_alive() {
+            return tree;
+        }
+
+        let mut visited = BTreeSet::new();
+        let mut queue = VecDeque::new();
+        visited.insert(root.key());
+        queue.push_back(root.clone());
+        while let Some(current) = queue.pop_front() {
+            for (connection, next) in current.forward_steps() {
+                if visited.insert(next.key()) {
+                    tree.push(connection);
+                    queue.push_back(next);
+                }
+            }
+        }
+        tree
+    }
+
+    /// Borrows the graph as if every connection was undirected.
+    ///
+    /// Unlike `symmetrize`, nothing is copied or added: the view follows
+    /// directed connections both ways as it goes.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut graph = Things::<&str, ()>::new();
+    /// let a = graph.new_thing("a");
+    /// let b = graph.new_thing("b");
+    /// graph.new_directed_connection(b.clone(), (), a.clone());
+    ///
+    /// let view = graph.undirected_view();
+    /// assert!(view.neighbors(&a)[0] == "b");
+    /// assert_eq!(view.bfs(&a).len(), 2);
+    /// ```
+    pub fn undirected_view(&self) -> UndirectedView<'_, T, C> {
+        UndirectedView { graph: self }
+    }
+
+    /// Returns the eccentricity of `thing`: the greatest number of hops from it
+    /// to any thing it can reach along a shortest path.
+    ///
+    /// Directed connections are followed from source to target and undirected
+    /// connections both ways, as in `visit_bfs`. Things with a low eccentricity
+    /// sit near the center of the part of the graph they reach, and those with
+    /// a high one near its edge.
+    ///
+    /// # Returns
+    /// The eccentricity, or `None` if `thing` is dead or reaches nothing else.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut graph = Things::<&str, ()>::new();
+    /// let a = graph.new_thing("a");
+    /// let b = graph.new_thing("b");
+    /// let c = graph.new_thing("c");
+    /// graph.new_undirected_connection([a.clone(), b.clone()], ());
+    /// graph.new_undirected_connection([b.clone(), c], ());
+    ///
+    /// assert_eq!(graph.eccentricity(&a), Some(2));
+    /// assert_eq!(graph.eccentricity(&b), Some(1));
+    /// ```
+    pub fn eccentricity(&self, thing: &Thing<T, C>) -> Option<usize> {
+        let mut distances = DistanceVisitor::new();
+        self.visit_bfs(thing, &mut distances);
+        // Breadth-first discovery goes by distance, so the last one is the greatest
+        let &(_, farthest) = distances.distances().last()?;
+        (farthest > 0).then_some(farthest)
+    }
+
+    /// Walks the graph breadth-first from `start`, reporting to `visitor`.
+    ///
+    /// `start` is discovered at depth 0. Each thing taken from the queue has its
+    /// live connections examined in order: directed connections from source to
+    /// target and undirected connections both ways. A thing that hasn't been
+    /// seen yet is discovered one level deeper if `should_follow` agrees, and a
+    /// thing is finished once all its connections have been examined. Every
+    /// thing is discovered at most once, so cycles are safe. Nothing is
+    /// reported if `start` is dead.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut graph = Things::<&str, ()>::new();
+    /// let a = graph.new_thing("a");
+    /// let b = graph.new_thing("b");
+    /// let c = graph.new_thing("c");
+    /// graph.new_directed_connection(a.clone(), (), b.clone());
+    /// graph.new_directed_connection(b.clone(), (), c.clone());
+    ///
+    /// let mut distances = DistanceVisitor::new();
+    /// graph.visit_bfs(&a, &mut distances);
+    /// assert_eq!(distances.distance_to(&c), Some(2));
+    /// ```
+    pub fn visit_bfs(&self, start: &Thing<T, C>, visitor: &mut impl GraphVisitor<T, C>) {
+        if !start.is_alive() {
+            return;
+        }
+
+        let mut visited = BTreeSet::new();
+        let mut queue = VecDeque::new();
+        visited.insert(start.key());
+        visitor.discover_thing(start, 0);
+        queue.push_back((start.clone(), 0));
+        while let Some((current, depth)) = queue.pop_front() {
+            for (connection, next) in current.forward_steps() {
+                visitor.examine_connection(&connection, &current, &next);
+                if visited.contains(&next.key()) || !visitor.should_follow(&connection, &next) {
+                    continue;
+                }
+                visited.insert(next.key());
+                visitor.discover_thing(&next, depth + 1);
+                queue.push_back((next, depth + 1));
+            }
+            visitor.finish_thing(&current);
+        }
+    }
+
+    /// Walks the graph depth-first from `start`, reporting to `visitor`.
+    ///
+    /// Follows the same rules as `visit_bfs`, except that a thing is explored
+    /// as soon as it is discovered and is only finished after everything
+    /// discovered from it has been finished. Depths are measured along the
+    /// depth-first tree. The walk keeps its own stack, so deep graphs don't
+    /// overflow the call stack.
+    pub fn visit_dfs(&self, start: &Thing<T, C>, visitor: &mut impl GraphVisitor<T, C>) {
+        if !start.is_alive() {
+            return;
+        }
+
+        let mut visited = BTreeSet::new();
+        visited.insert(start.key());
+        visitor.discover_thing(start, 0);
+        let mut stack = alloc::vec![(start.clone(), start.forward_steps().into_iter())];
+        while let Some((current, steps)) = stack.last_mut() {
+            let current = current.clone();
+            match steps.next() {
+                Some((connection, next)) => {
+                    visitor.examine_connection(&connection, &current, &next);
+                    if visited.contains(&next.key()) || !visitor.should_follow(&connection, &next) {
+                        continue;
+                    }
+                    visited.insert(next.key());
+                    visitor.discover_thing(&next, stack.len());
+                    let steps = next.forward_steps().into_iter();
+                    stack.push((next, steps));
+                }
+                None => {
+                    stack.pop();
+                    visitor.finish_thing(&current);
+                }
+            }
+        }
+    }
+
+    /// Returns the live things that no followed connection leads into.
+    ///
+    /// In a DAG these are the entry points. Only live connections accepted by
+    /// `follow` count. Undirected connections lead both ways, and isolated
+    /// things are both roots and leaves.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut pipeline = Things::<&str, &str>::new();
+    /// let fetch = pipeline.new_thing("fetch");
+    /// let build = pipeline.new_thing("build");
+    /// let ship = pipeline.new_thing("ship");
+    /// pipeline.new_directed_connection(fetch, "then", build.clone());
+    /// pipeline.new_directed_connection(build, "then", ship);
+    ///
+    /// let roots = pipeline.roots(|_| true);
+    /// let leaves = pipeline.leaves(|_| true);
+    /// assert!(roots.len() == 1 && roots[0] == "fetch");
+    /// assert!(leaves.len() == 1 && leaves[0] == "ship");
+    /// ```
+    pub fn roots(&self, follow: impl Fn(&Connection<T, C>) -> bool) -> Vec<Thing<T, C>> {
+        self.things_without_followed(follow, Direction::Towards)
+    }
+
+    /// Materializes the containment hierarchy as a forest of trees, one per root.
+    ///
+    /// Live directed connections accepted by `is_containment` lead from a
+    /// container to what it contains; undirected connections are ignored. The
+    /// roots are found as in `roots` and come in container order, and each
+    /// node's children come in the order their connections were attached to
+    /// it, which makes this suitable for laying out a scene graph.
+    ///
+    /// # Returns
+    /// - `Ok(trees)`: One tree per root
+    /// - `Err(ForestError::Diamond(thing))`: If `thing` is contained twice,
+    ///   which would put it in two places at once
+    /// - `Err(ForestError::Cycle(thing))`: If `thing` contains itself, directly
+    ///   or through others, so no root leads to it
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut windows = Things::<&str, &str>::new();
+    /// let editor = windows.new_thing("editor");
+    /// let toolbar = windows.new_thing("toolbar");
+    /// let palette = windows.new_thing("palette");
+    /// windows.new_directed_connection(editor, "contains", toolbar);
+    ///
+    /// let forest = windows.forest(|connection| *connection == "contains").unwrap();
+    /// assert_eq!(forest.len(), 2);
+    /// assert!(forest[0].thing == "editor" && forest[0].children[0].thing == "toolbar");
+    /// assert!(forest[1].thing == "palette" && forest[1].children.is_empty());
+    /// ```
+    pub fn forest(
+        &self,
+        is_containment: impl Fn(&Connection<T, C>) -> bool,
+    ) -> Result<Vec<TreeNode<T, C>>, ForestError<T, C>> {
+        let contains =
+            |connection: &Connection<T, C>| connection.is_directed() && is_containment(connection);
+
+        let mut parents = BTreeMap::new();
+        for connection in self
+            .connections
+            .iter()
+            .filter(|connection| connection.is_alive() && contains(connection))
+        {
+            let [_, child] = connection.ends();
+            *parents.entry(child.key()).or_insert(0usize) += 1;
+        }
+        if let Some(thing) = self
+            .things
+            .iter()
+            .find(|thing| parents.get(&thing.key()).is_some_and(|&count| count > 1))
+        {
+            return Err(ForestError::Diamond(thing.clone()));
+        }
+
+        // Every thing has at most one parent now, so each is reached at most once
+        let mut visited = BTreeSet::new();
+        let mut forest = Vec::new();
+        for root in self.roots(contains) {
+            // Number the tree's things in depth-first order, then assemble the
+            // nodes from the deepest up so no recursion is needed
+            let mut order: Vec<(Thing<T, C>, Option<usize>)> = Vec::new();
+            let mut stack = alloc::vec![(root, None)];
+            while let Some((thing, parent)) = stack.pop() {
+                visited.insert(thing.key());
+                let position = order.len();
+                let children = thing.outgoing();
+                for (_, child) in children
+                    .into_iter()
+                    .rev()
+                    .filter(|(connection, _)| contains(connection))
+                {
+                    stack.push((child, Some(position)));
+                }
+                order.push((thing, parent));
+            }
+
+            let mut children: Vec<Vec<TreeNode<T, C>>> = order.iter().map(|_| Vec::new()).collect();
+            let mut tree = None;
+            for (position, (thing, parent)) in order.into_iter().enumerate().rev() {
+                let mut own = core::mem::take(&mut children[position]);
+                own.reverse();
+                let node = TreeNode {
+                    thing,
+                    children: own,
+                };
+                match parent {
+                    Some(parent) => children[parent].push(node),
+                    None => tree = Some(node),
+                }
+            }
+            forest.extend(tree);
+        }
+
+        match self
+            .things
+            .iter()
+            .find(|thing| thing.is_alive() && !visited.contains(&thing.key()))
+        {
+            Some(thing) => Err(ForestError::Cycle(thing.clone())),
+            None => Ok(forest),
+        }
+    }
+
+    /// Returns the live things that no followed connection leads out of.
+    ///
+    /// In a DAG these are the exit points. See `roots` for how undirected
+    /// connections and isolated things count.
+    pub fn leaves(&self, follow: impl Fn(&Connection<T, C>) -> bool) -> Vec<Thing<T, C>> {
+        self.things_without_followed(follow, Direction::AwayFrom)
+    }
+
+    /// Collects the live things that no followed connection leads towards or
+    /// away from, in a single pass over the connections.
+    fn things_without_followed(
+        &self,
+        follow: impl Fn(&Connection<T, C>) -> bool,
+        direction: Direction,
+    ) -> Vec<Thing<T, C>> {
+        let mut flagged = BTreeSet::new();
+        for connection in self
+            .connections
+            .iter()
+            .filter(|connection| connection.is_alive() && follow(connection))
+        {
+            match connection.directed_ends() {
+                Some((from, to)) => {
+                    let end = match direction {
+                        Direction::Towards => to,
+                        Direction::AwayFrom => from,
+                    };
+                    flagged.insert(end.key());
+                }
+                None => {
+                    for thing in connection.ends() {
+                        flagged.insert(thing.key());
+                    }
+                }
+            }
+        }
+        self.things
+            .iter()
+            .filter(|thing| thing.is_alive() && !flagged.contains(&thing.key()))
+            .cloned()
+            .collect()
+    }
+
+    /// Checks whether `to` can be reached from `from`.
+    ///
+    /// Only connections accepted by `follow` are traversed, following the same
+    /// rules as `shortest_path`. A live thing can always reach itself.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut roles = Things::<&str, ()>::new();
+    /// let admin = roles.new_thing("admin");
+    /// let editor = roles.new_thing("editor");
+    /// roles.new_directed_connection(admin.clone(), (), editor.clone());
+    ///
+    /// assert!(roles.is_reachable(&admin, &editor, |_| true));
+    /// assert!(!roles.is_reachable(&editor, &admin, |_| true));
+    /// ```
+    pub fn is_reachable(
+        &self,
+        from: &Thing<T, C>,
+        to: &Thing<T, C>,
+        follow: impl Fn(&Connection<T, C>) -> bool,
+    ) -> bool {
+        self.search_path(from, to, |connection, _| follow(connection))
+            .is_some()
+    }
+
+    /// Checks whether `a` and `b` are in the same component, following
+    /// connections both ways regardless of direction.
+    ///
+    /// Searches breadth-first from both things at once, always growing the
+    /// smaller frontier, and stops as soon as the searches meet. Unlike
+    /// `split_components`, nothing is copied and only the things around the
+    /// two ends are visited. A live thing is in the same component as itself.
+    ///
+    /// # Returns
+    /// `true` if a chain of live connections joins them, `false` otherwise or
+    /// if either is dead.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut people = Things::<&str, ()>::new();
+    /// let alice = people.new_thing("alice");
+    /// let bob = people.new_thing("bob");
+    /// let carol = people.new_thing("carol");
+    /// people.new_directed_connection(bob.clone(), (), alice.clone());
+    ///
+    /// assert!(people.same_component(&alice, &bob));
+    /// assert!(!people.same_component(&alice, &carol));
+    /// ```
+    pub fn same_component(&self, a: &Thing<T, C>, b: &Thing<T, C>) -> bool {
+        if !a.is_alive() || !b.is_alive() {
+            return false;
+        }
+        if a.ptr_eq(b) {
+            return true;
+        }
+
+        let mut seen = [BTreeSet::from([a.key()]), BTreeSet::from([b.key()])];
+        let mut frontiers = [alloc::vec![a.clone()], alloc::vec![b.clone()]];
+        while !frontiers[0].is_empty() && !frontiers[1].is_empty() {
+            let side = usize::from(frontiers[1].len() < frontiers[0].len());
+            let mut level = Vec::new();
+            for thing in core::mem::take(&mut frontiers[side]) {
+                for next in thing.neighbors_alive() {
+                    if seen[1 - side].contains(&next.key()) {
+                        return true;
+                    }
+                    if seen[side].insert(next.key()) {
+                        level.push(next);
+                    }
+                }
+            }
+            frontiers[side] = level;
+        }
+        false
+    }
+
+    /// Checks whether the live things form a single component, following
+    /// connections both ways regardless of direction.
+    ///
+    /// Floods breadth-first from the first live thing and then checks that
+    /// every live thing was reached. Useful as a precondition for algorithms
+    /// that assume a connected graph, such as spanning trees or `eccentricity`
+    /// based measures.
+    ///
+    /// # Returns
+    /// `true` if every live thing can reach every other one, and also for a
+    /// graph with at most one live thing.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut network = Things::<&str, ()>::new();
+    /// let router = network.new_thing("router");
+    /// let laptop = network.new_thing("laptop");
+    /// network.new_directed_connection(laptop, (), router);
+    /// assert!(network.is_connected());
+    ///
+    /// network.new_thing("printer");
+    /// assert!(!network.is_connected());
+    /// ```
+    pub fn is_connected(&self) -> bool {
+        let mut live = self.things.iter().filter(|thing| thing.is_alive());
+        let Some(start) = live.next() else {
+            return true;
+        };
+
+        let mut reached = BTreeSet::from([start.key()]);
+        let mut queue = VecDeque::from([start.clone()]);
+        while let Some(current) = queue.pop_front() {
+            for next in current.neighbors_alive() {
+                if reached.insert(next.key()) {
+                    queue.push_back(next);
+                }
+            }
+        }
+        live.all(|thing| reached.contains(&thing.key()))
+    }
+
+    /// Checks whether the connections accepted by `follow` contain a cycle.
+    ///
+    /// Directed connections are followed from source to target. An undirected
+    /// connection can be walked there and back again, so any followed
+    /// undirected connection counts as a cycle, and so does a self-loop.
+    /// Dead things and connections are ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut steps = Things::<&str, ()>::new();
+    /// let fetch = steps.new_thing("fetch");
+    /// let build = steps.new_thing("build");
+    /// steps.new_directed_connection(fetch.clone(), (), build.clone());
+    /// assert!(!steps.has_cycle(|_| true));
+    ///
+    /// steps.new_directed_connection(build, (), fetch);
+    /// assert!(steps.has_cycle(|_| true));
+    /// ```
+    pub fn has_cycle(&self, follow: impl Fn(&Connection<T, C>) -> bool) -> bool {
+        let (things, index) = self.index_things();
+        let (successors, undirected) = followed_successors(&things, &index, &follow);
+        undirected || topological_order(&successors).is_none()
+    }
+
+    /// Collapses every strongly connected component into a single thing,
+    /// which leaves a graph without cycles.
+    ///
+    /// Things belong to the same component when each can reach the other,
+    /// following directed connections from source to target and undirected
+    /// ones both ways. Each component becomes a thing holding its members in
+    /// container order, and components are ordered by their first member.
+    /// Every live directed connection between two components becomes a
+    /// connection between them with a copy of its data; connections inside
+    /// a component are left out. Dead items are skipped.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut graph = Things::<&str, ()>::new();
+    /// let a = graph.new_thing("a");
+    /// let b = graph.new_thing("b");
+    /// let c = graph.new_thing("c");
+    /// graph.new_directed_connection(a.clone(), (), b.clone());
+    /// graph.new_directed_connection(b, (), a.clone());
+    /// graph.new_directed_connection(c.clone(), (), a);
+    ///
+    /// let condensed = graph.condensation();
+    /// assert!(!condensed.has_cycle(|_| true));
+    /// let members = condensed.find_thing_equal(&vec![c]).unwrap();
+    /// assert_eq!(members.outgoing().len(), 1);
+    /// ```
+    pub fn condensation(&self) -> Things<Vec<Thing<T, C>>, C>
+    where
+        C: Clone,
+    {
+        let (things, index) = self.index_things();
+        let (successors, _) = followed_successors(&things, &index, |_| true);
+        let component = strongly_connected_components(&successors);
+
+        let mut condensed = Things::new();
+        let mut members: Vec<Vec<Thing<T, C>>> = Vec::new();
+        for (at, thing) in things.iter().enumerate() {
+            if component[at] == members.len() {
+                members.push(Vec::new());
+            }
+            members[component[at]].push(thing.clone());
+        }
+        let super_things: Vec<_> = members
+            .into_iter()
+            .map(|members| condensed.new_thing(members))
+            .collect();
+
+        for connection in self
+            .connections
+            .iter()
+            .filter(|connection| connection.is_alive())
+        {
+            let Some((from, to)) = connection.directed_ends() else {
+                continue;
+            };
+            let (Some(&from), Some(&to)) = (index.get(&from.key()), index.get(&to.key())) else {
+                continue;
+            };
+            if component[from] != component[to] {
+                condensed.new_directed_connection(
+                    super_things[component[from]].clone(),
+                    connection.access(|data| data.clone()),
+                    super_things[component[to]].clone(),
+                );
+            }
+        }
+        condensed
+    }
+
+    /// Plans running things in dependency order, for the caller to drive.
+    ///
+    /// A live directed connection accepted by `is_dep` makes its source a
+    /// dependency of its target: the target only becomes ready once the
+    /// source is marked done. Things without dependencies are ready right
+    /// away. See `ExecutionPlan` for how to step through the plan.
+    ///
+    /// # Returns
+    /// - `Ok(plan)`: If the accepted connections have no cycle
+    /// - `Err(CycleError)`: Otherwise; as in `has_cycle`, an accepted
+    ///   undirected connection or self-loop counts as a cycle
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut build = Things::<&str, &str>::new();
+    /// let fetch = build.new_thing("fetch");
+    /// let compile = build.new_thing("compile");
+    /// build.new_directed_connection(fetch, "before", compile);
+    ///
+    /// let mut plan = build.execution_order(|_| true).unwrap();
+    /// let first = plan.next_ready().unwrap();
+    /// assert!(first == "fetch");
+    /// assert!(plan.next_ready().is_none());
+    ///
+    /// plan.mark_done(&first).unwrap();
+    /// assert!(plan.next_ready().unwrap() == "compile");
+    /// ```
+    pub fn execution_order(
+        &self,
+        is_dep: impl Fn(&Connection<T, C>) -> bool,
+    ) -> Result<ExecutionPlan<T, C>, CycleError> {
+        let (things, index) = self.index_things();
+        let (successors, undirected) = followed_successors(&things, &index, &is_dep);
+        if undirected || topological_order(&successors).is_none() {
+            return Err(CycleError);
+        }
+
+        let mut waiting_on = alloc::vec![0usize; things.len()];
+        for &next in successors.iter().flatten() {
+            waiting_on[next] += 1;
+        }
+        let ready = (0..things.len())
+            .filter(|&at| waiting_on[at] == 0)
+            .collect();
+        Ok(ExecutionPlan {
+            states: alloc::vec![PlanState::Waiting; things.len()],
+            things,
+            index,
+            successors,
+            waiting_on,
+            ready,
+        })
+    }
+
+    /// Finds everything reachable from each of several sources in one pass.
+    ///
+    /// Answers the same question as calling `is_reachable` for every source
+    /// and thing, but shares the work between sources. When the followed
+    /// connections form a DAG (see `has_cycle`), each thing's reachable set is
+    /// computed once and reused by everything that reaches it. Otherwise every
+    /// source gets its own breadth-first search, reusing one visited list.
+    ///
+    /// # Returns
+    /// One list per source, in the order of `sources`. Each list holds the
+    /// live things reachable from that source in container order, once each and
+    /// without the source itself. Dead sources reach nothing.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut access = Things::<&str, ()>::new();
+    /// let admin = access.new_thing("admin");
+    /// let editor = access.new_thing("editor");
+    /// let drafts = access.new_thing("drafts");
+    /// let settings = access.new_thing("settings");
+    /// access.new_directed_connection(admin.clone(), (), editor.clone());
+    /// access.new_directed_connection(admin.clone(), (), settings);
+    /// access.new_directed_connection(editor.clone(), (), drafts);
+    ///
+    /// let sets = access.reachable_sets(&[admin, editor], |_| true);
+    /// assert_eq!(sets[0].len(), 3);
+    /// assert!(sets[1][0] == "drafts");
+    /// ```
+    pub fn reachable_sets(
+        &self,
+        sources: &[Thing<T, C>],
+        follow: impl Fn(&Connection<T, C>) -> bool,
+    ) -> Vec<Vec<Thing<T, C>>> {
+        let (things, index) = self.index_things();
+        let (successors, undirected) = followed_successors(&things, &index, &follow);
+        let starts: Vec<Option<usize>> = sources
+            .iter()
+            .map(|source| index.get(&source.key()).copied())
+            .collect();
+        let order = if undirected {
+            None
+        } else {
+            topological_order(&successors)
+        };
+
+        let reached: Vec<Vec<usize>> = match order {
+            Some(order) => {
+                // Only things reachable from some source need a set
+                let mut needed = alloc::vec![false; things.len()];
+                let mut stack: Vec<usize> = starts.iter().flatten().copied().collect();
+                while let Some(current) = stack.pop() {
+                    if !needed[current] {
+                        needed[current] = true;
+                        stack.extend(&successors[current]);
+                    }
+                }
+
+                // Build sets from the sinks upwards, so successors come first
+                let mut memo: Vec<BTreeSet<usize>> = alloc::vec![BTreeSet::new(); things.len()];
+                for &current in order.iter().rev().filter(|&&current| needed[current]) {
+                    let mut set = BTreeSet::new();
+                    for &next in &successors[current] {
+                        set.insert(next);
+                        set.extend(&memo[next]);
+                    }
+                    memo[current] = set;
+                }
+                starts
+                    .iter()
+                    .map(|start| match start {
+                        Some(start) => memo[*start].iter().copied().collect(),
+                        None => Vec::new(),
+                    })
+                    .collect()
+            }
+            None => {
+                let mut visited_by = alloc::vec![usize::MAX; things.len()];
+                let mut queue = VecDeque::new();
+                starts
+                    .iter()
+                    .enumerate()
+                    .map(|(search, start)| {
+                        let Some(start) = *start else {
+                            return Vec::new();
+                        };
+                        let mut found = Vec::new();
+                        visited_by[start] = search;
+                        queue.push_back(start);
+                        while let Some(current) = queue.pop_front() {
+                            for &next in &successors[current] {
+                                if visited_by[next] != search {
+                                    visited_by[next] = search;
+                                    found.push(next);
+                                    queue.push_back(next);
+                                }
+                            }
+                        }
+                        found.sort_unstable();
+                        found
+                    })
+                    .collect()
+            }
+        };
+
+        reached
+            .into_iter()
+            .map(|set| set.into_iter().map(|at| things[at].clone()).collect())
+            .collect()
+    }
+
+    /// Counts the live things reachable from `start`, without collecting them.
+    ///
+    /// Follows directed connections from source to target and undirected ones
+    /// both ways, like `reachable_sets`, and gives the same number as the
+    /// length of its list: `start` itself isn't counted. Only a visited set is
+    /// kept, which makes this a cheap "blast radius" measure for everything
+    /// downstream of a thing.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut services = Things::<&str, ()>::new();
+    /// let database = services.new_thing("database");
+    /// let api = services.new_thing("api");
+    /// let web = services.new_thing("web");
+    /// services.new_directed_connection(database.clone(), (), api.clone());
+    /// services.new_directed_connection(api, (), web);
+    ///
+    /// assert_eq!(services.reachable_count(&database), 2);
+    /// ```
+    pub fn reachable_count(&self, start: &Thing<T, C>) -> usize {
+        if !start.is_alive() {
+            return 0;
+        }
+        let mut visited = BTreeSet::new();
+        let mut queue = VecDeque::new();
+        visited.insert(start.key());
+        queue.push_back(start.clone());
+        while let Some(current) = queue.pop_front() {
+            for (_, next) in current.forward_steps() {
+                if visited.insert(next.key()) {
+                    queue.push_back(next);
+                }
+            }
+        }
+        visited.len() - 1
+    }
+
+    /// Finds the highest scoring thing within `hops` steps of `start`.
+    ///
+    /// Walks breadth-first from `start` along the connections accepted by
+    /// `follow`, directed ones from source to target and undirected ones both
+    /// ways, and scores every thing reached with `score`. `start` itself is
+    /// never picked. Ties go to the thing fewer hops away, then to the thing
+    /// created first. Dead things and connections are skipped.
+    ///
+    /// # Returns
+    /// - `Some((thing, score))`: The best thing in range and its score
+    /// - `None`: If nothing is in range, or `start` is dead
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut network = Things::<&str, ()>::new();
+    /// let laptop = network.new_thing("laptop");
+    /// let router = network.new_thing("router");
+    /// let modem = network.new_thing("modem");
+    /// network.new_undirected_connection([laptop.clone(), router.clone()], ());
+    /// network.new_undirected_connection([router, modem], ());
+    ///
+    /// let relay = network.best_within(&laptop, 2, |thing| thing.connection_count() as u64, |_| true);
+    /// assert!(relay.is_some_and(|(thing, degree)| thing == "router" && degree == 2));
+    /// ```
+    pub fn best_within(
+        &self,
+        start: &Thing<T, C>,
+        hops: usize,
+        score: impl Fn(&Thing<T, C>) -> u64,
+        follow: impl Fn(&Connection<T, C>) -> bool,
+    ) -> Option<(Thing<T, C>, u64)> {
+        use core::cmp::Reverse;
+
+        if !start.is_alive() {
+            return None;
+        }
+        let mut best = None;
+        let mut best_rank = None;
+        let mut visited = BTreeSet::new();
+        let mut queue = VecDeque::new();
+        visited.insert(start.key());
+        queue.push_back((start.clone(), 0));
+        while let Some((current, depth)) = queue.pop_front() {
+            if depth == hops {
+                continue;
+            }
+            for (connection, next) in current.forward_steps() {
+                if !follow(&connection) || !visited.insert(next.key()) {
+                    continue;
+                }
+                let rank = (score(&next), Reverse(depth + 1), Reverse(next.seq()));
+                if best_rank.is_none_or(|best_rank| rank > best_rank) {
+                    best_rank = Some(rank);
+                    best = Some((next.clone(), rank.0));
+                }
+                queue.push_back((next, depth + 1));
+            }
+        }
+        best
+    }
+
+    /// Returns the `k` live things that score highest on a centrality measure,
+    /// highest first.
+    ///
+    /// Things with equal scores keep their container order. `Centrality::Degree`
+    /// is always available; the other measures need the `float` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut graph = Things::<&str, ()>::new();
+    /// let hub = graph.new_thing("hub");
+    /// for name in ["a", "b", "c"] {
+    ///     let spoke = graph.new_thing(name);
+    ///     graph.new_undirected_connection([hub.clone(), spoke], ());
+    /// }
+    ///
+    /// let top = graph.top_k_central(1, Centrality::Degree);
+    /// assert!(top[0].0 == "hub" && top[0].1 == 3.0);
+    /// ```
+    pub fn top_k_central(&self, k: usize, measure: Centrality) -> Vec<(Thing<T, C>, f64)> {
+        let mut scores: Vec<(Thing<T, C>, f64)> = match measure {
+            Centrality::Degree => self
+                .things
+                .iter()
+                .filter(|thing| thing.is_alive())
+                .map(|thing| (thing.clone(), thing.connection_count() as f64))
+                .collect(),
+            #[cfg(feature = "float")]
+            Centrality::PageRank => self.page_rank_scores(),
+            #[cfg(feature = "float")]
+            Centrality::Betweenness => self.betweenness(),
+        };
+        scores.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+        scores.truncate(k);
+        scores
+    }
+
+    /// Spreads a value outwards from `start` in breadth-first order.
+    ///
+    /// Every hop calls `step` with the accumulator of the current thing, the
+    /// connection being crossed and the thing on the other side. Returning
+    /// `Some` records the new accumulator for that thing and keeps spreading
+    /// from it, while returning `None` stops the propagation along that path.
+    ///
+    /// Directed connections are only followed from source to target, undirected
+    /// connections in both directions. Things are tracked by identity, so each
+    /// thing is recorded at most once with the value of its first (shortest)
+    /// arrival, which also makes the propagation safe on cyclic graphs.
+    /// Dead things and connections are skipped.
+    ///
+    /// # Returns
+    /// Every reached thing paired with its accumulator, in the order they were
+    /// reached. The start thing comes first, holding `init`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut network = Things::<&str, ()>::new();
+    ///
+    /// let source = network.new_thing("source");
+    /// let relay = network.new_thing("relay");
+    /// let far = network.new_thing("far");
+    /// network.new_directed_connection(source.clone(), (), relay.clone());
+    /// network.new_directed_connection(relay.clone(), (), far.clone());
+    ///
+    /// // A signal that loses one unit of strength per hop
+    /// let reached = network.propagate(&source, 1u32, |strength, _, _| strength.checked_sub(1));
+    ///
+    /// assert_eq!(reached.len(), 2);
+    /// assert_eq!(reached[1].1, 0);
+    /// ```
+    pub fn propagate<A: Clone>(
+        &self,
+        start: &Thing<T, C>,
+        init: A,
+        step: impl Fn(&A, &Connection<T, C>, &Thing<T, C>) -> Option<A>,
+    ) -> Vec<(Thing<T, C>, A)> {
+        let mut reached = Vec::new();
+        if !start.is_alive() {
+            return reached;
+        }
+
+        let mut visited = BTreeSet::new();
+        let mut queue = VecDeque::new();
+        visited.insert(start.key());
+        queue.push_back(reached.len());
+        reached.push((start.clone(), init));
+
+        while let Some(index) = queue.pop_front() {
+            let (current, value) = reached[index].clone();
+            for (connection, next) in current.forward_steps() {
+                if visited.contains(&next.key()) {
+                    continue;
+                }
+                if let Some(next_value) = step(&value, &connection, &next) {
+                    visited.insert(next.key());
+                    queue.push_back(reached.len());
+                    reached.push((next, next_value));
+                }
+            }
+        }
+
+        reached
+    }
+
+    /// Estimates how many shortest paths run through each connection.
+    ///
+    /// Connections with a high score are the bridges of the graph: removing
+    /// them splits it into loosely connected communities. Runs a breadth-first
+    /// search from `samples` source things, counting shortest paths and
+    /// accumulating each connection's share of them the way Brandes' algorithm
+    /// does for things. Only connections accepted by `follow` are traversed;
+    /// directed connections go from source to target and undirected ones both
+    /// ways. Self-loops never lie on a shortest path.
+    ///
+    /// Each source is chosen by calling `pick` with the number of live things;
+    /// the returned index (taken modulo that number) selects a thing in
+    /// container order, so a fixed picker gives a repeatable result. When
+    /// `samples` is at least the number of live things, every thing is used
+    /// once instead and the scores are exact. Like `betweenness`, paths are
+    /// counted from every source, so in an undirected graph each pair of
+    /// things contributes in both directions.
+    ///
+    /// # Returns
+    /// Every live connection paired with its score in units of
+    /// [`EDGE_BETWEENNESS_SCALE`], highest first. Equal scores keep container order.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut graph = Things::<&str, &str>::new();
+    /// let left = graph.new_thing("left");
+    /// let middle = graph.new_thing("middle");
+    /// let right = graph.new_thing("right");
+    /// graph.new_undirected_connection([left, middle.clone()], "a");
+    /// graph.new_undirected_connection([middle, right], "b");
+    ///
+    /// let scores = graph.edge_betweenness_approx(usize::MAX, |_| 0, |_| true);
+    /// // Each connection lies on the paths between its ends and one pair more,
+    /// // counted in both directions.
+    /// assert_eq!(scores[0].1, 4 * EDGE_BETWEENNESS_SCALE);
+    /// assert_eq!(scores[1].1, 4 * EDGE_BETWEENNESS_SCALE);
+    /// ```
+    pub fn edge_betweenness_approx(
+        &self,
+        samples: usize,
+        mut pick: impl FnMut(usize) -> usize,
+        follow: impl Fn(&Connection<T, C>) -> bool,
+    ) -> Vec<(Connection<T, C>, u64)> {
+        let (things, index) = self.index_things();
+        let count = things.len();
+        let connections: Vec<Connection<T, C>> = self
+            .connections
+            .iter()
+            .filter(|connection| connection.is_alive())
+            .cloned()
+            .collect();
+        let connection_index: BTreeMap<usize, usize> = connections
+            .iter()
+            .enumerate()
+            .map(|(position, connection)| (connection.key(), position))
+            .collect();
+
+        let adjacency: Vec<Vec<(usize, usize)>> = things
+            .iter()
+            .enumerate()
+            .map(|(position, thing)| {
+                thing
+                    .forward_steps()
+                    .iter()
+                    .filter(|(connection, _)| follow(connection))
+                    .filter_map(|(connection, next)| {
+                        let next = *index.get(&next.key())?;
+                        if next == position {
+                            return None;
+                        }
+                        Some((next, *connection_index.get(&connection.key())?))
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let sources: Vec<usize> = if samples >= count {
+            (0..count).collect()
+        } else {
+            (0..samples).map(|_| pick(count) % count).collect()
+        };
+
+        let mut scores = alloc::vec![0u64; connections.len()];
+        for source in sources {
+            let mut distance: Vec<Option<usize>> = alloc::vec![None; count];
+            let mut paths = alloc::vec![0u128; count];
+            // Each entry pairs the previous thing with the connection used to step from it
+            let mut predecessors: Vec<Vec<(usize, usize)>> = alloc::vec![Vec::new(); count];
+            let mut order = Vec::new();
+            let mut queue = VecDeque::new();
+
+            distance[source] = Some(0);
+            paths[source] = 1;
+            queue.push_back(source);
+            while let Some(current) = queue.pop_front() {
+                order.push(current);
+                let next_distance = distance[current].map_or(0, |known| known + 1);
+                for &(next, edge) in &adjacency[current] {
+                    match distance[next] {
+                        None => {
+                            distance[next] = Some(next_distance);
+                            queue.push_back(next);
+                        }
+                        Some(known) if known != next_distance => continue,
+                        Some(_) => {}
+                    }
+                    paths[next] = paths[next].saturating_add(paths[current]);
+                    predecessors[next].push((current, edge));
+                }
+            }
+
+            // Accumulate dependencies from the furthest things back towards the source
+            let mut dependency = alloc::vec![0u128; count];
+            while let Some(current) = order.pop() {
+                for &(previous, edge) in &predecessors[current] {
+                    let credit = paths[previous].saturating_mul(
+                        u128::from(EDGE_BETWEENNESS_SCALE).saturating_add(dependency[current]),
+                    ) / paths[current];
+                    dependency[previous] = dependency[previous].saturating_add(credit);
+                    scores[edge] =
+                        scores[edge].saturating_add(u64::try_from(credit).unwrap_or(u64::MAX));
+                }
+            }
+        }
+
+        let mut scored: Vec<(Connection<T, C>, u64)> =
+            connections.into_iter().zip(scores).collect();
+        scored.sort_by_key(|(_, score)| core::cmp::Reverse(*score));
+        scored
+    }
+}
+
+impl<T, C> Default for Things<T, C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// How many low bits of a sequence number are left to each shard of a
+/// `ShardedThings`; the bits above them name the shard.
+const SHARD_SEQ_SHIFT: u32 = 48;
+
+/// A graph split over several `Things` shards, for graphs so large that one
+/// pair of lists and one dead count become unwieldy.
+///
+/// New things go to the shards in turn, or to the shard picked by the key
+/// given to `with_shard_key`. Connections may join things in different shards:
+/// each connection is kept by the shard of its first thing (the source, for
+/// directed connections), which counts it once it is dead, and both things
+/// still see it in their connection lists. Walks follow the handles, so they
+/// cross shard boundaries without noticing them.
+///
+/// Each shard is cleaned on its own with `clean_shard`, and reports its own
+/// pressure with `shard_dead_percentage`. A dead connection between shards
+/// stays in the lists of live things until their shard is cleaned too.
+///
+/// Connection ids are unique across all shards.
+///
+/// # Examples
+///
+/// ```rust
+/// # use connect_things::*;
+/// let mut graph = ShardedThings::<&str, &str>::new(2);
+/// let alice = graph.new_thing("alice");
+/// let bob = graph.new_thing("bob");
+/// assert_ne!(graph.shard_of(&alice), graph.shard_of(&bob));
+///
+/// graph.new_undirected_connection([alice, bob.clone()], "friends");
+/// graph.kill_things(|thing| *thing == "alice");
+///
+/// // Only the shard that kept the connection and the thing has anything to clean
+/// assert_eq!(graph.shard_dead_percentage(0), Ok(100));
+/// assert_eq!(graph.shard_dead_percentage(1), Ok(0));
+/// graph.clean_shard(0);
+/// assert!(bob.neighbors_alive().is_empty());
+/// ```
+pub struct ShardedThings<T, C> {
+    shards: Vec<Things<T, C>>,
+    /// The shard of the next thing when shards are taken in turn.
+    next_shard: usize,
+    shard_key: Option<ShardKey<T>>,
+}
+
+impl<T, C> ShardedThings<T, C> {
+    /// Creates an empty graph with `shard_count` shards, which take new things
+    /// in turn.
+    ///
+    /// # Panics
+    /// If `shard_count` is 0 or above 65536.
+    pub fn new(shard_count: usize) -> ShardedThings<T, C> {
+        assert!(
+            (1..=1 << (64 - SHARD_SEQ_SHIFT)).contains(&shard_count),
+            "a sharded graph needs between 1 and 65536 shards"
+        );
+        let shards = (0..shard_count)
+            .map(|shard| {
+                let mut things = Things::new();
+                things.next_seq = ((shard as u64) << SHARD_SEQ_SHIFT) + 1;
+                things
+            })
+            .collect();
+        ShardedThings {
+            shards,
+            next_shard: 0,
+            shard_key: None,
+        }
+    }
+
+    /// Creates an empty graph with `shard_count` shards, putting each new thing
+    /// in the shard `key` picks for its data.
+    ///
+    /// Keys at or above `shard_count` wrap around.
+    ///
+    /// # Panics
+    /// Like `new`.
+    pub fn with_shard_key(
+        shard_count: usize,
+        key: impl Fn(&T) -> usize + 'static,
+    ) -> ShardedThings<T, C> {
+        let mut graph = ShardedThings::new(shard_count);
+        graph.shard_key = Some(Box::new(key));
+        graph
+    }
+
+    /// Returns the number of shards.
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// Gives read access to one shard, holding the things placed there and the
+    /// connections it keeps.
+    ///
+    /// # Panics
+    /// If `shard` is out of range.
+    pub fn shard(&self, shard: usize) -> &Things<T, C> {
+        &self.shards[shard]
+    }
+
+    /// Returns the shard holding `thing`.
+    ///
+    /// The shard is read from the range of sequence numbers the thing was
+    /// given, so a thing from another container is only caught when its
+    /// number falls outside every range this graph has handed out.
+    ///
+    /// # Returns
+    /// - `Some(shard)`: The shard that created `thing`
+    /// - `None`: If no shard of this graph could have created `thing`
+    pub fn shard_of(&self, thing: &Thing<T, C>) -> Option<usize> {
+        self.shard_of_seq(thing.seq())
+    }
+
+    /// Finds the shard that handed out a sequence number.
+    fn shard_of_seq(&self, seq: u64) -> Option<usize> {
+        let shard = (seq >> SHARD_SEQ_SHIFT) as usize;
+        self.shards
+            .get(shard)
+            .filter(|things| seq < things.next_seq)
+            .map(|_| shard)
+    }
+
+    /// Returns the shard holding `thing`, for the methods that need one.
+    fn shard_holding(&self, thing: &Thing<T, C>) -> usize {
+        self.shard_of(thing)
+            .expect("the thing doesn't belong to this sharded graph")
+    }
+
+    /// Creates a new thing in the next shard, or the one picked by the shard key.
+    pub fn new_thing(&mut self, data: T) -> Thing<T, C> {
+        let shard = match self.shard_key.as_ref() {
+            Some(key) => key(&data) % self.shards.len(),
+            None => {
+                let shard = self.next_shard;
+                self.next_shard = (shard + 1) % self.shards.len();
+                shard
+            }
+        };
+        self.shards[shard].new_thing(data)
+    }
+
+    /// Creates a new thing in a chosen shard.
+    ///
+    /// # Panics
+    /// If `shard` is out of range.
+    pub fn new_thing_in(&mut self, shard: usize, data: T) -> Thing<T, C> {
+        self.shards[shard].new_thing(data)
+    }
+
+    /// Creates a directed connection, kept by the shard of `from`.
+    ///
+    /// # Panics
+    /// If `from` doesn't belong to this graph, or under the rules of
+    /// `Things::new_directed_connection` for its shard.
+    pub fn new_directed_connection(
+        &mut self,
+        from: Thing<T, C>,
+        data: C,
+        to: Thing<T, C>,
+    ) -> Connection<T, C> {
+        let shard = self.shard_holding(&from);
+        self.shards[shard].new_directed_connection(from, data, to)
+    }
+
+    /// Creates an undirected connection, kept by the shard of the first thing.
+    ///
+    /// # Panics
+    /// If the first thing doesn't belong to this graph, or under the rules of
+    /// `Things::new_undirected_connection` for its shard.
+    pub fn new_undirected_connection(
+        &mut self,
+        things: [Thing<T, C>; 2],
+        data: C,
+    ) -> Connection<T, C> {
+        let shard = self.shard_holding(&things[0]);
+        self.shards[shard].new_undirected_connection(things, data)
+    }
+
+    /// Finds the first live thing holding data equal to `value`, searching the
+    /// shards in order.
+    pub fn find_thing_equal(&self, value: &T) -> Option<Thing<T, C>>
+    where
+        T: PartialEq,
+    {
+        self.shards
+            .iter()
+            .find_map(|shard| shard.find_thing_equal(value))
+    }
+
+    /// Finds the first live connection holding data equal to `value`, searching
+    /// the shards in order.
+    pub fn find_connection_equal(&self, value: &C) -> Option<Connection<T, C>>
+    where
+        C: PartialEq,
+    {
+        self.shards
+            .iter()
+            .find_map(|shard| shard.find_connection_equal(value))
+    }
+
+    /// Looks up a live connection by its id, in whichever shard keeps it.
+    pub fn get_connection_by_id(&self, id: ConnectionId) -> Option<Connection<T, C>> {
+        let shard = self.shard_of_seq(id.0)?;
+        self.shards[shard].get_connection_by_id(id)
+    }
+
+    /// Runs `get` on every thing, shard after shard, like
+    /// `Things::do_for_all_things`.
+    pub fn do_for_all_things<R>(&self, get: impl Fn(&Thing<T, C>) -> Do<R>) -> Vec<R> {
+        self.shards
+            .iter()
+            .flat_map(|shard| shard.do_for_all_things(&get))
+            .collect()
+    }
+
+    /// Runs `found` on every connection, shard after shard, like
+    /// `Things::do_for_all_connections`.
+    pub fn do_for_all_connections<R>(&self, found: impl Fn(&Connection<T, C>) -> Do<R>) -> Vec<R> {
+        self.shards
+            .iter()
+            .flat_map(|shard| shard.do_for_all_connections(&found))
+            .collect()
+    }
+
+    /// Walks the graph breadth-first from `start`, across shards, like
+    /// `Things::visit_bfs`.
+    ///
+    /// # Panics
+    /// If `start` doesn't belong to this graph.
+    pub fn visit_bfs(&self, start: &Thing<T, C>, visitor: &mut impl GraphVisitor<T, C>) {
+        self.shards[self.shard_holding(start)].visit_bfs(start, visitor);
+    }
+
+    /// Finds a path with the fewest steps between two things, across shards,
+    /// like `Things::shortest_path`.
+    ///
+    /// # Returns
+    /// - `Some(path)`: The things along the path, starting with `from` and ending with `to`
+    /// - `None`: If `to` can't be reached, or `from` doesn't belong to this graph
+    pub fn shortest_path(
+        &self,
+        from: &Thing<T, C>,
+        to: &Thing<T, C>,
+        follow: impl Fn(&Connection<T, C>) -> bool,
+    ) -> Option<Vec<Thing<T, C>>> {
+        self.shards[self.shard_of(from)?].shortest_path(from, to, follow)
+    }
+
+    /// Kills every thing matching `kill`, along with its connections.
+    ///
+    /// Each killed connection is counted by the shard keeping it, which may not
+    /// be the shard of the killed thing.
+    pub fn kill_things(&mut self, kill: impl Fn(&Thing<T, C>) -> bool) {
+        self.shards.iter_mut().for_each(Things::touch);
+        for shard in 0..self.shards.len() {
+            for position in 0..self.shards[shard].things.len() {
+                let thing = self.shards[shard].things[position].clone();
+                if !kill(&thing) {
+                    continue;
+                }
+                // Kill the connections first, so each one is counted where it is kept
+                let connections = thing.inner.borrow().connections.clone();
+                for connection in connections
+                    .iter()
+                    .filter(|connection| connection.is_alive())
+                {
+                    // A connection kept by another container isn't counted here
+                    if let Some(keeper) = self.shard_of_seq(connection.seq()) {
+                        let keeper = &mut self.shards[keeper];
+                        keeper.dead_amount = keeper.dead_amount.saturating_add(1);
+                    }
+                    connection.kill();
+                }
+                thing.kill(&mut self.shards[shard].dead_amount);
+            }
+        }
+    }
+
+    /// Kills every connection matching `kill`, returning how many died.
+    pub fn kill_connections(&mut self, kill: impl Fn(&Connection<T, C>) -> bool) -> usize {
+        self.shards
+            .iter_mut()
+            .map(|shard| shard.kill_connections(&kill))
+            .sum()
+    }
+
+    /// Computes the dead percentage of the whole graph, like
+    /// `Things::dead_percentage`.
+    ///
+    /// # Returns
+    /// - `Ok(percentage)`: Dead items as a percentage of all items
+    /// - `Err(())`: If every shard is empty
+    #[allow(clippy::result_unit_err)]
+    pub fn dead_percentage(&self) -> Result<usize, ()> {
+        let (dead, total) = self
+            .shards
+            .iter()
+            .fold((0usize, 0usize), |(dead, total), shard| {
+                (
+                    dead.saturating_add(shard.dead_amount),
+                    total
+                        .saturating_add(shard.things.len())
+                        .saturating_add(shard.connections.len()),
+                )
+            });
+        if total == 0 {
+            return Err(());
+        }
+        Ok(dead.saturating_mul(100) / total)
+    }
+
+    /// Computes the dead percentage of one shard, to decide whether it is
+    /// worth cleaning.
+    ///
+    /// # Panics
+    /// If `shard` is out of range.
+    #[allow(clippy::result_unit_err)]
+    pub fn shard_dead_percentage(&self, shard: usize) -> Result<usize, ()> {
+        self.shards[shard].pressure().ok_or(())
+    }
+
+    /// Removes the dead things and connections of one shard, leaving the other
+    /// shards alone.
+    ///
+    /// # Panics
+    /// If `shard` is out of range.
+    pub fn clean_shard(&mut self, shard: usize) {
+        self.shards[shard].clean();
+    }
+
+    /// Removes the dead things and connections of every shard.
+    pub fn clean(&mut self) {
+        self.shards.iter_mut().for_each(Things::clean);
+    }
+}
+
+/// An immutable, query-optimized copy of a graph, created by `Things::compile`.
+///
+/// Things and connections are identified by their position, from 0 up to
+/// `thing_count` or `connection_count`. Adjacency follows the same rules as
+/// the traversals on `Things`: directed connections lead from source to
+/// target, and undirected connections lead both ways.
+///
+/// # Panics
+/// Methods taking a position panic if it is out of range.
+pub struct CompiledGraph<T, C, K = ()> {
+    things: Vec<T>,
+    connection_data: Vec<C>,
+    /// Both endpoints of every connection, source first for directed ones.
+    ends: Vec<[usize; 2]>,
+    directed: Vec<bool>,
+    /// `out_edges[out_offsets[i]..out_offsets[i + 1]]` holds the
+    /// `(connection, neighbor)` pairs leading out of thing `i`.
+    out_offsets: Vec<usize>,
+    out_edges: Vec<(usize, usize)>,
+    in_offsets: Vec<usize>,
+    in_edges: Vec<(usize, usize)>,
+    /// Keys paired with thing positions, sorted by key.
+    keys: Vec<(K, usize)>,
+}
+
+impl<T, C, K> CompiledGraph<T, C, K> {
+    /// Returns the number of things.
+    pub fn thing_count(&self) -> usize {
+        self.things.len()
+    }
+
+    /// Returns the number of connections.
+    pub fn connection_count(&self) -> usize {
+        self.connection_data.len()
+    }
+
+    /// Returns the data of a thing.
+    pub fn data(&self, thing: usize) -> &T {
+        &self.things[thing]
+    }
+
+    /// Returns the data of a connection.
+    pub fn connection_data(&self, connection: usize) -> &C {
+        &self.connection_data[connection]
+    }
+
+    /// Returns the two things a connection joins, source first if it is directed.
+    pub fn connection_ends(&self, connection: usize) -> [usize; 2] {
+        self.ends[connection]
+    }
+
+    /// Returns true if the connection is directed.
+    pub fn is_directed(&self, connection: usize) -> bool {
+        self.directed[connection]
+    }
+
+    /// Returns the `(connection, neighbor)` pairs leading out of a thing.
+    pub fn neighbors(&self, thing: usize) -> &[(usize, usize)] {
+        &self.out_edges[self.out_offsets[thing]..self.out_offsets[thing + 1]]
+    }
+
+    /// Returns the `(connection, neighbor)` pairs leading into a thing.
+    pub fn incoming(&self, thing: usize) -> &[(usize, usize)] {
+        &self.in_edges[self.in_offsets[thing]..self.in_offsets[thing + 1]]
+    }
+
+    /// Returns how many connections lead out of a thing.
+    pub fn out_degree(&self, thing: usize) -> usize {
+        self.out_offsets[thing + 1] - self.out_offsets[thing]
+    }
+
+    /// Returns how many connections lead into a thing.
+    pub fn in_degree(&self, thing: usize) -> usize {
+        self.in_offsets[thing + 1] - self.in_offsets[thing]
+    }
+
+    /// Lists the things reachable from `start` in breadth-first order,
+    /// starting with `start` itself.
+    pub fn bfs(&self, start: usize) -> Vec<usize> {
+        let mut visited = alloc::vec![false; self.things.len()];
+        let mut order = alloc::vec![start];
+        visited[start] = true;
+        let mut done = 0;
+        while done < order.len() {
+            let current = order[done];
+            done += 1;
+            for &(_, next) in self.neighbors(current) {
+                if !visited[next] {
+                    visited[next] = true;
+                    order.push(next);
+                }
+            }
+        }
+        order
+    }
+
+    /// Finds a path with the fewest hops from `from` to `to`, only crossing
+    /// connections whose data is accepted by `follow`.
+    ///
+    /// # Returns
+    /// - `Some(path)`: The things along the path, starting with `from` and ending with `to`
+    /// - `None`: If `to` can't be reached
+    pub fn shortest_path(
+        &self,
+        from: usize,
+        to: usize,
+        follow: impl Fn(&C) -> bool,
+    ) -> Option<Vec<usize>> {
+        let mut previous = alloc::vec![None; self.things.len()];
+        let mut queue = VecDeque::new();
+        previous[from] = Some(from);
+        queue.push_back(from);
+        while let Some(current) = queue.pop_front() {
+            if current == to {
+                let mut path = alloc::vec![to];
+                let mut at = to;
+                while at != from {
+                    at = previous[at]?;
+                    path.push(at);
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for &(connection, next) in self.neighbors(current) {
+                if previous[next].is_none() && follow(&self.connection_data[connection]) {
+                    previous[next] = Some(current);
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+
+    /// Turns the compiled graph back into an editable container, with things
+    /// and connections in the same order.
+    pub fn decompile(self) -> Things<T, C> {
+        let mut graph = Things::new();
+        let things: Vec<_> = self
+            .things
+            .into_iter()
+            .map(|data| graph.new_thing(data))
+            .collect();
+        for ((data, [first, second]), directed) in self
+            .connection_data
+            .into_iter()
+            .zip(self.ends)
+            .zip(self.directed)
+        {
+            let (first, second) = (things[first].clone(), things[second].clone());
+            if directed {
+                graph.new_directed_connection(first, data, second);
+            } else {
+                graph.new_undirected_connection([first, second], data);
+            }
+        }
+        graph
+    }
+}
+
+impl<T, C, K: Ord> CompiledGraph<T, C, K> {
+    /// Finds a thing by the key it was indexed under with `Things::compile_with_key`.
+    ///
+    /// If several things share the key, the first in container order is
+    /// returned. Always `None` if the graph was compiled without a key.
+    pub fn find_by_key(&self, key: &K) -> Option<usize> {
+        let at = self.keys.partition_point(|(indexed, _)| indexed < key);
+        self.keys
+            .get(at)
+            .filter(|(indexed, _)| indexed == key)
+            .map(|(_, thing)| *thing)
+    }
+}
+
+/// Flattens per-thing lists into offsets and one contiguous list.
+fn compressed_rows(rows: Vec<Vec<(usize, usize)>>) -> (Vec<usize>, Vec<(usize, usize)>) {
+    let mut offsets = Vec::with_capacity(rows.len() + 1);
+    let mut entries = Vec::new();
+    offsets.push(0);
+    for row in rows {
+        entries.extend(row);
+        offsets.push(entries.len());
+    }
+    (offsets, entries)
+}
+
+/// How `Things::top_k_central` scores things.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[non_exhaustive]
+pub enum Centrality {
+    /// The number of live connections, whichever way they point, as counted
+    /// by `Thing::connection_count`. A self-loop counts twice.
+    Degree,
+    /// PageRank with a damping factor of 0.85, following directed
+    /// connections from source to target and undirected ones both ways.
+    /// Scores sum to 1.
+    #[cfg(feature = "float")]
+    PageRank,
+    /// The scores of `Things::betweenness`.
+    #[cfg(feature = "float")]
+    Betweenness,
+}
+
+/// Whether a container allows parallel connections and self-loops.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub enum GraphMode {
+    /// At most one connection joins two things in each direction, and no
+    /// thing is connected to itself. New connections are checked on creation.
+    Simple,
+    /// Any number of connections may join two things, including self-loops.
+    #[default]
+    Multi,
+}
+
+/// Why a `Things::try_new_directed_connection` or
+/// `Things::try_new_undirected_connection` call was refused.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum ConnectError {
+    /// A live connection already joins the things this way in `GraphMode::Simple`.
+    ParallelEdge,
+    /// The connection would join a thing to itself, which `GraphMode::Simple`
+    /// or `ThingsConfig::reject_self_loops` forbids.
+    SelfLoop,
+    /// An endpoint is dead while `ThingsConfig::strict_liveness` is enabled.
+    DeadEndpoint,
+    /// The container already holds `ThingsConfig::max_connections` connections.
+    LimitReached,
+}
+
+/// The minimum, maximum, sum and count of some values, made by
+/// `Things::aggregate_things` and `Things::aggregate_connections`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub struct AggregateResult {
+    /// The smallest value, `None` if there were no values.
+    pub min: Option<u64>,
+    /// The largest value, `None` if there were no values.
+    pub max: Option<u64>,
+    /// The sum of the values, saturating at `u64::MAX`.
+    pub sum: u64,
+    /// The number of values.
+    pub count: usize,
+}
+
+impl AggregateResult {
+    fn add(&mut self, value: u64) {
+        self.min = Some(self.min.map_or(value, |min| min.min(value)));
+        self.max = Some(self.max.map_or(value, |max| max.max(value)));
+        self.sum = self.sum.saturating_add(value);
+        self.count += 1;
+    }
+}
+
+/// Identifies a connection within its container, see `Connection::id`.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct ConnectionId(u64);
+
+impl ConnectionId {
+    /// Rebuilds an id from the number returned by `as_u64`.
+    ///
+    /// Any number is accepted; one that no connection has simply resolves to
+    /// nothing in `Things::get_connection_by_id`.
+    pub fn from_raw(raw: u64) -> ConnectionId {
+        ConnectionId(raw)
+    }
+
+    /// Returns the id as a plain number, for storing it outside the graph.
+    pub fn as_u64(self) -> u64 {
+        self.0
+    }
+}
+
+/// Returned by `Things::execution_order` when the dependencies have a cycle.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct CycleError;
+
+/// Returned by `Things::try_new_thing` when the container already holds
+/// `ThingsConfig::max_things` things.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct LimitReached;
+
+/// What `Things::contract_connection_with` does with connections that become
+/// self-loops.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub enum LoopPolicy {
+    /// Kill them.
+    #[default]
+    Drop,
+    /// Keep them as self-loops on the surviving thing.
+    Keep,
+}
+
+/// What `Things::contract_connection_with` does with moved connections that
+/// run parallel to a connection the surviving thing already has.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub enum ParallelPolicy {
+    /// Merge them into the existing connection: it keeps its data, weight
+    /// and identity, and the moved connection is killed.
+    #[default]
+    Merge,
+    /// Keep them next to the existing connection.
+    Keep,
+}
+
+/// The kind of item reported by a dead access audit.
+#[cfg(feature = "dead-access-audit")]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum AuditedItem {
+    /// A dead thing, handed out by a thing query or a connection's endpoint getter.
+    Thing,
+    /// A dead connection, handed out by a connection query of a container or thing.
+    Connection,
+}
+
+/// A dead item handed out while `Things::enable_dead_access_audit` is active.
+#[cfg(feature = "dead-access-audit")]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct DeadAccessEvent {
+    /// Whether a thing or a connection was handed out.
+    pub item: AuditedItem,
+    /// The creation sequence number of the item, unique within its container.
+    pub id: u64,
+    /// The method that handed it out, such as `"Things::do_for_all_things"`.
+    pub operation: &'static str,
+}
+
+/// Where a cost based search such as `Things::cheapest_path` gets the cost
+/// of each connection.
+pub enum CostSource<'a, T, C> {
+    /// Use the weight stored with `Connection::set_weight`. Connections
+    /// without one have infinite cost and are never traversed.
+    Annotation,
+    /// Compute the cost of each connection with a closure.
+    Closure(&'a dyn Fn(&Connection<T, C>) -> u64),
+}
+
+impl<T, C> CostSource<'_, T, C> {
+    /// Returns the cost of crossing `connection`, or `None` if it can't be crossed.
+    fn cost_of(&self, connection: &Connection<T, C>) -> Option<u64> {
+        match self {
+            CostSource::Annotation => connection.weight(),
+            CostSource::Closure(cost) => Some(cost(connection)),
+        }
+    }
+}
+
+impl<T, C> Clone for CostSource<'_, T, C> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T, C> Copy for CostSource<'_, T, C> {}
+
+/// A thing and everything it contains, as built by `Things::forest`.
+pub struct TreeNode<T, C> {
+    /// The thing at this node.
+    pub thing: Thing<T, C>,
+    /// The nodes of the things it directly contains, in layout order.
+    pub children: Vec<TreeNode<T, C>>,
+}
+
+impl<T, C> TreeNode<T, C> {
+    /// Visits this node and everything below it, each parent before its
+    /// children and children in order, passing each thing with its depth
+    /// below this node.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut ui = Things::<&str, ()>::new();
+    /// let window = ui.new_thing("window");
+    /// let panel = ui.new_thing("panel");
+    /// let button = ui.new_thing("button");
+    /// ui.new_directed_connection(window, (), panel.clone());
+    /// ui.new_directed_connection(panel, (), button);
+    ///
+    /// let forest = ui.forest(|_| true).unwrap();
+    /// let mut outline = Vec::new();
+    /// forest[0].depth_first(|thing, depth| outline.push((thing.get_copy(), depth)));
+    /// assert_eq!(outline, [("window", 0), ("panel", 1), ("button", 2)]);
+    /// ```
+    pub fn depth_first(&self, mut f: impl FnMut(&Thing<T, C>, usize)) {
+        let mut stack = alloc::vec![(self, 0)];
+        while let Some((node, depth)) = stack.pop() {
+            f(&node.thing, depth);
+            for child in node.children.iter().rev() {
+                stack.push((child, depth + 1));
+            }
+        }
+    }
+}
+
+impl<T, C> Drop for TreeNode<T, C> {
+    fn drop(&mut self) {
+        // Flatten the tree first, so dropping a deep one doesn't recurse
+        let mut stack = core::mem::take(&mut self.children);
+        while let Some(mut node) = stack.pop() {
+            stack.append(&mut node.children);
+        }
+    }
+}
+
+/// Why `Things::forest` couldn't build a forest.
+pub enum ForestError<T, C> {
+    /// The thing contains itself, directly or through others.
+    Cycle(Thing<T, C>),
+    /// The thing is contained by more than one connection.
+    Diamond(Thing<T, C>),
+}
+
+impl<T, C> Clone for ForestError<T, C> {
+    fn clone(&self) -> Self {
+        match self {
+            ForestError::Cycle(thing) => ForestError::Cycle(thing.clone()),
+            ForestError::Diamond(thing) => ForestError::Diamond(thing.clone()),
+        }
+    }
+}
+
+impl<T: core::fmt::Debug, C> core::fmt::Debug for ForestError<T, C> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let (name, thing) = match self {
+            ForestError::Cycle(thing) => ("Cycle", thing),
+            ForestError::Diamond(thing) => ("Diamond", thing),
+        };
+        let inner = thing.inner.borrow();
+        f.debug_tuple(name).field(&inner.data).finish()
+    }
+}
+
+/// A non-fatal anomaly reported by `Things::structural_warnings`.
+pub enum StructuralWarning<T, C> {
+    /// A connection joins a thing to itself.
+    SelfLoop(Connection<T, C>),
+    /// `duplicate` repeats `original`: it joins the same things the same way
+    /// and holds equal data.
+    ParallelEdge {
+        original: Connection<T, C>,
+        duplicate: Connection<T, C>,
+    },
+    /// A thing has no live connections.
+    IsolatedThing(Thing<T, C>),
+}
+
+impl<T, C> Clone for StructuralWarning<T, C> {
+    fn clone(&self) -> Self {
+        match self {
+            StructuralWarning::SelfLoop(connection) => {
+                StructuralWarning::SelfLoop(connection.clone())
+            }
+            StructuralWarning::ParallelEdge {
+                original,
+                duplicate,
+            } => StructuralWarning::ParallelEdge {
+                original: original.clone(),
+                duplicate: duplicate.clone(),
+            },
+            StructuralWarning::IsolatedThing(thing) => {
+                StructuralWarning::IsolatedThing(thing.clone())
+            }
+        }
+    }
+}
+
+/// The outcome of a search that may run out of budget, from
+/// `Things::shortest_path_bounded` or `Things::cheapest_path_bounded`.
+pub enum BoundedSearchResult<T, C> {
+    /// The things along the path, from the start to the goal.
+    Found(Vec<Thing<T, C>>),
+    /// The budget ran out before the goal was reached.
+    Truncated {
+        /// The distance from the start to the closest thing left unexpanded,
+        /// which the goal is at least as far as.
+        best_frontier_distance: usize,
+        /// How many things were expanded, which is the whole budget.
+        expanded: usize,
+    },
+    /// The search ran out of things to expand without reaching the goal.
+    Unreachable,
+}
+
+impl<T, C> BoundedSearchResult<T, C> {
+    /// Puts the outcome of a budgeted search in its public form.
+    fn from_search(
+        from: &Thing<T, C>,
+        search: Budgeted<Vec<Connection<T, C>>>,
+    ) -> BoundedSearchResult<T, C> {
+        match search {
+            Ok(Some(connections)) => Things::things_along(from, connections)
+                .map_or(BoundedSearchResult::Unreachable, BoundedSearchResult::Found),
+            Ok(None) => BoundedSearchResult::Unreachable,
+            Err((best_frontier_distance, expanded)) => BoundedSearchResult::Truncated {
+                best_frontier_distance,
+                expanded,
+            },
+        }
+    }
+}
+
+impl<T, C> Clone for BoundedSearchResult<T, C> {
+    fn clone(&self) -> Self {
+        match self {
+            BoundedSearchResult::Found(path) => BoundedSearchResult::Found(path.clone()),
+            BoundedSearchResult::Truncated {
+                best_frontier_distance,
+                expanded,
+            } => BoundedSearchResult::Truncated {
+                best_frontier_distance: *best_frontier_distance,
+                expanded: *expanded,
+            },
+            BoundedSearchResult::Unreachable => BoundedSearchResult::Unreachable,
+        }
+    }
+}
+
+/// How much work a single `Things::maintain` call may do.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct MaintenanceBudget {
+    /// The most dead things and connections to remove in one call.
+    pub max_items: usize,
+}
+
+/// Limits above which `Things::health_with` recommends doing something.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct HealthThresholds {
+    /// The highest acceptable dead percentage. Defaults to 25.
+    pub max_dead_percentage: usize,
+    /// The most acceptable orphaned connections. Defaults to 0.
+    pub max_orphaned_connections: usize,
+    /// The most acceptable parallel connections. Defaults to 0.
+    pub max_parallel_connections: usize,
+}
+
+impl Default for HealthThresholds {
+    fn default() -> Self {
+        HealthThresholds {
+            max_dead_percentage: 25,
+            max_orphaned_connections: 0,
+            max_parallel_connections: 0,
+        }
+    }
+}
+
+/// What `Things::health` found.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct HealthReport {
+    /// The dead percentage, `None` for an empty graph.
+    pub dead_percentage: Option<usize>,
+    /// How many weakly connected components the live things form.
+    pub components: usize,
+    /// How many live connections have an endpoint outside the container.
+    pub orphaned_connections: usize,
+    /// How many live connections repeat an earlier one between the same things.
+    pub parallel_connections: usize,
+    /// What to do about the most pressing problem.
+    pub recommendation: Recommendation,
+}
+
+/// The action recommended by `Things::health`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub enum Recommendation {
+    /// Nothing is above its threshold.
+    #[default]
+    None,
+    /// Too much of the graph is dead, call `Things::clean`.
+    Clean,
+    /// There are too many parallel connections, see
+    /// `Things::merge_parallel_connections`.
+    Dedup,
+    /// Connections point outside the container, see `Things::prune_orphaned`.
+    RepairDangling,
+}
+
+/// What a `Things::maintain` call did.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct MaintenanceReport {
+    /// How many dead things and connections were removed.
+    pub removed: usize,
+    /// The dead percentage before the call, `None` for an empty graph.
+    pub pressure_before: Option<usize>,
+    /// The dead percentage after the call, `None` for an empty graph.
+    pub pressure_after: Option<usize>,
+    /// Whether dead items remain for a later call.
+    pub more_work: bool,
+}
+
+/// What a `Things::recount` call found.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct RecountReport {
+    /// The cached dead count before the call.
+    pub previous: usize,
+    /// How many dead things are waiting to be cleaned.
+    pub actual_dead_things: usize,
+    /// How many dead connections are waiting to be cleaned.
+    pub actual_dead_connections: usize,
+    /// Whether the cached count was wrong and has been replaced.
+    pub corrected: bool,
+}
+
+/// Options that change how a `Things` container behaves.
+///
+/// Start from `ThingsConfig::new()`, which matches the behavior of
+/// `Things::new()`, and enable options with the builder methods. Pass the
+/// result to `Things::with_config` or `Things::set_config`.
+///
+/// # Examples
+///
+/// ```rust
+/// # use connect_things::*;
+/// let config = ThingsConfig::<&str>::new()
+///     .auto_clean_at(25)
+///     .strict_liveness(true);
+///
+/// assert_eq!(config.get_auto_clean_at(), Some(25));
+/// assert!(config.get_strict_liveness());
+/// assert!(!config.get_reject_self_loops());
+/// ```
+pub struct ThingsConfig<T> {
+    auto_clean_at: Option<usize>,
+    strict_liveness: bool,
+    reject_self_loops: bool,
+    mode: GraphMode,
+    /// How to compare thing data, if new things must be unique.
+    same_data: Option<fn(&T, &T) -> bool>,
+    thing_capacity: usize,
+    connection_capacity: usize,
+    max_things: usize,
+    max_connections: usize,
+    connection_history_depth: usize,
+}
+
+impl<T> ThingsConfig<T> {
+    /// Creates a configuration with every option turned off.
+    pub fn new() -> Self {
+        ThingsConfig {
+            auto_clean_at: None,
+            strict_liveness: false,
+            reject_self_loops: false,
+            mode: GraphMode::Multi,
+            same_data: None,
+            thing_capacity: 0,
+            connection_capacity: 0,
+            max_things: usize::MAX,
+            max_connections: usize::MAX,
+            connection_history_depth: DEFAULT_HISTORY_DEPTH,
+        }
+    }
+
+    /// Cleans the container automatically once its dead percentage (see
+    /// `Things::dead_percentage`) is at `percent` or above.
+    ///
+    /// Killing never cleans by itself. The threshold is checked at the start
+    /// of the next call that adds things or connections: `new_thing`, the
+    /// `new_*_connection` methods and their `try_` forms, `modify` and
+    /// `split_connection`. Dead items stay in place until then, and handles
+    /// to them keep working either way.
+    pub fn auto_clean_at(mut self, percent: usize) -> Self {
+        self.auto_clean_at = Some(percent);
+        self
+    }
+
+    /// Refuses to create connections to dead things. Creating one panics.
+    pub fn strict_liveness(mut self, strict: bool) -> Self {
+        self.strict_liveness = strict;
+        self
+    }
+
+    /// Refuses to create connections from a thing to itself. Creating one panics.
+    pub fn reject_self_loops(mut self, reject: bool) -> Self {
+        self.reject_self_loops = reject;
+        self
+    }
+
+    /// Chooses whether parallel connections and self-loops are allowed.
+    pub fn mode(mut self, mode: GraphMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Reserves space for this many things up front.
+    pub fn thing_capacity(mut self, capacity: usize) -> Self {
+        self.thing_capacity = capacity;
+        self
+    }
+
+    /// Reserves space for this many connections up front.
+    pub fn connection_capacity(mut self, capacity: usize) -> Self {
+        self.connection_capacity = capacity;
+        self
+    }
+
+    /// Refuses to hold more than this many things, dead or alive, until cleaned.
+    pub fn max_things(mut self, limit: usize) -> Self {
+        self.max_things = limit;
+        self
+    }
+
+    /// Refuses to hold more than this many connections, dead or alive, until cleaned.
+    pub fn max_connections(mut self, limit: usize) -> Self {
+        self.max_connections = limit;
+        self
+    }
+
+    /// Keeps at most `depth` previous values per connection while the
+    /// container records connection history (see
+    /// `Things::record_connection_history`). Defaults to 8.
+    pub fn connection_history_depth(mut self, depth: usize) -> Self {
+        self.connection_history_depth = depth;
+        self
+    }
+
+    /// Returns the auto-clean threshold, if automatic cleaning is enabled.
+    pub fn get_auto_clean_at(&self) -> Option<usize> {
+        self.auto_clean_at
+    }
+
+    /// Returns true if connections to dead things are refused.
+    pub fn get_strict_liveness(&self) -> bool {
+        self.strict_liveness
+    }
+
+    /// Returns true if self-loops are refused.
+    pub fn get_reject_self_loops(&self) -> bool {
+        self.reject_self_loops
+    }
+
+    /// Returns whether the container is a simple graph or a multigraph.
+    pub fn get_mode(&self) -> GraphMode {
+        self.mode
+    }
+
+    /// Returns true if new things with equal data are merged into existing ones.
+    pub fn get_unique_things(&self) -> bool {
+        self.same_data.is_some()
+    }
+
+    /// Returns the number of things to reserve space for.
+    pub fn get_thing_capacity(&self) -> usize {
+        self.thing_capacity
+    }
+
+    /// Returns the number of connections to reserve space for.
+    pub fn get_connection_capacity(&self) -> usize {
+        self.connection_capacity
+    }
+
+    /// Returns the most things the container may hold, `usize::MAX` if unlimited.
+    pub fn get_max_things(&self) -> usize {
+        self.max_things
+    }
+
+    /// Returns the most connections the container may hold, `usize::MAX` if unlimited.
+    pub fn get_max_connections(&self) -> usize {
+        self.max_connections
+    }
+
+    /// Returns the most previous values kept per connection while recording history.
+    pub fn get_connection_history_depth(&self) -> usize {
+        self.connection_history_depth
+    }
+}
+
+impl<T: PartialEq> ThingsConfig<T> {
+    /// Keeps thing data unique: `Things::new_thing` returns the existing live
+    /// thing with equal data instead of creating another one.
+    ///
+    /// Thing data only needs `PartialEq`, so there is no index to look it up
+    /// in: every new thing is compared against all the things in the
+    /// container, and filling a container with `n` things takes `O(n²)`
+    /// comparisons. For large graphs with hashable or ordered data, keep a map
+    /// from key to `Thing` next to the container instead.
+    pub fn unique_things(mut self, unique: bool) -> Self {
+        self.same_data = if unique { Some(T::eq) } else { None };
+        self
+    }
+}
+
+impl<T> Clone for ThingsConfig<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for ThingsConfig<T> {}
+
+impl<T> Default for ThingsConfig<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Hooks called by `Things::visit_bfs` and `Things::visit_dfs` while they walk a graph.
+///
+/// Every hook has a default implementation, so a visitor only implements the
+/// ones it cares about. `CollectingVisitor` and `DistanceVisitor` are small
+/// ready-made examples.
+///
+/// # Examples
+///
+/// ```rust
+/// # use connect_things::*;
+/// /// Counts things without walking past closed doors.
+/// struct OpenRooms(usize);
+///
+/// impl GraphVisitor<&str, &str> for OpenRooms {
+///     fn discover_thing(&mut self, _thing: &Thing<&str, &str>, _depth: usize) {
+///         self.0 += 1;
+///     }
+///
+///     fn should_follow(&mut self, connection: &Connection<&str, &str>, _next: &Thing<&str, &str>) -> bool {
+///         connection != &"closed door"
+///     }
+/// }
+///
+/// let mut house = Things::new();
+/// let hall = house.new_thing("hall");
+/// let kitchen = house.new_thing("kitchen");
+/// let vault = house.new_thing("vault");
+/// house.new_undirected_connection([hall.clone(), kitchen], "open door");
+/// house.new_undirected_connection([hall.clone(), vault], "closed door");
+///
+/// let mut rooms = OpenRooms(0);
+/// house.visit_bfs(&hall, &mut rooms);
+/// assert_eq!(rooms.0, 2);
+/// ```
+pub trait GraphVisitor<T, C> {
+    /// Called once for every thing when it is first reached, with its depth
+    /// (the start thing has depth 0).
+    fn discover_thing(&mut self, _thing: &Thing<T, C>, _depth: usize) {}
+
+    /// Called for every live connection leading out of a thing being explored,
+    /// whether or not the thing on the other end has been seen already.
+    fn examine_connection(
+        &mut self,
+        _connection: &Connection<T, C>,
+        _from: &Thing<T, C>,
+        _to: &Thing<T, C>,
+    ) {
+    }
+
+    /// Called once for every discovered thing after all its connections have
+    /// been examined.
+    fn finish_thing(&mut self, _thing: &Thing<T, C>) {}
+
+    /// Decides whether the walk may discover `next` through `connection`.
+    /// Only asked about things that haven't been discovered yet. Returning
+    /// false prunes everything that is only reachable that way.
+    fn should_follow(&mut self, _connection: &Connection<T, C>, _next: &Thing<T, C>) -> bool {
+        true
+    }
+}
+
+/// A `GraphVisitor` that gathers every thing it discovers, in discovery order.
+pub struct CollectingVisitor<T, C> {
+    things: Vec<Thing<T, C>>,
+}
+
+impl<T, C> CollectingVisitor<T, C> {
+    /// Creates a visitor that hasn't collected anything yet.
+    pub fn new() -> Self {
+        CollectingVisitor { things: Vec::new() }
+    }
+
+    /// Returns the things discovered so far.
+    pub fn things(&self) -> &[Thing<T, C>] {
+        &self.things
+    }
+
+    /// Returns the discovered things, consuming the visitor.
+    pub fn into_things(self) -> Vec<Thing<T, C>> {
+        self.things
+    }
+}
+
+impl<T, C> Default for CollectingVisitor<T, C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, C> GraphVisitor<T, C> for CollectingVisitor<T, C> {
+    fn discover_thing(&mut self, thing: &Thing<T, C>, _depth: usize) {
+        self.things.push(thing.clone());
+    }
+}
+
+/// A `GraphVisitor` that records how many hops away each discovered thing is.
+///
+/// Use it with `Things::visit_bfs` to get shortest hop counts. With
+/// `Things::visit_dfs` it records depths in the depth-first tree instead.
+pub struct DistanceVisitor<T, C> {
+    distances: Vec<(Thing<T, C>, usize)>,
+}
+
+impl<T, C> DistanceVisitor<T, C> {
+    /// Creates a visitor that hasn't recorded anything yet.
+    pub fn new() -> Self {
+        DistanceVisitor {
+            distances: Vec::new(),
+        }
+    }
+
+    /// Returns every discovered thing with its distance, in discovery order.
+    pub fn distances(&self) -> &[(Thing<T, C>, usize)] {
+        &self.distances
+    }
+
+    /// Returns the recorded distance of `thing`, matched by identity, or
+    /// `None` if it wasn't discovered.
+    pub fn distance_to(&self, thing: &Thing<T, C>) -> Option<usize> {
+        self.distances
+            .iter()
+            .find(|(discovered, _)| discovered.ptr_eq(thing))
+            .map(|(_, distance)| *distance)
+    }
+}
+
+impl<T, C> Default for DistanceVisitor<T, C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, C> GraphVisitor<T, C> for DistanceVisitor<T, C> {
+    fn discover_thing(&mut self, thing: &Thing<T, C>, depth: usize) {
+        self.distances.push((thing.clone(), depth));
+    }
+}
+
+/// Lists, for every indexed thing, the positions of the things it can step to
+/// over connections accepted by `follow`.
+///
+/// # Returns
+/// The successor lists, and whether any followed connection was undirected.
+fn followed_successors<T, C>(
+    things: &[Thing<T, C>],
+    index: &BTreeMap<usize, usize>,
+    follow: impl Fn(&Connection<T, C>) -> bool,
+) -> (Vec<Vec<usize>>, bool) {
+    let mut undirected = false;
+    let successors = things
+        .iter()
+        .map(|thing| {
+            let mut next_things = Vec::new();
+            for (connection, next) in thing.forward_steps() {
+                if !follow(&connection) {
+                    continue;
+                }
+                undirected |= connection.directed_ends().is_none();
+                if let Some(&at) = index.get(&next.key()) {
+                    next_things.push(at);
+                }
+            }
+            next_things
+        })
+        .collect();
+    (successors, undirected)
+}
+
+/// Orders positions so every one comes before its successors, or returns
+/// `None` if the successor lists contain a cycle.
+fn topological_order(successors: &[Vec<usize>]) -> Option<Vec<usize>> {
+    let mut incoming = alloc::vec![0usize; successors.len()];
+    for &next in successors.iter().flatten() {
+        incoming[next] += 1;
+    }
+    let mut order: Vec<usize> = (0..successors.len())
+        .filter(|&at| incoming[at] == 0)
+        .collect();
+    let mut done = 0;
+    while done < order.len() {
+        let current = order[done];
+        done += 1;
+        for &next in &successors[current] {
+            incoming[next] -= 1;
+            if incoming[next] == 0 {
+                order.push(next);
+            }
+        }
+    }
+    (order.len() == successors.len()).then_some(order)
+}
+
+/// Numbers the strongly connected components of the positions, using an
+/// iterative version of Tarjan's algorithm. Components are numbered in the
+/// order of their lowest position.
+fn strongly_connected_components(successors: &[Vec<usize>]) -> Vec<usize> {
+    const UNSEEN: usize = usize::MAX;
+    let count = successors.len();
+    let mut order = alloc::vec![UNSEEN; count];
+    let mut low = alloc::vec![0; count];
+    let mut on_stack = alloc::vec![false; count];
+    let mut stack = Vec::new();
+    let mut found = alloc::vec![UNSEEN; count];
+    let mut next_order = 0;
+    let mut components = 0;
+
+    for root in 0..count {
+        if order[root] != UNSEEN {
+            continue;
+        }
+        let mut work = alloc::vec![(root, 0usize)];
+        while let Some((current, step)) = work.last_mut() {
+            let current = *current;
+            if order[current] == UNSEEN {
+                order[current] = next_order;
+                low[current] = next_order;
+                next_order += 1;
+                stack.push(current);
+                on_stack[current] = true;
+            }
+            if let Some(&next) = successors[current].get(*step) {
+                *step += 1;
+                if order[next] == UNSEEN {
+                    work.push((next, 0));
+                } else if on_stack[next] {
+                    low[current] = low[current].min(order[next]);
+                }
+                continue;
+            }
+
+            work.pop();
+            if let Some(&(parent, _)) = work.last() {
+                low[parent] = low[parent].min(low[current]);
+            }
+            if low[current] == order[current] {
+                while let Some(member) = stack.pop() {
+                    on_stack[member] = false;
+                    found[member] = components;
+                    if member == current {
+                        break;
+                    }
+                }
+                components += 1;
+            }
+        }
+    }
+
+    // Renumber so components come in the order of their lowest position
+    let mut renumbered = alloc::vec![UNSEEN; components];
+    let mut next = 0;
+    found
+        .into_iter()
+        .map(|component| {
+            if renumbered[component] == UNSEEN {
+                renumbered[component] = next;
+                next += 1;
+            }
+            renumbered[component]
+        })
+        .collect()
+}
+
+/// Checks whether a live connection already joins two things the way a new
+/// connection would: the same way round for directed connections, or in either
+/// order for undirected ones. Things are matched by identity.
+fn has_parallel_connection<T, C>(
+    first: &Thing<T, C>,
+    second: &Thing<T, C>,
+    directed: bool,
+) -> bool {
+    let inner = first.inner.borrow();
+    inner.connections.iter().any(|connection| {
+        if !connection.is_alive() {
+            return false;
+        }
+        if directed {
+            connection.goes_between(first, second)
+        } else {
+            connection.is_undirected()
+                && connection
+                    .inner
+                    .borrow()
+                    .other_end(first)
+                    .is_some_and(|other| other.ptr_eq(second))
+        }
+    })
+}
+
+/// Picks the step whose share of the running weight total contains the roll.
+fn pick_weighted<T, C>(
+    steps: Vec<(Connection<T, C>, Thing<T, C>)>,
+    weight: impl Fn(&Connection<T, C>) -> u64,
+    roll: impl FnOnce(u64) -> u64,
+) -> Option<(Connection<T, C>, Thing<T, C>)> {
+    let weights: Vec<u64> = steps
+        .iter()
+        .map(|(connection, _)| weight(connection))
+        .collect();
+    let total = weights
+        .iter()
+        .fold(0u64, |total, weight| total.saturating_add(*weight));
+    if total == 0 {
+        return None;
+    }
+
+    let picked = share_holding(&weights, roll(total) % total)?;
+    steps.into_iter().nth(picked)
+}
+
+/// Finds the position of the weight whose share of the running total holds
+/// `rolled`.
+fn share_holding(weights: &[u64], rolled: u64) -> Option<usize> {
+    let mut running = 0u64;
+    weights.iter().position(|weight| {
+        running = running.saturating_add(*weight);
+        rolled < running
+    })
+}
+
+/// Draws a value in `0..bound` from `rng`, evenly spread.
+///
+/// Draws past the last whole multiple of `bound` would make the low values
+/// more likely, so they are thrown away.
+fn roll_below(rng: &mut impl FnMut() -> u64, bound: u64) -> u64 {
+    let limit = u64::MAX - u64::MAX % bound;
+    loop {
+        let rolled = rng();
+        if rolled < limit {
+            return rolled % bound;
+        }
+    }
+}
+
+/// Connection data with a counterpart that describes the same relationship
+/// from the other side, see `Things::reverse_connection_relabeled`.
+pub trait Reversible {
+    /// Returns the label for the connection pointing the other way.
+    fn reverse(&self) -> Self;
+}
+
+/// Picks up to `limit` items whose sequence number is past `after`, lowest
+/// first, keeping no more than `limit` candidates around while scanning.
+/// Items without a sequence number are skipped.
+fn page_after<I: Clone>(
+    items: &[I],
+    after: u64,
+    limit: usize,
+    seq: impl Fn(&I) -> Option<u64>,
+) -> (Vec<I>, Option<u64>) {
+    use alloc::collections::BinaryHeap;
+
+    // An empty page would hand back the same cursor forever
+    if limit == 0 {
+        return (Vec::new(), None);
+    }
+    let mut page: BinaryHeap<(u64, usize)> = BinaryHeap::new();
+    let mut more = false;
+    for (position, item) in items.iter().enumerate() {
+        let Some(item_seq) = seq(item).filter(|item_seq| *item_seq > after) else {
+            continue;
+        };
+        page.push((item_seq, position));
+        if page.len() > limit {
+            page.pop();
+            more = true;
+        }
+    }
+
+    let page = page.into_sorted_vec();
+    let cursor = match page.last() {
+        Some(&(last, _)) if more => Some(last),
+        None if more => Some(after),
+        _ => None,
+    };
+    let page = page
+        .into_iter()
+        .map(|(_, position)| items[position].clone())
+        .collect();
+    (page, cursor)
+}
+
+/// Clones the items into a list stably sorted by `key`, computing each key once.
+fn sort_by_extracted_key<'a, I: Clone + 'a, K: Ord>(
+    items: impl Iterator<Item = &'a I>,
+    key: impl Fn(&I) -> K,
+) -> Vec<I> {
+    let mut keyed: Vec<(K, I)> = items.map(|item| (key(item), item.clone())).collect();
+    keyed.sort_by(|a, b| a.0.cmp(&b.0));
+    keyed.into_iter().map(|(_, item)| item).collect()
+}
+
+/// What `Things::import_edges` does with a row that duplicates an existing connection.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum DuplicatePolicy {
+    /// Leave the existing connection alone and record the row as skipped.
+    Skip,
+    /// Create another connection in parallel to the existing one.
+    CreateParallel,
+    /// Abort the whole import.
+    Error,
+}
+
+/// The outcome of a successful `Things::import_edges` call.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ImportReport {
+    /// The number of connections created.
+    pub created: usize,
+    /// The indices of rows skipped because they duplicated an existing connection.
+    pub skipped_duplicates: Vec<usize>,
+}
+
+/// Why `Things::import_edges` rolled back an import.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub enum ImportAbort {
+    /// The row referenced a key that could not be resolved to a thing.
+    UnknownKey { row: usize, key: String },
+    /// The row duplicated an existing connection under `DuplicatePolicy::Error`.
+    Duplicate { row: usize },
+    /// The row would have broken a rule of the container's configuration.
+    Connect { row: usize, error: ConnectError },
+    /// The row would have gone past `ThingsConfig::max_connections`.
+    Limit { row: usize, error: LimitReached },
+}
+
+/// Things in dependency order, handed out as their dependencies finish,
+/// made by `Things::execution_order`.
+///
+/// `next_ready` hands out each thing whose dependencies are all done, and
+/// `mark_done` reports a handed out thing as finished, which may make the
+/// things depending on it ready. This lets a scheduler run independent things
+/// in parallel. The plan works on the graph as it was when it was made.
+pub struct ExecutionPlan<T, C> {
+    things: Vec<Thing<T, C>>,
+    index: BTreeMap<usize, usize>,
+    successors: Vec<Vec<usize>>,
+    /// How many dependencies of each thing are not done yet.
+    waiting_on: Vec<usize>,
+    ready: VecDeque<usize>,
+    states: Vec<PlanState>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum PlanState {
+    Waiting,
+    HandedOut,
+    Done,
+}
+
+impl<T, C> ExecutionPlan<T, C> {
+    /// Hands out the next thing whose dependencies are all done.
+    ///
+    /// Things become ready in container order at first, then in the order
+    /// their last dependency is marked done.
+    ///
+    /// # Returns
+    /// - `Some(thing)`: A thing that can run now
+    /// - `None`: If nothing is ready until more things are marked done, or
+    ///   everything was handed out
+    pub fn next_ready(&mut self) -> Option<Thing<T, C>> {
+        let next = self.ready.pop_front()?;
+        self.states[next] = PlanState::HandedOut;
+        Some(self.things[next].clone())
+    }
+
+    /// Reports a thing handed out by `next_ready` as finished.
+    ///
+    /// # Returns
+    /// - `Ok(())`: If the thing was handed out and not yet marked done
+    /// - `Err(())`: Otherwise, including for things outside the plan, with
+    ///   nothing changed
+    #[allow(clippy::result_unit_err)]
+    pub fn mark_done(&mut self, thing: &Thing<T, C>) -> Result<(), ()> {
+        let at = *self.index.get(&thing.key()).ok_or(())?;
+        if self.states[at] != PlanState::HandedOut {
+            return Err(());
+        }
+        self.states[at] = PlanState::Done;
+        for &next in &self.successors[at] {
+            self.waiting_on[next] -= 1;
+            if self.waiting_on[next] == 0 {
+                self.ready.push_back(next);
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns true once every thing in the plan is marked done.
+    pub fn is_finished(&self) -> bool {
+        self.states.iter().all(|state| *state == PlanState::Done)
+    }
+}
+
+/// Why `Things::from_adjacency` couldn't build a graph.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub enum AdjacencyError<K> {
+    /// The adjacency list has entries under a key that isn't a node.
+    UnknownSource(K),
+    /// An entry under `from` points to a key that isn't a node.
+    UnknownTarget { from: K, to: K },
+}
+
+/// An item removed by `Things::clean_with_progress`.
+pub enum CleanedItem<T, C> {
+    /// A dead thing.
+    Thing(Thing<T, C>),
+    /// A dead connection.
+    Connection(Connection<T, C>),
+}
+
+/// Options for `Things::to_record`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub struct SerializeOptions {
+    /// Whether to keep things and connections that are killed but not yet cleaned.
+    pub include_dead: bool,
+}
+
+/// A graph as plain data, made by `Things::to_record` and read back by
+/// `Things::from_record`.
+///
+/// Connections refer to things by their position in `things`, which makes
+/// the record straightforward to write out in any format.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct GraphRecord<T, C> {
+    /// The things, in container order.
+    pub things: Vec<ThingRecord<T>>,
+    /// The connections, in container order.
+    pub connections: Vec<ConnectionRecord<C>>,
+    /// The number of dead items the container was counting.
+    pub dead_amount: usize,
+}
+
+/// A thing in a `GraphRecord`.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct ThingRecord<T> {
+    /// The data of the thing.
+    pub data: T,
+    /// Whether the thing was alive.
+    pub alive: bool,
+}
+
+/// A connection in a `GraphRecord`.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct ConnectionRecord<C> {
+    /// The positions of the endpoints in `GraphRecord::things`, source first
+    /// for directed connections.
+    pub ends: [usize; 2],
+    /// Whether the connection is directed.
+    pub directed: bool,
+    /// The data of the connection.
+    pub data: C,
+    /// The weight set with `Connection::set_weight`, if any.
+    pub weight: Option<u64>,
+    /// Whether the connection was alive.
+    pub alive: bool,
+    /// The id of the connection, see `Connection::id`.
+    pub id: ConnectionId,
+}
+
+/// An item reported by `Things::export_with_labels`.
+///
+/// The label is only borrowed for the duration of the callback.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum ExportItem<'a> {
+    /// A live thing.
+    Thing {
+        /// The position of the thing among the exported things.
+        index: usize,
+        /// The label of the thing.
+        label: &'a str,
+    },
+    /// A live connection.
+    Connection {
+        /// The id of the connection, see `Connection::id`.
+        id: ConnectionId,
+        /// The positions of the endpoints among the exported things, source
+        /// first for directed connections.
+        ends: [usize; 2],
+        /// Whether the connection is directed.
+        directed: bool,
+        /// The label of the connection.
+        label: &'a str,
+    },
+}
+
+/// Values extracted from the things of a graph, made by
+/// `Things::with_thing_data_table`.
+///
+/// Values are found by the things' sequence numbers, so lookups should use
+/// handles from the graph the table was made from.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct DataTable<R> {
+    entries: BTreeMap<u64, R>,
+    generation: u64,
+}
+
+impl<R> DataTable<R> {
+    /// Returns the value extracted from `thing`, if it was alive when the table was made.
+    pub fn get<T, C>(&self, thing: &Thing<T, C>) -> Option<&R> {
+        self.entries.get(&thing.seq())
+    }
+
+    /// Returns the number of things in the table.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if the table holds no values.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns true if `graph` changed structure since the table was made.
+    ///
+    /// Only structural changes are noticed, such as creating, killing or
+    /// cleaning things. Changes made to data through `Thing::access_mut`
+    /// aren't, as the container doesn't see them.
+    pub fn is_stale<T, C>(&self, graph: &Things<T, C>) -> bool {
+        self.generation != graph.generation
+    }
+}
+
+/// Queues changes to a graph while it is being read, see `Things::modify`.
+pub struct GraphEditor<'a, T, C> {
+    graph: &'a Things<T, C>,
+    new_things: Vec<Thing<T, C>>,
+    new_connections: Vec<Connection<T, C>>,
+    killed_things: Vec<Thing<T, C>>,
+    killed_connections: Vec<Connection<T, C>>,
+}
+
+impl<'a, T, C> GraphEditor<'a, T, C> {
+    /// The graph as it was before any of the queued changes.
+    pub fn graph(&self) -> &'a Things<T, C> {
+        self.graph
+    }
+
+    /// Queues a new thing, returning its handle right away.
+    ///
+    /// The thing isn't part of `graph()` until the changes are applied.
+    pub fn new_thing(&mut self, data: T) -> Thing<T, C> {
+        let thing = Thing::new(data);
+        self.new_things.push(thing.clone());
+        thing
+    }
+
+    /// Queues a new directed connection, returning its handle right away.
+    pub fn new_directed_connection(
+        &mut self,
+        from: Thing<T, C>,
+        data: C,
+        to: Thing<T, C>,
+    ) -> Connection<T, C> {
+        let connection = Connection::new_directed(from, data, to);
+        self.new_connections.push(connection.clone());
+        connection
+    }
+
+    /// Queues a new undirected connection, returning its handle right away.
+    pub fn new_undirected_connection(
+        &mut self,
+        things: [Thing<T, C>; 2],
+        data: C,
+    ) -> Connection<T, C> {
+        let connection = Connection::new_undirected(things, data);
+        self.new_connections.push(connection.clone());
+        connection
+    }
+
+    /// Queues a thing to be killed, along with its connections.
+    pub fn kill_thing(&mut self, thing: &Thing<T, C>) {
+        self.killed_things.push(thing.clone());
+    }
+
+    /// Queues a connection to be killed.
+    pub fn kill_connection(&mut self, connection: &Connection<T, C>) {
+        self.killed_connections.push(connection.clone());
+    }
+}
+
+/// A borrowed graph that treats every connection as undirected, see
+/// `Things::undirected_view`.
+///
+/// Dead things and connections are skipped throughout.
+pub struct UndirectedView<'a, T, C> {
+    graph: &'a Things<T, C>,
+}
+
+impl<T, C> Clone for UndirectedView<'_, T, C> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T, C> Copy for UndirectedView<'_, T, C> {}
+
+impl<'a, T, C> UndirectedView<'a, T, C> {
+    /// The graph this view borrows.
+    pub fn graph(&self) -> &'a Things<T, C> {
+        self.graph
+    }
+
+    /// Lists the things joined to `thing` by a live connection in either direction.
+    ///
+    /// Each neighbor is listed once, in the order of the first connection
+    /// leading to it.
+    pub fn neighbors(&self, thing: &Thing<T, C>) -> Vec<Thing<T, C>> {
+        thing.neighbors_alive()
+    }
+
+    /// Counts the live connections of `thing`, whichever way they point.
+    ///
+    /// Parallel connections count separately and a self-loop counts twice,
+    /// as is usual for undirected degrees.
+    pub fn degree(&self, thing: &Thing<T, C>) -> usize {
+        thing.connection_count()
+    }
+
+    /// Lists the things reachable from `start` in breadth-first order,
+    /// starting with `start` itself.
+    ///
+    /// Empty if `start` is dead.
+    pub fn bfs(&self, start: &Thing<T, C>) -> Vec<Thing<T, C>> {
+        let mut order = Vec::new();
+        if !start.is_alive() {
+            return order;
+        }
+
+        let mut visited = BTreeSet::new();
+        let mut queue = VecDeque::new();
+        visited.insert(start.key());
+        queue.push_back(start.clone());
+        while let Some(current) = queue.pop_front() {
+            for next in current.neighbors_alive() {
+                if visited.insert(next.key()) {
+                    queue.push_back(next);
+                }
+            }
+            order.push(current);
+        }
+        order
+    }
+}
+
+/// One side of a thing, see `Thing::outgoing_view` and `Thing::incoming_view`.
+///
+/// Dead things and connections are skipped throughout, and a self-loop is
+/// listed once.
+pub struct DirectedView<T, C> {
+    thing: Thing<T, C>,
+    direction: Direction,
+    include_undirected: bool,
+}
+
+impl<T, C> Clone for DirectedView<T, C> {
+    fn clone(&self) -> Self {
+        DirectedView {
+            thing: self.thing.clone(),
+            direction: self.direction,
+            include_undirected: self.include_undirected,
+        }
+    }
+}
+
+impl<T, C> DirectedView<T, C> {
+    fn steps(&self) -> Vec<(Connection<T, C>, Thing<T, C>)> {
+        self.thing
+            .directed_neighbors(self.direction, self.include_undirected)
+    }
+
+    /// Returns which side of the thing the view explores: `AwayFrom` for the
+    /// outgoing side, `Towards` for the incoming one.
+    pub fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    /// Lists the connections on this side, in the order they were attached.
+    pub fn connections(&self) -> Vec<Connection<T, C>> {
+        self.steps()
+            .into_iter()
+            .map(|(connection, _)| connection)
+            .collect()
+    }
+
+    /// Lists the things at the other end of the connections on this side,
+    /// each once, in the order of the first connection joining them.
+    pub fn neighbors(&self) -> Vec<Thing<T, C>> {
+        let mut seen = BTreeSet::new();
+        self.steps()
+            .into_iter()
+            .filter_map(|(_, next)| seen.insert(next.key()).then_some(next))
+            .collect()
+    }
+
+    /// Folds over the connections on this side and the things at their other end.
+    pub fn fold<B>(
+        &self,
+        init: B,
+        mut f: impl FnMut(B, &Connection<T, C>, &Thing<T, C>) -> B,
+    ) -> B {
+        self.steps()
+            .iter()
+            .fold(init, |acc, (connection, next)| f(acc, connection, next))
+    }
+
+    /// Counts the connections on this side.
+    pub fn count(&self) -> usize {
+        self.steps().len()
+    }
+
+    /// Finds the first connection on this side accepted by `pred`, with the
+    /// thing at its other end.
+    pub fn find(
+        &self,
+        pred: impl Fn(&Connection<T, C>, &Thing<T, C>) -> bool,
+    ) -> Option<(Connection<T, C>, Thing<T, C>)> {
+        self.steps()
+            .into_iter()
+            .find(|(connection, next)| pred(connection, next))
+    }
+}
+
+/// The changes between two graphs, as reported by `Things::diff`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct GraphDiff<T, C> {
+    /// Data of the things only present in the newer graph.
+    pub added_things: Vec<T>,
+    /// Data of the things only present in the older graph.
+    pub removed_things: Vec<T>,
+    /// Connections only present in the newer graph.
+    pub added_connections: Vec<DiffConnection<T, C>>,
+    /// Connections only present in the older graph.
+    pub removed_connections: Vec<DiffConnection<T, C>>,
+}
+
+impl<T, C> GraphDiff<T, C> {
+    /// Returns true if the two graphs held the same payloads.
+    pub fn is_empty(&self) -> bool {
+        self.added_things.is_empty()
+            && self.removed_things.is_empty()
+            && self.added_connections.is_empty()
+            && self.removed_connections.is_empty()
+    }
+}
+
+/// A connection described by its payloads, as listed in a `GraphDiff`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct DiffConnection<T, C> {
+    /// The data of the connected things, source first if directed.
+    pub ends: [T; 2],
+    /// The data of the connection.
+    pub data: C,
+    /// Whether the connection is directed.
+    pub directed: bool,
+}
+
+/// Returns the items of `items` left over after pairing each one with a
+/// distinct `same` item of `against`.
+fn unmatched<X: Clone>(items: &[X], against: &[X], same: impl Fn(&X, &X) -> bool) -> Vec<X> {
+    let mut used = alloc::vec![false; against.len()];
+    items
+        .iter()
+        .filter(|item| {
+            let partner = against
+                .iter()
+                .enumerate()
+                .position(|(position, other)| !used[position] && same(item, other));
+            match partner {
+                Some(position) => {
+                    used[position] = true;
+                    false
+                }
+                None => true,
+            }
+        })
+        .cloned()
+        .collect()
+}
+
+#[cfg(feature = "float")]
+impl<T, C> Things<T, C> {
+    /// Computes the betweenness centrality of every live thing.
+    ///
+    /// A thing's betweenness is the number of shortest paths between other
+    /// things that pass through it, split evenly when several shortest paths
+    /// exist. Things with a high score act as brokers between parts of the graph.
+    ///
+    /// Paths follow directed connections from source to target and undirected
+    /// connections both ways, and every ordered pair of things is counted, so a
+    /// path across undirected connections contributes in both directions.
+    ///
+    /// # Returns
+    /// Every live thing paired with its score, in container order.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut graph = Things::<&str, ()>::new();
+    ///
+    /// let left = graph.new_thing("left");
+    /// let middle = graph.new_thing("middle");
+    /// let right = graph.new_thing("right");
+    /// graph.new_undirected_connection([left, middle.clone()], ());
+    /// graph.new_undirected_connection([middle, right], ());
+    ///
+    /// let scores = graph.betweenness();
+    /// assert_eq!(scores[1].1, 2.0);
+    /// ```
+    pub fn betweenness(&self) -> Vec<(Thing<T, C>, f64)> {
+        self.betweenness_weighted(|_| 1)
+    }
+
+    /// Computes betweenness centrality using weighted shortest paths.
+    ///
+    /// Works like [`Things::betweenness`], except that the length of a path is
+    /// the sum of `weight` over its connections instead of its number of hops,
+    /// so shortest paths are found with Dijkstra's algorithm. Paths with equal
+    /// total cost share the credit evenly.
+    ///
+    /// Weights should be positive; zero weight connections can make the path
+    /// counts unreliable. Self-loops never lie on a shortest path and are ignored.
+    ///
+    /// # Returns
+    /// Every live thing paired with its score, in container order.
+    pub fn betweenness_weighted(&self, weight: impl Fn(&C) -> u64) -> Vec<(Thing<T, C>, f64)> {
+        use alloc::collections::BinaryHeap;
+        use core::cmp::Reverse;
+
+        let (things, index) = self.index_things();
+        let count = things.len();
+
+        let adjacency: Vec<Vec<(usize, u64)>> = things
+            .iter()
+            .enumerate()
+            .map(|(position, thing)| {
+                thing
+                    .forward_steps()
+                    .iter()
+                    .filter_map(|(connection, next)| {
+                        let next = *index.get(&next.key())?;
+                        if next == position {
+                            return None;
+                        }
+                        Some((next, connection.access(|data| weight(data))))
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let mut scores = alloc::vec![0.0; count];
+
+        for source in 0..count {
+            let mut distance: Vec<Option<u64>> = alloc::vec![None; count];
+            let mut paths = alloc::vec![0.0f64; count];
+            let mut predecessors: Vec<Vec<usize>> = alloc::vec![Vec::new(); count];
+            let mut settled = alloc::vec![false; count];
+            let mut order = Vec::new();
+            let mut heap = BinaryHeap::new();
+
+            distance[source] = Some(0);
+            paths[source] = 1.0;
+            heap.push(Reverse((0u64, source)));
+
+            while let Some(Reverse((cost, current))) = heap.pop() {
+                if settled[current] {
+                    continue;
+                }
+                settled[current] = true;
+                order.push(current);
+
+                for &(next, step) in &adjacency[current] {
+                    let candidate = cost.saturating_add(step);
+                    match distance[next] {
+                        Some(known) if candidate > known => {}
+                        Some(known) if candidate == known => {
+                            paths[next] += paths[current];
+                            predecessors[next].push(current);
+                        }
+                        _ => {
+                            distance[next] = Some(candidate);
+                            paths[next] = paths[current];
+                            predecessors[next].clear();
+                            predecessors[next].push(current);
+                            heap.push(Reverse((candidate, next)));
+                        }
+                    }
+                }
+            }
+
+            // Accumulate dependencies from the furthest things back towards the source
+            let mut dependency = alloc::vec![0.0f64; count];
+            while let Some(current) = order.pop() {
+                for &previous in &predecessors[current] {
+                    dependency[previous] +=
+                        paths[previous] / paths[current] * (1.0 + dependency[current]);
+                }
+                if current != source {
+                    scores[current] += dependency[current];
+                }
+            }
+        }
+
+        things.into_iter().zip(scores).collect()
+    }
+
+    /// Scores every live thing with PageRank, as described on `Centrality::PageRank`.
+    fn page_rank_scores(&self) -> Vec<(Thing<T, C>, f64)> {
+        const DAMPING: f64 = 0.85;
+        const MAX_ROUNDS: usize = 100;
+        const TOLERANCE: f64 = 1e-10;
+
+        let (things, index) = self.index_things();
+        let count = things.len();
+        if count == 0 {
+            return Vec::new();
+        }
+        let successors: Vec<Vec<usize>> = things
+            .iter()
+            .map(|thing| {
+                thing
+                    .forward_steps()
+                    .iter()
+                    .filter_map(|(_, next)| index.get(&next.key()).copied())
+                    .collect()
+            })
+            .collect();
+
+        let share = 1.0 / count as f64;
+        let mut ranks = alloc::vec![share; count];
+        for _ in 0..MAX_ROUNDS {
+            // Things without successors spread their rank over everything
+            let dangling: f64 = (0..count)
+                .filter(|&at| successors[at].is_empty())
+                .map(|at| ranks[at])
+                .sum();
+            let base = (1.0 - DAMPING) * share + DAMPING * dangling * share;
+            let mut next = alloc::vec![base; count];
+            for (at, targets) in successors.iter().enumerate() {
+                let part = DAMPING * ranks[at] / targets.len().max(1) as f64;
+                for &target in targets {
+                    next[target] += part;
+                }
+            }
+            let change: f64 = ranks
+                .iter()
+                .zip(&next)
+                .map(|(old, new)| if old > new { old - new } else { new - old })
+                .sum();
+            ranks = next;
+            if change < TOLERANCE {
+                break;
+            }
+        }
+        things.into_iter().zip(ranks).collect()
+    }
+
+    /// Computes the average number of connections per live thing.
+    ///
+    /// Every live connection adds to the degree of both of its ends, so this is
+    /// `2 * connections / things`. Returns 0 for a graph without live things.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut graph = Things::<&str, ()>::new();
+    /// let hub = graph.new_thing("hub");
+    /// let a = graph.new_thing("a");
+    /// let b = graph.new_thing("b");
+    /// let c = graph.new_thing("c");
+    /// graph.new_undirected_connection([hub.clone(), a], ());
+    /// graph.new_undirected_connection([hub.clone(), b], ());
+    /// graph.new_directed_connection(hub, (), c);
+    ///
+    /// assert_eq!(graph.average_degree(), 1.5);
+    /// ```
+    pub fn average_degree(&self) -> f64 {
+        let things = self.things.iter().filter(|thing| thing.is_alive()).count();
+        if things == 0 {
+            return 0.0;
+        }
+        let connections = self
+            .connections
+            .iter()
+            .filter(|connection| connection.is_alive())
+            .count();
+        2.0 * connections as f64 / things as f64
+    }
+
+    /// Rescales the weights of all live connections so they sum to 1.0.
+    ///
+    /// This is a common preprocessing step before random walks or PageRank.
+    /// Each weight is read with `extract` and the normalized value is written
+    /// back with `store`.
+    ///
+    /// If the weights sum to zero they are left unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut graph = Things::<&str, f64>::new();
+    /// let a = graph.new_thing("a");
+    /// let b = graph.new_thing("b");
+    /// let heavy = graph.new_directed_connection(a.clone(), 3.0, b.clone());
+    /// let light = graph.new_directed_connection(b, 1.0, a);
+    ///
+    /// graph.normalize_weights(|weight| *weight, |weight, normalized| *weight = normalized);
+    /// assert!(heavy == 0.75 && light == 0.25);
+    /// ```
+    pub fn normalize_weights(&mut self, extract: impl Fn(&C) -> f64, store: impl Fn(&mut C, f64)) {
+        self.normalize_weights_to(1.0, extract, store);
+    }
+
+    /// Rescales the weights of all live connections so they sum to `total`.
+    ///
+    /// Works like [`Things::normalize_weights`] with a configurable total.
+    /// If the weights sum to zero they are left unchanged.
+    pub fn normalize_weights_to(
+        &mut self,
+        total: f64,
+        extract: impl Fn(&C) -> f64,
+        store: impl Fn(&mut C, f64),
+    ) {
+        let live = || {
+            self.connections
+                .iter()
+                .filter(|connection| connection.is_alive())
+        };
+        let sum: f64 = live().map(|connection| connection.access(&extract)).sum();
+        if sum == 0.0 {
+            return;
+        }
+        for connection in live() {
+            let mut inner = connection.inner.borrow_mut();
+            let data = inner.get_data_mut();
+            let normalized = extract(data) / sum * total;
+            store(data, normalized);
+        }
+    }
+}
+
+/// Consistency checks for tests and fuzzers that drive a graph.
+///
+/// Available with the `test-util` feature.
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_util {
+    use super::*;
+    use alloc::format;
+
+    /// Checks that the bookkeeping of `graph` is internally consistent.
+    ///
+    /// The checks are:
+    /// - No thing or connection is registered with the container twice
+    /// - Every endpoint of a container connection is a container thing
+    /// - Every endpoint lists the connection, once per end it occupies
+    /// - Every connection listed by a thing is a container connection with
+    ///   that thing as an endpoint
+    /// - The dead count equals the number of dead things and connections
+    ///
+    /// # Panics
+    /// On the first failed check, naming the check and the item by its
+    /// sequence number.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let mut graph = Things::<&str, ()>::new();
+    /// let a = graph.new_thing("a");
+    /// let b = graph.new_thing("b");
+    /// graph.new_undirected_connection([a, b], ());
+    /// graph.kill_things(|name| *name == "a");
+    ///
+    /// test_util::assert_graph_sound(&graph);
+    /// ```
+    pub fn assert_graph_sound<T, C>(graph: &Things<T, C>) {
+        let mut things = BTreeSet::new();
+        for thing in &graph.things {
+            if !things.insert(thing.key()) {
+                unsound(
+                    "unique things",
+                    format!("thing #{} is registered twice", thing.seq()),
+                );
+            }
+        }
+        let mut connections = BTreeSet::new();
+        for connection in &graph.connections {
+            if !connections.insert(connection.key()) {
+                unsound(
+                    "unique connections",
+                    format!("connection #{} is registered twice", connection.seq()),
+                );
+            }
+        }
+
+        for connection in &graph.connections {
+            let ends = connection.ends();
+            for end in &ends {
+                if !things.contains(&end.key()) {
+                    unsound(
+                        "registered endpoints",
+                        format!(
+                            "connection #{} has endpoint #{}, which isn't in the container",
+                            connection.seq(),
+                            end.seq()
+                        ),
+                    );
+                }
+                let expected = ends.iter().filter(|other| other.ptr_eq(end)).count();
+                let listed = end.with_connections(|listed| {
+                    listed
+                        .iter()
+                        .filter(|other| other.ptr_eq(connection))
+                        .count()
+                });
+                if listed != expected {
+                    unsound(
+                        "endpoint lists",
+                        format!(
+                            "connection #{} is listed {listed} times by endpoint #{}, expected {expected}",
+                            connection.seq(),
+                            end.seq()
+                        ),
+                    );
+                }
+            }
+        }
+
+        for thing in &graph.things {
+            thing.with_connections(|listed| {
+                for connection in listed {
+                    if !connections.contains(&connection.key()) {
+                        unsound(
+                            "registered connections",
+                            format!(
+                                "thing #{} lists connection #{}, which isn't in the container",
+                                thing.seq(),
+                                connection.seq()
+                            ),
+                        );
+                    }
+                    if !connection.ends().iter().any(|end| end.ptr_eq(thing)) {
+                        unsound(
+                            "registered connections",
+                            format!(
+                                "thing #{} lists connection #{}, which doesn't join it",
+                                thing.seq(),
+                                connection.seq()
+                            ),
+                        );
+                    }
+                }
+            });
+        }
+
+        let dead_things = graph
+            .things
+            .iter()
+            .filter(|thing| !thing.is_alive())
+            .count();
+        let dead_connections = graph
+            .connections
+            .iter()
+            .filter(|connection| !connection.is_alive())
+            .count();
+        if graph.dead_amount != dead_things + dead_connections {
+            unsound(
+                "dead count",
+                format!(
+                    "the container counts {} dead items, but holds {dead_things} dead things and {dead_connections} dead connections",
+                    graph.dead_amount
+                ),
+            );
+        }
+    }
+
+    /// Runs `assert_graph_sound`, then checks that the live things of `graph`
+    /// match `expected`, one `Thing::snapshot` per live thing in container order.
+    ///
+    /// # Panics
+    /// If `graph` is unsound, or naming the first live thing that differs from
+    /// its expected snapshot.
+    pub fn assert_graph_matches<T, C>(graph: &Things<T, C>, expected: &[ThingSnapshot<T, C>])
+    where
+        T: Clone + PartialEq + core::fmt::Debug,
+        C: Clone + PartialEq + core::fmt::Debug,
+    {
+        assert_graph_sound(graph);
+        let live: Vec<_> = graph
+            .things
+            .iter()
+            .filter(|thing| thing.is_alive())
+            .collect();
+        if live.len() != expected.len() {
+            unsound(
+                "snapshot",
+                format!("{} live things, expected {}", live.len(), expected.len()),
+            );
+        }
+        for (thing, expected) in live.into_iter().zip(expected) {
+            let actual = thing.snapshot();
+            if actual != *expected {
+                unsound(
+                    "snapshot",
+                    format!(
+                        "thing #{} is {actual:?}, expected {expected:?}",
+                        thing.seq()
+                    ),
+                );
+            }
+        }
+    }
+
+    fn unsound(check: &str, details: String) -> ! {
+        panic!("graph failed the \"{check}\" check: {details}")
+    }
+}
+
+/// Ready-made example graphs for tests, benchmarks and documentation.
+///
+/// Each function builds its graph with the public API only and returns it
+/// together with named handles to its things. Available with the `fixtures`
+/// feature.
+#[cfg(any(test, feature = "fixtures"))]
+pub mod fixtures {
+    use super::*;
+
+    /// The things of `knowledge_graph`.
+    pub struct KnowledgeHandles {
+        /// "Apple", which "is" "Fruit".
+        pub apple: Thing<&'static str, &'static str>,
+        /// "Apples", the "plural of" "Apple".
+        pub apples: Thing<&'static str, &'static str>,
+        /// "Pear", which "is" "Fruit".
+        pub pear: Thing<&'static str, &'static str>,
+        /// "Pears", the "plural of" "Pear".
+        pub pears: Thing<&'static str, &'static str>,
+        /// "Alice", who "likes to eat" "Apples" and "doesn't like to eat" "Pears".
+        pub alice: Thing<&'static str, &'static str>,
+        /// "Fruit", the category of "Apple" and "Pear".
+        pub fruit: Thing<&'static str, &'static str>,
+    }
+
+    /// The things of `social_network`.
+    pub struct SocialHandles {
+        /// "Alice", friends with Bob and Carol.
+        pub alice: Thing<&'static str, &'static str>,
+        /// "Bob", friends with Alice and Carol.
+        pub bob: Thing<&'static str, &'static str>,
+        /// "Carol", friends with Alice and Bob.
+        pub carol: Thing<&'static str, &'static str>,
+        /// "Dave", who "follows" Alice without being her friend.
+        pub dave: Thing<&'static str, &'static str>,
+    }
+
+    /// The things of `task_dag`.
+    pub struct TaskHandles {
+        /// "Design", which unlocks both implementation tasks.
+        pub design: Thing<&'static str, &'static str>,
+        /// "Implement Auth".
+        pub auth: Thing<&'static str, &'static str>,
+        /// "Implement UI".
+        pub ui: Thing<&'static str, &'static str>,
+        /// "Integration Testing", unlocked by both implementation tasks.
+        pub testing: Thing<&'static str, &'static str>,
+        /// "Deployment", the last task.
+        pub deployment: Thing<&'static str, &'static str>,
+    }
+
+    /// Builds a small taxonomy of foods, categories and preferences, the
+    /// knowledge graph from the crate documentation.
+    ///
+    /// Every connection is directed: "Apples" and "Pears" are the "plural of"
+    /// "Apple" and "Pear", which each "is" "Fruit", and "Alice" "likes to eat"
+    /// "Apples" but "doesn't like to eat" "Pears".
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let (graph, handles) = fixtures::knowledge_graph();
+    /// let liked = handles.alice.follow(&"likes to eat");
+    /// assert!(liked[0] == "Apples");
+    /// assert!(graph.is_reachable(&handles.alice, &handles.fruit, |_| true));
+    /// ```
+    pub fn knowledge_graph() -> (Things<&'static str, &'static str>, KnowledgeHandles) {
+        let mut graph = Things::new();
+
+        let apple = graph.new_thing("Apple");
+        let apples = graph.new_thing("Apples");
+        graph.new_directed_connection(apples.clone(), "plural of", apple.clone());
+
+        let pear = graph.new_thing("Pear");
+        let pears = graph.new_thing("Pears");
+        graph.new_directed_connection(pears.clone(), "plural of", pear.clone());
+
+        let alice = graph.new_thing("Alice");
+        graph.new_directed_connection(alice.clone(), "likes to eat", apples.clone());
+        graph.new_directed_connection(alice.clone(), "doesn't like to eat", pears.clone());
+
+        let fruit = graph.new_thing("Fruit");
+        graph.new_directed_connection(apple.clone(), "is", fruit.clone());
+        graph.new_directed_connection(pear.clone(), "is", fruit.clone());
+
+        let handles = KnowledgeHandles {
+            apple,
+            apples,
+            pear,
+            pears,
+            alice,
+            fruit,
+        };
+        (graph, handles)
+    }
+
+    /// Builds a small social network mixing symmetric and one-sided relations.
+    ///
+    /// "Alice", "Bob" and "Carol" form a triangle of undirected "friends"
+    /// connections, and "Dave" has a directed "follows" connection to "Alice".
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let (_graph, people) = fixtures::social_network();
+    /// assert_eq!(people.alice.neighbors_alive().len(), 3);
+    /// assert!(people.dave.follow(&"follows")[0] == "Alice");
+    /// ```
+    pub fn social_network() -> (Things<&'static str, &'static str>, SocialHandles) {
+        let mut graph = Things::new();
+
+        let alice = graph.new_thing("Alice");
+        let bob = graph.new_thing("Bob");
+        let carol = graph.new_thing("Carol");
+        let dave = graph.new_thing("Dave");
+
+        graph.new_undirected_connection([alice.clone(), bob.clone()], "friends");
+        graph.new_undirected_connection([bob.clone(), carol.clone()], "friends");
+        graph.new_undirected_connection([carol.clone(), alice.clone()], "friends");
+        graph.new_directed_connection(dave.clone(), "follows", alice.clone());
+
+        let handles = SocialHandles {
+            alice,
+            bob,
+            carol,
+            dave,
+        };
+        (graph, handles)
+    }
+
+    /// Builds a small project plan where each task "unlocks" the next ones.
+    ///
+    /// "Design" unlocks "Implement Auth" and "Implement UI", which both unlock
+    /// "Integration Testing", which unlocks "Deployment". The graph has no
+    /// cycles.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use connect_things::*;
+    /// let (project, tasks) = fixtures::task_dag();
+    /// let mut plan = project.execution_order(|_| true).unwrap();
+    /// assert!(plan.next_ready().unwrap() == "Design");
+    /// assert!(project.is_reachable(&tasks.design, &tasks.deployment, |_| true));
+    /// ```
+    pub fn task_dag() -> (Things<&'static str, &'static str>, TaskHandles) {
+        let mut project = Things::new();
+
+        let design = project.new_thing("Design");
+        let auth = project.new_thing("Implement Auth");
+        let ui = project.new_thing("Implement UI");
+        let testing = project.new_thing("Integration Testing");
+        let deployment = project.new_thing("Deployment");
+
+        project.new_directed_connection(design.clone(), "unlocks", auth.clone());
+        project.new_directed_connection(design.clone(), "unlocks", ui.clone());
+        project.new_directed_connection(auth.clone(), "unlocks", testing.clone());
+        project.new_directed_connection(ui.clone(), "unlocks", testing.clone());
+        project.new_directed_connection(testing.clone(), "unlocks", deployment.clone());
+
+        let handles = TaskHandles {
+            design,
+            auth,
+            ui,
+            testing,
+            deployment,
+        };
+        (project, handles)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::boxed::Box;
+    use alloc::format;
+    use alloc::string::{String, ToString};
+
+    #[test]
+    #[allow(clippy::needless_return)]
+    fn knowledge_representation_basic_query() {
+        let graph = fixtures::knowledge_graph().0;
+
+        // Query: What does Alice like to eat?
+        let alice = graph.find_thing(|data| *data == "Alice").unwrap();
+
+        let liked_food_connection = alice
+            .do_for_a_connection(|connection| {
+                return if connection.access(|data| *data == "likes to eat") {
+                    Do::Take(connection.clone())
+                } else {
+                    Do::Nothing
+                };
+            })
+            .unwrap();
+
+        // Use the new API that returns Option
+        let liked_food = liked_food_connection.get_directed_towards().unwrap();
+
+        let answer = format!(
+            "The thing alice likes to eat is: {}.",
+            liked_food.access(|data| data.to_ascii_lowercase())
+        );
+
+        assert_eq!("The thing alice likes to eat is: apples.", &answer);
+    }
+
+    #[test]
+    fn knowledge_representation_taxonomy_query() {
+        let graph = fixtures::knowledge_graph().0;
+
+        // Query: What are some examples of fruit?
+        let fruit_concept = graph.find_thing(|data| *data == "Fruit").unwrap();
+
+        // Find all things that are instances of fruit, through "is"
+        // relationships pointing to the fruit concept
+        let fruit_examples: Vec<_> = graph
+            .find_connections(|data| *data == "is")
+            .iter()
+            .filter(|conn| conn.get_direction_relative_to(&fruit_concept) == Ok(Direction::Towards))
+            .map(|conn| conn.get_directed_from().unwrap().access(|data| *data))
+            .collect();
+
+        assert!(fruit_examples.contains(&"Apple"));
+        assert!(fruit_examples.contains(&"Pear"));
+        assert_eq!(fruit_examples.len(), 2);
+    }
+
+    #[test]
+    #[allow(clippy::needless_return)]
+    fn social_network_simulation() {
+        let mut social_graph = Things::<String, String>::new();
+
+        // Create people
+        let alice = social_graph.new_thing("Alice".to_string());
+        let bob = social_graph.new_thing("Bob".to_string());
+        let charlie = social_graph.new_thing("Charlie".to_string());
+        let diana = social_graph.new_thing("Diana".to_string());
+
+        // Create friendships (undirected relationships)
+        social_graph
+            .new_undirected_connection([alice.clone(), bob.clone()], "friendship".to_string());
+        social_graph
+            .new_undirected_connection([bob.clone(), charlie.clone()], "friendship".to_string());
+        social_graph
+            .new_undirected_connection([alice.clone(), diana.clone()], "friendship".to_string());
+
+        // Create follows relationships (directed)
+        social_graph.new_directed_connection(charlie.clone(), "follows".to_string(), alice.clone());
+        social_graph.new_directed_connection(diana.clone(), "follows".to_string(), bob.clone());
+
+        // Test: Find Alice's friends
+        let alice_friendships = alice.do_for_all_connections(|conn| {
+            return if conn.is_undirected() && conn.access(|data| data == "friendship") {
+                Do::Take(conn.clone())
+            } else {
+                Do::Nothing
+            };
+        });
+
+        assert_eq!(alice_friendships.len(), 2); // Alice is friends with Bob and Diana
+
+        // Test: Find who follows Alice
+        let alice_followers: Vec<_> = social_graph
+            .find_connections(|data| data == "follows")
+            .iter()
+            .filter(|conn| conn.is_directed() && conn.get_directed_towards().unwrap() == alice)
+            .map(|conn| {
+                conn.get_directed_from()
+                    .unwrap()
+                    .access(|data| data.clone())
+            })
+            .collect();
+
+        assert!(alice_followers.contains(&"Charlie".to_string()));
+        assert_eq!(alice_followers.len(), 1);
+    }
+
+    #[test]
+    #[allow(clippy::needless_return)]
+    fn gui_component_hierarchy() {
+        // Simulate a simple GUI structure with containment and focus relationships
+        #[derive(Debug, Clone, PartialEq)]
+        struct Widget {
+            name: String,
+            widget_type: String,
+        }
+
+        #[derive(Debug, Clone, PartialEq)]
+        enum Relationship {
+            Contains,
+            FocusNext,
+            EventBubbles,
+        }
+
+        let mut gui = Things::<Widget, Relationship>::new();
+
+        // Create widgets
+        let window = gui.new_thing(Widget {
+            name: "MainWindow".to_string(),
+            widget_type: "Window".to_string(),
+        });
+
+        let dialog = gui.new_thing(Widget {
+            name: "SettingsDialog".to_string(),
+            widget_type: "Dialog".to_string(),
+        });
+
+        let ok_button = gui.new_thing(Widget {
+            name: "OkButton".to_string(),
+            widget_type: "Button".to_string(),
+        });
+
+        let cancel_button = gui.new_thing(Widget {
+            name: "CancelButton".to_string(),
+            widget_type: "Button".to_string(),
+        });
+
+        // Create containment hierarchy
+        gui.new_directed_connection(window.clone(), Relationship::Contains, dialog.clone());
+        gui.new_directed_connection(dialog.clone(), Relationship::Contains, ok_button.clone());
+        gui.new_directed_connection(
+            dialog.clone(),
+            Relationship::Contains,
+            cancel_button.clone(),
+        );
+
+        // Create focus chain
+        gui.new_directed_connection(
+            ok_button.clone(),
+            Relationship::FocusNext,
+            cancel_button.clone(),
+        );
+        gui.new_directed_connection(
+            cancel_button.clone(),
+            Relationship::FocusNext,
+            ok_button.clone(),
+        );
+
+        // Create event bubbling relationships
+        gui.new_directed_connection(
+            ok_button.clone(),
+            Relationship::EventBubbles,
+            dialog.clone(),
+        );
+        gui.new_directed_connection(
+            cancel_button.clone(),
+            Relationship::EventBubbles,
+            dialog.clone(),
+        );
+
+        // Test: Find all widgets contained in the dialog
+        let dialog_children: Vec<_> = dialog.do_for_all_connections(|conn| {
+            conn.access(|data| {
+                if matches!(data, Relationship::Contains) {
+                    if let Some(from) = conn.get_directed_from() {
+                        if from == dialog {
+                            Do::Take(
+                                conn.get_directed_towards()
+                                    .unwrap()
+                                    .access(|data| data.name.clone()),
+                            )
+                        } else {
+                            Do::Nothing
+                        }
+                    } else {
+                        Do::Nothing
+                    }
+                } else {
+                    Do::Nothing
+                }
+            })
+        });
+
+        assert!(dialog_children.contains(&"OkButton".to_string()));
+        assert!(dialog_children.contains(&"CancelButton".to_string()));
+        assert_eq!(dialog_children.len(), 2);
+
+        // Test: Find the next widget in focus chain from OK button
+        let next_focus = ok_button.do_for_a_connection(|conn| {
+            conn.access(|data| {
+                return if matches!(data, Relationship::FocusNext) {
+                    if let Ok(Direction::AwayFrom) = conn.get_direction_relative_to(&ok_button) {
+                        if let Some(to) = conn.get_directed_towards() {
+                            Do::Take(to.access(|data| data.name.clone()))
+                        } else {
+                            Do::Nothing
+                        }
+                    } else {
+                        Do::Nothing
+                    }
+                } else {
+                    Do::Nothing
+                };
+            })
+        });
+
+        assert_eq!(next_focus, Some("CancelButton".to_string()));
+    }
+
+    #[test]
+    #[allow(clippy::needless_return)]
+    fn task_dependency_graph() {
+        #[derive(Debug, Clone, PartialEq)]
+        struct Task {
+            name: String,
+            estimated_hours: u32,
+            completed: bool,
+        }
+
+        #[derive(Debug, Clone, PartialEq)]
+        enum TaskRelation {
+            DependsOn,
+            // Blocks,
+            // PartOf,
+        }
+
+        let mut project = Things::<Task, TaskRelation>::new();
+
+        // Create tasks
+        let design = project.new_thing(Task {
+            name: "Design System".to_string(),
+            estimated_hours: 40,
+            completed: true,
+        });
+
+        let implement_auth = project.new_thing(Task {
+            name: "Implement Authentication".to_string(),
+            estimated_hours: 20,
+            completed: false,
+        });
+
+        let implement_ui = project.new_thing(Task {
+            name: "Implement UI".to_string(),
+            estimated_hours: 60,
+            completed: false,
+        });
+
+        let testing = project.new_thing(Task {
+            name: "Integration Testing".to_string(),
+            estimated_hours: 30,
+            completed: false,
+        });
+
+        let deployment = project.new_thing(Task {
+            name: "Deployment".to_string(),
+            estimated_hours: 10,
+            completed: false,
+        });
+
+        // Create dependencies
+        project.new_directed_connection(
+            implement_auth.clone(),
+            TaskRelation::DependsOn,
+            design.clone(),
+        );
+        project.new_directed_connection(
+            implement_ui.clone(),
+            TaskRelation::DependsOn,
+            design.clone(),
+        );
+        project.new_directed_connection(
+            testing.clone(),
+            TaskRelation::DependsOn,
+            implement_auth.clone(),
+        );
+        project.new_directed_connection(
+            testing.clone(),
+            TaskRelation::DependsOn,
+            implement_ui.clone(),
+        );
+        project.new_directed_connection(
+            deployment.clone(),
+            TaskRelation::DependsOn,
+            testing.clone(),
+        );
+
+        // Test: Find all tasks that can be started now (dependencies completed)
+        let incomplete_tasks = project.find_things(|data| !data.completed);
+
+        let ready_tasks: Vec<_> = incomplete_tasks
+            .iter()
+            .map(|task| {
+                if task
+                    .do_for_all_connections(|conn| {
+                        if let Ok(Direction::AwayFrom) = conn.get_direction_relative_to(task) {
+                            conn.access(|data| {
+                                return if matches!(data, TaskRelation::DependsOn) {
+                                    return if let Some(to) = conn.get_directed_towards() {
+                                        Do::Take(to.access(|data| data.completed))
+                                    } else {
+                                        Do::Nothing
+                                    };
+                                } else {
+                                    Do::Nothing
+                                };
+                            })
+                        } else {
+                            Do::Nothing
+                        }
+                    })
+                    .iter()
+                    .all(|x| *x)
+                {
+                    Some(task.clone())
+                } else {
+                    None
+                }
+            })
+            .filter_map(|v| v.clone())
+            .map(|v| v.access(|data| data.name.clone()))
+            .collect();
+
+        // Only Auth and UI should be ready (Design is completed)
+        assert!(ready_tasks.contains(&"Implement Authentication".to_string()));
+        assert!(ready_tasks.contains(&"Implement UI".to_string()));
+        assert!(!ready_tasks.contains(&"Integration Testing".to_string())); // Depends on incomplete tasks
+        assert!(!ready_tasks.contains(&"Deployment".to_string())); // Depends on incomplete tasks
+    }
+
+    #[test]
+    #[allow(clippy::len_zero)]
+    fn memory_pressure_tracking() {
+        let mut graph = Things::new();
+
+        // Create some items
+        let thing1 = graph.new_thing("Thing1");
+        let thing2 = graph.new_thing("Thing2");
+        let thing3 = graph.new_thing("Thing3");
+
+        let _conn1 = graph.new_directed_connection(thing1, "relates", thing2.clone());
+        let _conn2 = graph.new_directed_connection(thing2, "relates", thing3);
+
+        // Initially, no dead items
+        assert_eq!(graph.dead_percentage().unwrap(), 0);
+
+        // Kill one thing (should kill the thing and its connections)
+        graph.kill_things(|thing| thing.access(|data| data == &"Thing1"));
+
+        // Should have some dead percentage now
+        let percentage_after_kill = graph.dead_percentage().unwrap();
+        assert!(percentage_after_kill > 0);
+        assert!(percentage_after_kill <= 100);
+
+        // Clean up and verify percentage returns to 0
+        graph.clean();
+        assert_eq!(graph.dead_percentage().unwrap(), 0);
+
+        // Verify remaining items are still accessible
+        let remaining_things = graph.do_for_all_things(|_| Do::Take(()));
+        assert!(remaining_things.len() > 0); // Should have some things left
+    }
+
+    #[test]
+    #[allow(clippy::len_zero)]
+    fn cascade_deletion_behavior() {
+        let mut graph = Things::new();
+
+        let alice = graph.new_thing("Alice");
+        let bob = graph.new_thing("Bob");
+        let charlie = graph.new_thing("Charlie");
+
+        // Create connections: Alice -> Bob, Bob -> Charlie
+        graph.new_directed_connection(alice.clone(), "knows", bob.clone());
+        graph.new_directed_connection(bob.clone(), "knows", charlie.clone());
+
+        // Kill Bob - this should kill Bob and all his connections
+        graph.kill_things(|thing| thing.access(|data| data == &"Bob"));
+
+        // Alice and Charlie should still be alive
+        assert!(alice.access(|_| true)); // Can still access Alice's data
+        assert!(charlie.access(|_| true)); // Can still access Charlie's data
+
+        // But Bob's connections should be dead
+        let alice_connections = alice.do_for_all_connections(|_| Do::Take(()));
+        // Alice's connection to Bob should still exist but be marked as dead
+        assert!(alice_connections.len() > 0);
+
+        // After cleanup, dead connections should be removed
+        graph.clean();
+        let alice_connections_after_clean = alice.do_for_all_connections(|_| Do::Take(()));
+        assert_eq!(alice_connections_after_clean.len(), 0); // Alice should have no live connections
+        test_util::assert_graph_sound(&graph);
+    }
+
+    #[test]
+    #[allow(clippy::needless_return)]
+    fn undirected_connections_behavior() {
+        let mut graph = Things::<String, String>::new();
+
+        let alice = graph.new_thing("Alice".to_string());
+        let bob = graph.new_thing("Bob".to_string());
+
+        // Create undirected friendship
+        let friendship =
+            graph.new_undirected_connection([alice.clone(), bob.clone()], "friendship".to_string());
+
+        let find_friendships = |thing: &Thing<_, _>| {
+            thing.do_for_all_connections(|conn| {
+                conn.access(|data| {
+                    return if data == "friendship" {
+                        Do::Take(conn.clone())
+                    } else {
+                        Do::Nothing
+                    };
+                })
+            })
+        };
+
+        let alice_friendships = find_friendships(&alice);
+        let bob_friendships = find_friendships(&bob);
+
+        // Both Alice and Bob should have the same connection in their lists
+
+        assert_eq!(alice_friendships.len(), 1);
+        assert_eq!(bob_friendships.len(), 1);
+
+        // The connection should be marked as undirected
+        assert!(friendship.is_undirected());
+        assert!(!friendship.is_directed());
+
+        // Directional methods should return None for undirected connections
+        assert!(friendship.get_directed_from().is_none());
+        assert!(friendship.get_directed_towards().is_none());
+
+        // Both people should be reachable from the connection using get_connected_things
+        let connected = friendship.get_things();
+        let names: Vec<String> = connected
+            .iter()
+            .map(|thing| thing.access(|data| data.clone()))
+            .collect();
+
+        assert!(names.contains(&"Alice".to_string()));
+        assert!(names.contains(&"Bob".to_string()));
+    }
+
+    #[test]
+    fn directed_connection_safety() {
+        let mut graph = Things::<String, String>::new();
+
+        let manager = graph.new_thing("Manager".to_string());
+        let employee = graph.new_thing("Employee".to_string());
+
+        // Create directed management relationship
+        let manages =
+            graph.new_directed_connection(manager.clone(), "manages".to_string(), employee.clone());
+
+        // Connection should be marked as directed
+        assert!(manages.is_directed());
+        assert!(!manages.is_undirected());
+
+        // Directional methods should work correctly
+        let from_person = manages.get_directed_from().unwrap();
+        let to_person = manages.get_directed_towards().unwrap();
+
+        assert_eq!(from_person.access(|data| data.clone()), "Manager");
+        assert_eq!(to_person.access(|data| data.clone()), "Employee");
+
+        // get_connected_things should return [from, to]
+        let connected = manages.get_things();
+        assert_eq!(connected[0].access(|data| data.clone()), "Manager");
+        assert_eq!(connected[1].access(|data| data.clone()), "Employee");
+    }
+
+    #[test]
+    #[allow(clippy::collapsible_if, clippy::needless_return)]
+    fn complex_knowledge_query() {
+        // Test a more complex knowledge representation scenario
+        let mut knowledge = Things::<String, String>::new();
+
+        // Create a small taxonomy
+        let animal = knowledge.new_thing("Animal".to_string());
+        let mammal = knowledge.new_thing("Mammal".to_string());
+        let dog = knowledge.new_thing("Dog".to_string());
+        let cat = knowledge.new_thing("Cat".to_string());
+
+        let fido = knowledge.new_thing("Fido".to_string());
+        let whiskers = knowledge.new_thing("Whiskers".to_string());
+
+        // Build taxonomy relationships
+        knowledge.new_directed_connection(mammal.clone(), "is_a".to_string(), animal.clone());
+        knowledge.new_directed_connection(dog.clone(), "is_a".to_string(), mammal.clone());
+        knowledge.new_directed_connection(cat.clone(), "is_a".to_string(), mammal.clone());
+
+        // Instance relationships
+        knowledge.new_directed_connection(fido.clone(), "instance_of".to_string(), dog.clone());
+        knowledge.new_directed_connection(whiskers.clone(), "instance_of".to_string(), cat.clone());
+
+        // Query: Find all animals (instances that are transitively related to Animal)
+        // This tests multi-hop traversal
+        let mut animal_instances = Vec::new();
+
+        // Find all instances
+        for instance_conn in knowledge.do_for_all_connections(|conn| {
+            conn.access(|data| {
+                return if data == "instance_of" {
+                    Do::Take(conn.clone())
+                } else {
+                    Do::Nothing
+                };
+            })
+        }) {
+            if let Some(instance) = instance_conn.get_directed_from() {
+                if let Some(species) = instance_conn.get_directed_towards() {
+                    // Check if this species is ultimately an animal
+                    let mut current = species;
+                    let mut is_animal = false;
+
+                    // Traverse up the hierarchy
+                    for _ in 0..10 {
+                        // Prevent infinite loops
+                        if current.access(|data| data == "Animal") {
+                            is_animal = true;
+                            break;
+                        }
+
+                        // Find parent class
+                        if let Some(parent_conn) = current.do_for_a_connection(|conn| {
+                            conn.access(|data| {
+                                return if data == "is_a" {
+                                    Do::Take(conn.clone())
+                                } else {
+                                    Do::Nothing
+                                };
+                            })
+                        }) {
+                            if let Some(parent) = parent_conn.get_directed_towards() {
+                                current = parent;
+                            } else {
+                                break;
+                            }
+                        } else {
+                            break;
+                        }
+                    }
+
+                    if is_animal {
+                        animal_instances.push(instance.access(|data| data.clone()));
+                    }
+                }
+            }
+        }
+
+        assert!(animal_instances.contains(&"Fido".to_string()));
+        assert!(animal_instances.contains(&"Whiskers".to_string()));
+        assert_eq!(animal_instances.len(), 2);
+    }
+
+    #[test]
+    fn propagation_decays_along_a_chain() {
+        let mut network = Things::<u32, ()>::new();
+
+        let nodes: Vec<_> = (0..6).map(|n| network.new_thing(n)).collect();
+        for pair in nodes.windows(2) {
+            network.new_directed_connection(pair[0].clone(), (), pair[1].clone());
+        }
+
+        // Lose one unit of strength per hop, stopping once it is used up
+        let reached = network.propagate(&nodes[0], 3u32, |strength, _, _| {
+            if *strength > 0 {
+                Some(strength - 1)
+            } else {
+                None
+            }
+        });
+
+        let values: Vec<_> = reached
+            .iter()
+            .map(|(thing, strength)| (thing.access(|data| *data), *strength))
+            .collect();
+
+        assert_eq!(values, [(0, 3), (1, 2), (2, 1), (3, 0)]);
+    }
+
+    #[test]
+    fn propagation_records_first_arrival() {
+        let mut network = Things::<&str, u32>::new();
+
+        // A diamond with a cheap and an expensive branch to the same sink
+        let top = network.new_thing("top");
+        let left = network.new_thing("left");
+        let right = network.new_thing("right");
+        let bottom = network.new_thing("bottom");
+
+        network.new_directed_connection(top.clone(), 1, left.clone());
+        network.new_directed_connection(top.clone(), 5, right.clone());
+        network.new_directed_connection(left.clone(), 1, bottom.clone());
+        network.new_directed_connection(right.clone(), 5, bottom.clone());
+        // A cycle back to the top must not be revisited
+        network.new_directed_connection(bottom.clone(), 1, top.clone());
+
+        let reached = network.propagate(&top, 0u32, |cost, conn, _| {
+            Some(cost + conn.access(|data| *data))
+        });
+
+        assert_eq!(reached.len(), 4);
+        let bottom_cost = reached
+            .iter()
+            .find(|(thing, _)| thing.ptr_eq(&bottom))
+            .map(|(_, cost)| *cost);
+        assert_eq!(bottom_cost, Some(2));
+    }
+
+    #[test]
+    fn kill_connections_counts_killed() {
+        let mut social_graph = Things::<&str, &str>::new();
+
+        let alice = social_graph.new_thing("Alice");
+        let bob = social_graph.new_thing("Bob");
+        let charlie = social_graph.new_thing("Charlie");
+
+        social_graph.new_directed_connection(alice.clone(), "follows", bob.clone());
+        social_graph.new_directed_connection(alice.clone(), "follows", charlie.clone());
+        social_graph.new_directed_connection(bob.clone(), "follows", charlie.clone());
+        social_graph.new_undirected_connection([alice, bob], "friendship");
+
+        let unfollowed = social_graph.kill_connections(|conn| conn == &"follows");
+        assert_eq!(unfollowed, 3);
+
+        // 3 dead out of 3 things and 4 connections
+        assert_eq!(social_graph.dead_percentage().unwrap(), 42);
+
+        // Already dead connections are not counted twice
+        let unfollowed_again = social_graph.kill_connections(|conn| conn == &"follows");
+        assert_eq!(unfollowed_again, 0);
+        assert_eq!(social_graph.dead_percentage().unwrap(), 42);
+    }
+
+    #[cfg(feature = "float")]
+    #[test]
+    fn weighting_changes_top_broker() {
+        let mut network = Things::<&str, u64>::new();
+
+        let left = [network.new_thing("l1"), network.new_thing("l2")];
+        let right = [network.new_thing("r1"), network.new_thing("r2")];
+        let hub = network.new_thing("hub");
+        let back_left = network.new_thing("back_left");
+        let back_right = network.new_thing("back_right");
+
+        // A short but expensive route through the hub, and a longer cheap
+        // route through the back channel
+        for thing in &left {
+            network.new_undirected_connection([thing.clone(), hub.clone()], 10);
+            network.new_undirected_connection([thing.clone(), back_left.clone()], 1);
+        }
+        for thing in &right {
+            network.new_undirected_connection([thing.clone(), hub.clone()], 10);
+            network.new_undirected_connection([thing.clone(), back_right.clone()], 1);
+        }
+        network.new_undirected_connection([back_left.clone(), back_right.clone()], 1);
+
+        fn top_broker(scores: Vec<(Thing<&'static str, u64>, f64)>) -> &'static str {
+            let mut best = &scores[0];
+            for entry in &scores {
+                if entry.1 > best.1 {
+                    best = entry;
+                }
+            }
+            best.0.access(|data| *data)
+        }
+
+        // Unweighted, the hub lies on every short route between the two sides
+        assert_eq!(top_broker(network.betweenness()), "hub");
+
+        // Weighted, traffic is routed through the cheap back channel instead
+        let weighted = top_broker(network.betweenness_weighted(|weight| *weight));
+        assert!(weighted == "back_left" || weighted == "back_right");
+    }
+
+    fn import_fixture() -> (
+        Things<&'static str, &'static str>,
+        [Thing<&'static str, &'static str>; 3],
+    ) {
+        import_fixture_with(ThingsConfig::new())
+    }
+
+    fn import_fixture_with(
+        config: ThingsConfig<&'static str>,
+    ) -> (
+        Things<&'static str, &'static str>,
+        [Thing<&'static str, &'static str>; 3],
+    ) {
+        let mut graph = Things::with_config(config);
+        let alice = graph.new_thing("alice");
+        let bob = graph.new_thing("bob");
+        let charlie = graph.new_thing("charlie");
+        graph.new_directed_connection(alice.clone(), "follows", bob.clone());
+        (graph, [alice, bob, charlie])
+    }
+
+    fn import_with(
+        graph: &mut Things<&'static str, &'static str>,
+        people: &[Thing<&'static str, &'static str>; 3],
+        rows: &[(&str, &str, &str)],
+        policy: DuplicatePolicy,
+    ) -> Result<ImportReport, ImportAbort> {
+        graph.import_edges(
+            rows.iter().copied(),
+            |key| {
+                people
+                    .iter()
+                    .find(|person| person.access(|data| *data == key))
+                    .cloned()
+            },
+            policy,
+            |label| match label {
+                "follows" => "follows",
+                _ => "knows",
+            },
+        )
+    }
+
+    #[test]
+    fn import_edges_clean_import() {
+        let (mut graph, people) = import_fixture();
+        let rows = [("bob", "follows", "charlie"), ("charlie", "knows", "alice")];
+
+        let report = import_with(&mut graph, &people, &rows, DuplicatePolicy::Error).unwrap();
+
+        assert_eq!(report.created, 2);
+        assert!(report.skipped_duplicates.is_empty());
+        assert_eq!(graph.do_for_all_connections(|_| Do::Take(())).len(), 3);
+        assert_eq!(people[2].do_for_all_connections(|_| Do::Take(())).len(), 2);
+    }
+
+    #[test]
+    fn import_edges_duplicate_policies() {
+        let rows = [
+            ("alice", "follows", "bob"),
+            ("bob", "follows", "alice"),
+            ("bob", "follows", "alice"),
+        ];
+
+        let (mut graph, people) = import_fixture();
+        let report = import_with(&mut graph, &people, &rows, DuplicatePolicy::Skip).unwrap();
+        assert_eq!(report.created, 1);
+        assert_eq!(report.skipped_duplicates, [0, 2]);
+        assert_eq!(graph.do_for_all_connections(|_| Do::Take(())).len(), 2);
+
+        let (mut graph, people) = import_fixture();
+        let report =
+            import_with(&mut graph, &people, &rows, DuplicatePolicy::CreateParallel).unwrap();
+        assert_eq!(report.created, 3);
+        assert!(report.skipped_duplicates.is_empty());
+        assert_eq!(graph.do_for_all_connections(|_| Do::Take(())).len(), 4);
+
+        let (mut graph, people) = import_fixture();
+        let abort = import_with(&mut graph, &people, &rows[1..], DuplicatePolicy::Error);
+        assert_eq!(abort, Err(ImportAbort::Duplicate { row: 1 }));
+        assert_eq!(graph.do_for_all_connections(|_| Do::Take(())).len(), 1);
+    }
+
+    #[test]
+    fn import_edges_rolls_back_on_unknown_key() {
+        let (mut graph, people) = import_fixture();
+        let rows = [
+            ("bob", "follows", "charlie"),
+            ("charlie", "knows", "alice"),
+            ("charlie", "knows", "dave"),
+            ("alice", "knows", "charlie"),
+        ];
+
+        let abort = import_with(&mut graph, &people, &rows, DuplicatePolicy::Skip);
+
+        assert_eq!(
+            abort,
+            Err(ImportAbort::UnknownKey {
+                row: 2,
+                key: String::from("dave"),
+            })
+        );
+
+        // Only the connection that existed before the import is left
+        assert_eq!(graph.do_for_all_connections(|_| Do::Take(())).len(), 1);
+        assert_eq!(people[0].do_for_all_connections(|_| Do::Take(())).len(), 1);
+        assert_eq!(people[1].do_for_all_connections(|_| Do::Take(())).len(), 1);
+        assert_eq!(people[2].do_for_all_connections(|_| Do::Take(())).len(), 0);
+        assert_eq!(graph.dead_percentage().unwrap(), 0);
+    }
+
+    #[test]
+    fn import_edges_rolls_back_on_rule_violations() {
+        let count =
+            |graph: &Things<&str, &str>| graph.do_for_all_connections(|_| Do::Take(())).len();
+
+        let (mut graph, people) = import_fixture_with(ThingsConfig::new().max_connections(2));
+        let rows = [("bob", "follows", "charlie"), ("charlie", "knows", "alice")];
+        let abort = import_with(&mut graph, &people, &rows, DuplicatePolicy::Skip);
+        assert_eq!(
+            abort,
+            Err(ImportAbort::Limit {
+                row: 1,
+                error: LimitReached,
+            })
+        );
+        assert_eq!(count(&graph), 1);
+
+        let (mut graph, people) = import_fixture_with(ThingsConfig::new().strict_liveness(true));
+        graph.kill_things(|name| *name == "charlie");
+        let rows = [("alice", "knows", "bob"), ("bob", "knows", "charlie")];
+        let abort = import_with(&mut graph, &people, &rows, DuplicatePolicy::Skip);
+        assert_eq!(
+            abort,
+            Err(ImportAbort::Connect {
+                row: 1,
+                error: ConnectError::DeadEndpoint,
+            })
+        );
+        assert_eq!(count(&graph), 1);
+
+        let (mut graph, people) = import_fixture_with(ThingsConfig::new().reject_self_loops(true));
+        let rows = [("bob", "knows", "charlie"), ("alice", "knows", "alice")];
+        let abort = import_with(&mut graph, &people, &rows, DuplicatePolicy::Skip);
+        assert_eq!(
+            abort,
+            Err(ImportAbort::Connect {
+                row: 1,
+                error: ConnectError::SelfLoop,
+            })
+        );
+        assert_eq!(count(&graph), 1);
+
+        let (mut graph, people) = import_fixture_with(ThingsConfig::new().mode(GraphMode::Simple));
+        let rows = [("bob", "knows", "charlie"), ("alice", "follows", "bob")];
+        let abort = import_with(&mut graph, &people, &rows, DuplicatePolicy::CreateParallel);
+        assert_eq!(
+            abort,
+            Err(ImportAbort::Connect {
+                row: 1,
+                error: ConnectError::ParallelEdge,
+            })
+        );
+        assert_eq!(count(&graph), 1);
+        test_util::assert_graph_sound(&graph);
+    }
+
+    #[test]
+    fn import_edges_never_auto_cleans() {
+        let (mut graph, people) = import_fixture_with(ThingsConfig::new().auto_clean_at(10));
+        graph.kill_things(|name| *name == "charlie");
+        let rows = [("alice", "knows", "bob"), ("bob", "knows", "dave")];
+        assert!(import_with(&mut graph, &people, &rows, DuplicatePolicy::Skip).is_err());
+        assert_eq!(graph.things.len(), 3);
+        assert_eq!(graph.connections.len(), 1);
+    }
+
+    #[test]
+    fn orphaned_connections_detected_and_pruned() {
+        let mut graph = Things::<&str, &str>::new();
+
+        let alice = graph.new_thing("Alice");
+        let bob = graph.new_thing("Bob");
+        graph.new_directed_connection(alice.clone(), "knows", bob.clone());
+
+        // A thing with the same data as Alice, but not part of the graph
+        let impostor = Thing::new("Alice");
+        let orphan = graph.new_directed_connection(bob.clone(), "knows", impostor.clone());
+
+        let orphaned = graph.orphaned_connections();
+        assert_eq!(orphaned.len(), 1);
+        assert!(orphaned[0].ptr_eq(&orphan));
+
+        assert_eq!(graph.prune_orphaned(), 1);
+        assert!(graph.orphaned_connections().is_empty());
+        assert!(!orphan.is_alive());
+        assert_eq!(bob.do_for_all_connections(|_| Do::Take(())).len(), 1);
+        assert_eq!(impostor.do_for_all_connections(|_| Do::Take(())).len(), 0);
+        assert_eq!(graph.do_for_all_connections(|_| Do::Take(())).len(), 1);
+        assert_eq!(graph.dead_percentage().unwrap(), 0);
+    }
+
+    #[test]
+    fn recency_survives_kills_and_cleans() {
+        let mut graph = Things::<&str, &str>::new();
+        let names = |things: Vec<Thing<&'static str, &'static str>>| -> Vec<&str> {
+            things
+                .iter()
+                .map(|thing| thing.access(|data| *data))
+                .collect()
+        };
+
+        let a = graph.new_thing("a");
+        let b = graph.new_thing("b");
+        graph.new_directed_connection(a.clone(), "ab", b.clone());
+        let c = graph.new_thing("c");
+        graph.new_directed_connection(b.clone(), "bc", c.clone());
+
+        assert_eq!(names(graph.recent_things(2)), ["c", "b"]);
+        assert_eq!(names(graph.recent_things(10)), ["c", "b", "a"]);
+
+        // Killing c also kills the "bc" connection
+        graph.kill_things(|thing| thing == &"c");
+        assert_eq!(names(graph.recent_things(2)), ["b", "a"]);
+        let recent_connections = graph.recent_connections(5);
+        assert_eq!(recent_connections.len(), 1);
+        assert!(recent_connections[0] == "ab");
+
+        graph.clean();
+        let d = graph.new_thing("d");
+        graph.new_undirected_connection([a.clone(), d.clone()], "ad");
+        assert_eq!(names(graph.recent_things(2)), ["d", "b"]);
+        let recent_connections = graph.recent_connections(5);
+        assert!(recent_connections[0] == "ad");
+        assert!(recent_connections[1] == "ab");
+
+        let reversed: Vec<_> = graph
+            .things_rev()
+            .map(|thing| thing.access(|data| *data))
+            .collect();
+        assert_eq!(reversed, ["d", "b", "a"]);
+    }
+
+    #[test]
+    fn connection_classes_track_lifecycle() {
+        #[derive(Debug, Clone, PartialEq)]
+        enum Relation {
+            Follows,
+            Friends,
+            Blocks,
+        }
+
+        fn classify(relation: &Relation) -> u16 {
+            match relation {
+                Relation::Follows => 0,
+                Relation::Friends => 1,
+                Relation::Blocks => 2,
+            }
+        }
+
+        let mut graph = Things::<&str, Relation>::new();
+        let alice = graph.new_thing("alice");
+        let bob = graph.new_thing("bob");
+        let charlie = graph.new_thing("charlie");
+
+        // Existing connections are picked up when registering
+        graph.new_directed_connection(alice.clone(), Relation::Follows, bob.clone());
+        graph.new_undirected_connection([alice.clone(), charlie.clone()], Relation::Friends);
+        graph.register_connection_class(classify);
+        assert_eq!(graph.count_of_class(0), 1);
+        assert_eq!(graph.count_of_class(1), 1);
+
+        graph.new_directed_connection(charlie.clone(), Relation::Follows, bob.clone());
+        graph.new_directed_connection(bob.clone(), Relation::Blocks, alice.clone());
+        assert_eq!(graph.count_of_class(0), 2);
+        assert_eq!(graph.count_of_class(2), 1);
+
+        // Killing charlie kills one "follows" and the friendship
+        graph.kill_things(|thing| thing == &"charlie");
+        assert_eq!(graph.count_of_class(0), 1);
+        assert_eq!(graph.count_of_class(1), 0);
+        assert!(graph.connections_of_class(0)[0].points_away_from(&alice));
+
+        graph.kill_connections(|connection| connection == &Relation::Blocks);
+        assert_eq!(graph.count_of_class(2), 0);
+
+        graph.clean();
+        assert_eq!(graph.count_of_class(0), 1);
+
+        // Queries only ever look inside the requested bucket
+        let buckets = &graph.classes.as_ref().unwrap().buckets;
+        assert_eq!(buckets[&0].len(), 1);
+        assert!(buckets[&1].is_empty());
+        assert!(buckets[&2].is_empty());
+        assert_eq!(graph.count_of_class(7), 0);
+
+        // Connections of other classes are never touched: reading one that is
+        // mutably borrowed would panic
+        let friends =
+            graph.new_undirected_connection([alice.clone(), bob.clone()], Relation::Friends);
+        let blocks = graph.new_directed_connection(bob.clone(), Relation::Blocks, alice.clone());
+        {
+            let _friends = friends.inner.borrow_mut();
+            let _blocks = blocks.inner.borrow_mut();
+            assert_eq!(graph.count_of_class(0), 1);
+            assert_eq!(graph.connections_of_class(0).len(), 1);
+        }
+        assert_eq!(graph.count_of_class(1), 1);
+        assert_eq!(graph.count_of_class(2), 1);
+    }
+
+    #[test]
+    fn find_cached_skips_rescans() {
+        use core::cell::Cell;
+
+        let mut graph = fixtures::knowledge_graph().0;
+        let scanned = Cell::new(0);
+        let find_alice = |thing: &Thing<&str, &str>| {
+            scanned.set(scanned.get() + 1);
+            thing == &"Alice"
+        };
+
+        let alice = graph.find_cached("alice", find_alice).unwrap();
+        let first_scan = scanned.get();
+        assert!(first_scan > 0);
+
+        let again = graph.find_cached("alice", find_alice).unwrap();
+        assert!(again.ptr_eq(&alice));
+        assert_eq!(scanned.get(), first_scan);
+
+        // A structural change invalidates the cache
+        graph.new_thing("Bob");
+        graph.find_cached("alice", find_alice).unwrap();
+        assert_eq!(scanned.get(), first_scan * 2);
+
+        // The cache stays bounded, evicting the least recently used key
+        graph.set_lookup_cache_capacity(2);
+        graph
+            .find_cached("apple", |thing| thing == &"Apple")
+            .unwrap();
+        graph.find_cached("pear", |thing| thing == &"Pear").unwrap();
+        assert_eq!(graph.lookup_cache.entries.len(), 2);
+        graph.find_cached("alice", find_alice).unwrap();
+        assert_eq!(scanned.get(), first_scan * 3);
+
+        // Failed lookups are not remembered
+        assert!(graph.find_cached("nobody", |_| false).is_none());
+        assert_eq!(graph.lookup_cache.entries.len(), 2);
+    }
+
+    #[test]
+    fn symmetrize_and_drop_reverse_duplicates() {
+        let mut graph = Things::<&str, &str>::new();
+        let live_connections = |graph: &Things<&str, &str>| graph.find_connections(|_| true).len();
+
+        // Two distinct things with the same data
+        let first = graph.new_thing("twin");
+        let second = graph.new_thing("twin");
+        let other = graph.new_thing("other");
+
+        graph.new_directed_connection(first.clone(), "parent_of", second.clone());
+        graph.new_directed_connection(second.clone(), "parent_of", other.clone());
+        graph.new_directed_connection(second.clone(), "parent_of", other.clone());
+        graph.new_directed_connection(other.clone(), "knows", other.clone());
+        graph.new_undirected_connection([first.clone(), other.clone()], "friends");
+
+        // Parallel connections share one reverse, the self-loop is skipped
+        assert_eq!(graph.symmetrize(|_| "child_of"), 2);
+        assert_eq!(live_connections(&graph), 7);
+        assert_eq!(graph.symmetrize(|_| "child_of"), 0);
+
+        let reverse = second
+            .do_for_a_connection(|conn| {
+                if conn == &"child_of" && conn.points_away_from(&second) {
+                    Do::Take(conn.clone())
+                } else {
+                    Do::Nothing
+                }
+            })
+            .unwrap();
+        assert!(reverse.directed_ends().unwrap().1.ptr_eq(&first));
+
+        let dropped = graph
+            .drop_reverse_duplicates(|older, newer| *older == "parent_of" && *newer == "child_of");
+        assert_eq!(dropped, 2);
+        assert_eq!(live_connections(&graph), 5);
+    }
+
+    #[test]
+    fn neighbors_with_edges_rebuild_adjacency() {
+        let mut graph = Things::<String, String>::new();
+
+        let alice = graph.new_thing("Alice".to_string());
+        let bob = graph.new_thing("Bob".to_string());
+        let charlie = graph.new_thing("Charlie".to_string());
+
+        graph.new_directed_connection(alice.clone(), "follows".to_string(), bob.clone());
+        graph.new_directed_connection(charlie.clone(), "follows".to_string(), alice.clone());
+        graph.new_undirected_connection([alice.clone(), bob.clone()], "friends".to_string());
+        let dead = graph
+            .new_undirected_connection([alice.clone(), charlie.clone()], "blocked".to_string());
+        graph.kill_connections(|conn| conn.ptr_eq(&dead));
+
+        let adjacency: Vec<String> = alice
+            .neighbors_with_edges()
+            .iter()
+            .map(|(connection, neighbor)| {
+                format!(
+                    "{} {}",
+                    connection.access(|data| data.clone()),
+                    neighbor.access(|data| data.clone())
+                )
+            })
+            .collect();
+
+        // Parallel connections to Bob each show up, dead ones don't
+        assert_eq!(adjacency, ["follows Bob", "follows Charlie", "friends Bob"]);
+
+        // A self-loop is listed once, but counted at both ends
+        graph.new_undirected_connection([bob.clone(), bob.clone()], "self".to_string());
+        let pairs = bob.neighbors_with_edges();
+        assert_eq!(pairs.len(), 3);
+        assert_eq!(
+            pairs.iter().filter(|(_, other)| other.ptr_eq(&bob)).count(),
+            1
+        );
+        assert_eq!(graph.undirected_view().degree(&bob), 4);
+    }
+
+    #[test]
+    fn shortest_path_avoiding_takes_detour() {
+        let mut roads = Things::<&str, &str>::new();
+
+        let home = roads.new_thing("home");
+        let bridge_end = roads.new_thing("bridge_end");
+        let village = roads.new_thing("village");
+        let town = roads.new_thing("town");
+        let work = roads.new_thing("work");
+
+        let toll = roads.new_undirected_connection([home.clone(), work.clone()], "toll");
+        roads.new_undirected_connection([home.clone(), village.clone()], "road");
+        roads.new_undirected_connection([village.clone(), town.clone()], "road");
+        roads.new_undirected_connection([town.clone(), work.clone()], "road");
+        let bridge = roads.new_undirected_connection([work.clone(), bridge_end.clone()], "road");
+
+        let direct = roads.shortest_path(&home, &work, |_| true).unwrap();
+        assert_eq!(direct.len(), 2);
+
+        let no_toll = [toll.clone()];
+        let detour = roads
+            .shortest_path_avoiding(&home, &work, &[], &no_toll, |_| true)
+            .unwrap();
+        let stops: Vec<_> = roads
+            .shortest_path(&home, &work, |conn| !conn.ptr_eq(&toll))
+            .unwrap()
+            .iter()
+            .map(|thing| thing.access(|data| *data))
+            .collect();
+        assert_eq!(detour.len(), 3);
+        assert_eq!(stops, ["home", "village", "town", "work"]);
+
+        // Avoiding the town as well leaves no way around the toll road
+        assert!(
+            roads
+                .shortest_path_avoiding(&home, &work, &[town], &no_toll, |_| true)
+                .is_none()
+        );
+        assert!(
+            roads
+                .shortest_path_avoiding_if(
+                    &home,
+                    &work,
+                    |thing| thing == &"village",
+                    |conn| { conn == &"road" }
+                )
+                .is_none()
+        );
+
+        // The bridge is the only way to its far end
+        assert!(
+            roads
+                .shortest_path_avoiding(&home, &bridge_end, &[], &[bridge], |_| true)
+                .is_none()
+        );
+
+        // Avoiding an endpoint yields nothing
+        assert!(
+            roads
+                .shortest_path_avoiding(&home, &work, core::slice::from_ref(&home), &[], |_| true)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn split_into_components() {
+        let mut graph = Things::<&str, &str>::new();
+
+        let alice = graph.new_thing("Alice");
+        let bob = graph.new_thing("Bob");
+        let apple = graph.new_thing("Apple");
+        let charlie = graph.new_thing("Charlie");
+        let fruit = graph.new_thing("Fruit");
+        let ghost = graph.new_thing("Ghost");
+
+        graph.new_undirected_connection([alice.clone(), bob.clone()], "friends");
+        graph.new_directed_connection(charlie.clone(), "follows", bob.clone());
+        graph.new_directed_connection(apple.clone(), "is", fruit.clone());
+        graph.new_directed_connection(ghost.clone(), "haunts", apple.clone());
+        graph.kill_things(|thing| thing == &"Ghost");
+
+        let mut components = graph.split_components();
+        assert_eq!(components.len(), 2);
+
+        let count = |graph: &Things<&str, &str>| {
+            (
+                graph.do_for_all_things(|_| Do::Take(())).len(),
+                graph.do_for_all_connections(|_| Do::Take(())).len(),
+            )
+        };
+        assert_eq!(count(&components[0]), (3, 2));
+        assert_eq!(count(&components[1]), (2, 1));
+        assert_eq!(components[1].dead_percentage().unwrap(), 0);
+
+        // Handles stay wired up within each component
+        let path = components[0].shortest_path(&charlie, &alice, |_| true);
+        assert_eq!(path.map(|path| path.len()), Some(3));
+    }
+
+    #[test]
+    fn payloads_without_partial_eq() {
+        trait Widget {
+            fn name(&self) -> &str;
+        }
+
+        struct Button;
+        impl Widget for Button {
+            fn name(&self) -> &str {
+                "button"
+            }
+        }
+
+        struct Panel(&'static str);
+        impl Widget for Panel {
+            fn name(&self) -> &str {
+                self.0
+            }
+        }
+
+        let mut gui = Things::<Box<dyn Widget>, Box<dyn Fn() -> u32>>::new();
+
+        let window = gui.new_thing(Box::new(Panel("window")));
+        let sidebar = gui.new_thing(Box::new(Panel("sidebar")));
+        let button = gui.new_thing(Box::new(Button));
+
+        let contains = gui.new_directed_connection(window.clone(), Box::new(|| 1), sidebar.clone());
+        gui.new_directed_connection(sidebar.clone(), Box::new(|| 2), button.clone());
+
+        // Traversal works by identity
+        let path = gui.shortest_path(&window, &button, |_| true).unwrap();
+        let names: Vec<_> = path
+            .iter()
+            .map(|thing| thing.access(|widget| String::from(widget.name())))
+            .collect();
+        assert_eq!(names, ["window", "sidebar", "button"]);
+
+        assert_eq!(
+            contains.get_direction_relative_to(&window),
+            Ok(Direction::AwayFrom)
+        );
+        assert!(contains.get_other_thing(&sidebar).unwrap().ptr_eq(&window));
+        assert_eq!(contains.access(|data| data()), 1);
+
+        let labels = sidebar.do_for_all_connections(|conn| Do::Take(conn.access(|data| data())));
+        assert_eq!(labels, [1, 2]);
+
+        // Killing and cleaning need no data comparisons either
+        gui.kill_things(|thing| thing.access(|widget| widget.name() == "sidebar"));
+        gui.clean();
+        assert_eq!(gui.do_for_all_things(|_| Do::Take(())).len(), 2);
+        assert_eq!(gui.do_for_all_connections(|_| Do::Take(())).len(), 0);
+        assert!(window.neighbors_with_edges().is_empty());
+    }
+
+    #[test]
+    fn directed_edge_respects_orientation() {
+        let mut project = Things::<&str, &str>::new();
+
+        let a = project.new_thing("A");
+        let b = project.new_thing("B");
+        let c = project.new_thing("C");
+        project.new_directed_connection(a.clone(), "depends_on", b.clone());
+        project.new_undirected_connection([b.clone(), c.clone()], "related");
+
+        assert!(project.has_directed_edge(&a, &b));
+        assert!(!project.has_directed_edge(&b, &a));
+        assert!(!project.has_directed_edge(&b, &c));
+        assert!(!project.has_directed_edge(&c, &b));
+
+        project.kill_connections(|conn| conn == &"depends_on");
+        assert!(!project.has_directed_edge(&a, &b));
+    }
+
+    #[test]
+    fn sorting_by_key_is_stable_and_extracts_once() {
+        use core::cell::Cell;
+
+        let mut report = Things::<(&str, u32), u32>::new();
+        let bread = report.new_thing(("bakery", 3));
+        let milk = report.new_thing(("dairy", 1));
+        let cake = report.new_thing(("bakery", 1));
+        let cheese = report.new_thing(("dairy", 1));
+        report.new_thing(("expired", 0));
+        report.kill_things(|thing| thing.access(|data| data.0 == "expired"));
+
+        let calls = Cell::new(0);
+        let sorted = report.sorted_things_by_key(|(category, rank)| {
+            calls.set(calls.get() + 1);
+            (*category, *rank)
+        });
+        assert_eq!(calls.get(), 4);
+        let expected = [&cake, &bread, &milk, &cheese];
+        assert!(sorted.iter().zip(expected).all(|(a, b)| a.ptr_eq(b)));
+
+        // Equal keys keep creation order
+        let by_rank = report.sorted_things_by_key(|(_, rank)| *rank);
+        let expected = [&milk, &cake, &cheese, &bread];
+        assert!(by_rank.iter().zip(expected).all(|(a, b)| a.ptr_eq(b)));
+
+        let first = report.new_directed_connection(bread.clone(), 2, milk.clone());
+        let second = report.new_directed_connection(bread.clone(), 1, cake.clone());
+        let third = report.new_directed_connection(cheese.clone(), 2, bread.clone());
+
+        calls.set(0);
+        let sorted = report.sorted_connections_by_key(|weight| {
+            calls.set(calls.get() + 1);
+            *weight
+        });
+        assert_eq!(calls.get(), 3);
+        let expected = [&second, &first, &third];
+        assert!(sorted.iter().zip(expected).all(|(a, b)| a.ptr_eq(b)));
+
+        let around_bread = bread.sorted_connections_by_key(|weight| core::cmp::Reverse(*weight));
+        let expected = [&first, &third, &second];
+        assert!(around_bread.iter().zip(expected).all(|(a, b)| a.ptr_eq(b)));
+
+        // A self-loop is listed and keyed once
+        let looped = report.new_undirected_connection([bread.clone(), bread.clone()], 0);
+        calls.set(0);
+        let around_bread = bread.sorted_connections_by_key(|weight| {
+            calls.set(calls.get() + 1);
+            *weight
+        });
+        assert_eq!(calls.get(), 4);
+        let expected = [&looped, &second, &first, &third];
+        assert_eq!(around_bread.len(), 4);
+        assert!(around_bread.iter().zip(expected).all(|(a, b)| a.ptr_eq(b)));
+    }
+
+    #[test]
+    fn bulk_mutation_skips_dead_items() {
+        let mut counters = Things::<u32, u32>::new();
+        let a = counters.new_thing(0);
+        let b = counters.new_thing(10);
+        let c = counters.new_thing(20);
+        let gone = counters.new_thing(100);
+        let ab = counters.new_undirected_connection([a.clone(), b.clone()], 1);
+        let cg = counters.new_directed_connection(c.clone(), 5, gone.clone());
+        counters.kill_things(|thing| *thing == 100);
+
+        let mut visited = 0;
+        counters.access_all_things_mut(|count| {
+            *count += 1;
+            visited += 1;
+        });
+        assert_eq!(visited, 3);
+        assert!(a == 1 && b == 11 && c == 21);
+        assert!(gone == 100);
+
+        counters.access_all_connections_mut(|weight| *weight *= 2);
+        assert!(ab == 2);
+        assert!(cg == 5);
+    }
+
+    #[cfg(feature = "float")]
+    #[test]
+    fn normalized_weights_sum_to_total() {
+        let mut walk = Things::<&str, (&str, f64)>::new();
+        let a = walk.new_thing("a");
+        let b = walk.new_thing("b");
+        let c = walk.new_thing("c");
+        walk.new_directed_connection(a.clone(), ("ab", 2.0), b.clone());
+        walk.new_directed_connection(b.clone(), ("bc", 5.0), c.clone());
+        walk.new_undirected_connection([a.clone(), c.clone()], ("ac", 3.0));
+        let stale = walk.new_directed_connection(c.clone(), ("ca", 90.0), a.clone());
+        walk.kill_connections(|connection| connection.access(|(label, _)| *label == "ca"));
+
+        let weights = |walk: &Things<&str, (&str, f64)>| {
+            walk.do_for_all_connections(|connection| {
+                if connection.is_alive() {
+                    Do::Take(connection.access(|(_, weight)| *weight))
+                } else {
+                    Do::Nothing
+                }
+            })
+        };
+
+        walk.normalize_weights(|(_, weight)| *weight, |(_, weight), value| *weight = value);
+        let sum: f64 = weights(&walk).iter().sum();
+        assert!((sum - 1.0).abs() < 1e-9);
+        assert!((weights(&walk)[1] - 0.5).abs() < 1e-9);
+        assert!(stale.access(|(_, weight)| *weight == 90.0));
+
+        walk.normalize_weights_to(
+            10.0,
+            |(_, weight)| *weight,
+            |(_, weight), value| *weight = value,
+        );
+        let sum: f64 = weights(&walk).iter().sum();
+        assert!((sum - 10.0).abs() < 1e-9);
+
+        // A zero-sum graph is left alone
+        let mut flat = Things::<(), f64>::new();
+        let x = flat.new_thing(());
+        let y = flat.new_thing(());
+        let edge = flat.new_undirected_connection([x, y], 0.0);
+        flat.normalize_weights(|weight| *weight, |weight, value| *weight = value);
+        assert!(edge == 0.0);
+    }
+
+    #[test]
+    fn reachable_sets_match_individual_queries() {
+        fn check(graph: &Things<u32, &'static str>, sources: &[Thing<u32, &'static str>]) {
+            let follow = |connection: &Connection<u32, &'static str>| connection != &"blocked";
+            let sets = graph.reachable_sets(sources, follow);
+            assert_eq!(sets.len(), sources.len());
+            let all = graph.do_for_all_things(|thing| Do::Take(thing.clone()));
+            for (source, set) in sources.iter().zip(&sets) {
+                for target in all.iter().filter(|target| !target.ptr_eq(source)) {
+                    let expected = target.is_alive() && graph.is_reachable(source, target, follow);
+                    let found = set.iter().filter(|thing| thing.ptr_eq(target)).count();
+                    assert_eq!(found, expected as usize);
+                }
+                assert!(!set.iter().any(|thing| thing.ptr_eq(source)));
+            }
+        }
+
+        // A DAG with shared structure takes the memoized path
+        let mut dag = Things::<u32, &str>::new();
+        let n: Vec<_> = (0..7).map(|i| dag.new_thing(i)).collect();
+        dag.new_directed_connection(n[0].clone(), "grants", n[2].clone());
+        dag.new_directed_connection(n[1].clone(), "grants", n[2].clone());
+        dag.new_directed_connection(n[2].clone(), "grants", n[3].clone());
+        dag.new_directed_connection(n[2].clone(), "grants", n[4].clone());
+        dag.new_directed_connection(n[3].clone(), "grants", n[4].clone());
+        dag.new_directed_connection(n[1].clone(), "blocked", n[5].clone());
+        dag.new_directed_connection(n[4].clone(), "grants", n[6].clone());
+        dag.kill_things(|thing| *thing == 6);
+        assert!(!dag.has_cycle(|_| true));
+        check(&dag, &n);
+
+        // A cycle and an undirected connection force the per-source fallback
+        let mut cyclic = Things::<u32, &str>::new();
+        let m: Vec<_> = (0..6).map(|i| cyclic.new_thing(i)).collect();
+        cyclic.new_directed_connection(m[0].clone(), "grants", m[1].clone());
+        cyclic.new_directed_connection(m[1].clone(), "grants", m[2].clone());
+        cyclic.new_directed_connection(m[2].clone(), "grants", m[0].clone());
+        cyclic.new_directed_connection(m[2].clone(), "grants", m[3].clone());
+        cyclic.new_undirected_connection([m[3].clone(), m[4].clone()], "grants");
+        cyclic.new_directed_connection(m[4].clone(), "blocked", m[5].clone());
+        assert!(cyclic.has_cycle(|_| true));
+        check(&cyclic, &m);
+
+        let sets = cyclic.reachable_sets(&[m[0].clone(), m[0].clone()], |_| true);
+        assert_eq!(sets[0].len(), 5);
+        assert_eq!(sets[1].len(), 5);
+    }
+
+    #[test]
+    fn weak_things_expire_after_cleaning() {
+        let mut graph = Things::<&str, &str>::new();
+        let hub = graph.new_thing("hub");
+        let leaf = graph.new_thing("leaf");
+        graph.new_undirected_connection([hub.clone(), leaf.clone()], "link");
+        graph.new_directed_connection(leaf.clone(), "self", leaf.clone());
+
+        let weak_leaf = leaf.downgrade();
+        let weak_hub = hub.downgrade();
+        assert!(weak_leaf.upgrade().unwrap().ptr_eq(&leaf));
+
+        graph.kill_things(|thing| *thing == "leaf");
+        // Dead but not yet cleaned
+        assert!(weak_leaf.upgrade().is_some_and(|thing| !thing.is_alive()));
+
+        graph.clean();
+        // Our own handle still keeps it in memory
+        assert!(weak_leaf.clone().upgrade().is_some());
+        drop(leaf);
+        assert!(weak_leaf.upgrade().is_none());
+
+        drop(hub);
+        assert!(weak_hub.upgrade().is_some_and(|thing| thing.is_alive()));
+    }
+
+    #[test]
+    fn public_enums_implement_common_traits() {
+        use core::fmt::Debug;
+        use core::hash::Hash;
+
+        fn value_type<X: Clone + Copy + PartialEq + Eq + Hash + Debug>() {}
+        fn owned_type<X: Clone + PartialEq + Eq + Hash + Debug>() {}
+        value_type::<Direction>();
+        value_type::<DuplicatePolicy>();
+        value_type::<GraphMode>();
+        value_type::<ConnectError>();
+        value_type::<LoopPolicy>();
+        value_type::<ParallelPolicy>();
+        value_type::<LimitReached>();
+        #[cfg(feature = "dead-access-audit")]
+        {
+            value_type::<AuditedItem>();
+            value_type::<DeadAccessEvent>();
+        }
+        value_type::<Centrality>();
+        value_type::<CycleError>();
+        value_type::<ConnectionId>();
+        value_type::<RecountReport>();
+        value_type::<HealthThresholds>();
+        value_type::<HealthReport>();
+        value_type::<Recommendation>();
+        value_type::<ExportItem<'static>>();
+        value_type::<AggregateResult>();
+        value_type::<Do<u32>>();
+        owned_type::<Do<String>>();
+        owned_type::<ImportAbort>();
+        owned_type::<AdjacencyError<String>>();
+
+        assert!(Do::Take(1).is_take());
+        assert!(!Do::<u8>::Nothing.is_take());
+        assert_eq!(Do::Take("x").into_option(), Some("x"));
+        assert_eq!(Do::<&str>::Nothing, Do::Nothing);
+
+        let mut seen = BTreeSet::new();
+        seen.insert(format!("{:?}", Direction::Towards));
+        seen.insert(format!("{:?}", Direction::AwayFrom));
+        assert_eq!(seen.len(), 2);
+    }
+
+    #[test]
+    fn roots_and_leaves_of_task_graph() {
+        let mut project = fixtures::task_dag().0;
+        let unlocks = |connection: &Connection<&str, &str>| connection == &"unlocks";
+
+        let roots = project.roots(unlocks);
+        let leaves = project.leaves(unlocks);
+        assert!(roots.len() == 1 && roots[0] == "Design");
+        assert!(leaves.len() == 1 && leaves[0] == "Deployment");
+        assert!(roots[0].is_root(unlocks) && !roots[0].is_leaf(unlocks));
+        assert!(leaves[0].is_leaf(unlocks) && !leaves[0].is_root(unlocks));
+
+        // Isolated things are both roots and leaves
+        let notes = project.new_thing("Notes");
+        assert!(
+            project
+                .roots(unlocks)
+                .iter()
+                .any(|thing| thing.ptr_eq(&notes))
+        );
+        assert!(
+            project
+                .leaves(unlocks)
+                .iter()
+                .any(|thing| thing.ptr_eq(&notes))
+        );
+        assert!(notes.is_root(unlocks) && notes.is_leaf(unlocks));
+
+        // Undirected connections lead both ways
+        let design = roots[0].clone();
+        project.new_undirected_connection([notes.clone(), design.clone()], "unlocks");
+        assert!(!notes.is_root(unlocks) && !notes.is_leaf(unlocks));
+        assert!(project.roots(unlocks).is_empty());
+
+        // Dead things and connections don't count
+        project.kill_things(|thing| *thing == "Notes");
+        assert!(design.is_root(unlocks));
+        assert_eq!(project.roots(unlocks).len(), 1);
+        assert_eq!(project.leaves(|_| false).len(), 5);
+    }
+
+    #[test]
+    fn relabel_connections_from_endpoints() {
+        let mut imported = Things::<(&str, &str), &str>::new();
+        let alice = imported.new_thing(("person", "alice"));
+        let bob = imported.new_thing(("person", "bob"));
+        let paper = imported.new_thing(("document", "paper"));
+        let wrote = imported.new_directed_connection(alice.clone(), "relates", paper.clone());
+        let cites = imported.new_directed_connection(paper.clone(), "relates", bob.clone());
+        let knows = imported.new_undirected_connection([bob.clone(), alice.clone()], "relates");
+        let gone = imported.new_directed_connection(bob.clone(), "relates", paper.clone());
+        imported.kill_connections(|connection| connection.ptr_eq(&gone));
+        imported.register_connection_class(|label| if *label == "relates" { 0 } else { 1 });
+        assert_eq!(imported.count_of_class(0), 3);
+
+        imported.relabel_connections(|from, data, to| {
+            let kinds = (from.access(|data| data.0), to.access(|data| data.0));
+            match kinds {
+                ("person", "document") => "authored",
+                ("person", "person") => {
+                    // Undirected endpoints come in creation order
+                    assert!(from == &("person", "bob"));
+                    "knows"
+                }
+                _ => *data,
+            }
+        });
+
+        assert!(wrote == "authored");
+        assert!(cites == "relates");
+        assert!(knows == "knows");
+        assert!(gone == "relates");
+        assert_eq!(imported.count_of_class(0), 1);
+        assert_eq!(imported.count_of_class(1), 2);
+    }
+
+    #[test]
+    fn config_toggles_change_behavior() {
+        let config = ThingsConfig::new()
+            .auto_clean_at(40)
+            .strict_liveness(true)
+            .reject_self_loops(true)
+            .unique_things(true)
+            .thing_capacity(32)
+            .connection_capacity(64);
+        let mut graph = Things::<&str, &str>::with_config(config);
+        assert_eq!(graph.config().get_auto_clean_at(), Some(40));
+        assert!(graph.config().get_strict_liveness());
+        assert!(graph.config().get_reject_self_loops());
+        assert!(graph.config().get_unique_things());
+        assert!(graph.things.capacity() >= 32);
+        assert!(graph.connections.capacity() >= 64);
+
+        // Unique things
+        let alice = graph.new_thing("alice");
+        assert!(graph.new_thing("alice").ptr_eq(&alice));
+        let bob = graph.new_thing("bob");
+        let carol = graph.new_thing("carol");
+        let dave = graph.new_thing("dave");
+        assert_eq!(graph.things.len(), 4);
+
+        graph.new_directed_connection(alice.clone(), "knows", bob.clone());
+        graph.new_directed_connection(carol.clone(), "knows", dave.clone());
+
+        // 1 of 6 items dead stays below the threshold
+        graph.kill_connections(|connection| connection.get_things()[0].ptr_eq(&alice));
+        assert_eq!(graph.connections.len(), 2);
+
+        // Killing carol brings it to 3 of 6, which the next addition cleans up
+        graph.kill_things(|thing| *thing == "carol");
+        assert_eq!(graph.dead_percentage(), Ok(50));
+        assert_eq!(graph.things.len(), 4);
+
+        // A dead thing's data no longer blocks a new one
+        let new_carol = graph.new_thing("carol");
+        assert!(!new_carol.ptr_eq(&carol));
+        assert_eq!(graph.dead_percentage(), Ok(0));
+        assert_eq!(graph.things.len(), 4);
+        assert_eq!(graph.connections.len(), 0);
+
+        // Loosening the rules at runtime
+        graph.set_config(graph.config().reject_self_loops(false).unique_things(false));
+        graph.new_directed_connection(bob.clone(), "likes", bob.clone());
+        assert!(!graph.new_thing("bob").ptr_eq(&bob));
+    }
+
+    #[test]
+    #[should_panic(expected = "strict liveness")]
+    fn strict_liveness_refuses_dead_endpoints() {
+        let mut graph = Things::<&str, ()>::with_config(ThingsConfig::new().strict_liveness(true));
+        let alive = graph.new_thing("alive");
+        let dead = graph.new_thing("dead");
+        graph.kill_things(|thing| *thing == "dead");
+        graph.new_undirected_connection([alive, dead], ());
+    }
+
+    #[test]
+    #[should_panic(expected = "self-loops")]
+    fn self_loops_can_be_rejected() {
+        let mut graph =
+            Things::<&str, ()>::with_config(ThingsConfig::new().reject_self_loops(true));
+        let alone = graph.new_thing("alone");
+        graph.new_directed_connection(alone.clone(), (), alone);
+    }
+
+    #[test]
+    fn outgoing_and_incoming_dependencies() {
+        let mut tasks = Things::<&str, &str>::new();
+        let deploy = tasks.new_thing("deploy");
+        let build = tasks.new_thing("build");
+        let test = tasks.new_thing("test");
+        let docs = tasks.new_thing("docs");
+        tasks.new_directed_connection(deploy.clone(), "depends_on", build.clone());
+        tasks.new_directed_connection(deploy.clone(), "depends_on", test.clone());
+        tasks.new_directed_connection(docs.clone(), "depends_on", deploy.clone());
+        tasks.new_undirected_connection([deploy.clone(), docs.clone()], "related");
+        let retry = tasks.new_directed_connection(deploy.clone(), "retries", deploy.clone());
+
+        let targets: Vec<_> = deploy
+            .outgoing()
+            .into_iter()
+            .filter(|(connection, _)| connection == &"depends_on")
+            .map(|(_, target)| target.access(|name| *name))
+            .collect();
+        assert_eq!(targets, ["build", "test"]);
+
+        let sources: Vec<_> = deploy
+            .incoming()
+            .into_iter()
+            .map(|(_, source)| source.access(|name| *name))
+            .collect();
+        assert_eq!(sources, ["docs", "deploy"]);
+        assert_eq!(deploy.outgoing().len(), 3);
+
+        tasks.kill_connections(|connection| connection.ptr_eq(&retry));
+        assert_eq!(deploy.outgoing().len(), 2);
+        assert_eq!(deploy.incoming().len(), 1);
+        assert!(build.outgoing().is_empty());
+    }
+
+    #[test]
+    fn bfs_tree_spans_reachable_things() {
+        let graph = fixtures::knowledge_graph().0;
+        let alice = graph.find_thing(|data| *data == "Alice").unwrap();
+
+        let tree = graph.bfs_tree(&alice);
+        let reachable = graph.reachable_sets(core::slice::from_ref(&alice), |_| true);
+        assert_eq!(tree.len(), reachable[0].len());
+        assert_eq!(tree.len(), 5);
+
+        // Fruit is reached through Apple first, so the edge from Pear is left out
+        let into_fruit: Vec<_> = tree
+            .iter()
+            .filter(|connection| {
+                connection
+                    .get_directed_towards()
+                    .is_some_and(|to| to == "Fruit")
+            })
+            .collect();
+        assert_eq!(into_fruit.len(), 1);
+        assert!(
+            into_fruit[0]
+                .get_directed_from()
+                .is_some_and(|from| from == "Apple")
+        );
+
+        let fruit = into_fruit[0].get_directed_towards().unwrap();
+        assert!(graph.bfs_tree(&fruit).is_empty());
+    }
+
+    #[test]
+    fn visitors_prune_and_log() {
+        struct Logger {
+            log: Vec<String>,
+            pruned: &'static str,
+        }
+
+        impl GraphVisitor<&'static str, ()> for Logger {
+            fn discover_thing(&mut self, thing: &Thing<&'static str, ()>, depth: usize) {
+                self.log
+                    .push(format!("discover {} {}", thing.access(|name| *name), depth));
+            }
+
+            fn finish_thing(&mut self, thing: &Thing<&'static str, ()>) {
+                self.log
+                    .push(format!("finish {}", thing.access(|name| *name)));
+            }
+
+            fn should_follow(
+                &mut self,
+                _: &Connection<&'static str, ()>,
+                next: &Thing<&'static str, ()>,
+            ) -> bool {
+                next != &self.pruned
+            }
+        }
+
+        //   root -> left -> left_leaf
+        //   root -> right -> right_leaf
+        let mut tree = Things::<&str, ()>::new();
+        let root = tree.new_thing("root");
+        let left = tree.new_thing("left");
+        let right = tree.new_thing("right");
+        tree.new_directed_connection(root.clone(), (), left.clone());
+        tree.new_directed_connection(root.clone(), (), right.clone());
+        let left_leaf = tree.new_thing("left_leaf");
+        tree.new_directed_connection(left.clone(), (), left_leaf.clone());
+        let right_leaf = tree.new_thing("right_leaf");
+        tree.new_directed_connection(right.clone(), (), right_leaf);
+
+        let mut bfs = Logger {
+            log: Vec::new(),
+            pruned: "right",
+        };
+        tree.visit_bfs(&root, &mut bfs);
+        assert_eq!(
+            bfs.log,
+            [
+                "discover root 0",
+                "discover left 1",
+                "finish root",
+                "discover left_leaf 2",
+                "finish left",
+                "finish left_leaf",
+            ]
+        );
+
+        let mut dfs = Logger {
+            log: Vec::new(),
+            pruned: "left",
+        };
+        tree.visit_dfs(&root, &mut dfs);
+        assert_eq!(
+            dfs.log,
+            [
+                "discover root 0",
+                "discover right 1",
+                "discover right_leaf 2",
+                "finish right_leaf",
+                "finish right",
+                "finish root",
+            ]
+        );
+
+        let mut collected = CollectingVisitor::new();
+        tree.visit_dfs(&root, &mut collected);
+        assert_eq!(collected.things().len(), 5);
+        assert!(collected.into_things()[2].ptr_eq(&left_leaf));
+
+        let mut distances = DistanceVisitor::new();
+        tree.visit_bfs(&left, &mut distances);
+        assert_eq!(distances.distances().len(), 2);
+        assert_eq!(distances.distance_to(&left_leaf), Some(1));
+        assert_eq!(distances.distance_to(&root), None);
+    }
+
+    #[test]
+    fn alive_variants_skip_dead_endpoints() {
+        let mut graph = Things::<&str, &str>::new();
+        let keeper = graph.new_thing("keeper");
+        let doomed = graph.new_thing("doomed");
+        let towards = graph.new_directed_connection(keeper.clone(), "to", doomed.clone());
+        let from = graph.new_directed_connection(doomed.clone(), "from", keeper.clone());
+        let both = graph.new_undirected_connection([keeper.clone(), doomed.clone()], "both");
+        assert!(towards.get_directed_towards_alive().is_some());
+        assert!(from.get_directed_from_alive().is_some());
+        assert!(both.get_other_thing_alive(&keeper).is_some());
+        assert_eq!(keeper.neighbors_alive().len(), 1);
+
+        graph.kill_things(|thing| *thing == "doomed");
+
+        // Raw accessors still hand out the dead endpoint until cleaning
+        assert!(
+            towards
+                .get_directed_towards()
+                .is_some_and(|thing| thing.ptr_eq(&doomed))
+        );
+        assert!(
+            from.get_directed_from()
+                .is_some_and(|thing| thing.ptr_eq(&doomed))
+        );
+        assert!(
+            both.get_other_thing(&keeper)
+                .is_ok_and(|thing| thing.ptr_eq(&doomed))
+        );
+
+        assert!(towards.get_directed_towards_alive().is_none());
+        assert!(from.get_directed_from_alive().is_none());
+        assert!(both.get_other_thing_alive(&keeper).is_none());
+        assert!(keeper.neighbors_alive().is_empty());
+        assert!(keeper.neighbors_with_edges().is_empty());
+
+        // The live side still resolves
+        assert!(
+            towards
+                .get_directed_from_alive()
+                .is_some_and(|thing| thing.ptr_eq(&keeper))
+        );
+        assert!(
+            both.get_other_thing_alive(&doomed)
+                .is_some_and(|thing| thing.ptr_eq(&keeper))
+        );
+    }
+
+    #[test]
+    fn contains_data_over_taxonomy() {
+        let mut graph = fixtures::knowledge_graph().0;
+        assert!(graph.contains_data(&"Fruit"));
+        assert!(graph.contains_data(&"Alice"));
+        assert!(!graph.contains_data(&"Banana"));
+        assert!(graph.contains_connection_data(&"plural of"));
+        assert!(!graph.contains_connection_data(&"hates"));
+
+        // Dead items don't count
+        graph.kill_things(|thing| *thing == "Alice");
+        assert!(!graph.contains_data(&"Alice"));
+        assert!(!graph.contains_connection_data(&"likes to eat"));
+        assert!(graph.contains_connection_data(&"is"));
+    }
+
+    #[cfg(feature = "float")]
+    #[test]
+    fn average_degree_of_small_graph() {
+        let mut graph = Things::<&str, &str>::new();
+        assert_eq!(graph.average_degree(), 0.0);
+
+        let a = graph.new_thing("a");
+        let b = graph.new_thing("b");
+        let c = graph.new_thing("c");
+        let d = graph.new_thing("d");
+        graph.new_undirected_connection([a.clone(), b.clone()], "ab");
+        graph.new_undirected_connection([b.clone(), c.clone()], "bc");
+        graph.new_directed_connection(c.clone(), "ca", a.clone());
+        graph.new_directed_connection(c, "cd", d);
+        assert_eq!(graph.average_degree(), 2.0);
+
+        // d and its connection are gone: 3 connections over 3 things
+        graph.kill_things(|thing| *thing == "d");
+        assert_eq!(graph.average_degree(), 2.0);
+
+        graph.kill_connections(|connection| connection == &"ab");
+        assert!((graph.average_degree() - 4.0 / 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn compiled_graph_matches_live_graph() {
+        let mut social = Things::<(u32, &str), &str>::new();
+        let names = ["alice", "bob", "carol", "dave", "erin", "frank"];
+        let people: Vec<_> = names
+            .iter()
+            .enumerate()
+            .map(|(id, name)| social.new_thing((100 - id as u32, *name)))
+            .collect();
+        social.new_undirected_connection([people[0].clone(), people[1].clone()], "friends");
+        social.new_directed_connection(people[1].clone(), "follows", people[2].clone());
+        social.new_directed_connection(people[2].clone(), "follows", people[3].clone());
+        social.new_directed_connection(people[3].clone(), "blocks", people[0].clone());
+        social.new_undirected_connection([people[3].clone(), people[4].clone()], "friends");
+        social.new_directed_connection(people[5].clone(), "follows", people[0].clone());
+        social.kill_things(|thing| thing.access(|(_, name)| *name == "frank"));
+
+        let compiled = social.compile_with_key(|(id, _)| *id);
+        assert_eq!(compiled.thing_count(), 5);
+        assert_eq!(compiled.connection_count(), 5);
+
+        let live: Vec<_> = people[..5].to_vec();
+        for (at, person) in live.iter().enumerate() {
+            assert!(person.access(|data| data == compiled.data(at)));
+            assert_eq!(compiled.find_by_key(&(100 - at as u32)), Some(at));
+
+            let reachable = social.reachable_sets(core::slice::from_ref(person), |_| true);
+            assert_eq!(compiled.bfs(at).len(), reachable[0].len() + 1);
+
+            for (target_at, target) in live.iter().enumerate() {
+                let expected =
+                    social.shortest_path(person, target, |connection| connection != &"blocks");
+                let found = compiled.shortest_path(at, target_at, |data| *data != "blocks");
+                assert_eq!(
+                    expected.map(|path| path.len()),
+                    found.map(|path| path.len())
+                );
+            }
+        }
+        assert_eq!(compiled.find_by_key(&1), None);
+        assert_eq!(compiled.out_degree(3), 2);
+        assert_eq!(compiled.in_degree(0), 2);
+        assert_eq!(compiled.incoming(2), &[(1, 1)]);
+
+        // Round trip
+        let rebuilt = compiled.decompile();
+        let recompiled = rebuilt.compile();
+        let original = social.compile();
+        assert_eq!(recompiled.thing_count(), 5);
+        assert_eq!(recompiled.connection_count(), original.connection_count());
+        for at in 0..recompiled.connection_count() {
+            assert_eq!(recompiled.connection_data(at), original.connection_data(at));
+            assert_eq!(recompiled.connection_ends(at), original.connection_ends(at));
+            assert_eq!(recompiled.is_directed(at), original.is_directed(at));
+        }
+    }
+
+    #[test]
+    fn sorted_things_iterate_alphabetically() {
+        let mut graph = fixtures::knowledge_graph().0;
+        graph.kill_things(|thing| *thing == "Pears");
+        graph.clean();
+        graph.sort_things_by(|a, b| a.cmp(b));
+
+        let names = graph.do_for_all_things(|thing| Do::Take(thing.access(|name| *name)));
+        assert_eq!(names, ["Alice", "Apple", "Apples", "Fruit", "Pear"]);
+
+        // Recency still follows creation order
+        assert!(graph.recent_things(1)[0] == "Fruit");
+
+        graph.sort_things_by(|a, b| b.len().cmp(&a.len()));
+        let names = graph.do_for_all_things(|thing| Do::Take(thing.access(|name| *name)));
+        assert_eq!(names, ["Apples", "Alice", "Apple", "Fruit", "Pear"]);
+    }
+
+    #[test]
+    fn weighted_picks_hit_bucket_boundaries() {
+        let mut influence = Things::<&str, u64>::new();
+        let source = influence.new_thing("source");
+        let a = influence.new_thing("a");
+        let b = influence.new_thing("b");
+        let c = influence.new_thing("c");
+        let z = influence.new_thing("z");
+        influence.new_directed_connection(source.clone(), 2, a.clone());
+        influence.new_directed_connection(source.clone(), 0, z.clone());
+        influence.new_undirected_connection([source.clone(), b.clone()], 3);
+        influence.new_directed_connection(c.clone(), 5, source.clone());
+
+        let weight = |connection: &Connection<&str, u64>| connection.access(|weight| *weight);
+        let pick = |roll: u64| {
+            let (_, neighbor) = source
+                .pick_neighbor_weighted(weight, |total| {
+                    assert_eq!(total, 5);
+                    roll
+                })
+                .unwrap();
+            neighbor.access(|name| *name)
+        };
+        // Buckets: a 0..2, z empty, b 2..5, and c can't be reached from source
+        assert_eq!(pick(0), "a");
+        assert_eq!(pick(1), "a");
+        assert_eq!(pick(2), "b");
+        assert_eq!(pick(4), "b");
+        assert_eq!(pick(5), "a");
+
+        // c steps to source, and its self-loop counts once: source 0..5, c 5..6
+        influence.new_directed_connection(c.clone(), 1, c.clone());
+        let from_c = |roll: u64| {
+            let (_, neighbor) = c
+                .pick_neighbor_weighted(weight, |total| {
+                    assert_eq!(total, 6);
+                    roll
+                })
+                .unwrap();
+            neighbor.access(|name| *name)
+        };
+        assert_eq!(from_c(4), "source");
+        assert_eq!(from_c(5), "c");
+
+        // Only outgoing directed connections: a 0..2, z empty
+        let outgoing = |roll: u64| {
+            let (_, neighbor) = source
+                .pick_outgoing_weighted(weight, |total| {
+                    assert_eq!(total, 2);
+                    roll
+                })
+                .unwrap();
+            neighbor.access(|name| *name)
+        };
+        assert_eq!(outgoing(0), "a");
+        assert_eq!(outgoing(1), "a");
+
+        // No weight and no connections both give nothing
+        assert!(z.pick_neighbor_weighted(weight, |_| 0).is_none());
+        assert!(a.pick_outgoing_weighted(weight, |_| 0).is_none());
+        influence.kill_things(|thing| *thing == "a");
+        assert!(source.pick_outgoing_weighted(weight, |_| 0).is_none());
+    }
+
+    #[test]
+    fn is_connected_through_uses_identity() {
+        let mut graph = Things::<&str, &str>::new();
+        let hub = graph.new_thing("hub");
+        let left = graph.new_thing("left");
+        let right = graph.new_thing("right");
+        let to_left = graph.new_directed_connection(hub.clone(), "same", left.clone());
+        let to_right = graph.new_directed_connection(hub.clone(), "same", right.clone());
+
+        assert!(hub.is_connected_through(&to_left));
+        assert!(hub.is_connected_through(&to_right));
+        assert!(left.is_connected_through(&to_left));
+        // Equal data, but not one of left's connections
+        assert!(to_left == to_right);
+        assert!(!left.is_connected_through(&to_right));
+
+        let foreign = Connection::new_directed(hub.clone(), "same", left.clone());
+        assert!(!hub.is_connected_through(&foreign));
+    }
+
+    #[test]
+    fn maintain_converges_within_budget() {
+        let mut world = Things::<u32, u32>::new();
+        let entities: Vec<_> = (0..12).map(|id| world.new_thing(id)).collect();
+        for pair in entities.windows(2) {
+            world.new_undirected_connection([pair[0].clone(), pair[1].clone()], 0);
+        }
+        let survivor = entities[1].clone();
+        world.kill_things(|thing| thing.access(|id| id % 3 == 0));
+        world.kill_connections(|connection| connection.get_things()[0] == 7);
+        let dead = world
+            .things
+            .iter()
+            .filter(|thing| !thing.is_alive())
+            .count()
+            + world
+                .connections
+                .iter()
+                .filter(|connection| !connection.is_alive())
+                .count();
+
+        let budget = MaintenanceBudget { max_items: 3 };
+        let mut removed = 0;
+        let mut frames = 0;
+        let mut last_pressure = world.dead_percentage().ok();
+        loop {
+            let report = world.maintain(budget);
+            frames += 1;
+            assert!(report.removed <= budget.max_items);
+            assert_eq!(report.pressure_before, last_pressure);
+            assert!(report.pressure_after <= report.pressure_before);
+            last_pressure = report.pressure_after;
+            removed += report.removed;
+            if !report.more_work {
+                break;
+            }
+        }
+
+        assert_eq!(removed, dead);
+        assert_eq!(frames, dead.div_ceil(3));
+        assert_eq!(world.dead_percentage(), Ok(0));
+        assert_eq!(world.things.len(), 8);
+        assert!(
+            world
+                .connections
+                .iter()
+                .all(|connection| connection.is_alive())
+        );
+        assert_eq!(survivor.neighbors_with_edges().len(), 1);
+        assert_eq!(survivor.inner.borrow().connections.len(), 1);
+
+        let idle = world.maintain(budget);
+        assert_eq!(idle.removed, 0);
+        assert!(!idle.more_work);
+    }
+
+    #[test]
+    fn simple_mode_rejects_parallel_edges_and_self_loops() {
+        let mut graph =
+            Things::<&str, &str>::with_config(ThingsConfig::new().mode(GraphMode::Simple));
+        assert_eq!(graph.config().get_mode(), GraphMode::Simple);
+        let a = graph.new_thing("a");
+        let b = graph.new_thing("b");
+
+        let ab = graph
+            .try_new_directed_connection(a.clone(), "ab", b.clone())
+            .ok()
+            .unwrap();
+        assert_eq!(
+            graph
+                .try_new_directed_connection(a.clone(), "ab again", b.clone())
+                .err(),
+            Some(ConnectError::ParallelEdge)
+        );
+        assert_eq!(
+            graph
+                .try_new_directed_connection(a.clone(), "loop", a.clone())
+                .err(),
+            Some(ConnectError::SelfLoop)
+        );
+        assert_eq!(
+            graph
+                .try_new_undirected_connection([b.clone(), b.clone()], "loop")
+                .err(),
+            Some(ConnectError::SelfLoop)
+        );
+        assert!(
+            graph
+                .try_new_undirected_connection([a.clone(), b.clone()], "both")
+                .is_ok()
+        );
+        assert_eq!(
+            graph
+                .try_new_undirected_connection([b.clone(), a.clone()], "both again")
+                .err(),
+            Some(ConnectError::ParallelEdge)
+        );
+
+        // Once the existing edge is dead, it no longer blocks a new one
+        graph.kill_connections(|connection| connection.ptr_eq(&ab));
+        assert!(
+            graph
+                .try_new_directed_connection(a.clone(), "ab", b.clone())
+                .is_ok()
+        );
+        assert!(graph.violations_of_simple().is_empty());
+
+        let mut strict = Things::<&str, ()>::with_config(ThingsConfig::new().strict_liveness(true));
+        let alive = strict.new_thing("alive");
+        let dead = strict.new_thing("dead");
+        strict.kill_things(|thing| *thing == "dead");
+        assert_eq!(
+            strict.try_new_directed_connection(alive, (), dead).err(),
+            Some(ConnectError::DeadEndpoint)
+        );
+    }
+
+    #[test]
+    fn violations_of_simple_audits_multigraph() {
+        let mut graph = Things::<&str, &str>::new();
+        let a = graph.new_thing("a");
+        let b = graph.new_thing("b");
+        graph.new_directed_connection(a.clone(), "ab", b.clone());
+        graph.new_directed_connection(b.clone(), "ba", a.clone());
+        graph.new_directed_connection(a.clone(), "ab twin", b.clone());
+        graph.new_undirected_connection([a.clone(), b.clone()], "both");
+        graph.new_directed_connection(b.clone(), "loop", b.clone());
+        let gone = graph.new_undirected_connection([b.clone(), a.clone()], "both twin");
+        graph.kill_connections(|connection| connection.ptr_eq(&gone));
+
+        let labels: Vec<_> = graph
+            .violations_of_simple()
+            .iter()
+            .map(|connection| connection.access(|label| *label))
+            .collect();
+        assert_eq!(labels, ["ab twin", "loop"]);
+
+        graph.kill_connections(|connection| connection == &"ab twin" || connection == &"loop");
+        assert!(graph.violations_of_simple().is_empty());
+        graph.set_config(ThingsConfig::new().mode(GraphMode::Simple));
+        assert!(graph.try_new_directed_connection(a, "ab twin", b).is_err());
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "simple graph mode")]
+    fn simple_mode_checks_infallible_constructors_in_debug() {
+        let mut graph =
+            Things::<&str, ()>::with_config(ThingsConfig::new().mode(GraphMode::Simple));
+        let a = graph.new_thing("a");
+        let b = graph.new_thing("b");
+        graph.new_undirected_connection([a.clone(), b.clone()], ());
+        graph.new_undirected_connection([b, a], ());
+    }
+
+    #[test]
+    fn path_edges_follow_route() {
+        let graph = fixtures::knowledge_graph().0;
+        let find = |name: &'static str| graph.find_thing(|data| *data == name).unwrap();
+        let (alice, fruit) = (find("Alice"), find("Fruit"));
+
+        let path = graph.shortest_path(&alice, &fruit, |_| true).unwrap();
+        let labels: Vec<_> = graph
+            .path_edges(&path)
+            .unwrap()
+            .iter()
+            .map(|connection| connection.access(|label| *label))
+            .collect();
+        assert_eq!(labels, ["likes to eat", "plural of", "is"]);
+
+        // Against the direction of the connections there is no route
+        let backwards: Vec<_> = path.iter().rev().cloned().collect();
+        assert!(graph.path_edges(&backwards).is_none());
+        assert!(graph.path_edges(&[alice.clone(), fruit]).is_none());
+        assert!(
+            graph
+                .path_edges(&[alice])
+                .is_some_and(|edges| edges.is_empty())
+        );
+    }
+
+    #[test]
+    fn data_lookups_with_duplicates() {
+        let mut graph = Things::<&str, &str>::new();
+        let first = graph.new_thing("twin");
+        let second = graph.new_thing("twin");
+        let other = graph.new_thing("other");
+        let older = graph.new_directed_connection(first.clone(), "link", other.clone());
+        let newer = graph.new_directed_connection(second.clone(), "link", other.clone());
+
+        // The first live match wins, even though the data is shared
+        assert!(graph.find_thing_equal(&"twin").unwrap().ptr_eq(&first));
+        assert!(graph.find_connection_equal(&"link").unwrap().ptr_eq(&older));
+        assert!(graph.contains_data(&"twin"));
+
+        graph.kill_things(|thing| thing.ptr_eq(&first));
+        assert!(graph.find_thing_equal(&"twin").unwrap().ptr_eq(&second));
+        assert!(graph.find_connection_equal(&"link").unwrap().ptr_eq(&newer));
+
+        graph.kill_things(|thing| thing.ptr_eq(&second));
+        assert!(graph.find_thing_equal(&"twin").is_none());
+        assert!(graph.find_connection_equal(&"link").is_none());
+        assert!(!graph.contains_data(&"twin"));
+        assert!(!graph.contains_connection_data(&"link"));
+    }
+
+    #[test]
+    fn structural_warnings_flag_anomalies() {
+        let mut imported = Things::<&str, &str>::new();
+        let a = imported.new_thing("a");
+        let b = imported.new_thing("b");
+        let lonely = imported.new_thing("lonely");
+        let original = imported.new_undirected_connection([a.clone(), b.clone()], "knows");
+        let duplicate = imported.new_undirected_connection([b.clone(), a.clone()], "knows");
+        // Different data or direction is not a duplicate
+        imported.new_undirected_connection([a.clone(), b.clone()], "likes");
+        imported.new_directed_connection(a.clone(), "knows", b.clone());
+        let self_loop = imported.new_directed_connection(b.clone(), "mentions", b.clone());
+
+        let warnings = imported.structural_warnings();
+        assert_eq!(warnings.len(), 3);
+        assert!(
+            matches!(&warnings[0], StructuralWarning::SelfLoop(connection) if connection.ptr_eq(&self_loop))
+        );
+        assert!(matches!(
+            &warnings[1],
+            StructuralWarning::ParallelEdge { original: first, duplicate: second }
+                if first.ptr_eq(&original) && second.ptr_eq(&duplicate)
+        ));
+        assert!(
+            matches!(&warnings[2].clone(), StructuralWarning::IsolatedThing(thing) if thing.ptr_eq(&lonely))
+        );
+
+        imported.kill_things(|thing| *thing == "lonely");
+        imported.kill_connections(|connection| {
+            connection.ptr_eq(&duplicate) || connection.ptr_eq(&self_loop)
+        });
+        assert!(imported.structural_warnings().is_empty());
+    }
+
+    #[test]
+    fn split_connection_keeps_adjacency_consistent() {
+        let mut graph = Things::<&str, &str>::new();
+        let a = graph.new_thing("a");
+        let b = graph.new_thing("b");
+        let c = graph.new_thing("c");
+        let directed = graph.new_directed_connection(a.clone(), "ab", b.clone());
+        let undirected = graph.new_undirected_connection([b.clone(), c.clone()], "bc");
+
+        let (ab_middle, to_middle, from_middle) = graph
+            .split_connection(&directed, "ab mid", "a-mid", "mid-b")
+            .unwrap();
+        assert!(!directed.is_alive());
+        assert!(to_middle.get_directed_from().unwrap().ptr_eq(&a));
+        assert!(to_middle.get_directed_towards().unwrap().ptr_eq(&ab_middle));
+        assert!(from_middle.get_directed_from().unwrap().ptr_eq(&ab_middle));
+        assert!(from_middle.get_directed_towards().unwrap().ptr_eq(&b));
+        assert_eq!(a.outgoing().len(), 1);
+        assert_eq!(ab_middle.incoming().len(), 1);
+        assert_eq!(ab_middle.outgoing().len(), 1);
+        assert_eq!(b.incoming().len(), 1);
+        assert!(!graph.has_directed_edge(&a, &b));
+
+        let (bc_middle, near, far) = graph
+            .split_connection(&undirected, "bc mid", "b-mid", "mid-c")
+            .unwrap();
+        assert!(!undirected.is_alive());
+        assert!(near.is_undirected() && far.is_undirected());
+        assert!(near.get_things()[0].ptr_eq(&b) && far.get_things()[1].ptr_eq(&c));
+        assert_eq!(b.neighbors_alive().len(), 2);
+        assert_eq!(bc_middle.neighbors_alive().len(), 2);
+        assert_eq!(c.neighbors_alive().len(), 1);
+        assert!(c.neighbors_alive()[0].ptr_eq(&bc_middle));
+
+        assert_eq!(graph.dead_percentage(), Ok(2 * 100 / 11));
+        graph.clean();
+        assert_eq!(b.inner.borrow().connections.len(), 2);
+        assert_eq!(graph.connections.len(), 4);
+        test_util::assert_graph_sound(&graph);
+
+        // A dead connection can't be split again
+        assert!(
+            graph
+                .split_connection(&directed, "again", "a-again", "again-b")
+                .is_err()
+        );
+        assert_eq!(graph.things.len(), 5);
+    }
+
+    #[test]
+    fn split_connection_checks_limits_before_killing() {
+        extern crate std;
+        use std::panic::{AssertUnwindSafe, catch_unwind};
+
+        let mut graph = Things::<&str, &str>::with_limits(3, 2);
+        let a = graph.new_thing("a");
+        let b = graph.new_thing("b");
+        let edge = graph.new_directed_connection(a, "ab", b);
+
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            graph.split_connection(&edge, "mid", "a-mid", "mid-b")
+        }));
+        assert!(result.is_err());
+        assert!(edge.is_alive());
+        assert_eq!((graph.things.len(), graph.dead_amount), (2, 0));
+        test_util::assert_graph_sound(&graph);
+    }
+
+    #[test]
+    fn perspective_from_each_endpoint() {
+        let mut graph = Things::<&str, &str>::new();
+        let parent = graph.new_thing("parent");
+        let child = graph.new_thing("child");
+        let stranger = graph.new_thing("stranger");
+        let edge = graph.new_directed_connection(parent.clone(), "parent_of", child.clone());
+
+        let from_parent = edge.perspective(&parent).unwrap();
+        assert!(from_parent.near.ptr_eq(&parent) && from_parent.far.ptr_eq(&child));
+        assert!(from_parent.directed && from_parent.outgoing);
+
+        let from_child = edge.perspective(&child).unwrap().clone();
+        assert!(from_child.near.ptr_eq(&child) && from_child.far.ptr_eq(&parent));
+        assert!(from_child.directed && !from_child.outgoing);
+
+        assert!(edge.perspective(&stranger).is_none());
+
+        let friends = graph.new_undirected_connection([child.clone(), stranger.clone()], "friends");
+        for (near, far) in [(&child, &stranger), (&stranger, &child)] {
+            let view = friends.perspective(near).unwrap();
+            assert!(view.far.ptr_eq(far));
+            assert!(!view.directed && view.outgoing);
+        }
+    }
+
+    #[test]
+    fn contract_connection_merges_endpoints() {
+        // Contracting one side of a triangle leaves two things and one connection
+        let mut triangle = Things::<String, &str>::new();
+        let a = triangle.new_thing("a".to_string());
+        let b = triangle.new_thing("b".to_string());
+        let c = triangle.new_thing("c".to_string());
+        let ab = triangle.new_undirected_connection([a.clone(), b.clone()], "ab");
+        let bc = triangle.new_undirected_connection([b.clone(), c.clone()], "bc");
+        let ca = triangle.new_undirected_connection([c.clone(), a.clone()], "ca");
+
+        let survivor = triangle
+            .contract_connection(&ab, |data, other| data.push_str(&other))
+            .unwrap();
+        assert!(survivor.ptr_eq(&a));
+        assert!(survivor == "ab".to_string());
+        assert!(b == String::new());
+        assert!(!b.is_alive() && !ab.is_alive());
+        assert!(b.inner.borrow().connections.is_empty());
+        // Moved onto a, bc runs parallel to ca and is merged into it
+        assert!(!bc.is_alive() && ca.is_alive());
+        triangle.clean();
+        assert_eq!(triangle.things.len(), 2);
+        assert_eq!(triangle.connections.len(), 1);
+        assert_eq!((a.connection_count(), c.connection_count()), (1, 1));
+        test_util::assert_graph_sound(&triangle);
+
+        // Contracting again is an error
+        assert!(triangle.contract_connection(&ab, |_, _| {}).is_err());
+
+        // Parallel connections can be kept instead
+        let mut loose = Things::<&str, &str>::new();
+        let a = loose.new_thing("a");
+        let b = loose.new_thing("b");
+        let c = loose.new_thing("c");
+        let ab = loose.new_undirected_connection([a.clone(), b.clone()], "ab");
+        loose.new_undirected_connection([b, c.clone()], "bc");
+        loose.new_undirected_connection([c.clone(), a.clone()], "ca");
+        loose
+            .contract_connection_with(&ab, LoopPolicy::Drop, ParallelPolicy::Keep, |_, _| {})
+            .unwrap();
+        assert_eq!((a.connection_count(), c.connection_count()), (2, 2));
+
+        // A moved directed connection keeps its identity and direction, and
+        // opposite directions are not parallel
+        let mut chain = Things::<&str, &str>::new();
+        let a = chain.new_thing("a");
+        let b = chain.new_thing("b");
+        let c = chain.new_thing("c");
+        let ab = chain.new_undirected_connection([a.clone(), b.clone()], "ab");
+        let bc = chain.new_directed_connection(b.clone(), "bc", c.clone());
+        let ca = chain.new_directed_connection(c.clone(), "ca", a.clone());
+        chain.contract_connection(&ab, |_, _| {}).unwrap();
+        chain.clean();
+        assert_eq!(chain.connections.len(), 2);
+        assert!(bc.get_directed_from().unwrap().ptr_eq(&a));
+        assert!(chain.has_directed_edge(&a, &c));
+        assert!(chain.has_directed_edge(&c, &a));
+        assert!(ca.is_alive());
+        assert_eq!(a.outgoing().len(), 1);
+        assert_eq!(a.incoming().len(), 1);
+        assert_eq!(c.neighbors_with_edges().len(), 2);
+
+        // Parallel connections between the endpoints become self-loops
+        let mut doubled = Things::<u32, &str>::new();
+        let x = doubled.new_thing(1);
+        let y = doubled.new_thing(2);
+        let first = doubled.new_undirected_connection([x.clone(), y.clone()], "first");
+        let second = doubled.new_directed_connection(y.clone(), "second", x.clone());
+        let merged = doubled
+            .contract_connection(&first, |data, other| *data += other)
+            .unwrap();
+        assert!(merged == 3);
+        assert!(!second.is_alive());
+        assert!(merged.neighbors_with_edges().is_empty());
+        assert_eq!(doubled.dead_percentage(), Ok(75));
+
+        let mut kept = Things::<u32, &str>::new();
+        let x = kept.new_thing(1);
+        let y = kept.new_thing(2);
+        let first = kept.new_undirected_connection([x.clone(), y.clone()], "first");
+        let second = kept.new_directed_connection(y.clone(), "second", x.clone());
+        let merged = kept
+            .contract_connection_with(&first, LoopPolicy::Keep, ParallelPolicy::Keep, |_, _| {})
+            .unwrap();
+        assert!(second.is_alive());
+        assert!(kept.has_directed_edge(&merged, &merged));
+        assert_eq!(merged.outgoing().len(), 1);
+        kept.clean();
+        assert_eq!(merged.inner.borrow().connections.len(), 2);
+        test_util::assert_graph_sound(&chain);
+    }
+
+    #[test]
+    fn snapshot_reflects_current_edges() {
+        let mut graph = Things::<&str, &str>::new();
+        let hub = graph.new_thing("hub");
+        let parent = graph.new_thing("parent");
+        let child = graph.new_thing("child");
+        let peer = graph.new_thing("peer");
+        graph.new_directed_connection(parent.clone(), "parent_of", hub.clone());
+        graph.new_directed_connection(hub.clone(), "parent_of", child.clone());
+        graph.new_undirected_connection([hub.clone(), peer.clone()], "peer_of");
+
+        let snapshot = hub.snapshot();
+        assert_eq!(snapshot.data, "hub");
+        assert_eq!(
+            snapshot.edges,
+            [
+                ("parent_of", "parent", Some(Direction::Towards)),
+                ("parent_of", "child", Some(Direction::AwayFrom)),
+                ("peer_of", "peer", None),
+            ]
+        );
+
+        graph.kill_things(|thing| *thing == "child");
+        hub.access_mut(|data| *data = "renamed");
+        let later = hub.snapshot();
+        assert_eq!(later.data, "renamed");
+        assert_eq!(later.edges.len(), 2);
+        assert_ne!(later, snapshot);
+    }
+
+    /// Two triangles joined by a single bridge connection.
+    fn barbell_graph() -> Things<&'static str, &'static str> {
+        let mut graph = Things::new();
+        let names = ["a1", "a2", "a3", "b1", "b2", "b3"];
+        let things: Vec<_> = names.iter().map(|name| graph.new_thing(*name)).collect();
+        for (first, second, label) in [
+            (0, 1, "a"),
+            (1, 2, "a"),
+            (2, 0, "a"),
+            (3, 4, "b"),
+            (4, 5, "b"),
+            (5, 3, "b"),
+            (2, 3, "bridge"),
+        ] {
+            graph.new_undirected_connection([things[first].clone(), things[second].clone()], label);
+        }
+        graph
+    }
+
+    #[test]
+    fn edge_betweenness_ranks_the_bridge_first() {
+        let graph = barbell_graph();
+
+        let exact = graph.edge_betweenness_approx(usize::MAX, |_| 0, |_| true);
+        assert_eq!(exact.len(), 7);
+        assert!(exact[0].0 == "bridge");
+        // Three things on each side, counted from both ends
+        assert_eq!(exact[0].1, 18 * EDGE_BETWEENNESS_SCALE);
+        assert!(exact[1..].iter().all(|(_, score)| *score < exact[0].1));
+
+        let picker = || {
+            let mut next = 0;
+            move |count: usize| {
+                next = (next + 4) % count;
+                next
+            }
+        };
+        let sampled = graph.edge_betweenness_approx(3, picker(), |_| true);
+        let again = graph.edge_betweenness_approx(3, picker(), |_| true);
+        assert!(sampled[0].0 == "bridge");
+        assert!(
+            sampled
+                .iter()
+                .zip(&again)
+                .all(|(first, second)| first.0.ptr_eq(&second.0) && first.1 == second.1)
+        );
+
+        let blocked =
+            graph.edge_betweenness_approx(usize::MAX, |_| 0, |connection| *connection != "bridge");
+        assert!(
+            blocked
+                .iter()
+                .any(|(connection, score)| *connection == "bridge" && *score == 0)
+        );
+    }
+
+    #[test]
+    fn diff_reports_changes_against_a_modified_copy() {
+        let original = fixtures::task_dag().0;
+        assert!(original.diff(&original).is_empty());
+
+        let mut modified = fixtures::task_dag().0;
+        modified.kill_things(|task| *task == "Implement UI");
+        let design = modified.find_thing_equal(&"Design").unwrap();
+        let review = modified.new_thing("Review");
+        modified.new_directed_connection(design, "unlocks", review);
+        modified.clean();
+
+        let diff = original.diff(&modified);
+        assert_eq!(diff.added_things, ["Review"]);
+        assert_eq!(diff.removed_things, ["Implement UI"]);
+        assert_eq!(diff.added_connections.len(), 1);
+        assert_eq!(diff.added_connections[0].ends, ["Design", "Review"]);
+        assert_eq!(diff.removed_connections.len(), 2);
+        assert!(
+            diff.removed_connections
+                .iter()
+                .all(|connection| connection.ends.contains(&"Implement UI") && connection.directed)
+        );
+
+        let reverse = modified.diff(&original);
+        assert_eq!(reverse.added_things, diff.removed_things);
+        assert_eq!(reverse.removed_connections, diff.added_connections);
+    }
+
+    #[test]
+    fn kill_callbacks_fire_exactly_once() {
+        use core::cell::Cell;
+
+        // Counts how often its resource was released
+        let tracker = || {
+            let released = Rc::new(Cell::new(0));
+            let counter = released.clone();
+            (released, move |_: &&str| counter.set(counter.get() + 1))
+        };
+
+        let mut graph = Things::<&str, &str>::new();
+        let window = graph.new_thing("window");
+        let button = graph.new_thing("button");
+        let label = graph.new_thing("label");
+        let contains = graph.new_directed_connection(window.clone(), "contains", button.clone());
+        let bound = graph.new_undirected_connection([button.clone(), label.clone()], "bound");
+
+        let (window_released, release) = tracker();
+        window.on_kill(release);
+        let (contains_released, release) = tracker();
+        contains.on_kill(release);
+        let (bound_released, release) = tracker();
+        bound.on_kill(release);
+        let (label_released, release) = tracker();
+        label.on_kill(release);
+
+        // Direct kill of a connection leaves its things alone
+        graph.kill_connections(|connection| *connection == "bound");
+        assert_eq!(bound_released.get(), 1);
+        assert_eq!(label_released.get(), 0);
+
+        // Killing a thing cascades to its live connections
+        graph.kill_things(|thing| *thing == "window");
+        assert_eq!(window_released.get(), 1);
+        assert_eq!(contains_released.get(), 1);
+
+        // Repeated kills of dead items don't fire again
+        graph.kill_things(|thing| *thing == "window");
+        graph.kill_connections(|_| true);
+        assert_eq!(window_released.get(), 1);
+        assert_eq!(contains_released.get(), 1);
+        assert_eq!(bound_released.get(), 1);
+
+        // Registering on a dead thing fires immediately, once
+        let (late_released, release) = tracker();
+        window.on_kill(release);
+        assert_eq!(late_released.get(), 1);
+
+        graph.clean();
+        assert_eq!(label_released.get(), 0);
+        graph.kill_things(|_| true);
+        assert_eq!(label_released.get(), 1);
+    }
+
+    #[test]
+    fn deep_paths_do_not_overflow_the_stack() {
+        extern crate std;
+
+        // A small stack makes any recursive traversal overflow long before the end
+        let worker = std::thread::Builder::new()
+            .stack_size(128 * 1024)
+            .spawn(|| {
+                const LENGTH: usize = 10_000;
+                let mut graph = Things::<usize, ()>::new();
+                let mut previous = graph.new_thing(0);
+                let first = previous.clone();
+                for position in 1..LENGTH {
+                    let next = graph.new_thing(position);
+                    graph.new_directed_connection(previous, (), next.clone());
+                    previous = next;
+                }
+                let last = previous;
+
+                let mut visitor = CollectingVisitor::new();
+                graph.visit_dfs(&first, &mut visitor);
+                assert_eq!(visitor.things().len(), LENGTH);
+                assert!(visitor.things()[LENGTH - 1] == LENGTH - 1);
+
+                assert_eq!(graph.bfs_tree(&first).len(), LENGTH - 1);
+                assert!(!graph.has_cycle(|_| true));
+                assert!(graph.is_reachable(&first, &last, |_| true));
+                let path = graph.shortest_path(&first, &last, |_| true).unwrap();
+                assert_eq!(path.len(), LENGTH);
+                assert_eq!(graph.split_components().len(), 1);
+            })
+            .unwrap();
+        worker.join().unwrap();
+    }
+
+    #[test]
+    fn cheapest_path_reads_weight_annotations() {
+        let mut graph = Things::<&str, &str>::new();
+        let a = graph.new_thing("a");
+        let b = graph.new_thing("b");
+        let c = graph.new_thing("c");
+        let d = graph.new_thing("d");
+        let direct = graph.new_directed_connection(a.clone(), "direct", d.clone());
+        let ab = graph.new_directed_connection(a.clone(), "ab", b.clone());
+        let bc = graph.new_undirected_connection([b.clone(), c.clone()], "bc");
+        let cd = graph.new_directed_connection(c.clone(), "cd", d.clone());
+        ab.set_weight(2);
+        bc.set_weight(3);
+        cd.set_weight(1);
+
+        // The direct connection has no weight, so it is never taken
+        let (cost, path) = graph.cheapest_path(&a, &d, CostSource::Annotation).unwrap();
+        assert_eq!(cost, 6);
+        assert!(
+            path.iter()
+                .zip([&ab, &bc, &cd])
+                .all(|(step, expected)| step.ptr_eq(expected))
+        );
+        assert!(
+            graph
+                .cheapest_path(&d, &a, CostSource::Annotation)
+                .is_none()
+        );
+
+        direct.set_weight(5);
+        let (cost, path) = graph.cheapest_path(&a, &d, CostSource::Annotation).unwrap();
+        assert_eq!((cost, path.len()), (5, 1));
+
+        graph.clear_weights();
+        assert_eq!(ab.weight(), None);
+        assert!(
+            graph
+                .cheapest_path(&a, &d, CostSource::Annotation)
+                .is_none()
+        );
+        assert_eq!(
+            graph
+                .cheapest_path(&a, &a, CostSource::Annotation)
+                .unwrap()
+                .0,
+            0
+        );
+
+        let by_length =
+            |connection: &Connection<&str, &str>| connection.access(|data| data.len() as u64);
+        let (cost, path) = graph
+            .cheapest_path(&a, &d, CostSource::Closure(&by_length))
+            .unwrap();
+        assert_eq!((cost, path.len()), (6, 1));
+    }
+
+    #[test]
+    fn merge_parallel_connections_sums_weights() {
+        let mut graph = Things::<&str, u32>::new();
+        let alice = graph.new_thing("alice");
+        let bob = graph.new_thing("bob");
+        let carol = graph.new_thing("carol");
+        let first = graph.new_undirected_connection([alice.clone(), bob.clone()], 3);
+        graph.new_undirected_connection([bob.clone(), alice.clone()], 4);
+        graph.new_undirected_connection([alice.clone(), bob.clone()], 5);
+        graph.new_directed_connection(alice.clone(), 1, bob.clone());
+        graph.new_directed_connection(bob.clone(), 2, alice.clone());
+        graph.new_undirected_connection([alice.clone(), carol.clone()], 7);
+
+        // Undirected and opposite directed connections stay apart
+        assert_eq!(graph.merge_parallel_connections(true, |a, b| a + b), 2);
+        assert!(first == 12);
+        assert_eq!(graph.connections.len(), 4);
+        assert_eq!(alice.inner.borrow().connections.len(), 4);
+        assert_eq!(bob.inner.borrow().connections.len(), 3);
+        assert_eq!(graph.dead_amount, 0);
+
+        assert_eq!(graph.merge_parallel_connections(false, |a, b| a + b), 1);
+        assert_eq!(bob.inner.borrow().connections.len(), 2);
+        assert!(
+            bob.inner
+                .borrow()
+                .connections
+                .iter()
+                .any(|connection| *connection == 3)
+        );
+        assert_eq!(graph.merge_parallel_connections(false, |a, b| a + b), 0);
+        test_util::assert_graph_sound(&graph);
+    }
+
+    #[test]
+    fn merge_parallel_connections_writes_the_merged_data_once() {
+        let mut graph = Things::<&str, u32>::new();
+        let alice = graph.new_thing("Alice");
+        let bob = graph.new_thing("Bob");
+        graph.register_connection_class(|count| u16::from(*count >= 10));
+        let first = graph.new_directed_connection(alice.clone(), 4, bob.clone());
+        let second = graph.new_directed_connection(alice.clone(), 6, bob.clone());
+
+        // The survivor can be read while merging, and still holds its old data
+        let merged = graph.merge_parallel_connections(true, |a, b| {
+            assert!(first == 4);
+            a + b
+        });
+        assert_eq!(merged, 1);
+        assert!(first == 10 && second == 6);
+        assert_eq!(graph.count_of_class(0), 0);
+        assert_eq!(graph.count_of_class(1), 1);
+    }
+
+    #[test]
+    fn connection_count_and_scoped_connection_access() {
+        let (mut graph, handles) = fixtures::knowledge_graph();
+        let alice = handles.alice;
+        let total = alice.with_connections(|connections| connections.len());
+        assert_eq!(alice.connection_count(), total);
+
+        let likes = alice.with_connections(|connections| {
+            connections
+                .iter()
+                .filter(|connection| connection.access(|data| *data == "likes to eat"))
+                .count()
+        });
+        assert_eq!((total, likes), (2, 1));
+
+        graph.kill_connections(|connection| *connection == "likes to eat");
+        assert_eq!(alice.connection_count(), total - likes);
+        // Dead connections stay in the raw list until cleaned
+        assert_eq!(
+            alice.with_connections(|connections| connections.len()),
+            total
+        );
+        graph.clean();
+        assert_eq!(
+            alice.with_connections(|connections| connections.len()),
+            total - likes
+        );
+
+        let lonely = graph.new_thing("Lonely");
+        graph.new_undirected_connection([lonely.clone(), lonely.clone()], "self");
+        assert_eq!(lonely.connection_count(), 2);
+    }
+
+    #[test]
+    fn snapshot_passes_allow_mutating_the_container() {
+        let mut graph = fixtures::knowledge_graph().0;
+        let before = graph.things.len();
+
+        graph.for_each_thing_snapshot(|graph, thing| {
+            let shadow = graph.new_thing("shadow");
+            graph.new_directed_connection(thing, "shadowed_by", shadow);
+        });
+        assert_eq!(graph.things.len(), before * 2);
+        assert_eq!(
+            graph
+                .do_for_all_things(|thing| if *thing == "shadow" {
+                    Do::Take(())
+                } else {
+                    Do::Nothing
+                })
+                .len(),
+            before
+        );
+
+        // Connections created during the pass are not visited
+        let connections = graph.connections.len();
+        let mut visited = 0;
+        graph.for_each_connection_snapshot(|graph, connection| {
+            visited += 1;
+            let [first, second] = connection.get_things();
+            graph.new_undirected_connection([first, second], "echo");
+        });
+        assert_eq!(visited, connections);
+        assert_eq!(graph.connections.len(), connections * 2);
+
+        // Connections killed before their turn are skipped
+        let mut visited = 0;
+        graph.for_each_connection_snapshot(|graph, _| {
+            visited += 1;
+            graph.kill_connections(|_| true);
+        });
+        assert_eq!(visited, 1);
+    }
+
+    #[test]
+    fn dead_subgraph_matches_a_killed_subtree() {
+        let mut tree = Things::<&str, &str>::new();
+        let root = tree.new_thing("root");
+        let branch = tree.new_thing("branch");
+        let left = tree.new_thing("left");
+        let right = tree.new_thing("right");
+        let other = tree.new_thing("other");
+        tree.new_directed_connection(root.clone(), "child", branch.clone());
+        tree.new_directed_connection(branch.clone(), "child", left);
+        tree.new_directed_connection(branch, "child", right);
+        tree.new_directed_connection(root, "child", other);
+        assert_eq!(tree.dead_subgraph().things.len(), 0);
+
+        let subtree = ["branch", "left", "right"];
+        tree.kill_things(|thing| subtree.iter().any(|name| thing == name));
+        let deleted = tree.dead_subgraph();
+
+        assert_eq!(deleted.things.len(), 3);
+        assert!(
+            deleted
+                .things
+                .iter()
+                .zip(subtree)
+                .all(|(thing, name)| *thing == name)
+        );
+        let branch = deleted.find_thing_equal(&"branch").unwrap();
+        let children: Vec<_> = branch
+            .outgoing()
+            .iter()
+            .map(|(_, child)| child.access(|name| *name))
+            .collect();
+        assert_eq!(children, ["left", "right"]);
+        // The connection from the live root is left out
+        assert_eq!(deleted.connections.len(), 2);
+        assert!(branch.incoming().is_empty());
+
+        // Nothing is left to show once cleaned
+        tree.clean();
+        assert_eq!(tree.dead_subgraph().things.len(), 0);
+    }
+
+    #[test]
+    fn copy_reads_match_closure_access() {
+        use core::cell::Cell;
+
+        let mut graph = Things::<u64, u32>::new();
+        let mut previous = graph.new_thing(0);
+        for value in 1..100 {
+            let next = graph.new_thing(value);
+            graph.new_directed_connection(previous, value as u32, next.clone());
+            previous = next;
+        }
+
+        // The closure based path runs its closure once per read; the copy path has none
+        let calls = Cell::new(0);
+        let by_access: u64 = graph
+            .things
+            .iter()
+            .map(|thing| {
+                thing.access(|data| {
+                    calls.set(calls.get() + 1);
+                    *data
+                })
+            })
+            .sum();
+        let by_copy: u64 = graph.things.iter().map(|thing| thing.get_copy()).sum();
+        assert_eq!(calls.get(), 100);
+        assert_eq!(by_access, by_copy);
+
+        let weights: u32 = graph
+            .connections
+            .iter()
+            .map(|connection| connection.get_copy())
+            .sum();
+        assert_eq!(weights, (1..100).sum());
+
+        // SAFETY: the closures only read, and nothing borrows the things meanwhile
+        let unchecked: u64 = graph
+            .things
+            .iter()
+            .map(|thing| unsafe { thing.map_data_unchecked(|data| *data) })
+            .sum();
+        assert_eq!(unchecked, by_copy);
+
+        // Ordinary shared borrows may overlap the unchecked read
+        let first = &graph.things[0];
+        let nested =
+            unsafe { first.map_data_unchecked(|outer| first.access(|inner| outer + inner)) };
+        assert_eq!(nested, 0);
+        first.access_mut(|data| *data = 7);
+        assert_eq!(unsafe { first.map_data_unchecked(|data| *data) }, 7);
+    }
+
+    #[test]
+    fn find_bindings_return_the_first_match() {
+        let project = fixtures::task_dag().0;
+        let unlocks = |connection: &Connection<&str, &str>| *connection == "unlocks";
+
+        // Unique match
+        let (from, edge, to) = project
+            .find_binding2(|task| *task == "Integration Testing", unlocks, |_| true)
+            .unwrap();
+        assert!(from == "Integration Testing" && edge == "unlocks" && to == "Deployment");
+        assert!(
+            project
+                .find_binding2(|task| *task == "Deployment", unlocks, |_| true)
+                .is_none()
+        );
+
+        // Design unlocks both Auth and UI; the connection attached first wins
+        let (_, _, first) = project
+            .find_binding2(|task| *task == "Design", unlocks, |_| true)
+            .unwrap();
+        assert!(first == "Implement Auth");
+
+        // Ambiguous chains resolve to the first thing in container order
+        let (start, _, middle, _, end) = project
+            .find_binding3(|_| true, unlocks, |_| true, unlocks, |_| true)
+            .unwrap();
+        assert!(start == "Design" && middle == "Implement Auth" && end == "Integration Testing");
+
+        // A triangle closes when the third binding is the first thing again
+        let mut triangle = Things::<&str, &str>::new();
+        let a = triangle.new_thing("a");
+        let b = triangle.new_thing("b");
+        let c = triangle.new_thing("c");
+        triangle.new_directed_connection(a.clone(), "next", b.clone());
+        triangle.new_directed_connection(b, "next", c.clone());
+        triangle.new_undirected_connection([c, a.clone()], "next");
+        let closing = triangle.find_binding3(
+            |thing| thing.ptr_eq(&a),
+            |_| true,
+            |_| true,
+            |_| true,
+            |thing| *thing == "c",
+        );
+        assert!(closing.is_some_and(|(_, _, middle, _, _)| middle == "b"));
+        // An undirected connection is not walked back and forth
+        assert!(
+            triangle
+                .find_binding3(
+                    |thing| *thing == "c",
+                    |_| true,
+                    |_| true,
+                    |_| true,
+                    |thing| *thing == "c"
+                )
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn reattach_connection_moves_degrees() {
+        let mut graph = Things::<&str, &str>::new();
+        let a = graph.new_thing("a");
+        let b = graph.new_thing("b");
+        let c = graph.new_thing("c");
+        let edge = graph.new_directed_connection(a.clone(), "edge", b.clone());
+        let link = graph.new_undirected_connection([b.clone(), c.clone()], "link");
+
+        graph.reattach_connection(&edge, &c, &a);
+        assert_eq!(
+            (
+                a.connection_count(),
+                b.connection_count(),
+                c.connection_count()
+            ),
+            (1, 1, 2)
+        );
+        assert!(edge.get_directed_from().unwrap().ptr_eq(&c));
+        assert!(edge.get_directed_towards().unwrap().ptr_eq(&a));
+        assert!(edge == "edge" && edge.is_directed());
+        assert!(c.outgoing().iter().any(|(_, next)| next.ptr_eq(&a)));
+        assert!(b.outgoing().iter().all(|(_, next)| !next.ptr_eq(&a)));
+
+        // Reversing the ends of an undirected connection changes nothing
+        graph.reattach_connection(&link, &c, &b);
+        assert_eq!(b.connection_count(), 1);
+
+        // Self-loops are attached at both ends
+        graph.reattach_connection(&link, &b, &b);
+        assert_eq!((b.connection_count(), c.connection_count()), (2, 1));
+        assert_eq!(graph.connections.len(), 2);
+        test_util::assert_graph_sound(&graph);
+    }
+
+    #[test]
+    #[cfg(feature = "dead-access-audit")]
+    fn dead_access_audit_reports_cascade_deletion() {
+        use core::cell::RefCell;
+
+        let mut graph = Things::new();
+        let alice = graph.new_thing("Alice");
+        let bob = graph.new_thing("Bob");
+        let charlie = graph.new_thing("Charlie");
+        let knows_bob = graph.new_directed_connection(alice.clone(), "knows", bob.clone());
+        graph.new_directed_connection(bob.clone(), "knows", charlie.clone());
 
-        let pear = graph.new_thing("Pear");
-        let pears = graph.new_thing("Pears");
-        graph.new_directed_connection(pears.clone(), "plural of", pear.clone());
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let log = events.clone();
+        graph.enable_dead_access_audit(move |event| log.borrow_mut().push(event));
+        let take = || core::mem::take(&mut *events.borrow_mut());
 
-        let alice = graph.new_thing("Alice");
-        graph.new_directed_connection(alice.clone(), "likes to eat", apples);
-        graph.new_directed_connection(alice, "doesn't like to eat", pears);
+        // Nothing is dead yet
+        alice.do_for_all_connections(|_| Do::Take(()));
+        graph.do_for_all_things(|_| Do::Take(()));
+        assert!(take().is_empty());
 
-        let fruit = graph.new_thing("Fruit");
-        graph.new_directed_connection(apple, "is", fruit.clone());
-        graph.new_directed_connection(pear, "is", fruit);
+        graph.kill_things(|thing| thing.access(|data| data == &"Bob"));
+        let alice_connections =
+            alice.do_for_all_connections(|connection| Do::Take(connection.clone()));
+        assert!(!alice_connections.is_empty());
+        assert!(knows_bob.get_directed_towards().is_some());
+        assert!(knows_bob.get_directed_towards_alive().is_none());
+        assert!(knows_bob.get_other_thing_alive(&alice).is_none());
+        graph.do_for_all_things(|_| Do::Take(()));
+        assert_eq!(
+            take(),
+            [
+                DeadAccessEvent {
+                    item: AuditedItem::Connection,
+                    id: knows_bob.seq(),
+                    operation: "Thing::do_for_all_connections",
+                },
+                DeadAccessEvent {
+                    item: AuditedItem::Thing,
+                    id: bob.seq(),
+                    operation: "Connection::get_directed_towards",
+                },
+                DeadAccessEvent {
+                    item: AuditedItem::Thing,
+                    id: bob.seq(),
+                    operation: "Things::do_for_all_things",
+                },
+            ]
+        );
 
-        graph
+        // Internal traversals don't report
+        graph.shortest_path(&alice, &charlie, |_| true);
+        graph.dead_subgraph();
+        assert!(take().is_empty());
+
+        graph.clean();
+        alice.do_for_all_connections(|_| Do::Take(()));
+        graph.do_for_all_things(|_| Do::Take(()));
+        graph.do_for_all_connections(|_| Do::Take(()));
+        assert!(take().is_empty());
+
+        graph.disable_dead_access_audit();
+        graph.kill_things(|_| true);
+        graph.do_for_all_things(|_| Do::Take(()));
+        assert!(take().is_empty());
     }
 
     #[test]
-    fn knowledge_representation_basic_query() {
-        let graph = test_knowledge_graph();
-
-        // Query: What does Alice like to eat?
-        let alice = graph
-            .do_for_a_thing(|thing| {
-                return if thing.access(|data| *data == "Alice") {
-                    Do::Take(thing.clone())
-                } else {
-                    Do::Nothing
-                };
+    fn weighted_random_neighbor_prefers_heavy_edges() {
+        let mut graph = Things::<&str, u64>::new();
+        let here = graph.new_thing("here");
+        let light = graph.new_thing("light");
+        let heavy = graph.new_thing("heavy");
+        graph.new_undirected_connection([here.clone(), light.clone()], 1);
+        graph.new_directed_connection(here.clone(), 99, heavy.clone());
+
+        // A fixed sequence of rolls: light only owns roll 0 of 0..100
+        let mut rolls = [0u64, 1, 50, 99, 100, 250].into_iter();
+        let mut rng = || rolls.next().unwrap();
+        let picks: Vec<_> = (0..6)
+            .map(|_| {
+                here.weighted_random_neighbor(|weight| *weight, &mut rng)
+                    .unwrap()
             })
-            .unwrap();
-
-        let liked_food_connection = alice
-            .do_for_a_connection(|connection| {
-                return if connection.access(|data| *data == "likes to eat") {
-                    Do::Take(connection.clone())
-                } else {
-                    Do::Nothing
-                };
-            })
-            .unwrap();
-
-        // Use the new API that returns Option
-        let liked_food = liked_food_connection.get_directed_towards().unwrap();
+            .collect();
+        assert!(picks[0] == "light");
+        assert!(
+            picks[1..]
+                .iter()
+                .enumerate()
+                .all(|(at, pick)| (at == 3) == (*pick == "light"))
+        );
 
-        let answer = format!(
-            "The thing alice likes to eat is: {}.",
-            liked_food.access(|data| data.to_ascii_lowercase())
+        // All-zero weights fall back to a uniform pick by connection order
+        let mut counter = 0;
+        let mut rng = || {
+            counter += 1;
+            counter
+        };
+        let uniform: Vec<_> = (0..4)
+            .map(|_| here.weighted_random_neighbor(|_| 0, &mut rng).unwrap())
+            .collect();
+        assert!(uniform[0] == "heavy" && uniform[1] == "light");
+        assert!(uniform[2] == "heavy" && uniform[3] == "light");
+
+        assert!(
+            light
+                .weighted_random_neighbor(|weight| *weight, &mut || 0)
+                .unwrap()
+                == "here"
+        );
+        // Heavy only has a connection coming in, which a walk can't take
+        assert!(
+            heavy
+                .weighted_random_neighbor(|weight| *weight, &mut || 0)
+                .is_none()
         );
 
-        assert_eq!("The thing alice likes to eat is: apples.", &answer);
+        // A roll from the uneven tail of the range is drawn again
+        let mut rolls = [u64::MAX, 1].into_iter();
+        let mut draws = 0;
+        let mut rng = || {
+            draws += 1;
+            rolls.next().unwrap()
+        };
+        let weight = |weight: &u64| if *weight == 1 { 1 } else { 2 };
+        assert!(here.weighted_random_neighbor(weight, &mut rng).unwrap() == "heavy");
+        assert_eq!(draws, 2);
+        graph.kill_things(|thing| *thing != "here");
+        assert!(
+            here.weighted_random_neighbor(|weight| *weight, &mut || 0)
+                .is_none()
+        );
     }
 
     #[test]
-    fn knowledge_representation_taxonomy_query() {
-        let graph = test_knowledge_graph();
+    fn limits_refuse_growth_until_cleaned() {
+        let mut graph = Things::<u32, u32>::with_limits(3, 2);
+        let things: Vec<_> = (0..3).map(|id| graph.try_new_thing(id).unwrap()).collect();
+        assert_eq!(graph.try_new_thing(3).err(), Some(LimitReached));
+        assert!(
+            graph
+                .try_new_directed_connection(things[0].clone(), 0, things[1].clone())
+                .is_ok()
+        );
+        assert!(
+            graph
+                .try_new_undirected_connection([things[1].clone(), things[2].clone()], 1)
+                .is_ok()
+        );
+        assert_eq!(
+            graph
+                .try_new_directed_connection(things[2].clone(), 2, things[0].clone())
+                .err(),
+            Some(ConnectError::LimitReached)
+        );
+        assert_eq!(graph.remaining_capacity(), (0, 0));
 
-        // Query: What are some examples of fruit?
-        let fruit_concept = graph
-            .do_for_a_thing(|thing| {
-                return if thing.access(|data| *data == "Fruit") {
-                    Do::Take(thing.clone())
-                } else {
-                    Do::Nothing
-                };
-            })
-            .unwrap();
+        // Killed items keep taking up room until cleaned
+        graph.kill_things(|thing| thing.access(|id| *id == 0));
+        assert_eq!(graph.try_new_thing(3).err(), Some(LimitReached));
+        assert_eq!(graph.remaining_capacity(), (0, 0));
 
-        // Find all things that are instances of fruit
-        let fruit_examples: Vec<_> = graph.do_for_all_connections(|conn| {
-            // Find "is" relationships pointing to the fruit concept
-            return if conn.access(|data| *data == "is") {
-                if let Ok(Direction::Towards) = conn.get_direction_relative_to(&fruit_concept) {
-                    Do::Take(conn.get_directed_from().unwrap().access(|data| *data))
-                } else {
-                    Do::Nothing
-                }
-            } else {
-                Do::Nothing
-            };
-        });
+        graph.clean();
+        assert_eq!(graph.remaining_capacity(), (1, 1));
+        let fresh = graph.try_new_thing(3).unwrap();
+        assert!(
+            graph
+                .try_new_directed_connection(fresh, 3, things[2].clone())
+                .is_ok()
+        );
+        assert_eq!(graph.remaining_capacity(), (0, 0));
+    }
 
-        assert!(fruit_examples.contains(&"Apple"));
-        assert!(fruit_examples.contains(&"Pear"));
-        assert_eq!(fruit_examples.len(), 2);
+    #[test]
+    fn huge_limits_reserve_nothing() {
+        let mut graph = Things::<u32, u32>::with_limits(usize::MAX, 8);
+        let first = graph.new_thing(1);
+        let second = graph.new_thing(2);
+        graph.new_directed_connection(first, 1, second);
+        assert_eq!(graph.remaining_capacity(), (usize::MAX - 2, 7));
     }
 
     #[test]
-    fn social_network_simulation() {
-        let mut social_graph = Things::<String, String>::new();
+    fn symmetrize_checks_the_limit_before_adding() {
+        extern crate std;
+        use std::panic::{AssertUnwindSafe, catch_unwind};
+
+        let mut graph = Things::<&str, &str>::with_limits(3, 3);
+        let a = graph.new_thing("a");
+        let b = graph.new_thing("b");
+        let c = graph.new_thing("c");
+        graph.new_directed_connection(a.clone(), "next", b.clone());
+        graph.new_directed_connection(b, "next", c);
+
+        // Two reverses are needed but only one fits, so none is added
+        let result = catch_unwind(AssertUnwindSafe(|| graph.symmetrize(|_| "previous")));
+        assert!(result.is_err());
+        assert_eq!(graph.connections.len(), 2);
+        assert!(a.incoming().is_empty());
+    }
 
-        // Create people
-        let alice = social_graph.new_thing("Alice".to_string());
-        let bob = social_graph.new_thing("Bob".to_string());
-        let charlie = social_graph.new_thing("Charlie".to_string());
-        let diana = social_graph.new_thing("Diana".to_string());
+    #[test]
+    #[should_panic(expected = "limit of 1 things")]
+    fn new_thing_panics_at_the_limit() {
+        let mut graph = Things::<u32, ()>::with_limits(1, 1);
+        graph.new_thing(0);
+        graph.new_thing(1);
+    }
 
-        // Create friendships (undirected relationships)
-        social_graph
-            .new_undirected_connection([alice.clone(), bob.clone()], "friendship".to_string());
-        social_graph
-            .new_undirected_connection([bob.clone(), charlie.clone()], "friendship".to_string());
-        social_graph
-            .new_undirected_connection([alice.clone(), diana.clone()], "friendship".to_string());
+    #[test]
+    fn reachable_count_matches_reachable_sets() {
+        let mut graph = fixtures::knowledge_graph().0;
+        let loop_start = graph.new_thing("Loop");
+        let loop_end = graph.new_thing("Back");
+        graph.new_directed_connection(loop_start.clone(), "to", loop_end.clone());
+        graph.new_directed_connection(loop_end, "to", loop_start);
+
+        let things = graph.do_for_all_things(|thing| Do::Take(thing.clone()));
+        let sets = graph.reachable_sets(&things, |_| true);
+        for (thing, set) in things.iter().zip(&sets) {
+            assert_eq!(graph.reachable_count(thing), set.len());
+        }
 
-        // Create follows relationships (directed)
-        social_graph.new_directed_connection(charlie.clone(), "follows".to_string(), alice.clone());
-        social_graph.new_directed_connection(diana.clone(), "follows".to_string(), bob.clone());
+        let project = fixtures::task_dag().0;
+        let design = project.find_thing_equal(&"Design").unwrap();
+        assert_eq!(project.reachable_count(&design), 4);
+        let deployment = project.find_thing_equal(&"Deployment").unwrap();
+        assert_eq!(project.reachable_count(&deployment), 0);
+    }
 
-        // Test: Find Alice's friends
-        let alice_friendships = alice.do_for_all_connections(|conn| {
-            return if conn.is_undirected() && conn.access(|data| data == "friendship") {
-                Do::Take(conn.clone())
-            } else {
-                Do::Nothing
-            };
+    #[test]
+    fn modify_applies_changes_after_the_walk() {
+        let mut project = fixtures::task_dag().0;
+        let before = project.connections.len();
+
+        // Add a shortcut from every task to everything two steps ahead, and
+        // retire the tasks without successors
+        project.modify(|editor| {
+            let graph = editor.graph();
+            for task in graph.do_for_all_things(|task| Do::Take(task.clone())) {
+                for (_, next) in task.outgoing() {
+                    for (_, after) in next.outgoing() {
+                        editor.new_directed_connection(task.clone(), "shortcut", after);
+                    }
+                }
+                if task.outgoing().is_empty() {
+                    let archive = editor.new_thing("Archive");
+                    editor.new_directed_connection(task.clone(), "archived_in", archive);
+                    editor.kill_thing(&task);
+                }
+            }
+            // Nothing is applied while the closure runs
+            assert_eq!(graph.connections.len(), before);
+            assert!(graph.find_thing_equal(&"Archive").is_none());
         });
 
-        assert_eq!(alice_friendships.len(), 2); // Alice is friends with Bob and Diana
-
-        // Test: Find who follows Alice
-        let alice_followers: Vec<_> = social_graph.do_for_all_connections(|conn| {
-            return if conn.is_directed() && conn.access(|data| data == "follows") {
-                conn.get_directed_towards().unwrap().access(|data| {
-                    return if data == "Alice" {
-                        Do::Take(
-                            conn.get_directed_from()
-                                .unwrap()
-                                .access(|data| data.clone()),
-                        )
-                    } else {
-                        Do::Nothing
-                    };
-                })
+        let shortcuts = project.do_for_all_connections(|connection| {
+            if *connection == "shortcut" {
+                Do::Take(connection.clone())
             } else {
                 Do::Nothing
-            };
+            }
         });
-
-        assert!(alice_followers.contains(&"Charlie".to_string()));
-        assert_eq!(alice_followers.len(), 1);
+        // Design reaches Testing twice, Auth and UI reach Deployment once each
+        assert_eq!(shortcuts.len(), 4);
+        let archive = project.find_thing_equal(&"Archive").unwrap();
+        // The archive link was created first, then died with Deployment
+        assert_eq!(archive.with_connections(|connections| connections.len()), 1);
+        assert!(archive.incoming().is_empty());
+        assert!(project.find_thing_equal(&"Deployment").is_none());
+        test_util::assert_graph_sound(&project);
     }
 
     #[test]
-    fn gui_component_hierarchy() {
-        // Simulate a simple GUI structure with containment and focus relationships
-        #[derive(Debug, Clone, PartialEq)]
-        struct Widget {
-            name: String,
-            widget_type: String,
+    fn modify_applies_nothing_when_the_queue_breaks_a_rule() {
+        extern crate std;
+        use std::panic::{AssertUnwindSafe, catch_unwind};
+
+        let mut graph = Things::<&str, &str>::with_limits(4, 2);
+        let a = graph.new_thing("Alice");
+        let b = graph.new_thing("Bob");
+
+        // The third connection doesn't fit, so neither do the first two
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            graph.modify(|editor| {
+                let carol = editor.new_thing("Carol");
+                editor.new_directed_connection(a.clone(), "knows", b.clone());
+                editor.new_directed_connection(b.clone(), "knows", carol.clone());
+                editor.new_directed_connection(carol, "knows", a.clone());
+                editor.kill_thing(&b);
+            })
+        }));
+        assert!(result.is_err());
+        assert_eq!((graph.things.len(), graph.connections.len()), (2, 0));
+        assert!(b.is_alive() && a.connection_count() == 0);
+        test_util::assert_graph_sound(&graph);
+
+        // Two queued connections parallel to each other
+        let mut simple =
+            Things::<&str, &str>::with_config(ThingsConfig::new().mode(GraphMode::Simple));
+        let a = simple.new_thing("Alice");
+        let b = simple.new_thing("Bob");
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            simple.modify(|editor| {
+                editor.new_undirected_connection([a.clone(), b.clone()], "friends");
+                editor.new_undirected_connection([b.clone(), a.clone()], "friends");
+            })
+        }));
+        assert_eq!(result.is_err(), cfg!(debug_assertions));
+        if result.is_err() {
+            assert!(simple.connections.is_empty());
         }
+    }
 
-        #[derive(Debug, Clone, PartialEq)]
-        enum Relationship {
-            Contains,
-            FocusNext,
-            EventBubbles,
+    #[test]
+    fn cursor_pages_survive_mutation_between_calls() {
+        let mut graph = Things::<u32, ()>::new();
+        for number in 0..20 {
+            graph.new_thing(number);
+        }
+        let original: Vec<_> = graph.things.clone();
+
+        let mut seen: Vec<u32> = Vec::new();
+        let mut cursor = Some(0);
+        let mut round = 0u32;
+        while let Some(after) = cursor {
+            let (page, next) = graph.cursor_after(after, 3);
+            seen.extend(page.iter().map(|thing| thing.get_copy()));
+            cursor = next;
+
+            // Between pages: kill a thing further ahead, add one, and clean
+            round += 1;
+            let ahead = &original[(5 * round as usize) % 20];
+            let target = ahead.get_copy();
+            if ahead.is_alive() && !seen.contains(&target) {
+                graph.kill_things(|number| *number == target);
+            }
+            if round <= 4 {
+                graph.new_thing(100 + round);
+            }
+            graph.clean();
         }
 
-        let mut gui = Things::<Widget, Relationship>::new();
+        let mut unique = seen.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), seen.len(), "nothing is yielded twice");
+        for thing in original.iter().filter(|thing| thing.is_alive()) {
+            assert!(seen.contains(&thing.get_copy()));
+        }
+        // Insertions made while paging show up on later pages
+        for number in 101..=104 {
+            assert!(seen.contains(&number));
+        }
+        assert!(
+            seen.iter()
+                .all(|number| graph.find_thing_equal(number).is_some())
+        );
+    }
 
-        // Create widgets
-        let window = gui.new_thing(Widget {
-            name: "MainWindow".to_string(),
-            widget_type: "Window".to_string(),
-        });
+    #[test]
+    fn connection_cursor_skips_dead_connections() {
+        let mut graph = Things::<u32, u32>::new();
+        let hub = graph.new_thing(0);
+        for number in 1..=6 {
+            let spoke = graph.new_thing(number);
+            graph.new_directed_connection(hub.clone(), number, spoke);
+        }
 
-        let dialog = gui.new_thing(Widget {
-            name: "SettingsDialog".to_string(),
-            widget_type: "Dialog".to_string(),
-        });
+        let (first, cursor) = graph.connection_cursor_after(0, 4);
+        assert_eq!(first.len(), 4);
+        graph.kill_connections(|label| *label == 5);
+        graph.clean();
+        let (rest, cursor) = graph.connection_cursor_after(cursor.unwrap(), 4);
+        assert_eq!(rest.len(), 1);
+        assert!(rest[0] == 6);
+        assert!(cursor.is_none());
+
+        // Exactly filled pages still end the walk
+        let (_, cursor) = graph.connection_cursor_after(0, 5);
+        assert!(cursor.is_none());
+        let (empty, cursor) = graph.connection_cursor_after(0, 0);
+        assert!(empty.is_empty());
+        assert_eq!(cursor, None);
+        let (empty, cursor) = graph.cursor_after(0, 0);
+        assert!(empty.is_empty() && cursor.is_none());
+    }
 
-        let ok_button = gui.new_thing(Widget {
-            name: "OkButton".to_string(),
-            widget_type: "Button".to_string(),
-        });
+    #[test]
+    fn other_endpoints_dedup_things_but_not_pairs() {
+        let mut graph = Things::<&str, &str>::new();
+        let a = graph.new_thing("a");
+        let b = graph.new_thing("b");
+        let c = graph.new_thing("c");
+        graph.new_directed_connection(a.clone(), "road", b.clone());
+        graph.new_undirected_connection([b.clone(), a.clone()], "road");
+        graph.new_directed_connection(a.clone(), "road", a.clone());
+        graph.new_directed_connection(a.clone(), "rail", c.clone());
+
+        let roads = a.other_endpoints(|connection| *connection == "road");
+        assert_eq!(roads.len(), 2);
+        assert!(roads[0] == "b" && roads[1] == "a");
+
+        let with = a.other_endpoints_with(|connection| *connection == "road");
+        assert_eq!(with.len(), 3);
+        assert!(with[0].1 == "b" && with[1].1 == "b");
+        assert!(with[2].1 == "a");
+
+        graph.kill_things(|name| *name == "b");
+        let roads = a.other_endpoints(|_| true);
+        assert!(roads.len() == 2 && roads[0] == "a" && roads[1] == "c");
+    }
 
-        let cancel_button = gui.new_thing(Widget {
-            name: "CancelButton".to_string(),
-            widget_type: "Button".to_string(),
-        });
+    #[test]
+    fn reverse_connection_relabeled_flips_and_relabels() {
+        #[derive(Clone, Copy, PartialEq, Debug)]
+        enum Social {
+            Follows,
+            FollowedBy,
+            Friends,
+        }
 
-        // Create containment hierarchy
-        gui.new_directed_connection(window.clone(), Relationship::Contains, dialog.clone());
-        gui.new_directed_connection(dialog.clone(), Relationship::Contains, ok_button.clone());
-        gui.new_directed_connection(
-            dialog.clone(),
-            Relationship::Contains,
-            cancel_button.clone(),
-        );
+        impl Reversible for Social {
+            fn reverse(&self) -> Self {
+                match self {
+                    Social::Follows => Social::FollowedBy,
+                    Social::FollowedBy => Social::Follows,
+                    Social::Friends => Social::Friends,
+                }
+            }
+        }
 
-        // Create focus chain
-        gui.new_directed_connection(
-            ok_button.clone(),
-            Relationship::FocusNext,
-            cancel_button.clone(),
+        let mut graph = Things::<&str, Social>::new();
+        graph.register_connection_class(|social| *social as u16);
+        let ann = graph.new_thing("Ann");
+        let bo = graph.new_thing("Bo");
+        let follows = graph.new_directed_connection(ann.clone(), Social::Follows, bo.clone());
+        let friends = graph.new_undirected_connection([ann.clone(), bo.clone()], Social::Friends);
+
+        assert!(graph.reverse_connection_relabeled(&follows));
+        assert!(follows == Social::FollowedBy);
+        assert!(follows.get_directed_from().unwrap() == "Bo");
+        assert!(follows.get_directed_towards().unwrap() == "Ann");
+        assert_eq!(bo.outgoing().len(), 1);
+        assert!(ann.outgoing().is_empty());
+        assert_eq!(
+            graph.connections_of_class(Social::FollowedBy as u16).len(),
+            1
         );
-        gui.new_directed_connection(
-            cancel_button.clone(),
-            Relationship::FocusNext,
-            ok_button.clone(),
+        assert!(
+            graph
+                .connections_of_class(Social::Follows as u16)
+                .is_empty()
         );
 
-        // Create event bubbling relationships
-        gui.new_directed_connection(
-            ok_button.clone(),
-            Relationship::EventBubbles,
-            dialog.clone(),
-        );
-        gui.new_directed_connection(
-            cancel_button.clone(),
-            Relationship::EventBubbles,
-            dialog.clone(),
+        // Flipping back restores the original label
+        assert!(graph.reverse_connection_relabeled(&follows));
+        assert!(follows == Social::Follows);
+        assert!(!graph.reverse_connection_relabeled(&friends));
+        assert!(friends == Social::Friends);
+    }
+
+    #[test]
+    fn undirected_view_follows_connections_both_ways() {
+        let (project, handles) = fixtures::task_dag();
+        let testing = handles.testing;
+        assert_eq!(testing.outgoing().len(), 1);
+
+        let view = project.undirected_view();
+        let neighbors = view.neighbors(&testing);
+        assert_eq!(neighbors.len(), 3);
+        assert!(neighbors.iter().any(|thing| *thing == "Implement Auth"));
+        assert!(neighbors.iter().any(|thing| *thing == "Implement UI"));
+        assert!(neighbors.iter().any(|thing| *thing == "Deployment"));
+        assert_eq!(view.degree(&testing), 3);
+
+        // Deployment has no outgoing connections, but reaches everything here
+        let deployment = handles.deployment;
+        let order = view.bfs(&deployment);
+        assert_eq!(order.len(), 5);
+        assert!(order[0] == "Deployment" && order[1] == "Integration Testing");
+        assert!(order[4] == "Design");
+        // Nothing was added to the graph
+        assert_eq!(project.connections.len(), 5);
+    }
+
+    /// A graph where `b` is dead, and the connection `a -> c` was killed
+    /// separately, before `b`.
+    fn soft_deleted_fixture() -> Things<&'static str, &'static str> {
+        let mut graph = Things::new();
+        let a = graph.new_thing("a");
+        let b = graph.new_thing("b");
+        let c = graph.new_thing("c");
+        graph.new_directed_connection(a.clone(), "ab", b.clone());
+        graph.new_undirected_connection([b, c.clone()], "bc");
+        graph.new_directed_connection(a.clone(), "ac", c.clone());
+        graph.new_directed_connection(c, "ca", a);
+        graph.kill_connections(|label| *label == "ac");
+        graph.kill_things(|name| *name == "b");
+        graph
+    }
+
+    #[test]
+    fn record_round_trip_keeps_dead_items_dead() {
+        let graph = soft_deleted_fixture();
+        let record = graph.to_record(SerializeOptions { include_dead: true });
+        assert_eq!(record.things.len(), 3);
+        assert_eq!(record.connections.len(), 4);
+        let alive: Vec<_> = record.connections.iter().map(|c| c.alive).collect();
+        assert_eq!(alive, [false, false, false, true]);
+
+        let mut restored = Things::from_record(record.clone());
+        assert_eq!(restored.dead_amount, graph.dead_amount);
+        assert_eq!(
+            restored.to_record(SerializeOptions { include_dead: true }),
+            record
         );
+        let alive: Vec<_> = restored
+            .things
+            .iter()
+            .map(|thing| thing.is_alive())
+            .collect();
+        assert_eq!(alive, [true, false, true]);
+        let a = restored.find_thing_equal(&"a").unwrap();
+        assert_eq!(a.with_connections(|connections| connections.len()), 3);
+
+        // Cleaning the restored graph ends where cleaning the original would
+        restored.clean();
+        assert_eq!(restored.things.len(), 2);
+        assert_eq!(restored.connections.len(), 1);
+        assert_eq!(restored.dead_amount, 0);
+        assert_eq!(a.with_connections(|connections| connections.len()), 1);
+    }
 
-        // Test: Find all widgets contained in the dialog
-        let dialog_children: Vec<_> = dialog.do_for_all_connections(|conn| {
-            conn.access(|data| {
-                if matches!(data, Relationship::Contains) {
-                    if let Some(from) = conn.get_directed_from() {
-                        if from == dialog {
-                            Do::Take(
-                                conn.get_directed_towards()
-                                    .unwrap()
-                                    .access(|data| data.name.clone()),
-                            )
-                        } else {
-                            Do::Nothing
-                        }
-                    } else {
-                        Do::Nothing
-                    }
-                } else {
-                    Do::Nothing
-                }
-            })
+    #[test]
+    fn record_without_dead_items_omits_them() {
+        let graph = soft_deleted_fixture();
+        let record = graph.to_record(SerializeOptions::default());
+        assert_eq!(record.things.len(), 2);
+        assert_eq!(record.connections.len(), 1);
+        assert_eq!(record.connections[0].ends, [1, 0]);
+        assert_eq!(record.dead_amount, 0);
+
+        let restored = Things::from_record(record);
+        assert_eq!(restored.dead_amount, 0);
+        assert!(restored.things.iter().all(|thing| thing.is_alive()));
+        let c = restored.find_thing_equal(&"c").unwrap();
+        assert!(c.outgoing()[0].1 == "a");
+    }
+
+    #[test]
+    fn record_can_be_taken_while_data_is_read() {
+        let graph = soft_deleted_fixture();
+        let connection = graph.find_connection_equal(&"ca").unwrap();
+        connection.set_weight(4);
+        let record = connection.access(|_| graph.to_record(SerializeOptions::default()));
+        assert_eq!(record.connections[0].weight, Some(4));
+    }
+
+    #[test]
+    fn best_within_finds_relays_in_range() {
+        // A star of stars: the centre has three arms, two of which are hubs
+        // of their own stars. From the small star's leaf, the centre is the
+        // best relay two hops away and the big hub the best three hops away.
+        let mut network = Things::<&str, ()>::new();
+        let centre = network.new_thing("centre");
+        let small = network.new_thing("small");
+        let big = network.new_thing("big");
+        network.new_undirected_connection([centre.clone(), small.clone()], ());
+        network.new_undirected_connection([centre.clone(), big.clone()], ());
+        let side = network.new_thing("side");
+        network.new_undirected_connection([centre.clone(), side], ());
+        let leaf = network.new_thing("leaf");
+        network.new_undirected_connection([small.clone(), leaf.clone()], ());
+        for name in ["b1", "b2", "b3", "b4"] {
+            let spoke = network.new_thing(name);
+            network.new_undirected_connection([big.clone(), spoke], ());
+        }
+        let degree = |thing: &Thing<&str, ()>| thing.connection_count() as u64;
+
+        let (relay, score) = network.best_within(&leaf, 2, degree, |_| true).unwrap();
+        assert!(relay == "centre" && score == 3);
+        let (relay, score) = network.best_within(&leaf, 3, degree, |_| true).unwrap();
+        assert!(relay == "big" && score == 5);
+        // The start is excluded even when it scores best
+        let (relay, _) = network.best_within(&big, 1, degree, |_| true).unwrap();
+        assert!(relay == "centre");
+        // Equal scores go to the closer thing, then the older one
+        let (relay, _) = network.best_within(&centre, 2, |_| 1, |_| true).unwrap();
+        assert!(relay == "small");
+
+        let lonely = network.new_thing("lonely");
+        assert!(network.best_within(&lonely, 3, degree, |_| true).is_none());
+        assert!(network.best_within(&leaf, 0, degree, |_| true).is_none());
+        network.kill_things(|name| *name == "small");
+        assert!(network.best_within(&leaf, 3, degree, |_| true).is_none());
+    }
+
+    #[test]
+    fn follow_answers_what_alice_likes_to_eat() {
+        let (_graph, handles) = fixtures::knowledge_graph();
+        let alice = handles.alice;
+
+        let liked = alice.follow(&"likes to eat");
+        assert_eq!(liked.len(), 1);
+        assert!(liked[0] == "Apples");
+        assert!(liked[0].follow(&"plural of")[0] == "Apple");
+
+        // Incoming connections aren't followed
+        let apple = handles.apple;
+        assert!(apple.follow(&"plural of").is_empty());
+        assert!(alice.follow(&"is").is_empty());
+
+        // A self-loop leads back once, whichever way it goes
+        let mut graph = Things::<&str, &str>::new();
+        let me = graph.new_thing("me");
+        graph.new_undirected_connection([me.clone(), me.clone()], "mirror");
+        graph.new_directed_connection(me.clone(), "echo", me.clone());
+        assert_eq!(me.follow(&"mirror").len(), 1);
+        assert!(me.follow(&"mirror")[0].ptr_eq(&me));
+        assert_eq!(me.follow(&"echo").len(), 1);
+    }
+
+    #[test]
+    fn data_table_borrows_each_live_thing_once() {
+        use core::cell::Cell;
+
+        let (mut graph, handles) = fixtures::knowledge_graph();
+        graph.kill_things(|name| *name == "Fruit");
+        let calls = Cell::new(0);
+        let table = graph.with_thing_data_table(|name| {
+            calls.set(calls.get() + 1);
+            name.len()
+        });
+        assert_eq!(calls.get(), 5);
+        assert_eq!(table.len(), 5);
+
+        let alice = handles.alice;
+        assert_eq!(table.get(&alice), Some(&5));
+        let fruit = graph
+            .things
+            .iter()
+            .find(|thing| **thing == "Fruit")
+            .unwrap();
+        assert_eq!(table.get(fruit), None);
+
+        assert!(!table.is_stale(&graph));
+        graph.new_thing("Banana");
+        assert!(table.is_stale(&graph));
+        // Values recorded before the change are still there
+        assert_eq!(table.get(&alice), Some(&5));
+    }
+
+    #[test]
+    fn clean_with_progress_reports_every_dead_item() {
+        let mut graph = fixtures::knowledge_graph().0;
+        graph.kill_connections(|label| *label == "is");
+        graph.kill_things(|name| *name == "Alice");
+        let dead_things = graph
+            .things
+            .iter()
+            .filter(|thing| !thing.is_alive())
+            .count();
+        let dead_connections = graph
+            .connections
+            .iter()
+            .filter(|connection| !connection.is_alive())
+            .count();
+        assert_eq!((dead_things, dead_connections), (1, 4));
+
+        let mut things = Vec::new();
+        let mut connections = 0;
+        graph.clean_with_progress(|item| match item {
+            CleanedItem::Thing(thing) => things.push(thing),
+            CleanedItem::Connection(_) => connections += 1,
         });
+        assert_eq!(things.len(), dead_things);
+        assert!(things[0] == "Alice");
+        assert_eq!(connections, dead_connections);
+        assert_eq!(graph.dead_amount, 0);
+        assert_eq!(graph.things.len(), 5);
+        assert_eq!(graph.connections.len(), 2);
+        test_util::assert_graph_sound(&graph);
+    }
 
-        assert!(dialog_children.contains(&"OkButton".to_string()));
-        assert!(dialog_children.contains(&"CancelButton".to_string()));
-        assert_eq!(dialog_children.len(), 2);
+    #[test]
+    #[should_panic(expected = "\"endpoint lists\" check: connection #")]
+    fn assert_graph_sound_names_the_broken_check() {
+        let (graph, handles) = fixtures::knowledge_graph();
+        test_util::assert_graph_sound(&graph);
+
+        // Take a connection out of one endpoint only
+        let mut alice = handles.alice;
+        unsafe { alice.remove_connections(|connection| *connection == "likes to eat") };
+        test_util::assert_graph_sound(&graph);
+    }
 
-        // Test: Find the next widget in focus chain from OK button
-        let next_focus = ok_button.do_for_a_connection(|conn| {
-            conn.access(|data| {
-                return if matches!(data, Relationship::FocusNext) {
-                    if let Ok(Direction::AwayFrom) = conn.get_direction_relative_to(&ok_button) {
-                        if let Some(to) = conn.get_directed_towards() {
-                            Do::Take(to.access(|data| data.name.clone()))
-                        } else {
-                            Do::Nothing
-                        }
-                    } else {
-                        Do::Nothing
-                    }
-                } else {
-                    Do::Nothing
-                };
-            })
-        });
+    #[test]
+    fn assert_graph_matches_compares_snapshots() {
+        let mut graph = Things::<&str, &str>::new();
+        let a = graph.new_thing("a");
+        let b = graph.new_thing("b");
+        graph.new_directed_connection(a, "to", b);
+        let expected = [
+            ThingSnapshot {
+                data: "a",
+                edges: alloc::vec![("to", "b", Some(Direction::AwayFrom))],
+            },
+            ThingSnapshot {
+                data: "b",
+                edges: alloc::vec![("to", "a", Some(Direction::Towards))],
+            },
+        ];
+        test_util::assert_graph_matches(&graph, &expected);
+    }
 
-        assert_eq!(next_focus, Some("CancelButton".to_string()));
+    #[test]
+    fn set_direction_converts_undirected_connections() {
+        use core::cell::Cell;
+
+        let mut graph = Things::<&str, &str>::new();
+        let a = graph.new_thing("a");
+        let b = graph.new_thing("b");
+        let c = graph.new_thing("c");
+        let link = graph.new_undirected_connection([a.clone(), b.clone()], "link");
+        link.set_weight(7);
+        let killed = Rc::new(Cell::new(false));
+        let flag = killed.clone();
+        link.on_kill(move |_| flag.set(true));
+
+        assert_eq!(graph.set_direction(&link, &b), Ok(()));
+        assert!(link.is_directed());
+        assert!(link.get_directed_from().unwrap() == "b");
+        assert!(link.get_directed_towards().unwrap() == "a");
+        assert_eq!(link.weight(), Some(7));
+        assert_eq!(b.outgoing().len(), 1);
+        assert_eq!(a.incoming().len(), 1);
+        assert_eq!(a.connection_count(), 1);
+
+        // Directed connections are flipped when needed
+        assert_eq!(graph.set_direction(&link, &a), Ok(()));
+        assert!(link.get_directed_from().unwrap() == "a");
+        assert_eq!(graph.set_direction(&link, &a), Ok(()));
+        assert_eq!(graph.set_direction(&link, &c), Err(()));
+        assert!(link.get_directed_from().unwrap() == "a");
+
+        graph.kill_things(|name| *name == "a");
+        assert!(killed.get());
+        assert_eq!(graph.set_direction(&link, &b), Err(()));
+        test_util::assert_graph_sound(&graph);
     }
 
     #[test]
-    fn task_dependency_graph() {
-        #[derive(Debug, Clone, PartialEq)]
-        struct Task {
-            name: String,
-            estimated_hours: u32,
-            completed: bool,
+    fn adjacency_listing_order_is_stable() {
+        let (mut graph, handles) = fixtures::knowledge_graph();
+        let alice = handles.alice;
+        let extra = graph.new_thing("Extra");
+        graph.new_undirected_connection([alice.clone(), extra.clone()], "knows");
+        graph.new_directed_connection(alice.clone(), "likes to eat", extra);
+
+        let first = graph.adjacency_listing();
+        let second = graph.adjacency_listing();
+        assert_eq!(first.len(), second.len());
+        for ((thing, connections), (again, again_connections)) in first.iter().zip(&second) {
+            assert!(thing.ptr_eq(again));
+            assert_eq!(connections.len(), again_connections.len());
+            assert!(
+                connections
+                    .iter()
+                    .zip(again_connections)
+                    .all(|(a, b)| a.ptr_eq(b))
+            );
         }
 
-        #[derive(Debug, Clone, PartialEq)]
-        enum TaskRelation {
-            DependsOn,
-            // Blocks,
-            // PartOf,
+        // Removing unrelated items keeps the survivors in their order
+        graph.kill_things(|name| *name == "Pears" || *name == "Pear");
+        graph.clean();
+        let expected: Vec<_> = first
+            .iter()
+            .filter(|(thing, _)| thing.is_alive())
+            .map(|(thing, connections)| {
+                let alive: Vec<_> = connections
+                    .iter()
+                    .filter(|c| c.is_alive())
+                    .cloned()
+                    .collect();
+                (thing.clone(), alive)
+            })
+            .collect();
+        let after = graph.adjacency_listing();
+        assert_eq!(after.len(), first.len() - 2);
+        assert_eq!(after.len(), expected.len());
+        for ((thing, connections), (kept, kept_connections)) in after.iter().zip(&expected) {
+            assert!(thing.ptr_eq(kept));
+            assert_eq!(connections.len(), kept_connections.len());
+            assert!(
+                connections
+                    .iter()
+                    .zip(kept_connections)
+                    .all(|(a, b)| a.ptr_eq(b))
+            );
         }
+        let alice_row = after.iter().find(|(thing, _)| *thing == "Alice").unwrap();
+        assert_eq!(alice_row.1.len(), 3);
+    }
 
-        let mut project = Things::<Task, TaskRelation>::new();
+    #[test]
+    fn subtree_eq_compares_reachable_structure() {
+        let mut graph = Things::<&str, &str>::new();
+        let branch = |graph: &mut Things<&'static str, &'static str>, leaf: &'static str| {
+            let top = graph.new_thing("menu");
+            let item = graph.new_thing("item");
+            let detail = graph.new_thing(leaf);
+            graph.new_directed_connection(top.clone(), "contains", item.clone());
+            graph.new_directed_connection(item.clone(), "shows", detail);
+            // A cycle back to the top of the branch
+            graph.new_directed_connection(item, "back", top.clone());
+            top
+        };
+        let first = branch(&mut graph, "price");
+        let second = branch(&mut graph, "price");
+        let divergent = branch(&mut graph, "photo");
+
+        assert!(first.subtree_eq(&second));
+        assert!(second.subtree_eq(&first));
+        assert!(first.subtree_eq(&first));
+        assert!(!first.subtree_eq(&divergent));
+
+        // Extra structure below a node breaks the match, undirected or not
+        let item = second.follow(&"contains").remove(0);
+        let note = graph.new_thing("note");
+        graph.new_undirected_connection([item, note], "contains");
+        assert!(!first.subtree_eq(&second));
+        graph.kill_things(|name| *name == "note");
+        assert!(first.subtree_eq(&second));
+    }
 
-        // Create tasks
-        let design = project.new_thing(Task {
-            name: "Design System".to_string(),
-            estimated_hours: 40,
-            completed: true,
-        });
+    #[test]
+    fn connection_history_is_bounded_and_opt_in() {
+        let config = ThingsConfig::new().connection_history_depth(3);
+        let mut graph = Things::<&str, u32>::with_config(config);
+        let a = graph.new_thing("a");
+        let b = graph.new_thing("b");
+        let before = graph.new_directed_connection(a.clone(), 0, b.clone());
+
+        // While off, connections carry nothing and record nothing
+        before.set(1);
+        assert_eq!(before.replace(2), 1);
+        assert!(before.history().is_empty());
+        assert!(before.inner.borrow().history().is_none());
+
+        graph.record_connection_history(true);
+        let after = graph.new_directed_connection(b, 100, a);
+        for value in 3..=7 {
+            before.set(value);
+        }
+        assert_eq!(before.history(), [6, 5, 4]);
+        after.access_mut(|value| *value += 1);
+        assert_eq!(after.replace(200), 101);
+        assert_eq!(after.history(), [101]);
+
+        // Reading the history doesn't conflict with reading the data
+        assert_eq!(after.access(|_| after.history()), [101]);
+
+        after.clear_history();
+        assert!(after.history().is_empty());
+        after.set(300);
+        assert_eq!(after.history(), [200]);
+
+        graph.record_connection_history(false);
+        assert!(before.history().is_empty());
+        assert!(after.inner.borrow_mut().history_mut().is_none());
+        before.set(8);
+        assert!(before.history().is_empty());
+    }
 
-        let implement_auth = project.new_thing(Task {
-            name: "Implement Authentication".to_string(),
-            estimated_hours: 20,
-            completed: false,
-        });
+    #[test]
+    fn top_k_central_ranks_the_hub_first() {
+        let project = fixtures::task_dag().0;
+        // Integration Testing waits on two tasks and unlocks a third
+        let top = project.top_k_central(1, Centrality::Degree);
+        assert_eq!(top.len(), 1);
+        assert!(top[0].0 == "Integration Testing" && top[0].1 == 3.0);
+
+        let all = project.top_k_central(usize::MAX, Centrality::Degree);
+        assert_eq!(all.len(), 5);
+        assert!(all.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+        // Equal scores keep container order
+        assert!(all[1].0 == "Design" && all[2].0 == "Implement Auth");
+        assert!(all[4].0 == "Deployment");
+        assert!(project.top_k_central(0, Centrality::Degree).is_empty());
+    }
 
-        let implement_ui = project.new_thing(Task {
-            name: "Implement UI".to_string(),
-            estimated_hours: 60,
-            completed: false,
-        });
+    #[test]
+    #[cfg(feature = "float")]
+    fn top_k_central_float_measures() {
+        let graph = fixtures::knowledge_graph().0;
+        let ranks = graph.top_k_central(usize::MAX, Centrality::PageRank);
+        let total: f64 = ranks.iter().map(|(_, rank)| rank).sum();
+        assert!(total > 0.999 && total < 1.001);
+        // Everything flows into Fruit
+        assert!(ranks[0].0 == "Fruit");
+
+        let broker = graph.top_k_central(1, Centrality::Betweenness);
+        let best = graph
+            .betweenness()
+            .into_iter()
+            .map(|(_, score)| score)
+            .fold(0.0, f64::max);
+        assert_eq!(broker[0].1, best);
+    }
 
-        let testing = project.new_thing(Task {
-            name: "Integration Testing".to_string(),
-            estimated_hours: 30,
-            completed: false,
-        });
+    #[test]
+    fn from_adjacency_builds_keyed_graphs() {
+        let nodes = BTreeMap::from([(1, "Design"), (2, "Build"), (3, "Ship"), (4, "Team")]);
+        let adjacency = BTreeMap::from([
+            (1, alloc::vec![("unlocks", 2, true)]),
+            (2, alloc::vec![("unlocks", 3, true), ("owned by", 4, false)]),
+        ]);
+        let graph = Things::<&str, &str>::from_adjacency(nodes.clone(), adjacency).unwrap();
+        assert_eq!(graph.things.len(), 4);
+        assert_eq!(graph.connections.len(), 3);
+        assert!(graph.things[0] == "Design" && graph.things[3] == "Team");
+        let build = graph.find_thing_equal(&"Build").unwrap();
+        assert!(build.follow(&"unlocks")[0] == "Ship");
+        assert_eq!(build.incoming().len(), 1);
+        let team = graph.find_thing_equal(&"Team").unwrap();
+        assert!(team.follow(&"owned by")[0] == "Build");
+        test_util::assert_graph_sound(&graph);
+
+        let dangling = BTreeMap::from([(3, alloc::vec![("unlocks", 5, true)])]);
+        assert_eq!(
+            Things::<&str, &str>::from_adjacency(nodes.clone(), dangling).err(),
+            Some(AdjacencyError::UnknownTarget { from: 3, to: 5 })
+        );
+        let unknown = BTreeMap::from([(9, Vec::new())]);
+        assert_eq!(
+            Things::<&str, &str>::from_adjacency(nodes, unknown).err(),
+            Some(AdjacencyError::UnknownSource(9))
+        );
+    }
 
-        let deployment = project.new_thing(Task {
-            name: "Deployment".to_string(),
-            estimated_hours: 10,
-            completed: false,
-        });
+    #[test]
+    fn execution_plan_follows_dependencies() {
+        let (mut project, handles) = fixtures::task_dag();
+        let unlocks = |connection: &Connection<&str, &str>| *connection == "unlocks";
+        let mut plan = project.execution_order(unlocks).unwrap();
+
+        let design = plan.next_ready().unwrap();
+        assert!(design == "Design");
+        assert!(plan.next_ready().is_none());
+        let testing = handles.testing;
+        assert_eq!(plan.mark_done(&testing), Err(()));
+        plan.mark_done(&design).unwrap();
+        assert_eq!(plan.mark_done(&design), Err(()));
+
+        let first = plan.next_ready().unwrap();
+        let second = plan.next_ready().unwrap();
+        assert!(first != second);
+        assert!(first == "Implement Auth" || first == "Implement UI");
+        assert!(second == "Implement Auth" || second == "Implement UI");
+        plan.mark_done(&first).unwrap();
+        assert!(plan.next_ready().is_none());
+        plan.mark_done(&second).unwrap();
+
+        let testing = plan.next_ready().unwrap();
+        assert!(testing == "Integration Testing");
+        assert!(!plan.is_finished());
+        plan.mark_done(&testing).unwrap();
+        let deployment = plan.next_ready().unwrap();
+        assert!(deployment == "Deployment");
+        plan.mark_done(&deployment).unwrap();
+        assert!(plan.next_ready().is_none());
+        assert!(plan.is_finished());
+
+        let design = handles.design;
+        let deployment = handles.deployment;
+        project.new_directed_connection(deployment, "unlocks", design);
+        assert!(project.execution_order(unlocks).is_err());
+    }
 
-        // Create dependencies
-        project.new_directed_connection(
-            implement_auth.clone(),
-            TaskRelation::DependsOn,
-            design.clone(),
+    #[test]
+    fn aggregates_over_live_items() {
+        let mut graph = Things::<u64, u64>::new();
+        assert_eq!(
+            graph.aggregate_things(|value| *value),
+            AggregateResult::default()
         );
-        project.new_directed_connection(
-            implement_ui.clone(),
-            TaskRelation::DependsOn,
-            design.clone(),
+        let empty = graph.aggregate_connections(|value| *value);
+        assert_eq!(
+            (empty.count, empty.min, empty.max, empty.sum),
+            (0, None, None, 0)
         );
-        project.new_directed_connection(
-            testing.clone(),
-            TaskRelation::DependsOn,
-            implement_auth.clone(),
+
+        let things: Vec<_> = [5, 1, 9, 4]
+            .into_iter()
+            .map(|value| graph.new_thing(value))
+            .collect();
+        graph.new_directed_connection(things[0].clone(), 10, things[1].clone());
+        graph.new_directed_connection(things[1].clone(), 20, things[2].clone());
+        graph.new_undirected_connection([things[2].clone(), things[3].clone()], 30);
+        graph.kill_things(|value| *value == 9);
+
+        let alive = graph.aggregate_things(|value| *value);
+        assert_eq!(
+            alive,
+            AggregateResult {
+                min: Some(1),
+                max: Some(5),
+                sum: 10,
+                count: 3
+            }
         );
-        project.new_directed_connection(
-            testing.clone(),
-            TaskRelation::DependsOn,
-            implement_ui.clone(),
+        let even = graph.aggregate_things_filtered(|value| value % 2 == 0, |value| *value);
+        assert_eq!((even.count, even.sum, even.min), (1, 4, Some(4)));
+        let connections = graph.aggregate_connections(|value| *value);
+        assert_eq!(
+            connections,
+            AggregateResult {
+                min: Some(10),
+                max: Some(10),
+                sum: 10,
+                count: 1
+            }
         );
-        project.new_directed_connection(
-            deployment.clone(),
-            TaskRelation::DependsOn,
-            testing.clone(),
+        let none = graph.aggregate_connections_filtered(|value| *value > 10, |value| *value);
+        assert_eq!(none.count, 0);
+        assert_eq!(graph.aggregate_things(|_| u64::MAX).sum, u64::MAX);
+    }
+
+    #[test]
+    fn condensation_collapses_a_cycle() {
+        let mut graph = Things::<&str, &str>::new();
+        let a = graph.new_thing("a");
+        let b = graph.new_thing("b");
+        let c = graph.new_thing("c");
+        let x = graph.new_thing("x");
+        let y = graph.new_thing("y");
+        let z = graph.new_thing("z");
+        graph.new_directed_connection(a.clone(), "next", b.clone());
+        graph.new_directed_connection(b.clone(), "next", c.clone());
+        graph.new_directed_connection(c.clone(), "next", a.clone());
+        graph.new_directed_connection(x, "feeds", a.clone());
+        graph.new_directed_connection(c.clone(), "feeds", y.clone());
+        graph.new_directed_connection(a.clone(), "also feeds", y.clone());
+        graph.new_directed_connection(y, "feeds", z);
+        assert!(graph.has_cycle(|_| true));
+
+        let condensed = graph.condensation();
+        assert!(!condensed.has_cycle(|_| true));
+        assert_eq!(condensed.things.len(), 4);
+        let cycle = &condensed.things[0];
+        assert!(cycle.access(|members| {
+            members.len() == 3
+                && members[0].ptr_eq(&a)
+                && members[1].ptr_eq(&b)
+                && members[2].ptr_eq(&c)
+        }));
+        assert!(
+            condensed.things[1..]
+                .iter()
+                .all(|thing| thing.access(|members| members.len() == 1))
         );
+        // Both connections from the cycle to y are kept, the cycle itself is gone
+        assert_eq!(condensed.connections.len(), 4);
+        assert_eq!(cycle.incoming().len(), 1);
+        assert_eq!(cycle.outgoing().len(), 2);
+        assert!(cycle.follow(&"feeds")[0].access(|members| members[0] == "y"));
+        test_util::assert_graph_sound(&condensed);
+    }
 
-        // Test: Find all tasks that can be started now (dependencies completed)
-        let incomplete_tasks: Vec<_> = project.do_for_all_things(|task| {
-            return if !task.access(|data| data.completed) {
-                Do::Take(task.clone())
+    #[test]
+    fn panicking_kill_predicates_leave_a_sound_graph() {
+        extern crate std;
+        use std::panic::{AssertUnwindSafe, catch_unwind};
+
+        let mut graph = fixtures::task_dag().0;
+        let seen = core::cell::Cell::new(0);
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            graph.kill_things(|_| {
+                seen.set(seen.get() + 1);
+                if seen.get() == 3 {
+                    panic!("predicate failed");
+                }
+                true
+            })
+        }));
+        assert!(result.is_err());
+        assert_eq!(seen.get(), 3);
+        assert!(graph.dead_amount > 0);
+        test_util::assert_graph_sound(&graph);
+
+        let mut graph = fixtures::knowledge_graph().0;
+        let calls = core::cell::Cell::new(0);
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            graph.kill_connections(|_| {
+                calls.set(calls.get() + 1);
+                if calls.get() == 4 {
+                    panic!("predicate failed");
+                }
+                true
+            })
+        }));
+        assert!(result.is_err());
+        assert_eq!(graph.dead_amount, 3);
+        test_util::assert_graph_sound(&graph);
+        graph.clean();
+        test_util::assert_graph_sound(&graph);
+    }
+
+    #[test]
+    fn panicking_callbacks_leave_a_sound_graph() {
+        extern crate std;
+        use std::panic::{AssertUnwindSafe, catch_unwind};
+
+        // A kill callback failing halfway through a cascade
+        let (mut graph, handles) = fixtures::task_dag();
+        let testing = handles.testing;
+        let connections = testing.do_for_all_connections(|connection| Do::Take(connection.clone()));
+        connections[0].on_kill(|_| panic!("callback failed"));
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            graph.kill_things(|name| *name == "Integration Testing")
+        }));
+        assert!(result.is_err());
+        assert_eq!(graph.dead_amount, 1);
+        test_util::assert_graph_sound(&graph);
+
+        // A progress callback failing while cleaning
+        graph.kill_things(|name| *name == "Deployment");
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            graph.clean_with_progress(|_| panic!("report failed"))
+        }));
+        assert!(result.is_err());
+        assert_eq!(graph.dead_amount, 0);
+        assert!(graph.things.iter().all(|thing| thing.is_alive()));
+        test_util::assert_graph_sound(&graph);
+
+        // A merge failing after the duplicates were picked
+        let mut chat = Things::<&str, u32>::new();
+        let alice = chat.new_thing("alice");
+        let bob = chat.new_thing("bob");
+        for _ in 0..3 {
+            chat.new_directed_connection(alice.clone(), 1, bob.clone());
+        }
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            chat.merge_parallel_connections(true, |_, _| panic!("merge failed"))
+        }));
+        assert!(result.is_err());
+        assert_eq!(chat.connections.len(), 1);
+        test_util::assert_graph_sound(&chat);
+    }
+
+    #[test]
+    fn connection_ids_survive_cleaning() {
+        let (mut graph, handles) = fixtures::knowledge_graph();
+        let alice = handles.alice;
+        let likes = alice.do_for_all_connections(|connection| {
+            if *connection == "likes to eat" {
+                Do::Take(connection.clone())
             } else {
                 Do::Nothing
-            };
+            }
         });
+        let id = likes[0].id();
+        assert!(graph.get_connection_by_id(id).unwrap().ptr_eq(&likes[0]));
 
-        let ready_tasks: Vec<_> = incomplete_tasks
+        // Unrelated cleanup moves the connection within the container
+        graph.kill_things(|name| *name == "Apple" || *name == "Pear");
+        graph.clean();
+        let resolved = graph.get_connection_by_id(id).unwrap();
+        assert!(resolved.ptr_eq(&likes[0]));
+        assert!(resolved.id() == id);
+
+        let other = graph.connections.last().unwrap().id();
+        assert!(other != id);
+        assert!(!graph.get_connection_by_id(other).unwrap().ptr_eq(&likes[0]));
+    }
+
+    #[test]
+    fn eccentricity_is_lowest_in_the_center() {
+        let mut graph = Things::<usize, ()>::new();
+        let path: Vec<_> = (0..5).map(|position| graph.new_thing(position)).collect();
+        for pair in path.windows(2) {
+            graph.new_undirected_connection([pair[0].clone(), pair[1].clone()], ());
+        }
+        let lonely = graph.new_thing(5);
+
+        assert_eq!(graph.eccentricity(&path[2]), Some(2));
+        assert_eq!(graph.eccentricity(&path[0]), Some(4));
+        assert_eq!(graph.eccentricity(&path[4]), Some(4));
+        assert_eq!(graph.eccentricity(&lonely), None);
+
+        // Directed connections only count forwards
+        let mut chain = Things::<usize, ()>::new();
+        let first = chain.new_thing(0);
+        let last = chain.new_thing(1);
+        chain.new_directed_connection(first.clone(), (), last.clone());
+        assert_eq!(chain.eccentricity(&first), Some(1));
+        assert_eq!(chain.eccentricity(&last), None);
+    }
+
+    #[test]
+    fn join_connects_matching_things_across_graphs() {
+        let mut people = Things::<(&str, u32), &str>::new();
+        let alice = people.new_thing(("alice", 1));
+        let bob = people.new_thing(("bob", 2));
+        people.new_thing(("carol", 1));
+        people.new_undirected_connection([alice, bob], "knows");
+        let mut companies = Things::<(&str, u32), &str>::new();
+        companies.new_thing(("acme", 1));
+        companies.new_thing(("initech", 2));
+        companies.new_thing(("globex", 3));
+
+        let joined = Things::join(&people, &companies, |person, company| {
+            (person.1 == company.1).then_some("works_at")
+        });
+        assert_eq!(joined.things.len(), 6);
+        let works_at: Vec<_> = joined
+            .connections
             .iter()
-            .map(|task| {
-                if task
-                    .do_for_all_connections(|conn| {
-                        if let Ok(Direction::AwayFrom) = conn.get_direction_relative_to(task) {
-                            conn.access(|data| {
-                                return if matches!(data, TaskRelation::DependsOn) {
-                                    return if let Some(to) = conn.get_directed_towards() {
-                                        Do::Take(to.access(|data| data.completed))
-                                    } else {
-                                        Do::Nothing
-                                    };
-                                } else {
-                                    Do::Nothing
-                                };
-                            })
-                        } else {
-                            Do::Nothing
-                        }
-                    })
-                    .iter()
-                    .all(|x| *x)
-                {
-                    Some(task.clone())
-                } else {
-                    None
-                }
+            .filter(|connection| **connection == "works_at")
+            .map(|connection| {
+                let from = connection.get_directed_from().unwrap().get_copy().0;
+                let to = connection.get_directed_towards().unwrap().get_copy().0;
+                (from, to)
             })
-            .filter_map(|v| v.clone())
-            .map(|v| v.access(|data| data.name.clone()))
             .collect();
+        assert_eq!(
+            works_at,
+            [("alice", "acme"), ("bob", "initech"), ("carol", "acme")]
+        );
+        assert!(joined.find_connection_equal(&"knows").is_some());
+        test_util::assert_graph_sound(&joined);
+
+        // The inputs are left alone
+        assert_eq!(people.things.len(), 3);
+        assert_eq!(people.connections.len(), 1);
+        assert_eq!(companies.connections.len(), 0);
+
+        let keyed = Things::join_by_key(
+            &people,
+            &companies,
+            |person| person.1,
+            |company| company.1,
+            |_, _| "works_at",
+        );
+        assert_eq!(keyed.connections.len(), joined.connections.len());
+        for (keyed, joined) in keyed.connections.iter().zip(joined.connections.iter()) {
+            assert!(keyed.access(|data| joined == data));
+            for (keyed_end, joined_end) in keyed.ends().iter().zip(joined.ends().iter()) {
+                assert!(keyed_end.access(|data| joined_end == data));
+            }
+        }
+    }
 
-        // Only Auth and UI should be ready (Design is completed)
-        assert!(ready_tasks.contains(&"Implement Authentication".to_string()));
-        assert!(ready_tasks.contains(&"Implement UI".to_string()));
-        assert!(!ready_tasks.contains(&"Integration Testing".to_string())); // Depends on incomplete tasks
-        assert!(!ready_tasks.contains(&"Deployment".to_string())); // Depends on incomplete tasks
+    #[test]
+    fn fixtures_build_sound_graphs() {
+        let (graph, knowledge) = fixtures::knowledge_graph();
+        assert_eq!((graph.things.len(), graph.connections.len()), (6, 6));
+        assert!(knowledge.alice == "Alice" && knowledge.fruit == "Fruit");
+        assert!(knowledge.apples.follow(&"plural of")[0].ptr_eq(&knowledge.apple));
+        assert!(knowledge.pear.follow(&"is")[0].ptr_eq(&knowledge.fruit));
+        test_util::assert_graph_sound(&graph);
+
+        let (graph, people) = fixtures::social_network();
+        assert_eq!((graph.things.len(), graph.connections.len()), (4, 4));
+        assert_eq!(people.bob.neighbors_alive().len(), 2);
+        assert!(people.carol.follow(&"friends").len() == 2);
+        assert!(people.alice.follow(&"follows").is_empty());
+        assert!(people.dave == "Dave");
+        test_util::assert_graph_sound(&graph);
+
+        let (project, tasks) = fixtures::task_dag();
+        assert_eq!((project.things.len(), project.connections.len()), (5, 5));
+        assert!(tasks.auth == "Implement Auth" && tasks.ui == "Implement UI");
+        assert!(tasks.testing.follow(&"unlocks")[0].ptr_eq(&tasks.deployment));
+        assert!(!project.has_cycle(|_| true));
+        test_util::assert_graph_sound(&project);
     }
 
     #[test]
-    fn memory_pressure_tracking() {
-        let mut graph = Things::new();
+    fn sources_of_finds_followers_in_one_call() {
+        let (mut graph, people) = fixtures::social_network();
+        let eve = graph.new_thing("Eve");
+        graph.new_directed_connection(eve.clone(), "follows", people.alice.clone());
+        graph.new_directed_connection(eve.clone(), "follows", people.alice.clone());
+        graph.new_directed_connection(people.alice.clone(), "follows", people.bob.clone());
+        graph.new_directed_connection(people.carol.clone(), "blocks", people.alice.clone());
+
+        let followers = graph.sources_of(&people.alice, |label| *label == "follows");
+        assert_eq!(followers.len(), 2);
+        assert!(followers[0].ptr_eq(&people.dave));
+        assert!(followers[1].ptr_eq(&eve));
+
+        let followed = graph.targets_of(&people.alice, |label| *label == "follows");
+        assert_eq!(followed.len(), 1);
+        assert!(followed[0].ptr_eq(&people.bob));
+
+        // Friendships are undirected, so they are neither
+        assert!(
+            graph
+                .sources_of(&people.bob, |label| *label == "friends")
+                .is_empty()
+        );
 
-        // Create some items
-        let thing1 = graph.new_thing("Thing1");
-        let thing2 = graph.new_thing("Thing2");
-        let thing3 = graph.new_thing("Thing3");
+        graph.kill_things(|name| *name == "Dave");
+        let followers = graph.sources_of(&people.alice, |label| *label == "follows");
+        assert_eq!(followers.len(), 1);
+    }
 
-        let _conn1 = graph.new_directed_connection(thing1, "relates", thing2.clone());
-        let _conn2 = graph.new_directed_connection(thing2, "relates", thing3);
+    #[test]
+    fn recount_reports_and_fixes_drift() {
+        let (mut graph, knowledge) = fixtures::knowledge_graph();
+        let clean = graph.recount();
+        assert_eq!(
+            clean,
+            RecountReport {
+                previous: 0,
+                actual_dead_things: 0,
+                actual_dead_connections: 0,
+                corrected: false,
+            }
+        );
 
-        // Initially, no dead items
-        assert_eq!(graph.dead_percentage().unwrap(), 0);
+        // Killing a dead thing again used to count it twice
+        graph.kill_things(|name| *name == "Alice");
+        graph.kill_things(|name| *name == "Alice");
+        assert!(!knowledge.alice.is_alive());
+        let report = graph.recount();
+        assert_eq!(report.previous, 3);
+        assert_eq!(
+            (report.actual_dead_things, report.actual_dead_connections),
+            (1, 2)
+        );
+        assert!(!report.corrected);
 
-        // Kill one thing (should kill the thing and its connections)
-        graph.kill_things(|thing| thing.access(|data| data == &"Thing1"));
+        // Drift from anywhere else is found and replaced
+        graph.dead_amount += 4;
+        let report = graph.recount();
+        assert_eq!(report.previous, 7);
+        assert!(report.corrected);
+        assert_eq!(graph.dead_amount, 3);
+        assert!(!graph.recount().corrected);
 
-        // Should have some dead percentage now
-        let percentage_after_kill = graph.dead_percentage().unwrap();
-        assert!(percentage_after_kill > 0);
-        assert!(percentage_after_kill <= 100);
+        graph.clean();
+        assert_eq!(graph.recount().previous, 0);
+        test_util::assert_graph_sound(&graph);
+    }
+
+    #[test]
+    fn clean_unordered_keeps_the_same_items_as_clean() {
+        fn build() -> Things<u32, u32> {
+            let mut graph = Things::new();
+            let things: Vec<_> = (0..200).map(|number| graph.new_thing(number)).collect();
+            for (position, pair) in things.windows(2).enumerate() {
+                graph.new_directed_connection(pair[0].clone(), position as u32, pair[1].clone());
+            }
+            graph.kill_things(|thing| thing.access(|number| number % 3 == 0));
+            graph.kill_connections(|label| label.access(|label| label % 7 == 0));
+            graph
+        }
+        fn contents(graph: &Things<u32, u32>) -> (Vec<u32>, Vec<(u32, u32, u32)>) {
+            let mut things: Vec<_> = graph.things.iter().map(|thing| thing.get_copy()).collect();
+            let mut connections: Vec<_> = graph
+                .connections
+                .iter()
+                .map(|connection| {
+                    let [from, to] = connection.ends();
+                    (connection.get_copy(), from.get_copy(), to.get_copy())
+                })
+                .collect();
+            things.sort();
+            connections.sort();
+            (things, connections)
+        }
+
+        let mut ordered = build();
+        let mut unordered = build();
+        ordered.clean();
+        unordered.clean_unordered();
+
+        assert_eq!(contents(&ordered), contents(&unordered));
+        assert_eq!(unordered.dead_amount, 0);
+        test_util::assert_graph_sound(&unordered);
+        for thing in unordered.things.iter() {
+            let before = ordered.find_thing_equal(&thing.get_copy()).unwrap();
+            let lists = |thing: &Thing<u32, u32>| {
+                thing.do_for_all_connections(|connection| Do::Take(connection.get_copy()))
+            };
+            assert_eq!(lists(thing), lists(&before));
+        }
+    }
+
+    #[test]
+    fn directional_views_only_see_their_side() {
+        let (mut graph, tasks) = fixtures::task_dag();
+        let review = graph.new_thing("Review");
+        graph.new_undirected_connection([tasks.testing.clone(), review.clone()], "pairs with");
+        graph.new_directed_connection(tasks.testing.clone(), "retries", tasks.testing.clone());
+
+        let outgoing = tasks.testing.outgoing_view(false);
+        let incoming = tasks.testing.incoming_view(false);
+        assert_eq!(outgoing.direction(), Direction::AwayFrom);
+        assert_eq!(incoming.direction(), Direction::Towards);
+        let expected: Vec<_> = tasks
+            .testing
+            .outgoing()
+            .into_iter()
+            .map(|(connection, next)| (connection.get_copy(), next.get_copy()))
+            .collect();
+        let seen: Vec<_> = outgoing
+            .connections()
+            .into_iter()
+            .map(|connection| connection.get_copy())
+            .zip(outgoing.neighbors().into_iter().map(|next| next.get_copy()))
+            .collect();
+        assert_eq!(seen, expected);
+        assert_eq!(incoming.count(), tasks.testing.incoming().len());
+        assert_eq!(incoming.neighbors().len(), 3);
+
+        // Neither side reaches the other
+        assert!(outgoing.find(|_, next| next.ptr_eq(&tasks.auth)).is_none());
+        assert!(
+            incoming
+                .find(|_, next| next.ptr_eq(&tasks.deployment))
+                .is_none()
+        );
+        assert!(outgoing.find(|_, next| next.ptr_eq(&review)).is_none());
+
+        // Undirected connections are on both sides when asked for
+        let outgoing = tasks.testing.outgoing_view(true);
+        let incoming = tasks.testing.incoming_view(true);
+        assert!(outgoing.find(|_, next| next.ptr_eq(&review)).is_some());
+        assert!(incoming.find(|_, next| next.ptr_eq(&review)).is_some());
+        assert_eq!(outgoing.count(), 3);
+        let labels = incoming.fold(Vec::new(), |mut labels, connection, _| {
+            labels.push(connection.get_copy());
+            labels
+        });
+        assert_eq!(labels, ["unlocks", "unlocks", "pairs with", "retries"]);
+
+        graph.kill_things(|name| *name == "Review");
+        assert_eq!(tasks.testing.outgoing_view(true).count(), 2);
+    }
+
+    #[test]
+    fn same_component_meets_in_the_middle() {
+        let (mut graph, people) = fixtures::social_network();
+        let erin = graph.new_thing("Erin");
+        let frank = graph.new_thing("Frank");
+        graph.new_undirected_connection([erin.clone(), frank.clone()], "friends");
+        let hermit = graph.new_thing("Hermit");
+
+        // Direction doesn't matter
+        assert!(graph.same_component(&people.bob, &people.dave));
+        assert!(graph.same_component(&people.dave, &people.carol));
+        assert!(graph.same_component(&erin, &frank));
+        assert!(graph.same_component(&hermit, &hermit));
+
+        assert!(!graph.same_component(&people.alice, &erin));
+        assert!(!graph.same_component(&frank, &people.dave));
+        assert!(!graph.same_component(&hermit, &people.bob));
+
+        graph.kill_connections(|connection| *connection == "follows");
+        assert!(!graph.same_component(&people.dave, &people.alice));
+        graph.kill_things(|name| *name == "Frank");
+        assert!(!graph.same_component(&erin, &frank));
+    }
+
+    #[test]
+    fn export_with_labels_lends_labels_without_copying() {
+        let mut graph = Things::<String, (u8, String)>::new();
+        let quoted = graph.new_thing("say \"hi\"".to_string());
+        let plain = graph.new_thing("plain".to_string());
+        let gone = graph.new_thing("gone".to_string());
+        let edge = graph
+            .new_undirected_connection([quoted.clone(), plain.clone()], (1, "a<b".to_string()));
+        graph.new_directed_connection(plain.clone(), (2, "to gone".to_string()), gone);
+        graph.new_directed_connection(plain.clone(), (3, "loop".to_string()), plain.clone());
+        graph.kill_things(|name| name.access(|name| name == "gone"));
+
+        let mut things = Vec::new();
+        let mut connections = Vec::new();
+        graph.export_with_labels(
+            String::as_str,
+            |(_, label)| label.as_str(),
+            |item| match item {
+                ExportItem::Thing { index, label } => {
+                    // The label points into the data itself
+                    let data_ptr = graph.things[index].access(|data| data.as_ptr());
+                    assert!(core::ptr::eq(label.as_ptr(), data_ptr));
+                    things.push((index, label.to_string()));
+                }
+                ExportItem::Connection {
+                    id,
+                    ends,
+                    directed,
+                    label,
+                } => {
+                    connections.push((id, ends, directed, label.to_string()));
+                }
+            },
+        );
+
+        // Labels are handed over unescaped
+        assert_eq!(
+            things,
+            [(0, "say \"hi\"".to_string()), (1, "plain".to_string())]
+        );
+        assert_eq!(connections.len(), 2);
+        assert_eq!(
+            connections[0],
+            (edge.id(), [0, 1], false, "a<b".to_string())
+        );
+        assert_eq!(connections[1].1, [1, 1]);
+        assert!(connections[1].2);
+    }
+
+    #[test]
+    fn health_recommends_fixing_the_worst_problem() {
+        let (mut graph, people) = fixtures::social_network();
+        let healthy = graph.health();
+        assert_eq!(
+            healthy,
+            HealthReport {
+                dead_percentage: Some(0),
+                components: 1,
+                orphaned_connections: 0,
+                parallel_connections: 0,
+                recommendation: Recommendation::None,
+            }
+        );
+        graph.new_thing("Hermit");
+        graph.new_directed_connection(people.carol.clone(), "follows", people.carol.clone());
+        let report = graph.health();
+        assert_eq!(report.components, 2);
+        assert_eq!(report.parallel_connections, 0);
+
+        graph.new_undirected_connection([people.bob.clone(), people.alice.clone()], "friends");
+        let report = graph.health();
+        assert_eq!(report.parallel_connections, 1);
+        assert_eq!(report.recommendation, Recommendation::Dedup);
+        let lenient = HealthThresholds {
+            max_parallel_connections: 1,
+            ..HealthThresholds::default()
+        };
+        assert_eq!(
+            graph.health_with(lenient).recommendation,
+            Recommendation::None
+        );
 
-        // Clean up and verify percentage returns to 0
+        graph.kill_things(|name| *name == "Alice");
+        let report = graph.health();
+        assert!(report.dead_percentage.unwrap() > 25);
+        assert_eq!(report.recommendation, Recommendation::Clean);
         graph.clean();
-        assert_eq!(graph.dead_percentage().unwrap(), 0);
-
-        // Verify remaining items are still accessible
-        let remaining_things = graph.do_for_all_things(|_| Do::Take(()));
-        assert!(remaining_things.len() > 0); // Should have some things left
+        assert_eq!(graph.health().recommendation, Recommendation::None);
+
+        let stranger = Thing::new("Stranger");
+        graph.new_directed_connection(people.dave.clone(), "follows", stranger);
+        graph.kill_things(|name| *name == "Bob");
+        assert_eq!(
+            graph.health().recommendation,
+            Recommendation::RepairDangling
+        );
+        graph.prune_orphaned();
+        assert_eq!(graph.health().recommendation, Recommendation::Clean);
     }
 
     #[test]
-    fn cascade_deletion_behavior() {
-        let mut graph = Things::new();
+    fn trim_leaves_strips_dangling_chains() {
+        let mut graph = Things::<&str, ()>::new();
+        let core: Vec<_> = ["a", "b", "c", "d"]
+            .iter()
+            .map(|name| graph.new_thing(*name))
+            .collect();
+        for position in 0..core.len() {
+            let next = &core[(position + 1) % core.len()];
+            graph.new_undirected_connection([core[position].clone(), next.clone()], ());
+        }
+        // A chain of three hanging off the cycle, one of two, and a lone thing
+        let x1 = graph.new_thing("x1");
+        let x2 = graph.new_thing("x2");
+        let x3 = graph.new_thing("x3");
+        graph.new_directed_connection(core[0].clone(), (), x1.clone());
+        graph.new_directed_connection(x1.clone(), (), x2.clone());
+        graph.new_directed_connection(x2.clone(), (), x3.clone());
+        let y1 = graph.new_thing("y1");
+        let y2 = graph.new_thing("y2");
+        graph.new_undirected_connection([y1.clone(), core[2].clone()], ());
+        graph.new_undirected_connection([y1.clone(), y2.clone()], ());
+        graph.new_thing("alone");
+
+        // Each round peels one layer
+        assert_eq!(graph.trim_leaves(1), 3);
+        assert!(!x3.is_alive() && !y2.is_alive() && x2.is_alive());
+        test_util::assert_graph_sound(&graph);
+
+        assert_eq!(graph.trim_leaves(0), 3);
+        let survivors: Vec<_> = graph
+            .things
+            .iter()
+            .filter(|thing| thing.is_alive())
+            .map(|thing| thing.get_copy())
+            .collect();
+        assert_eq!(survivors, ["a", "b", "c", "d"]);
+        assert!(core.iter().all(|thing| thing.neighbors_alive().len() == 2));
+        test_util::assert_graph_sound(&graph);
+        assert_eq!(graph.trim_leaves(0), 0);
+    }
 
-        let alice = graph.new_thing("Alice");
-        let bob = graph.new_thing("Bob");
-        let charlie = graph.new_thing("Charlie");
+    fn two_windows() -> Things<&'static str, &'static str> {
+        let mut ui = Things::new();
+        let editor = ui.new_thing("editor");
+        let menu = ui.new_thing("menu");
+        let canvas = ui.new_thing("canvas");
+        let file = ui.new_thing("file");
+        let palette = ui.new_thing("palette");
+        let swatch = ui.new_thing("swatch");
+        ui.new_directed_connection(editor.clone(), "contains", menu.clone());
+        ui.new_directed_connection(editor.clone(), "contains", canvas.clone());
+        ui.new_directed_connection(menu.clone(), "contains", file);
+        ui.new_directed_connection(palette.clone(), "contains", swatch);
+        // Not part of the hierarchy
+        ui.new_directed_connection(palette, "docks to", editor);
+        ui.new_undirected_connection([menu, canvas], "aligned");
+        ui
+    }
 
-        // Create connections: Alice -> Bob, Bob -> Charlie
-        graph.new_directed_connection(alice.clone(), "knows", bob.clone());
-        graph.new_directed_connection(bob.clone(), "knows", charlie.clone());
+    #[test]
+    fn forest_follows_containment_in_layout_order() {
+        let ui = two_windows();
+        let forest = ui.forest(|connection| *connection == "contains").unwrap();
+        let mut outline = Vec::new();
+        for tree in forest.iter() {
+            tree.depth_first(|thing, depth| outline.push((thing.get_copy(), depth)));
+        }
+        assert_eq!(
+            outline,
+            [
+                ("editor", 0),
+                ("menu", 1),
+                ("file", 2),
+                ("canvas", 1),
+                ("palette", 0),
+                ("swatch", 1),
+            ]
+        );
+    }
 
-        // Kill Bob - this should kill Bob and all his connections
-        graph.kill_things(|thing| thing.access(|data| data == &"Bob"));
+    #[test]
+    fn forest_reports_diamonds_and_cycles() {
+        let mut ui = two_windows();
+        let canvas = ui.find_thing_equal(&"canvas").unwrap();
+        let swatch = ui.find_thing_equal(&"swatch").unwrap();
+        ui.new_directed_connection(swatch.clone(), "contains", canvas.clone());
+        match ui.forest(|connection| *connection == "contains") {
+            Err(ForestError::Diamond(thing)) => assert!(thing.ptr_eq(&canvas)),
+            _ => panic!("expected a diamond"),
+        }
 
-        // Alice and Charlie should still be alive
-        assert!(alice.access(|_| true)); // Can still access Alice's data
-        assert!(charlie.access(|_| true)); // Can still access Charlie's data
+        let mut looped = two_windows();
+        let menu = looped.find_thing_equal(&"menu").unwrap();
+        let file = looped.find_thing_equal(&"file").unwrap();
+        looped.kill_connections(|connection| {
+            *connection == "contains" && connection.get_directed_towards().unwrap() == "menu"
+        });
+        looped.new_directed_connection(file, "contains", menu.clone());
+        match looped.forest(|connection| *connection == "contains") {
+            Err(ForestError::Cycle(thing)) => assert!(thing.ptr_eq(&menu)),
+            _ => panic!("expected a cycle"),
+        }
+    }
 
-        // But Bob's connections should be dead
-        let alice_connections = alice.do_for_all_connections(|_| Do::Take(()));
-        // Alice's connection to Bob should still exist but be marked as dead
-        assert!(alice_connections.len() > 0);
+    #[test]
+    fn k_core_drops_pendant_things() {
+        let mut graph = barbell_graph();
+        let pendant = graph.new_thing("pendant");
+        let chain = graph.new_thing("chain");
+        let bar = graph.find_thing_equal(&"a1").unwrap();
+        graph.new_directed_connection(bar, "hangs", pendant.clone());
+        graph.new_directed_connection(pendant, "hangs", chain);
+        graph.new_thing("alone");
+
+        let core = graph.k_core(2);
+        let names: Vec<_> = core.things.iter().map(|thing| thing.get_copy()).collect();
+        let expected: Vec<_> = graph
+            .things
+            .iter()
+            .map(|thing| thing.get_copy())
+            .filter(|name| !["pendant", "chain", "alone"].contains(name))
+            .collect();
+        assert_eq!(names, expected);
+        assert!(core.find_connection_equal(&"hangs").is_none());
+        assert!(
+            core.things
+                .iter()
+                .all(|thing| thing.neighbors_with_edges().len() >= 2)
+        );
+        test_util::assert_graph_sound(&core);
 
-        // After cleanup, dead connections should be removed
-        graph.clean();
-        let alice_connections_after_clean = alice.do_for_all_connections(|_| Do::Take(()));
-        assert_eq!(alice_connections_after_clean.len(), 0); // Alice should have no live connections
+        // The original is left alone
+        assert_eq!(graph.dead_amount, 0);
+        assert!(graph.find_thing_equal(&"pendant").is_some());
+        assert!(graph.k_core(100).things.is_empty());
     }
 
     #[test]
-    fn undirected_connections_behavior() {
-        let mut graph = Things::<String, String>::new();
-
-        let alice = graph.new_thing("Alice".to_string());
-        let bob = graph.new_thing("Bob".to_string());
+    fn edges_sorted_by_orders_by_weight_field() {
+        #[derive(Clone, Copy, PartialEq)]
+        struct Road {
+            weight: u32,
+            id: u32,
+        }
+        let mut graph = Things::<u32, Road>::new();
+        let towns: Vec<_> = (0..4).map(|town| graph.new_thing(town)).collect();
+        for (id, (first, second, weight)) in [(0, 1, 5), (1, 2, 1), (2, 3, 5), (3, 0, 3), (0, 2, 1)]
+            .into_iter()
+            .enumerate()
+        {
+            let road = Road {
+                weight,
+                id: id as u32,
+            };
+            graph.new_undirected_connection([towns[first].clone(), towns[second].clone()], road);
+        }
+        graph.kill_connections(|road| road.access(|road| road.id == 3));
+
+        let by_weight =
+            graph.edges_sorted_by(|a, b| a.access(|a| b.access(|b| a.weight.cmp(&b.weight))));
+        let ids: Vec<_> = by_weight.iter().map(|road| road.get_copy().id).collect();
+        // Ties keep creation order, and the dead road is left out
+        assert_eq!(ids, [1, 4, 0, 2]);
+
+        let heaviest_first =
+            graph.edges_sorted_by(|a, b| b.access(|b| a.access(|a| b.weight.cmp(&a.weight))));
+        assert_eq!(heaviest_first[0].get_copy().id, 0);
+        assert_eq!(heaviest_first.len(), 4);
+    }
 
-        // Create undirected friendship
-        let friendship =
-            graph.new_undirected_connection([alice.clone(), bob.clone()], "friendship".to_string());
+    #[test]
+    fn sharded_cross_shard_connection_lifecycle() {
+        let mut graph = ShardedThings::<&str, &str>::new(2);
+        let alice = graph.new_thing("alice");
+        let bob = graph.new_thing("bob");
+        let carol = graph.new_thing("carol");
+        assert_eq!(
+            [&alice, &bob, &carol].map(|thing| graph.shard_of(thing)),
+            [Some(0), Some(1), Some(0)]
+        );
 
-        let find_friendships = |thing: &Thing<_, _>| {
-            thing.do_for_all_connections(|conn| {
-                conn.access(|data| {
-                    return if data == "friendship" {
-                        Do::Take(conn.clone())
-                    } else {
-                        Do::Nothing
-                    };
-                })
-            })
-        };
+        // Kept by shard 0, the shard of alice, and seen by bob in shard 1
+        let friends = graph.new_undirected_connection([alice.clone(), bob.clone()], "friends");
+        graph.new_directed_connection(bob.clone(), "follows", carol.clone());
+        assert_eq!(graph.shard(0).connections.len(), 1);
+        assert_eq!(graph.shard(1).connections.len(), 1);
+        assert!(graph.get_connection_by_id(friends.id()).is_some());
+        assert_eq!(
+            graph.shortest_path(&alice, &carol, |_| true).unwrap().len(),
+            3
+        );
 
-        let alice_friendships = find_friendships(&alice);
-        let bob_friendships = find_friendships(&bob);
+        // Killing bob in shard 1 kills both connections, each counted where it is kept
+        graph.kill_things(|thing| *thing == "bob");
+        assert!(!friends.is_alive());
+        assert_eq!(graph.shards[0].dead_amount, 1);
+        assert_eq!(graph.shards[1].dead_amount, 2);
+        for shard in graph.shards.iter_mut() {
+            assert!(!shard.recount().corrected);
+        }
+        assert!(graph.get_connection_by_id(friends.id()).is_none());
+
+        graph.clean_shard(1);
+        // Bob and his connection were all that shard 1 had
+        assert_eq!(graph.shard_dead_percentage(1), Err(()));
+        // The dead friendship is still kept by shard 0 until it is cleaned
+        assert_eq!(graph.shard(0).connections.len(), 1);
+        assert_eq!(alice.inner.borrow().connections.len(), 1);
+        graph.clean_shard(0);
+        assert!(graph.shard(0).connections.is_empty());
+        assert!(alice.inner.borrow().connections.is_empty());
+        assert_eq!(graph.dead_percentage(), Ok(0));
+        for shard in graph.shards.iter() {
+            test_util::assert_graph_sound(shard);
+        }
+    }
 
-        // Both Alice and Bob should have the same connection in their lists
+    #[test]
+    fn sharded_pressure_stays_in_its_shard() {
+        let mut graph = ShardedThings::<u32, ()>::with_shard_key(3, |number| *number as usize);
+        let things: Vec<_> = (0..9).map(|number| graph.new_thing(number)).collect();
+        for pair in things.windows(2) {
+            graph.new_undirected_connection([pair[0].clone(), pair[1].clone()], ());
+        }
+        // Shard 1 holds 1, 4 and 7, and keeps the connections starting there
+        graph.kill_connections(|connection| connection.ends()[0].access(|number| number % 3 == 1));
+        assert_eq!(graph.shard_dead_percentage(1), Ok(50));
+        assert_eq!(graph.shard_dead_percentage(0), Ok(0));
+        assert_eq!(graph.shard_dead_percentage(2), Ok(0));
+        assert_eq!(graph.dead_percentage(), Ok(3 * 100 / 17));
+
+        graph.clean_shard(1);
+        assert_eq!(graph.dead_percentage(), Ok(0));
+        assert_eq!(graph.shard(1).connections.len(), 0);
+    }
 
-        assert_eq!(alice_friendships.len(), 1);
-        assert_eq!(bob_friendships.len(), 1);
+    #[test]
+    fn sharded_queries_cover_every_shard() {
+        let mut graph = ShardedThings::<u32, u32>::new(4);
+        let things: Vec<_> = (0..10).map(|number| graph.new_thing(number)).collect();
+        for (index, pair) in things.windows(2).enumerate() {
+            graph.new_directed_connection(pair[0].clone(), index as u32, pair[1].clone());
+        }
+        graph.kill_things(|thing| thing.access(|number| number % 4 == 3));
+
+        let all = graph.do_for_all_things(|thing| Do::Take(thing.get_copy()));
+        let mut union = Vec::new();
+        for shard in 0..graph.shard_count() {
+            union.extend(
+                graph
+                    .shard(shard)
+                    .do_for_all_things(|thing| Do::Take(thing.get_copy())),
+            );
+        }
+        assert_eq!(all, union);
+        assert_eq!(all, [0, 4, 8, 1, 5, 9, 2, 6, 3, 7]);
+
+        let connections =
+            graph.do_for_all_connections(|connection| Do::Take(connection.get_copy()));
+        let mut union = Vec::new();
+        for shard in 0..graph.shard_count() {
+            union.extend(
+                graph
+                    .shard(shard)
+                    .do_for_all_connections(|connection| Do::Take(connection.get_copy())),
+            );
+        }
+        assert_eq!(connections, union);
+        assert_eq!(connections.len(), 9);
+
+        assert!(graph.find_thing_equal(&6).is_some());
+        assert!(graph.find_thing_equal(&7).is_none());
+        assert!(graph.find_connection_equal(&4).is_some());
+        let mut reached = CollectingVisitor::new();
+        graph.visit_bfs(&things[4], &mut reached);
+        assert_eq!(reached.things().len(), 3);
+    }
 
-        // The connection should be marked as undirected
-        assert!(friendship.is_undirected());
-        assert!(!friendship.is_directed());
+    #[test]
+    fn is_connected_tells_one_component_from_two() {
+        let (mut graph, _) = fixtures::social_network();
+        assert!(graph.is_connected());
+
+        // Dave only follows Alice, so losing that link leaves him on his own
+        graph.kill_connections(|connection| *connection == "follows");
+        assert!(!graph.is_connected());
+        graph.kill_things(|thing| *thing == "Dave");
+        assert!(graph.is_connected());
+
+        let mut halves = barbell_graph();
+        assert!(halves.is_connected());
+        halves.kill_connections(|connection| *connection == "bridge");
+        assert!(!halves.is_connected());
+
+        let mut tiny = Things::<&str, ()>::new();
+        assert!(tiny.is_connected());
+        tiny.new_thing("alone");
+        assert!(tiny.is_connected());
+    }
 
-        // Directional methods should return None for undirected connections
-        assert!(friendship.get_directed_from().is_none());
-        assert!(friendship.get_directed_towards().is_none());
+    #[test]
+    fn find_by_data_skips_dead_and_survives_panics() {
+        extern crate std;
+        use std::panic::{AssertUnwindSafe, catch_unwind};
+
+        let (mut graph, people) = fixtures::social_network();
+        assert!(graph.find_thing(|name| name.starts_with('C')).unwrap() == people.carol);
+        assert_eq!(graph.find_things(|name| name.len() == 5).len(), 2);
+        assert_eq!(
+            graph
+                .find_connections(|relation| *relation == "friends")
+                .len(),
+            3
+        );
+        assert!(
+            graph
+                .find_connection(|relation| *relation == "follows")
+                .is_some()
+        );
 
-        // Both people should be reachable from the connection using get_connected_things
-        let connected = friendship.get_things();
-        let names: Vec<String> = connected
-            .iter()
-            .map(|thing| thing.access(|data| data.clone()))
-            .collect();
+        graph.kill_things(|thing| *thing == "Carol");
+        assert!(graph.find_thing(|name| name.starts_with('C')).is_none());
+        assert_eq!(graph.find_things(|name| name.len() == 5).len(), 1);
+        assert_eq!(
+            graph
+                .find_connections(|relation| *relation == "friends")
+                .len(),
+            1
+        );
 
-        assert!(names.contains(&"Alice".to_string()));
-        assert!(names.contains(&"Bob".to_string()));
+        // A panicking predicate leaves no borrow behind
+        let panicked = catch_unwind(AssertUnwindSafe(|| {
+            graph.find_thing(|_| panic!("predicate failed"))
+        }));
+        assert!(panicked.is_err());
+        let panicked = catch_unwind(AssertUnwindSafe(|| {
+            graph.find_connections(|_| panic!("predicate failed"))
+        }));
+        assert!(panicked.is_err());
+        assert!(graph.find_thing(|name| *name == "Alice").unwrap() == people.alice);
+        people.alice.access_mut(|name| *name = "Alicia");
+        assert!(graph.find_thing(|name| *name == "Alicia").is_some());
+        assert!(
+            graph
+                .find_connection(|relation| *relation == "follows")
+                .is_some()
+        );
     }
 
     #[test]
-    fn directed_connection_safety() {
-        let mut graph = Things::<String, String>::new();
-
-        let manager = graph.new_thing("Manager".to_string());
-        let employee = graph.new_thing("Employee".to_string());
-
-        // Create directed management relationship
-        let manages =
-            graph.new_directed_connection(manager.clone(), "manages".to_string(), employee.clone());
+    fn bidirectional_path_matches_plain_search_and_explores_less() {
+        let mut grid = Things::<(usize, usize), ()>::new();
+        let side = 30;
+        let cells: Vec<_> = (0..side * side)
+            .map(|index| grid.new_thing((index / side, index % side)))
+            .collect();
+        for row in 0..side {
+            for column in 0..side {
+                let here = cells[row * side + column].clone();
+                if column + 1 < side {
+                    grid.new_undirected_connection(
+                        [here.clone(), cells[row * side + column + 1].clone()],
+                        (),
+                    );
+                }
+                if row + 1 < side {
+                    grid.new_undirected_connection(
+                        [here, cells[(row + 1) * side + column].clone()],
+                        (),
+                    );
+                }
+            }
+        }
 
-        // Connection should be marked as directed
-        assert!(manages.is_directed());
-        assert!(!manages.is_undirected());
+        // Along one row the shortest path is unique
+        let (from, to) = (&cells[15 * side + 5], &cells[15 * side + 25]);
+        // Both searches count the things they discover the same way
+        let mut plain_discovered = 0;
+        let plain = grid
+            .search_path_within(from, to, |_, _| true, usize::MAX, &mut plain_discovered)
+            .unwrap()
+            .unwrap();
+        let plain = Things::things_along(from, plain).unwrap();
+        let mut discovered = 0;
+        let bidirectional = grid
+            .search_bidirectional(from, to, &mut discovered)
+            .unwrap();
+        let coordinates = |path: &[Thing<(usize, usize), ()>]| {
+            path.iter().map(|cell| cell.get_copy()).collect::<Vec<_>>()
+        };
+        assert_eq!(coordinates(&bidirectional), coordinates(&plain));
+        assert_eq!(bidirectional.len(), 21);
+        assert!(
+            discovered < plain_discovered,
+            "{discovered} vs {plain_discovered}"
+        );
 
-        // Directional methods should work correctly
-        let from_person = manages.get_directed_from().unwrap();
-        let to_person = manages.get_directed_towards().unwrap();
+        // Across the grid, paths tie but their lengths agree
+        for (start, end) in [
+            (0, side * side - 1),
+            (3, 17 * side + 2),
+            (9 * side + 9, 9 * side + 9),
+        ] {
+            let plain = grid
+                .shortest_path(&cells[start], &cells[end], |_| true)
+                .unwrap();
+            let both = grid
+                .shortest_path_bidirectional(&cells[start], &cells[end])
+                .unwrap();
+            assert_eq!(both.len(), plain.len());
+            assert!(both[0] == cells[start].get_copy());
+            assert!(both[both.len() - 1] == cells[end].get_copy());
+            assert!(grid.path_edges(&both).is_some());
+        }
+    }
 
-        assert_eq!(from_person.access(|data| data.clone()), "Manager");
-        assert_eq!(to_person.access(|data| data.clone()), "Employee");
+    #[test]
+    fn bidirectional_path_respects_direction_and_liveness() {
+        let (mut tasks, plan) = fixtures::task_dag();
+        let plain = tasks
+            .shortest_path(&plan.design, &plan.deployment, |_| true)
+            .unwrap();
+        let both = tasks
+            .shortest_path_bidirectional(&plan.design, &plan.deployment)
+            .unwrap();
+        assert_eq!(both.len(), plain.len());
+        assert!(tasks.path_edges(&both).is_some());
+        assert!(
+            tasks
+                .shortest_path_bidirectional(&plan.deployment, &plan.design)
+                .is_none()
+        );
 
-        // get_connected_things should return [from, to]
-        let connected = manages.get_things();
-        assert_eq!(connected[0].access(|data| data.clone()), "Manager");
-        assert_eq!(connected[1].access(|data| data.clone()), "Employee");
+        // Every route to deployment goes through testing
+        tasks.kill_things(|task| *task == "Integration Testing");
+        assert!(
+            tasks
+                .shortest_path_bidirectional(&plan.design, &plan.deployment)
+                .is_none()
+        );
+        assert!(
+            tasks
+                .shortest_path_bidirectional(&plan.testing, &plan.testing)
+                .is_none()
+        );
     }
 
     #[test]
-    fn complex_knowledge_query() {
-        // Test a more complex knowledge representation scenario
-        let mut knowledge = Things::<String, String>::new();
-
-        // Create a small taxonomy
-        let animal = knowledge.new_thing("Animal".to_string());
-        let mammal = knowledge.new_thing("Mammal".to_string());
-        let dog = knowledge.new_thing("Dog".to_string());
-        let cat = knowledge.new_thing("Cat".to_string());
+    fn bounded_search_truncates_or_matches_unbounded() {
+        let graph = barbell_graph();
+        let find = |name: &'static str| graph.find_thing(|data| *data == name).unwrap();
+        let (a1, b3) = (find("a1"), find("b3"));
+        let names = |path: &[Thing<&'static str, &'static str>]| {
+            path.iter()
+                .map(|thing| thing.get_copy())
+                .collect::<Vec<_>>()
+        };
 
-        let fido = knowledge.new_thing("Fido".to_string());
-        let whiskers = knowledge.new_thing("Whiskers".to_string());
+        match graph.shortest_path_bounded(&a1, &b3, |_| true, 2) {
+            BoundedSearchResult::Truncated {
+                best_frontier_distance,
+                expanded,
+            } => {
+                assert_eq!(expanded, 2);
+                assert_eq!(best_frontier_distance, 1);
+            }
+            _ => panic!("a budget of two can't cross the bridge"),
+        }
+        let unbounded = graph.shortest_path(&a1, &b3, |_| true).unwrap();
+        match graph.shortest_path_bounded(&a1, &b3, |_| true, 100) {
+            BoundedSearchResult::Found(path) => assert_eq!(names(&path), names(&unbounded)),
+            _ => panic!("the budget is large enough"),
+        }
+        // A zero budget still answers trivial queries
+        assert!(matches!(
+            graph.shortest_path_bounded(&a1, &a1, |_| true, 0),
+            BoundedSearchResult::Found(path) if path.len() == 1
+        ));
+
+        for connection in graph.connections.iter() {
+            connection.set_weight(if *connection == "bridge" { 10 } else { 1 });
+        }
+        let (cost, cheapest) = graph
+            .cheapest_path(&a1, &b3, CostSource::Annotation)
+            .unwrap();
+        assert_eq!(cost, 12);
+        match graph.cheapest_path_bounded(&a1, &b3, CostSource::Annotation, 100) {
+            BoundedSearchResult::Found(path) => {
+                assert_eq!(graph.path_edges(&path).unwrap().len(), cheapest.len());
+                assert!(path[path.len() - 1] == "b3");
+            }
+            _ => panic!("the budget is large enough"),
+        }
+        assert!(matches!(
+            graph.cheapest_path_bounded(&a1, &b3, CostSource::Annotation, 3),
+            BoundedSearchResult::Truncated { expanded: 3, .. }
+        ));
+    }
 
-        // Build taxonomy relationships
-        knowledge.new_directed_connection(mammal.clone(), "is_a".to_string(), animal.clone());
-        knowledge.new_directed_connection(dog.clone(), "is_a".to_string(), mammal.clone());
-        knowledge.new_directed_connection(cat.clone(), "is_a".to_string(), mammal.clone());
+    #[test]
+    fn bounded_search_proves_unreachable() {
+        let mut graph = barbell_graph();
+        graph.kill_connections(|connection| *connection == "bridge");
+        let a1 = graph.find_thing(|data| *data == "a1").unwrap();
+        let b1 = graph.find_thing(|data| *data == "b1").unwrap();
+        assert!(matches!(
+            graph.shortest_path_bounded(&a1, &b1, |_| true, 1000),
+            BoundedSearchResult::Unreachable
+        ));
+        assert!(matches!(
+            graph.cheapest_path_bounded(&a1, &b1, CostSource::Closure(&|_| 1), 1000),
+            BoundedSearchResult::Unreachable
+        ));
+        // With a budget too small to explore its half, it can't tell
+        assert!(matches!(
+            graph.shortest_path_bounded(&a1, &b1, |_| true, 1),
+            BoundedSearchResult::Truncated { expanded: 1, .. }
+        ));
+    }
 
-        // Instance relationships
-        knowledge.new_directed_connection(fido.clone(), "instance_of".to_string(), dog.clone());
-        knowledge.new_directed_connection(whiskers.clone(), "instance_of".to_string(), cat.clone());
+    #[test]
+    fn sharded_graph_doesnt_claim_foreign_things() {
+        let mut graph = ShardedThings::<&str, ()>::new(2);
+        let alice = graph.new_thing("Alice");
 
-        // Query: Find all animals (instances that are transitively related to Animal)
-        // This tests multi-hop traversal
-        let mut animal_instances = Vec::new();
+        // Numbered in a shard this graph doesn't have
+        let mut wider = ShardedThings::<&str, ()>::new(4);
+        let strangers: Vec<_> = ["Bob", "Carol", "Dave", "Eve"]
+            .map(|name| wider.new_thing(name))
+            .into();
+        assert_eq!(wider.shard_of(&strangers[3]), Some(3));
+        assert_eq!(graph.shard_of(&strangers[3]), None);
+
+        // Numbered past what shard 0 has handed out
+        let mut plain = Things::<&str, ()>::new();
+        let first = plain.new_thing("Frank");
+        let second = plain.new_thing("Grace");
+        assert_eq!(graph.shard_of(&alice), Some(0));
+        assert_eq!(graph.shard_of(&first), Some(0));
+        assert_eq!(graph.shard_of(&second), None);
+        assert!(graph.shortest_path(&second, &alice, |_| true).is_none());
+    }
 
-        // Find all instances
-        for instance_conn in knowledge.do_for_all_connections(|conn| {
-            conn.access(|data| {
-                return if data == "instance_of" {
-                    Do::Take(conn.clone())
-                } else {
-                    Do::Nothing
-                };
-            })
-        }) {
-            if let Some(instance) = instance_conn.get_directed_from() {
-                if let Some(species) = instance_conn.get_directed_towards() {
-                    // Check if this species is ultimately an animal
-                    let mut current = species;
-                    let mut is_animal = false;
+    #[test]
+    fn connection_ids_survive_a_record_round_trip() {
+        let mut graph = Things::<&str, &str>::new();
+        let a = graph.new_thing("a");
+        let b = graph.new_thing("b");
+        graph.new_directed_connection(a.clone(), "dropped", b.clone());
+        let kept = graph.new_undirected_connection([a, b], "kept");
+        graph.kill_connections(|data| *data == "dropped");
+        graph.clean();
 
-                    // Traverse up the hierarchy
-                    for _ in 0..10 {
-                        // Prevent infinite loops
-                        if current.access(|data| data == "Animal") {
-                            is_animal = true;
-                            break;
-                        }
+        let raw = kept.id().as_u64();
+        let record = graph.to_record(SerializeOptions {
+            include_dead: false,
+        });
+        let mut restored = Things::from_record(record);
+        let found = restored
+            .get_connection_by_id(ConnectionId::from_raw(raw))
+            .unwrap();
+        assert!(found == "kept");
+        assert!(found.id() == kept.id());
+
+        // New connections never take a restored id
+        let [c, d] = [restored.new_thing("c"), restored.new_thing("d")];
+        let fresh = restored.new_directed_connection(c, "fresh", d);
+        assert!(fresh.id() > kept.id());
+        assert!(
+            restored
+                .get_connection_by_id(ConnectionId::from_raw(raw))
+                .unwrap()
+                == "kept"
+        );
+    }
 
-                        // Find parent class
-                        if let Some(parent_conn) = current.do_for_a_connection(|conn| {
-                            conn.access(|data| {
-                                return if data == "is_a" {
-                                    Do::Take(conn.clone())
-                                } else {
-                                    Do::Nothing
-                                };
-                            })
-                        }) {
-                            if let Some(parent) = parent_conn.get_directed_towards() {
-                                current = parent;
-                            } else {
-                                break;
-                            }
-                        } else {
-                            break;
-                        }
-                    }
+    #[test]
+    fn exporters_take_thing_data_from_a_table() {
+        let mut graph = Things::<&str, &str>::new();
+        let a = graph.new_thing("a");
+        let b = graph.new_thing("b");
+        graph.new_directed_connection(a.clone(), "next", b.clone());
+        graph.new_thing("gone");
+        graph.kill_things(|name| *name == "gone");
+
+        // A table with different values shows where the data came from
+        let table = graph.with_thing_data_table(|name| if *name == "a" { "A" } else { *name });
+        let record = graph.to_record_with_table(SerializeOptions { include_dead: true }, &table);
+        let data: Vec<_> = record.things.iter().map(|thing| thing.data).collect();
+        assert_eq!(data, ["A", "b", "gone"]);
+        assert_eq!(record.connections.len(), 1);
+        assert_eq!(
+            graph
+                .to_record(SerializeOptions { include_dead: true })
+                .things[0]
+                .data,
+            "a"
+        );
 
-                    if is_animal {
-                        animal_instances.push(instance.access(|data| data.clone()));
-                    }
+        let mut labels = Vec::new();
+        graph.export_with_table(
+            &table,
+            |name| name,
+            |label| label,
+            |item| match item {
+                ExportItem::Thing { label, .. } => labels.push(label.to_string()),
+                ExportItem::Connection { ends, label, .. } => {
+                    assert_eq!(ends, [0, 1]);
+                    labels.push(label.to_string());
                 }
-            }
-        }
+            },
+        );
+        assert_eq!(labels, ["A", "b", "next"]);
+
+        // Things added after the table was made are still recorded from the graph
+        graph.new_thing("c");
+        let record = graph.to_record_with_table(
+            SerializeOptions {
+                include_dead: false,
+            },
+            &table,
+        );
+        assert_eq!(record.things[2].data, "c");
+    }
 
-        assert!(animal_instances.contains(&"Fido".to_string()));
-        assert!(animal_instances.contains(&"Whiskers".to_string()));
-        assert_eq!(animal_instances.len(), 2);
+    #[test]
+    fn contract_connection_checks_kept_connections_first() {
+        extern crate std;
+        use std::panic::{AssertUnwindSafe, catch_unwind};
+
+        // Data without Clone is moved out of the absorbed thing
+        #[derive(Default, PartialEq, Debug)]
+        struct Size(u32);
+        let mut sizes = Things::<Size, ()>::new();
+        let a = sizes.new_thing(Size(2));
+        let b = sizes.new_thing(Size(3));
+        let ab = sizes.new_undirected_connection([a.clone(), b.clone()], ());
+        sizes
+            .contract_connection(&ab, |size, other| size.0 += other.0)
+            .unwrap();
+        assert!(a == Size(5));
+        assert!(b == Size(0));
+
+        // A kept self-loop is refused when self-loops are rejected
+        let mut rejecting =
+            Things::<&str, ()>::with_config(ThingsConfig::new().reject_self_loops(true));
+        let x = rejecting.new_thing("x");
+        let y = rejecting.new_thing("y");
+        let first = rejecting.new_undirected_connection([x.clone(), y.clone()], ());
+        rejecting.new_directed_connection(y.clone(), (), x.clone());
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            rejecting.contract_connection_with(
+                &first,
+                LoopPolicy::Keep,
+                ParallelPolicy::Merge,
+                |_, _| {},
+            )
+        }));
+        assert!(result.is_err());
+        assert!(first.is_alive() && y.is_alive());
+        assert_eq!(x.connection_count(), 2);
+        // Dropping it is fine
+        rejecting.contract_connection(&first, |_, _| {}).unwrap();
+        assert!(x.neighbors_with_edges().is_empty());
+
+        // In simple mode, kept parallel connections are refused in debug builds
+        let mut simple =
+            Things::<&str, ()>::with_config(ThingsConfig::new().mode(GraphMode::Simple));
+        let a = simple.new_thing("a");
+        let b = simple.new_thing("b");
+        let c = simple.new_thing("c");
+        let ab = simple.new_undirected_connection([a.clone(), b.clone()], ());
+        simple.new_undirected_connection([b.clone(), c.clone()], ());
+        simple.new_undirected_connection([c.clone(), a.clone()], ());
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            simple.contract_connection_with(&ab, LoopPolicy::Drop, ParallelPolicy::Keep, |_, _| {})
+        }));
+        assert_eq!(result.is_err(), cfg!(debug_assertions));
+        if cfg!(debug_assertions) {
+            assert!(ab.is_alive() && b.is_alive());
+            simple.contract_connection(&ab, |_, _| {}).unwrap();
+            assert_eq!((a.connection_count(), c.connection_count()), (1, 1));
+        }
+        simple.clean();
+        test_util::assert_graph_sound(&simple);
     }
 }